@@ -0,0 +1,134 @@
+//! Per sample cost of the code the ADC interrupt runs, there are 10 µs per sample
+//! at 100 kHz to share with everything else in the handler.
+//!
+//! `cargo bench -p app-measurements`, no criterion so that its `std` features
+//! don't leak into the `no_std` build
+
+use std::hint::black_box;
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+use app_measurements::util::LaxMonotonic;
+use app_measurements::{CalibrationResult, CalibrationState, Measurement, TriggerThresholds};
+
+const SAMPLES: usize = 1_000_000;
+const SAMPLE_PERIOD_NANOS: f64 = 10_000.0;
+const RUNS: usize = 10;
+const BASELINE: u16 = 200;
+const OPEN_LEVEL: u16 = 3000;
+/// A pulse every 100 ms at 100 kHz
+const PULSE_PERIOD_SAMPLES: usize = 10_000;
+
+// Same as the firmware defaults
+const TRIGGER_THRESHOLDS: TriggerThresholds = TriggerThresholds {
+    low_ratio: 1.0,
+    high_ratio: 1.0,
+    low_delta: 4096 / 32,
+    high_delta: 4096 / 16,
+};
+
+/// Wall time since the first call
+struct StdClock;
+
+impl LaxMonotonic for StdClock {
+    type Instant = fugit::TimerInstantU64<1_000_000>;
+    type Duration = fugit::TimerDurationU64<1_000_000>;
+
+    fn now() -> Self::Instant {
+        static START: OnceLock<Instant> = OnceLock::new();
+        let elapsed = START.get_or_init(Instant::now).elapsed();
+        Self::Instant::from_ticks(elapsed.as_micros() as u64)
+    }
+}
+
+/// Noisy baseline with square pulses from 1/8000 to 1/20 at the full rate
+fn synthetic_samples() -> Vec<u16> {
+    let mut seed = 1u32;
+    (0..SAMPLES)
+        .map(|i| {
+            seed = seed.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+            let noise = (seed >> 28) as u16;
+            let pulse = i / PULSE_PERIOD_SAMPLES;
+            let width = 1 << (pulse % 12);
+            if i % PULSE_PERIOD_SAMPLES < width.min(PULSE_PERIOD_SAMPLES / 2) {
+                OPEN_LEVEL + noise
+            } else {
+                BASELINE + noise
+            }
+        })
+        .collect()
+}
+
+fn calibration() -> CalibrationResult {
+    CalibrationResult {
+        average: BASELINE + 8,
+        min: BASELINE,
+        max: BASELINE + 15,
+        covered: 0,
+    }
+}
+
+fn measurement() -> Measurement<StdClock> {
+    Measurement::new(calibration(), TRIGGER_THRESHOLDS)
+        .with_sample_rate(100_000)
+        .with_saturation_level(4095)
+        .with_response_time(20_000)
+}
+
+/// Fastest of [`RUNS`] passes over all samples, the others caught the host busy
+fn bench(name: &str, samples: &[u16], mut pass: impl FnMut(&[u16])) {
+    pass(samples);
+    let best = (0..RUNS)
+        .map(|_| {
+            let start = Instant::now();
+            pass(samples);
+            start.elapsed()
+        })
+        .min()
+        .unwrap_or(Duration::ZERO);
+    let nanos_per_sample = best.as_nanos() as f64 / samples.len() as f64;
+    println!(
+        "{:<32} {:>8.1} ns/sample {:>6.2}% of a sample period",
+        name,
+        nanos_per_sample,
+        nanos_per_sample * 100.0 / SAMPLE_PERIOD_NANOS
+    );
+}
+
+fn main() {
+    let samples = synthetic_samples();
+
+    bench("measurement", &samples, |samples| {
+        let mut measurement = measurement();
+        for &value in samples {
+            measurement.step(black_box(value));
+            // Rearmed like after the results screen
+            if measurement.is_done() {
+                black_box(measurement.result());
+                measurement = self::measurement();
+            }
+        }
+    });
+
+    bench("measurement auto trigger low", &samples, |samples| {
+        let mut measurement = measurement().with_auto_trigger_low(BASELINE + 15);
+        for &value in samples {
+            measurement.step(black_box(value));
+            if measurement.is_done() {
+                black_box(measurement.result());
+                measurement = self::measurement().with_auto_trigger_low(BASELINE + 15);
+            }
+        }
+    });
+
+    bench("calibration", &samples, |samples| {
+        let mut state = CalibrationState::default();
+        state.begin();
+        for &value in samples {
+            state.step(black_box(value));
+            if !state.is_in_progress() {
+                state.begin();
+            }
+        }
+    });
+}
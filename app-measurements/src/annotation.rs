@@ -0,0 +1,64 @@
+use crate::ShutterSpeed;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CameraSlot {
+    None,
+    A,
+    B,
+    C,
+}
+
+impl CameraSlot {
+    pub const ALL: [CameraSlot; 4] = [
+        CameraSlot::None,
+        CameraSlot::A,
+        CameraSlot::B,
+        CameraSlot::C,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            CameraSlot::None => "-",
+            CameraSlot::A => "A",
+            CameraSlot::B => "B",
+            CameraSlot::C => "C",
+        }
+    }
+}
+
+/// What the user says is being tested
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Annotation {
+    /// Index into [`crate::util::KNOWN_SHUTTER_DURATIONS`]
+    pub nominal_speed: Option<usize>,
+    /// 1/N s for odd marked speeds like 1/100 or 1/400, wins over `nominal_speed`
+    pub custom_speed: Option<u16>,
+    pub camera: CameraSlot,
+}
+
+impl Annotation {
+    pub fn nominal(&self) -> Option<ShutterSpeed> {
+        match self.custom_speed {
+            Some(denominator) => Some(ShutterSpeed::from_denominator(denominator as u32)),
+            None => self.nominal_speed.and_then(ShutterSpeed::known),
+        }
+    }
+
+    pub fn nominal_duration(&self) -> Option<f32> {
+        self.nominal().map(|s| s.secs())
+    }
+
+    pub fn nominal_duration_micros(&self) -> Option<u64> {
+        self.nominal().map(|s| s.micros())
+    }
+}
+
+impl Default for Annotation {
+    fn default() -> Self {
+        Self {
+            nominal_speed: None,
+            custom_speed: None,
+            camera: CameraSlot::None,
+        }
+    }
+}
@@ -1,7 +1,9 @@
 use heapless::HistoryBuffer;
 use infinity_sampler::SamplingRate;
 
-#[derive(Clone, Debug, Copy)]
+use crate::SensorFault;
+
+#[derive(Clone, Debug, Copy, PartialEq)]
 pub struct TriggerThresholds {
     pub low_ratio: f32,
     pub high_ratio: f32,
@@ -19,67 +21,267 @@ impl TriggerThresholds {
         (((calibration.max as f32 * self.high_ratio) + self.high_delta as f32) as u16)
             .max(calibration.max + 10)
     }
+
+    /// The same thresholds for samples in `to_range` counts instead of `from_range`,
+    /// the ratios apply to the dark level either way
+    pub fn rescaled(&self, from_range: u16, to_range: u16) -> Self {
+        let rescale =
+            |delta: u16| (delta as u32 * to_range as u32 / from_range.max(1) as u32) as u16;
+        Self {
+            low_delta: rescale(self.low_delta),
+            high_delta: rescale(self.high_delta),
+            ..*self
+        }
+    }
+
+    pub fn from_levels(calibration: &CalibrationResult, low: u16, high: u16) -> Self {
+        Self {
+            low_ratio: 1.0,
+            high_ratio: 1.0,
+            low_delta: low.saturating_sub(calibration.max),
+            high_delta: high.saturating_sub(calibration.max),
+        }
+    }
 }
 
 const CALIBRATION_SAMPLES: usize = 1024;
 const CALIBRATION_SAMPLE_RATE_DIVISOR: u32 = 50;
+/// The covered level barely moves, a quarter of the ambient capture is plenty
+const COVERED_SAMPLES: usize = 256;
+/// 100 ms at 100 kHz without oversampling
+const SPOT_CHECK_SAMPLES: usize = 200;
 
 #[derive(Clone, Debug, Default)]
 pub struct CalibrationResult {
     pub average: u16,
     pub min: u16,
     pub max: u16,
+    /// Average with the sensor covered, what it reads with no light at all
+    pub covered: u16,
+}
+
+impl CalibrationResult {
+    fn from_samples(samples: &[u16]) -> Self {
+        let sum = samples.iter().fold(0, |acc, &x| acc + x as u64);
+        Self {
+            average: (sum / samples.len() as u64) as u16,
+            min: *samples.iter().min().unwrap(),
+            max: *samples.iter().max().unwrap(),
+            covered: 0,
+        }
+    }
+
+    /// A covered sensor that still reads high is leaking light or current, and
+    /// would eat into the range left for the pulse
+    pub fn sensor_fault(&self, max_covered: u16) -> Option<SensorFault> {
+        (self.covered > max_covered).then_some(SensorFault::Leaking {
+            covered: self.covered,
+        })
+    }
+
+    /// Whether the baseline moved too far from `previous` to keep using it
+    fn drifted_from(&self, previous: &CalibrationResult, max_drift: u16) -> bool {
+        self.average.abs_diff(previous.average) > max_drift
+            || self.max > previous.max.saturating_add(max_drift)
+    }
+}
+
+/// What the user is asked to do while calibrating
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CalibrationStage {
+    Cover,
+    Covered,
+    Uncover,
+    Ambient,
+    SpotCheck,
 }
 
 #[derive(Clone)]
 pub enum CalibrationState {
     Done(CalibrationResult),
+    /// Waiting for the sensor to be covered, see [`CalibrationState::resume`]
+    Cover,
+    Covered {
+        buffer: HistoryBuffer<u16, COVERED_SAMPLES>,
+        rate: SamplingRate,
+    },
+    /// Waiting for the sensor to be uncovered again
+    Uncover {
+        covered: u16,
+    },
+    /// The ambient level, which the thresholds are set from
     InProgress {
+        covered: u16,
         buffer: HistoryBuffer<u16, CALIBRATION_SAMPLES>,
         rate: SamplingRate,
     },
+    /// Keeps `previous` unless the baseline drifted, a full calibration follows then
+    SpotCheck {
+        previous: CalibrationResult,
+        max_drift: u16,
+        buffer: HistoryBuffer<u16, SPOT_CHECK_SAMPLES>,
+        rate: SamplingRate,
+    },
 }
 
 impl CalibrationState {
+    /// Both stages, starting with the prompt to cover the sensor
     pub fn begin(&mut self) {
+        *self = CalibrationState::Cover;
+    }
+
+    /// Ambient stage only, keeping a `covered` level taken before
+    pub fn begin_ambient(&mut self, covered: u16) {
         *self = CalibrationState::InProgress {
+            covered,
+            buffer: <_>::default(),
+            rate: SamplingRate::new(CALIBRATION_SAMPLE_RATE_DIVISOR),
+        };
+    }
+
+    /// Whether sampling is held until the user has done what [`CalibrationState::stage`] asks
+    pub fn is_waiting(&self) -> bool {
+        matches!(
+            self,
+            CalibrationState::Cover | CalibrationState::Uncover { .. }
+        )
+    }
+
+    /// Past a prompt, once the user had the time to follow it
+    pub fn resume(&mut self) {
+        match *self {
+            CalibrationState::Cover => {
+                *self = CalibrationState::Covered {
+                    buffer: <_>::default(),
+                    rate: SamplingRate::new(CALIBRATION_SAMPLE_RATE_DIVISOR),
+                }
+            }
+            CalibrationState::Uncover { covered } => self.begin_ambient(covered),
+            _ => {}
+        }
+    }
+
+    pub fn stage(&self) -> Option<CalibrationStage> {
+        match self {
+            CalibrationState::Done(_) => None,
+            CalibrationState::Cover => Some(CalibrationStage::Cover),
+            CalibrationState::Covered { .. } => Some(CalibrationStage::Covered),
+            CalibrationState::Uncover { .. } => Some(CalibrationStage::Uncover),
+            CalibrationState::InProgress { .. } => Some(CalibrationStage::Ambient),
+            CalibrationState::SpotCheck { .. } => Some(CalibrationStage::SpotCheck),
+        }
+    }
+
+    /// Quick recalibration from the last result
+    pub fn begin_spot_check(&mut self, previous: CalibrationResult, max_drift: u16) {
+        *self = CalibrationState::SpotCheck {
+            previous,
+            max_drift,
             buffer: <_>::default(),
             rate: SamplingRate::new(CALIBRATION_SAMPLE_RATE_DIVISOR),
         };
     }
 
+    pub fn is_in_progress(&self) -> bool {
+        !matches!(self, CalibrationState::Done(_))
+    }
+
     pub fn step(&mut self, value: u16) {
         match *self {
+            CalibrationState::Covered {
+                ref mut buffer,
+                ref mut rate,
+            } => {
+                if rate.step() {
+                    buffer.write(value);
+                    if buffer.len() == buffer.capacity() {
+                        let covered = CalibrationResult::from_samples(buffer.as_slice()).average;
+                        *self = CalibrationState::Uncover { covered };
+                    }
+                }
+            }
             CalibrationState::InProgress {
+                covered,
+                ref mut buffer,
+                ref mut rate,
+            } => {
+                if rate.step() {
+                    buffer.write(value);
+                    if buffer.len() == buffer.capacity() {
+                        let mut result = CalibrationResult::from_samples(buffer.as_slice());
+                        result.covered = covered;
+                        *self = CalibrationState::Done(result);
+                    }
+                }
+            }
+            CalibrationState::SpotCheck {
+                ref previous,
+                max_drift,
                 ref mut buffer,
                 ref mut rate,
             } => {
                 if rate.step() {
                     buffer.write(value);
                     if buffer.len() == buffer.capacity() {
-                        let sum = buffer.iter().fold(0, |acc, &x| acc + x as u64);
-                        let count = buffer.len() as u32;
-                        let average = (sum / count as u64) as u16;
-                        *self = CalibrationState::Done(CalibrationResult {
-                            average,
-                            min: *buffer.iter().min().unwrap(),
-                            max: *buffer.iter().max().unwrap(),
-                        });
+                        let check = CalibrationResult::from_samples(buffer.as_slice());
+                        if check.drifted_from(previous, max_drift) {
+                            // Only the ambient light moves
+                            self.begin_ambient(previous.covered);
+                        } else {
+                            *self = CalibrationState::Done(previous.clone());
+                        }
                     }
                 }
             }
-            CalibrationState::Done(_) => {}
+            CalibrationState::Done(_)
+            | CalibrationState::Cover
+            | CalibrationState::Uncover { .. } => {}
         }
     }
 
+    pub fn cancel(&mut self) {
+        *self = CalibrationState::default();
+    }
+
+    /// Of the current stage, zero while waiting on the user
     pub fn progress(&self) -> Option<u8> {
         match *self {
+            CalibrationState::Cover | CalibrationState::Uncover { .. } => Some(0),
+            CalibrationState::Covered { ref buffer, .. } => {
+                Some((buffer.len() * 100 / buffer.capacity()) as u8)
+            }
             CalibrationState::InProgress { ref buffer, .. } => {
                 Some((buffer.len() * 100 / buffer.capacity()) as u8)
             }
+            CalibrationState::SpotCheck { ref buffer, .. } => {
+                Some((buffer.len() * 100 / buffer.capacity()) as u8)
+            }
             CalibrationState::Done(_) => None,
         }
     }
+
+    /// Raw ADC samples left until the current stage completes
+    pub fn remaining_samples(&self) -> Option<u32> {
+        match *self {
+            CalibrationState::Covered {
+                ref buffer,
+                ref rate,
+            } => Some((buffer.capacity() - buffer.len()) as u32 * rate.divisor()),
+            CalibrationState::InProgress {
+                ref buffer,
+                ref rate,
+                ..
+            } => Some((buffer.capacity() - buffer.len()) as u32 * rate.divisor()),
+            CalibrationState::SpotCheck {
+                ref buffer,
+                ref rate,
+                ..
+            } => Some((buffer.capacity() - buffer.len()) as u32 * rate.divisor()),
+            CalibrationState::Done(_)
+            | CalibrationState::Cover
+            | CalibrationState::Uncover { .. } => None,
+        }
+    }
 }
 
 impl Default for CalibrationState {
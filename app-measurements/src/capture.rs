@@ -0,0 +1,93 @@
+use heapless::HistoryBuffer;
+use infinity_sampler::SamplingRate;
+
+use crate::measurement::{MARGIN_SAMPLES, SAMPLING_BUFFER_LEN};
+use crate::MeasurementResult;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CaptureEdge {
+    Rising,
+    Falling,
+}
+
+/// Open time measurement from timer input-capture timestamps, for accessories
+/// that output a clean digital signal (high while the shutter is open)
+pub struct CaptureMeasurement {
+    clock_hz: u32,
+    high_level: u16,
+    opened_at: Option<u64>,
+    result: Option<MeasurementResult>,
+}
+
+impl CaptureMeasurement {
+    /// `high_level` is only used to draw the synthesized waveform
+    pub fn new(clock_hz: u32, high_level: u16) -> Self {
+        Self {
+            clock_hz,
+            high_level,
+            opened_at: None,
+            result: None,
+        }
+    }
+
+    pub fn on_edge(&mut self, edge: CaptureEdge, ticks: u64) {
+        if self.result.is_some() {
+            return;
+        }
+
+        match (edge, self.opened_at) {
+            (CaptureEdge::Rising, _) => {
+                self.opened_at = Some(ticks);
+            }
+            (CaptureEdge::Falling, Some(opened_at)) => {
+                let duration_nanos =
+                    ticks.saturating_sub(opened_at) * 1_000_000_000 / self.clock_hz as u64;
+                self.result = Some(self.make_result(duration_nanos));
+            }
+            // Falling edge without a rising one - armed while the shutter was open
+            (CaptureEdge::Falling, None) => (),
+        }
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.result.is_some()
+    }
+
+    pub fn take_result(self) -> Option<MeasurementResult> {
+        self.result
+    }
+
+    fn make_result(&self, duration_nanos: u64) -> MeasurementResult {
+        let mut sample_buffer = HistoryBuffer::new();
+        for _ in 0..MARGIN_SAMPLES {
+            sample_buffer.write(0);
+        }
+        for _ in 0..SAMPLING_BUFFER_LEN {
+            sample_buffer.write(self.high_level);
+        }
+        for _ in 0..MARGIN_SAMPLES {
+            sample_buffer.write(0);
+        }
+
+        // Round to the nearest microsecond
+        let duration_micros = (duration_nanos + 500) / 1000;
+        MeasurementResult {
+            duration_micros,
+            // A digital signal is either fully open or closed
+            integrated_duration_micros: duration_micros,
+            half_peak_duration_micros: duration_micros,
+            samples_since_start: SAMPLING_BUFFER_LEN + MARGIN_SAMPLES,
+            samples_since_end: MARGIN_SAMPLES,
+            sample_buffer,
+            sample_rate: SamplingRate::new(1),
+            oversampling: 1,
+            second_pulse: None,
+            release_lag_micros: None,
+            sync_offset_micros: None,
+            fired_lag_micros: None,
+            sample_interval_nanos: (1_000_000_000 / self.clock_hz).max(1),
+            clipped: false,
+            burst: None,
+        }
+    }
+}
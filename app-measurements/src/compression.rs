@@ -0,0 +1,201 @@
+use heapless::Vec;
+
+/// A dozen of them with a length prefix each would fit the smallest 16 KiB sector of
+/// the F401 flash. For now they're only kept in RAM, see [`crate::TraceHistory`].
+pub const COMPRESSED_TRACE_CAPACITY: usize = 16 * 1024 / 12 - 2;
+
+// 0xxxxxxx: delta of -64..=63 from the previous sample
+const SHORT_DELTA: u8 = 0b0000_0000;
+const SHORT_DELTA_MASK: u8 = 0b1000_0000;
+// 10nnnnnn: previous sample repeated n + 1 times
+const RUN: u8 = 0b1000_0000;
+const RUN_MASK: u8 = 0b1100_0000;
+const MAX_RUN: usize = 64;
+// 110ddddd dddddddd: delta of -4096..=4095
+const LONG_DELTA: u8 = 0b1100_0000;
+const LONG_DELTA_MASK: u8 = 0b1110_0000;
+// 11100000 lo hi: raw sample
+const LITERAL: u8 = 0b1110_0000;
+
+/// Delta and run length encoded light level samples. A flat baseline
+/// costs a byte per 64 samples and ADC noise a byte per sample.
+pub type CompressedTrace = Vec<u8, COMPRESSED_TRACE_CAPACITY>;
+
+/// `None` if the trace is too noisy to fit in [`COMPRESSED_TRACE_CAPACITY`]
+pub fn compress_trace(samples: impl IntoIterator<Item = u16>) -> Option<CompressedTrace> {
+    let mut out = CompressedTrace::new();
+    let mut previous = 0u16;
+    let mut run = 0;
+
+    for sample in samples {
+        if sample == previous && run < MAX_RUN {
+            run += 1;
+            continue;
+        }
+        flush_run(&mut out, &mut run)?;
+
+        let delta = sample as i32 - previous as i32;
+        match delta {
+            0 => run = 1,
+            -64..=63 => out
+                .push(SHORT_DELTA | (delta as u8 & !SHORT_DELTA_MASK))
+                .ok()?,
+            -4096..=4095 => {
+                let [hi, lo] = (delta as u16).to_be_bytes();
+                out.extend_from_slice(&[LONG_DELTA | (hi & !LONG_DELTA_MASK), lo])
+                    .ok()?;
+            }
+            _ => {
+                let [lo, hi] = sample.to_le_bytes();
+                out.extend_from_slice(&[LITERAL, lo, hi]).ok()?;
+            }
+        }
+        previous = sample;
+    }
+    flush_run(&mut out, &mut run)?;
+    Some(out)
+}
+
+fn flush_run(out: &mut CompressedTrace, run: &mut usize) -> Option<()> {
+    if *run > 0 {
+        out.push(RUN | (*run - 1) as u8).ok()?;
+        *run = 0;
+    }
+    Some(())
+}
+
+/// Yields the samples of a [`CompressedTrace`] in order, stops early on
+/// a truncated trace
+pub struct TraceDecoder<'a> {
+    data: &'a [u8],
+    previous: u16,
+    run: usize,
+}
+
+impl<'a> TraceDecoder<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self {
+            data,
+            previous: 0,
+            run: 0,
+        }
+    }
+
+    fn take<const N: usize>(&mut self) -> Option<[u8; N]> {
+        let (bytes, rest) = self.data.split_first_chunk::<N>()?;
+        self.data = rest;
+        Some(*bytes)
+    }
+}
+
+impl Iterator for TraceDecoder<'_> {
+    type Item = u16;
+
+    fn next(&mut self) -> Option<u16> {
+        if self.run > 0 {
+            self.run -= 1;
+            return Some(self.previous);
+        }
+
+        let [token] = self.take::<1>()?;
+        self.previous = if token & SHORT_DELTA_MASK == SHORT_DELTA {
+            // Sign extend from 7 bits
+            let delta = ((token << 1) as i8 >> 1) as i16;
+            self.previous.wrapping_add_signed(delta)
+        } else if token & RUN_MASK == RUN {
+            self.run = (token & !RUN_MASK) as usize;
+            self.previous
+        } else if token & LONG_DELTA_MASK == LONG_DELTA {
+            let [lo] = self.take::<1>()?;
+            // Sign extend from 13 bits
+            let delta = ((u16::from_be_bytes([token, lo]) << 3) as i16) >> 3;
+            self.previous.wrapping_add_signed(delta)
+        } else if token == LITERAL {
+            u16::from_le_bytes(self.take::<2>()?)
+        } else {
+            self.data = &[];
+            return None;
+        };
+        Some(self.previous)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use std::vec::Vec;
+
+    use super::*;
+    use crate::SAMPLING_BUFFER_LEN_WITH_MARGINS;
+
+    fn round_trip(samples: &[u16]) -> CompressedTrace {
+        let compressed = compress_trace(samples.iter().copied()).unwrap();
+        let decoded: Vec<u16> = TraceDecoder::new(&compressed).collect();
+        assert_eq!(decoded, samples);
+        compressed
+    }
+
+    #[test]
+    fn runs_longer_than_max_run_split() {
+        let mut samples = std::vec![0; MAX_RUN * 3 + 5];
+        samples.extend([100; MAX_RUN + 1]);
+        let compressed = round_trip(&samples);
+        // Four runs of zeros, one delta and two runs of the rest
+        assert_eq!(compressed.len(), 4 + 1 + 2);
+    }
+
+    #[test]
+    fn deltas_at_the_token_edges() {
+        let base = 5000;
+        for (delta, len) in [
+            (63, 1),
+            (-64, 1),
+            (64, 2),
+            (-65, 2),
+            (4095, 2),
+            (-4096, 2),
+            (4096, 3),
+            (-4097, 3),
+        ] {
+            let sample = (base + delta) as u16;
+            let compressed = round_trip(&[base as u16, sample]);
+            // The first sample is a literal from zero
+            assert_eq!(compressed.len(), 3 + len, "delta {}", delta);
+        }
+    }
+
+    #[test]
+    fn literals_for_big_jumps() {
+        let compressed = round_trip(&[0, u16::MAX, 0, 40_000, 1]);
+        assert_eq!(compressed[1], LITERAL);
+    }
+
+    #[test]
+    fn noisy_result_buffer_fits() {
+        // ADC noise, a byte per sample
+        let samples: Vec<u16> = (0..SAMPLING_BUFFER_LEN_WITH_MARGINS)
+            .map(|i| (2000 + (i * 7919 % 49) as i32 - 24) as u16)
+            .collect();
+        round_trip(&samples);
+
+        // Dropped rather than cut short
+        let jumps = (0..COMPRESSED_TRACE_CAPACITY).map(|i| if i % 2 == 0 { 0 } else { 1000 });
+        assert!(compress_trace(jumps).is_none());
+    }
+
+    #[test]
+    fn truncated_trace_stops_early() {
+        let samples = [0, 0, 30, 2000, 60_000, 60_000, 59_990, 100];
+        let compressed = round_trip(&samples);
+        for len in 0..compressed.len() {
+            let decoded: Vec<u16> = TraceDecoder::new(&compressed[..len]).collect();
+            assert!(
+                samples.starts_with(&decoded),
+                "{} bytes decoded to {:?}",
+                len,
+                decoded
+            );
+        }
+    }
+}
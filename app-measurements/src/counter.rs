@@ -0,0 +1,85 @@
+use crate::calibration::TriggerThresholds;
+use crate::util::{LaxDuration, LaxMonotonic};
+use crate::CalibrationResult;
+
+/// Counts threshold crossings without recording the waveform
+pub struct EventCounter<M: LaxMonotonic> {
+    trigger_high: u16,
+    trigger_low: u16,
+    triggered: bool,
+    count: u32,
+    first_event: Option<M::Instant>,
+    last_event: Option<M::Instant>,
+}
+
+impl<M: LaxMonotonic> EventCounter<M> {
+    pub fn new(calibration: &CalibrationResult, trigger_thresholds: &TriggerThresholds) -> Self {
+        Self {
+            trigger_high: trigger_thresholds.trigger_high(calibration),
+            trigger_low: trigger_thresholds.trigger_low(calibration),
+            triggered: false,
+            count: 0,
+            first_event: None,
+            last_event: None,
+        }
+    }
+
+    #[inline(always)]
+    pub fn step(&mut self, value: u16) {
+        if self.triggered {
+            if value < self.trigger_low {
+                self.triggered = false;
+            }
+        } else if value > self.trigger_high {
+            self.triggered = true;
+            self.count += 1;
+
+            let now = M::now();
+            self.first_event.get_or_insert(now);
+            self.last_event = Some(now);
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.count = 0;
+        self.first_event = None;
+        self.last_event = None;
+    }
+
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+
+    pub fn first_event(&self) -> Option<M::Instant> {
+        self.first_event
+    }
+
+    /// Average rate between the first and the last counted event
+    pub fn events_per_minute(&self) -> Option<u32> {
+        let (first, last) = (self.first_event?, self.last_event?);
+        let micros = (last - first).to_micros();
+        if self.count < 2 || micros == 0 {
+            return None;
+        }
+        Some(((self.count - 1) as u64 * 60_000_000 / micros) as u32)
+    }
+}
+
+/// Watching for a second release right after a measurement, a camera with a
+/// double exposure lock shouldn't fire again until it's wound on
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RefireCheck {
+    /// No light for the whole watch
+    Locked,
+    /// Light again this long into the watch
+    Refired { after_millis: u32 },
+}
+
+impl RefireCheck {
+    pub fn label(&self) -> &'static str {
+        match self {
+            RefireCheck::Locked => "LOCKED",
+            RefireCheck::Refired { .. } => "REFIRED",
+        }
+    }
+}
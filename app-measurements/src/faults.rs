@@ -0,0 +1,87 @@
+/// Sampling faults since power on
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct AdcFaults {
+    /// DMA transfer errors, the buffer is dropped
+    pub transfer_errors: u32,
+    /// Conversions the DMA didn't pick up in time
+    pub overruns: u32,
+    /// Measurements restarted because samples went missing
+    pub invalidated: u32,
+    /// See [`StuckDetector`]
+    pub stuck: u32,
+}
+
+/// Something wrong with the light sensor itself rather than the sampling
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SensorFault {
+    /// The ADC kept reading `value`, the photodiode line has likely come off
+    Stuck { value: u16 },
+    /// Still read `covered` with the sensor covered during calibration
+    Leaking { covered: u16 },
+}
+
+/// The clock every duration is counted in isn't running at the rate the firmware
+/// assumes, results would come out scaled by the difference
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ClockFault {
+    /// Running off the internal RC oscillator, the crystal didn't start
+    NoCrystal,
+    /// The core clock came out at `measured_hz` instead
+    WrongRate { measured_hz: u32 },
+}
+
+/// Core clock cycles counted over `interval_micros` of the tick timer, compared with
+/// `expected_hz`. A cycle counter that doesn't move at all fails too.
+pub fn check_clock_rate(
+    cycles: u32,
+    interval_micros: u32,
+    expected_hz: u32,
+    tolerance_ppm: u32,
+) -> Option<ClockFault> {
+    let measured_hz = (cycles as u64 * 1_000_000 / interval_micros.max(1) as u64) as u32;
+    let off_ppm = (measured_hz.abs_diff(expected_hz) as u64 * 1_000_000) / expected_hz as u64;
+    (off_ppm > tolerance_ppm as u64).then_some(ClockFault::WrongRate { measured_hz })
+}
+
+/// Raw conversions never repeat for long with a sensor attached, its noise alone
+/// moves the lowest bits
+pub struct StuckDetector {
+    limit: u32,
+    /// Saturation is a legitimate constant reading during long exposures
+    ignored: Option<u16>,
+    last: u16,
+    run: u32,
+}
+
+impl StuckDetector {
+    pub fn new(limit: u32) -> Self {
+        Self {
+            limit,
+            ignored: None,
+            last: 0,
+            run: 0,
+        }
+    }
+
+    pub fn with_ignored(mut self, value: u16) -> Self {
+        self.ignored = Some(value);
+        self
+    }
+
+    /// Reports the fault once, when the run of equal values reaches the limit
+    #[inline(always)]
+    pub fn step(&mut self, value: u16) -> Option<SensorFault> {
+        if value != self.last || self.ignored == Some(value) {
+            self.last = value;
+            self.run = 0;
+            return None;
+        }
+        self.run = self.run.saturating_add(1);
+        (self.run == self.limit).then_some(SensorFault::Stuck { value })
+    }
+
+    /// For a fresh start after sampling was paused
+    pub fn reset(&mut self) {
+        self.run = 0;
+    }
+}
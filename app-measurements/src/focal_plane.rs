@@ -0,0 +1,62 @@
+use crate::{ChannelTiming, ScanResult};
+
+/// Photodiodes across the film gate: left, center and right
+pub const FOCAL_PLANE_SENSORS: usize = 3;
+pub const FOCAL_PLANE_CENTER: usize = 1;
+
+/// Exposure at three points across the gate and how long each curtain takes to cross it.
+/// Taken with a [`crate::ScanMeasurement`], the sensors being its channels.
+#[derive(Clone, Debug)]
+pub struct FocalPlaneResult {
+    pub sensors: [ChannelTiming; FOCAL_PLANE_SENSORS],
+    /// Between neighbouring sensors
+    pub pitch_um: u32,
+    pub frame_width_um: u32,
+    pub sample_interval_micros: u32,
+}
+
+impl FocalPlaneResult {
+    pub fn exposure_micros(&self, sensor: usize) -> Option<u64> {
+        self.sensors.get(sensor)?.open_micros()
+    }
+
+    /// Exposure at `sensor` relative to the center of the gate
+    pub fn deviation_percent(&self, sensor: usize) -> Option<i64> {
+        let center = self.exposure_micros(FOCAL_PLANE_CENTER)?.max(1) as i64;
+        Some((self.exposure_micros(sensor)? as i64 - center) * 100 / center)
+    }
+
+    /// Time for the opening curtain to cross the frame width, negative when it
+    /// travels from right to left
+    pub fn first_curtain_micros(&self) -> Option<i64> {
+        self.travel_micros(|sensor| sensor.opened_at_micros)
+    }
+
+    /// Same as [`Self::first_curtain_micros`] for the closing curtain
+    pub fn second_curtain_micros(&self) -> Option<i64> {
+        self.travel_micros(|sensor| sensor.closed_at_micros)
+    }
+
+    fn travel_micros(&self, edge: impl Fn(&ChannelTiming) -> Option<u64>) -> Option<i64> {
+        let left = edge(&self.sensors[0])? as i64;
+        let right = edge(&self.sensors[FOCAL_PLANE_SENSORS - 1])? as i64;
+        let span_um = self.pitch_um.max(1) as i64 * (FOCAL_PLANE_SENSORS as i64 - 1);
+        Some((right - left) * self.frame_width_um as i64 / span_um)
+    }
+
+    /// One sample either way on every edge
+    pub fn uncertainty_micros(&self) -> u64 {
+        self.sample_interval_micros as u64
+    }
+}
+
+impl From<&ScanResult<FOCAL_PLANE_SENSORS>> for FocalPlaneResult {
+    fn from(result: &ScanResult<FOCAL_PLANE_SENSORS>) -> Self {
+        Self {
+            sensors: result.channels,
+            pitch_um: result.pitch_um,
+            frame_width_um: result.frame_height_um,
+            sample_interval_micros: result.sweep_interval_micros,
+        }
+    }
+}
@@ -0,0 +1,153 @@
+use micromath::F32Ext;
+
+use crate::{MeasurementResult, TriggerThresholds};
+
+/// Below this share of the stock module's reading the pulse is too close to the noise
+const MIN_GAIN_PERCENT: u32 = 5;
+/// 10 to 90 % of a first order step
+const EDGE_TIME_CONSTANTS: f32 = 2.197;
+/// Edges steeper than this many samples are taken as instantaneous
+const MIN_EDGE_SAMPLES: f32 = 2.0;
+
+/// What a sensor module makes of a known emitter pulse, learned from the loopback so
+/// that modules other than the stock one trigger and integrate the same way
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AccessoryGain {
+    /// Pulse height against the stock module's at the same emitter intensity
+    pub gain_percent: u16,
+    /// First order time constant, 0 when the edges were within a couple of samples
+    pub response_time_nanos: u32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GainFault {
+    /// The ADC saturated, the pulse height is unknown
+    Clipped,
+    /// Too little light came back to tell the gain
+    TooDim,
+}
+
+impl GainFault {
+    pub fn label(&self) -> &'static str {
+        match self {
+            GainFault::Clipped => "CLIPPED",
+            GainFault::TooDim => "TOO DIM",
+        }
+    }
+}
+
+impl AccessoryGain {
+    /// From the loopback pulse measured with the emitter at `emitter_percent`, against
+    /// `reference_amplitude`: what the stock module reads above `dark_level` at full
+    /// intensity, in the same ADC counts
+    pub fn learn(
+        result: &MeasurementResult,
+        dark_level: u16,
+        emitter_percent: u8,
+        reference_amplitude: u16,
+    ) -> Result<Self, GainFault> {
+        if result.clipped {
+            return Err(GainFault::Clipped);
+        }
+        let len = result.sample_buffer.len();
+        let start = len.saturating_sub(result.samples_since_start);
+        let end = len.saturating_sub(result.samples_since_end).max(start);
+
+        // The middle half, well clear of both edges
+        let plateau = start + (end - start) / 4..end - (end - start) / 4;
+        let (sum, count) = result
+            .sample_buffer
+            .oldest_ordered()
+            .skip(plateau.start)
+            .take(plateau.len())
+            .fold((0u64, 0u64), |(sum, count), &value| {
+                (sum + value as u64, count + 1)
+            });
+        let amplitude = (sum / count.max(1)).saturating_sub(dark_level as u64) as u32;
+
+        let gain_percent = amplitude * 100 * 100
+            / (reference_amplitude as u32 * emitter_percent.max(1) as u32).max(1);
+        if gain_percent < MIN_GAIN_PERCENT {
+            return Err(GainFault::TooDim);
+        }
+
+        let level = |fraction: f32| dark_level as f32 + amplitude as f32 * fraction;
+        let middle = (start + end) / 2;
+        let samples = || result.sample_buffer.oldest_ordered().copied();
+        let rise = edge_samples(samples().take(middle), level(0.1), level(0.9), false);
+        let fall = edge_samples(samples().skip(middle), level(0.9), level(0.1), true);
+        let edge = match (rise, fall) {
+            (Some(rise), Some(fall)) => (rise + fall) / 2.0,
+            (Some(edge), None) | (None, Some(edge)) => edge,
+            (None, None) => 0.0,
+        };
+        let sample_nanos = result.sample_interval_nanos as f32 * result.effective_divisor() as f32;
+        let response_time_nanos = if edge < MIN_EDGE_SAMPLES {
+            0
+        } else {
+            (edge * sample_nanos / EDGE_TIME_CONSTANTS).round() as u32
+        };
+
+        Ok(Self {
+            gain_percent: gain_percent.min(u16::MAX as u32) as u16,
+            response_time_nanos,
+        })
+    }
+
+    /// Low enough for the dimmest module worth learning, the emitter is the only light
+    pub fn learning_thresholds(thresholds: TriggerThresholds) -> TriggerThresholds {
+        Self {
+            gain_percent: MIN_GAIN_PERCENT as u16,
+            response_time_nanos: 0,
+        }
+        .scale(thresholds)
+    }
+
+    /// The trigger deltas shrink or grow with the pulse height, the ratios are to the
+    /// dark level which the module sets itself
+    pub fn scale(&self, thresholds: TriggerThresholds) -> TriggerThresholds {
+        let scale = |delta: u16| {
+            (delta as u32 * self.gain_percent as u32 / 100).clamp(1, u16::MAX as u32) as u16
+        };
+        TriggerThresholds {
+            low_delta: scale(thresholds.low_delta),
+            high_delta: scale(thresholds.high_delta),
+            ..thresholds
+        }
+    }
+}
+
+/// Samples between crossing `from` and crossing `to`, interpolated between the samples
+/// on either side of each
+fn edge_samples(
+    samples: impl Iterator<Item = u16> + Clone,
+    from: f32,
+    to: f32,
+    falling: bool,
+) -> Option<f32> {
+    let from = crossing(samples.clone(), from, falling)?;
+    let to = crossing(samples, to, falling)?;
+    (to >= from).then_some(to - from)
+}
+
+fn crossing(samples: impl Iterator<Item = u16>, level: f32, falling: bool) -> Option<f32> {
+    let mut previous: Option<f32> = None;
+    for (index, value) in samples.enumerate() {
+        let value = value as f32;
+        let crossed = if falling {
+            value <= level
+        } else {
+            value >= level
+        };
+        if crossed {
+            return Some(match previous {
+                Some(previous) if previous != value => {
+                    index as f32 - 1.0 + (level - previous) / (value - previous)
+                }
+                _ => index as f32,
+            });
+        }
+        previous = Some(value);
+    }
+    None
+}
@@ -0,0 +1,113 @@
+/// ST7735S frame memory, the visible window has to fit inside it
+pub const DISPLAY_FRAME_WIDTH: u16 = 132;
+pub const DISPLAY_FRAME_HEIGHT: u16 = 162;
+/// Smaller than this, the screens stop fitting
+const MIN_SIZE: u16 = 64;
+/// Offsets get six bits of the register
+const MAX_OFFSET: u16 = 0x3f;
+/// Tells a stored geometry apart from an empty or foreign register
+const TAG: u32 = 0xd << 28;
+const TAG_MASK: u32 = 0xf << 28;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DisplayGeometryField {
+    OffsetX,
+    OffsetY,
+    Width,
+    Height,
+}
+
+impl DisplayGeometryField {
+    pub const ALL: [DisplayGeometryField; 4] = [
+        DisplayGeometryField::OffsetX,
+        DisplayGeometryField::OffsetY,
+        DisplayGeometryField::Width,
+        DisplayGeometryField::Height,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            DisplayGeometryField::OffsetX => "OFFSET X",
+            DisplayGeometryField::OffsetY => "OFFSET Y",
+            DisplayGeometryField::Width => "WIDTH",
+            DisplayGeometryField::Height => "HEIGHT",
+        }
+    }
+
+    pub fn next(&self) -> Self {
+        let index = Self::ALL.iter().position(|f| f == self).unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+}
+
+/// Visible window of the panel within the controller's frame memory,
+/// off-spec modules show garbage columns without adjusting it
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DisplayGeometry {
+    pub offset_x: u16,
+    pub offset_y: u16,
+    pub width: u16,
+    pub height: u16,
+}
+
+impl DisplayGeometry {
+    pub fn get(&self, field: DisplayGeometryField) -> u16 {
+        match field {
+            DisplayGeometryField::OffsetX => self.offset_x,
+            DisplayGeometryField::OffsetY => self.offset_y,
+            DisplayGeometryField::Width => self.width,
+            DisplayGeometryField::Height => self.height,
+        }
+    }
+
+    /// Clamped so that the window stays inside the frame memory
+    pub fn adjust(&mut self, field: DisplayGeometryField, delta: isize) {
+        let nudge = |value: u16, max: u16| (value as isize + delta).clamp(0, max as isize) as u16;
+        match field {
+            DisplayGeometryField::OffsetX => {
+                self.offset_x = nudge(
+                    self.offset_x,
+                    MAX_OFFSET.min(DISPLAY_FRAME_WIDTH - self.width),
+                )
+            }
+            DisplayGeometryField::OffsetY => {
+                self.offset_y = nudge(
+                    self.offset_y,
+                    MAX_OFFSET.min(DISPLAY_FRAME_HEIGHT - self.height),
+                )
+            }
+            DisplayGeometryField::Width => {
+                self.width = nudge(self.width, DISPLAY_FRAME_WIDTH - self.offset_x).max(MIN_SIZE)
+            }
+            DisplayGeometryField::Height => {
+                self.height = nudge(self.height, DISPLAY_FRAME_HEIGHT - self.offset_y).max(MIN_SIZE)
+            }
+        }
+    }
+
+    /// Packs into a single backup register
+    pub fn encode(&self) -> u32 {
+        TAG | (self.offset_x.min(MAX_OFFSET) as u32) << 22
+            | (self.offset_y.min(MAX_OFFSET) as u32) << 16
+            | (self.width as u32 & 0xff) << 8
+            | self.height as u32 & 0xff
+    }
+
+    /// `None` for anything that doesn't fit the frame memory
+    pub fn decode(word: u32) -> Option<Self> {
+        if word & TAG_MASK != TAG {
+            return None;
+        }
+        let geometry = Self {
+            offset_x: (word >> 22 & 0x3f) as u16,
+            offset_y: (word >> 16 & 0x3f) as u16,
+            width: (word >> 8 & 0xff) as u16,
+            height: (word & 0xff) as u16,
+        };
+        let fits = geometry.width >= MIN_SIZE
+            && geometry.height >= MIN_SIZE
+            && geometry.offset_x + geometry.width <= DISPLAY_FRAME_WIDTH
+            && geometry.offset_y + geometry.height <= DISPLAY_FRAME_HEIGHT;
+        fits.then_some(geometry)
+    }
+}
@@ -0,0 +1,30 @@
+use heapless::HistoryBuffer;
+
+use crate::{Annotation, CompressedTrace, MeasurementResult};
+
+pub const HISTORY_LEN: usize = 32;
+/// Only the latest entries keep their sample buffer, compressed and in RAM
+pub const TRACE_HISTORY_LEN: usize = 4;
+
+/// Compact record of a finished measurement, without the sample buffer
+#[derive(Clone, Copy, Debug)]
+pub struct HistoryEntry {
+    pub annotation: Annotation,
+    pub duration_micros: u64,
+    pub integrated_duration_micros: u64,
+    pub uncertainty_micros: u64,
+}
+
+impl HistoryEntry {
+    pub fn new(result: &MeasurementResult, annotation: Annotation) -> Self {
+        Self {
+            annotation,
+            duration_micros: result.duration_micros,
+            integrated_duration_micros: result.integrated_duration_micros,
+            uncertainty_micros: result.uncertainty_micros(),
+        }
+    }
+}
+
+pub type History = HistoryBuffer<HistoryEntry, HISTORY_LEN>;
+pub type TraceHistory = HistoryBuffer<CompressedTrace, TRACE_HISTORY_LEN>;
@@ -0,0 +1,286 @@
+/// Level changes closer together than this are contact bounce
+const DEBOUNCE_MS: u32 = 50;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ButtonGesture {
+    Short,
+    /// Fires while still held, the release that follows is ignored
+    Long,
+    Double,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ButtonTimings {
+    pub long_press_ms: u32,
+    /// Window for the second press, 0 turns double presses off so short
+    /// presses fire right on release
+    pub double_press_ms: u32,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum ButtonState {
+    Idle,
+    Held {
+        since_ms: u32,
+        consumed: bool,
+    },
+    /// A short press that might still turn into a double press
+    Released {
+        at_ms: u32,
+    },
+}
+
+/// Recognizes gestures on a single push button from its level over time.
+/// Needs to be polled while not idle, gestures are decided by timeouts too.
+#[derive(Clone, Debug)]
+pub struct ButtonInput {
+    timings: ButtonTimings,
+    state: ButtonState,
+    last_change_ms: Option<u32>,
+}
+
+impl ButtonInput {
+    pub fn new(timings: ButtonTimings) -> Self {
+        Self {
+            timings,
+            state: ButtonState::Idle,
+            last_change_ms: None,
+        }
+    }
+
+    /// Takes effect from the next press
+    pub fn set_timings(&mut self, timings: ButtonTimings) {
+        self.timings = timings;
+    }
+
+    /// Both on edges and when polled, `pressed` is the current pin level
+    pub fn update(&mut self, pressed: bool, now_ms: u32) -> Option<ButtonGesture> {
+        let was_pressed = matches!(self.state, ButtonState::Held { .. });
+        let settled = self
+            .last_change_ms
+            .is_none_or(|at_ms| now_ms.wrapping_sub(at_ms) >= DEBOUNCE_MS);
+        if pressed != was_pressed && settled {
+            self.last_change_ms = Some(now_ms);
+            if pressed {
+                self.press(now_ms)
+            } else {
+                self.release(now_ms)
+            }
+        } else {
+            self.poll(now_ms)
+        }
+    }
+
+    fn press(&mut self, now_ms: u32) -> Option<ButtonGesture> {
+        let (consumed, gesture) = match self.state {
+            ButtonState::Released { at_ms }
+                if now_ms.wrapping_sub(at_ms) < self.timings.double_press_ms =>
+            {
+                (true, Some(ButtonGesture::Double))
+            }
+            // Missed the poll, the previous press still counts
+            ButtonState::Released { .. } => (false, Some(ButtonGesture::Short)),
+            _ => (false, None),
+        };
+        self.state = ButtonState::Held {
+            since_ms: now_ms,
+            consumed,
+        };
+        gesture
+    }
+
+    fn release(&mut self, now_ms: u32) -> Option<ButtonGesture> {
+        let ButtonState::Held { since_ms, consumed } = self.state else {
+            return None;
+        };
+        self.state = ButtonState::Idle;
+        if consumed {
+            None
+        } else if now_ms.wrapping_sub(since_ms) >= self.timings.long_press_ms {
+            Some(ButtonGesture::Long)
+        } else if self.timings.double_press_ms == 0 {
+            Some(ButtonGesture::Short)
+        } else {
+            self.state = ButtonState::Released { at_ms: now_ms };
+            None
+        }
+    }
+
+    fn poll(&mut self, now_ms: u32) -> Option<ButtonGesture> {
+        match self.state {
+            ButtonState::Held {
+                since_ms,
+                ref mut consumed,
+            } if !*consumed && now_ms.wrapping_sub(since_ms) >= self.timings.long_press_ms => {
+                *consumed = true;
+                Some(ButtonGesture::Long)
+            }
+            ButtonState::Released { at_ms }
+                if now_ms.wrapping_sub(at_ms) >= self.timings.double_press_ms =>
+            {
+                self.state = ButtonState::Idle;
+                Some(ButtonGesture::Short)
+            }
+            _ => None,
+        }
+    }
+
+    /// Nothing left to time out, no need to poll
+    pub fn is_idle(&self) -> bool {
+        match self.state {
+            ButtonState::Idle => true,
+            ButtonState::Held { consumed, .. } => consumed,
+            ButtonState::Released { .. } => false,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+enum FixtureState {
+    Open,
+    Closed {
+        since_ms: u32,
+    },
+    /// Already started a measurement, waits for the lid to open again
+    Fired,
+}
+
+/// Limit switch of a test stand lid, starts a measurement once the lid has stayed
+/// closed for the settle time. Polled, contact bounce never lasts that long.
+#[derive(Clone, Debug)]
+pub struct FixtureInput {
+    settle_ms: u32,
+    state: FixtureState,
+}
+
+impl FixtureInput {
+    /// A lid that is closed from the start doesn't count, only closing it does
+    pub fn new(settle_ms: u32) -> Self {
+        Self {
+            settle_ms,
+            state: FixtureState::Fired,
+        }
+    }
+
+    pub fn set_settle_ms(&mut self, settle_ms: u32) {
+        self.settle_ms = settle_ms;
+    }
+
+    /// `true` once per closing
+    pub fn update(&mut self, closed: bool, now_ms: u32) -> bool {
+        match self.state {
+            _ if !closed => {
+                self.state = FixtureState::Open;
+                false
+            }
+            FixtureState::Open => {
+                self.state = FixtureState::Closed { since_ms: now_ms };
+                false
+            }
+            FixtureState::Closed { since_ms }
+                if now_ms.wrapping_sub(since_ms) >= self.settle_ms =>
+            {
+                self.state = FixtureState::Fired;
+                true
+            }
+            FixtureState::Closed { .. } | FixtureState::Fired => false,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AccessoryEvent {
+    Connected,
+    Disconnected,
+    /// Dropped out for longer than the debounce time during a measurement and came
+    /// back within the grace period
+    Glitched,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum AccessoryState {
+    Connected,
+    Disconnected,
+    /// The sense level flipped, waiting for it to hold before reporting
+    Changing {
+        connected: bool,
+        since_ms: u32,
+    },
+}
+
+/// Sense line of the accessory connector. Polled, a level has to hold for the debounce
+/// time before it counts, and for the grace period while a measurement is running
+/// so that a jiggled connector doesn't abort it.
+#[derive(Clone, Debug)]
+pub struct AccessoryInput {
+    debounce_ms: u32,
+    grace_ms: u32,
+    state: AccessoryState,
+}
+
+impl AccessoryInput {
+    /// Starts out connected, the app boots into the start screen
+    pub fn new(debounce_ms: u32, grace_ms: u32) -> Self {
+        Self {
+            debounce_ms,
+            grace_ms,
+            state: AccessoryState::Connected,
+        }
+    }
+
+    pub fn is_connected(&self) -> bool {
+        match self.state {
+            AccessoryState::Connected => true,
+            AccessoryState::Disconnected => false,
+            // Still the old level until it settles
+            AccessoryState::Changing { connected, .. } => !connected,
+        }
+    }
+
+    /// `busy` stretches the debounce to the grace period, only for unplugging
+    pub fn update(&mut self, present: bool, busy: bool, now_ms: u32) -> Option<AccessoryEvent> {
+        match self.state {
+            AccessoryState::Changing {
+                connected,
+                since_ms,
+            } if connected == present => {
+                let hold_ms = if !connected && busy {
+                    self.grace_ms
+                } else {
+                    self.debounce_ms
+                };
+                if now_ms.wrapping_sub(since_ms) < hold_ms {
+                    return None;
+                }
+                if connected {
+                    self.state = AccessoryState::Connected;
+                    Some(AccessoryEvent::Connected)
+                } else {
+                    self.state = AccessoryState::Disconnected;
+                    Some(AccessoryEvent::Disconnected)
+                }
+            }
+            // Bounced back before settling
+            AccessoryState::Changing {
+                connected,
+                since_ms,
+            } => {
+                self.state = if connected {
+                    AccessoryState::Disconnected
+                } else {
+                    AccessoryState::Connected
+                };
+                (!connected && busy && now_ms.wrapping_sub(since_ms) >= self.debounce_ms)
+                    .then_some(AccessoryEvent::Glitched)
+            }
+            _ if present != self.is_connected() => {
+                self.state = AccessoryState::Changing {
+                    connected: present,
+                    since_ms: now_ms,
+                };
+                None
+            }
+            AccessoryState::Connected | AccessoryState::Disconnected => None,
+        }
+    }
+}
@@ -1,10 +1,64 @@
 #![no_std]
+// The tests bring std along, its float methods shadow micromath's
+#![cfg_attr(test, allow(unused_imports))]
 
+mod annotation;
+mod calibration;
+mod capture;
+mod compression;
+mod counter;
+mod faults;
+mod focal_plane;
+mod gain;
+mod geometry;
+mod history;
+mod input;
+mod log_queue;
+mod loopback;
 mod measurement;
+mod monitor;
+mod oversampling;
+mod peak;
+mod profile;
+mod profiling;
+mod protocol;
+mod report;
+mod scan;
+mod sequence;
+mod session;
+mod soak;
+mod speed;
+mod tolerance;
+mod triggers;
 pub mod util;
-mod calibration;
+pub use annotation::*;
 pub use calibration::*;
+pub use capture::*;
+pub use compression::*;
+pub use counter::*;
+pub use faults::*;
+pub use focal_plane::*;
+pub use gain::*;
+pub use geometry::*;
+pub use history::*;
+pub use infinity_sampler::SamplingRate;
+pub use input::*;
+pub use log_queue::LogQueue;
+pub use loopback::*;
 pub use measurement::*;
+pub use monitor::*;
+pub use oversampling::Oversampler;
+pub use peak::PeakHold;
+pub use profile::*;
+pub use profiling::*;
+pub use protocol::*;
+pub use report::*;
+pub use scan::*;
+pub use sequence::*;
+pub use session::*;
+pub use soak::*;
+pub use speed::*;
+pub use tolerance::*;
+pub use triggers::*;
 #[cfg(feature = "cortex-m")]
 pub use util::CycleCounterClock;
-pub use infinity_sampler::SamplingRate;
@@ -0,0 +1,74 @@
+use heapless::Deque;
+
+/// Text waiting to go out over the USB console. Bounded, a writer that outruns
+/// the host loses the oldest whole lines instead of stalling.
+pub struct LogQueue<const N: usize> {
+    bytes: Deque<u8, N>,
+    dropped_lines: u32,
+}
+
+impl<const N: usize> LogQueue<N> {
+    pub const fn new() -> Self {
+        Self {
+            bytes: Deque::new(),
+            dropped_lines: 0,
+        }
+    }
+
+    /// Lines longer than the whole queue are cut short
+    pub fn push(&mut self, line: &[u8]) {
+        let line = &line[..line.len().min(N)];
+        while self.free() < line.len() {
+            self.drop_oldest_line();
+        }
+        for &byte in line {
+            // Room was made above
+            let _ = self.bytes.push_back(byte);
+        }
+    }
+
+    /// Moves bytes from the front into `buffer`, returns how many
+    pub fn pop_into(&mut self, buffer: &mut [u8]) -> usize {
+        let mut count = 0;
+        for slot in buffer.iter_mut() {
+            let Some(byte) = self.bytes.pop_front() else {
+                break;
+            };
+            *slot = byte;
+            count += 1;
+        }
+        count
+    }
+
+    /// Lines lost since the last call
+    pub fn take_dropped_lines(&mut self) -> u32 {
+        core::mem::take(&mut self.dropped_lines)
+    }
+
+    pub fn clear(&mut self) {
+        self.bytes.clear();
+    }
+
+    pub fn free(&self) -> usize {
+        N - self.bytes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    fn drop_oldest_line(&mut self) {
+        while let Some(byte) = self.bytes.pop_front() {
+            if byte == b'\n' {
+                break;
+            }
+        }
+        self.dropped_lines += 1;
+    }
+}
+
+impl<const N: usize> Default for LogQueue<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,39 @@
+use crate::{DurationMethod, MeasurementResult, Verdict};
+
+/// The emitter pulsed for a known time and measured back through the sensor,
+/// what the whole pipeline gets wrong on an exposure that is known exactly
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LoopbackCheck {
+    pub commanded_micros: u64,
+    pub measured_micros: u64,
+    pub uncertainty_micros: u64,
+    /// From the emitter switching on to the measurement triggering
+    pub latency_micros: Option<u64>,
+}
+
+impl LoopbackCheck {
+    pub fn new(result: &MeasurementResult, commanded_micros: u64, method: DurationMethod) -> Self {
+        Self {
+            commanded_micros,
+            measured_micros: result.duration_micros_by(method),
+            uncertainty_micros: result.uncertainty_micros(),
+            latency_micros: result.fired_lag_micros,
+        }
+    }
+
+    /// Measured minus commanded
+    pub fn error_micros(&self) -> i64 {
+        self.measured_micros as i64 - self.commanded_micros as i64
+    }
+
+    /// Passes within `tolerance_percent` of the commanded time, give or take a sample
+    pub fn verdict(&self, tolerance_percent: u8) -> Verdict {
+        let allowed =
+            self.commanded_micros * tolerance_percent as u64 / 100 + self.uncertainty_micros;
+        if self.error_micros().unsigned_abs() <= allowed {
+            Verdict::Pass
+        } else {
+            Verdict::Fail
+        }
+    }
+}
@@ -1,14 +1,27 @@
-use heapless::HistoryBuffer;
+use heapless::{HistoryBuffer, Vec};
 use infinity_sampler::{SamplingOutcome, SamplingRate, SamplingReservoir};
+use micromath::F32Ext;
 
 use crate::calibration::TriggerThresholds;
+use crate::triggers::{TriggerChannels, LIGHT_CHANNEL, SYNC_CHANNEL, TRIGGER_CHANNELS};
 use crate::util::{HistoryBufferDoubleEndedIterator, LaxDuration, LaxMonotonic};
 use crate::CalibrationResult;
 
-const MARGIN_SAMPLES: usize = 100;
+pub(crate) const MARGIN_SAMPLES: usize = 100;
+// Upper bound on how long a second pulse can hold the measurement open
+const SECOND_PULSE_TIMEOUT_MICROS: u64 = 1_000_000;
+/// Samples the reservoir may see while the shutter is open, well short of its 32 bit
+/// counters wrapping. About three hours at the full sample rate.
+pub const MAX_EXPOSURE_SAMPLES: usize = 1 << 30;
 pub const SAMPLING_BUFFER_LEN: usize = 512;
 pub const SAMPLING_BUFFER_LEN_WITH_MARGINS: usize = SAMPLING_BUFFER_LEN + 2 * MARGIN_SAMPLES;
 pub type ResultBuffer = HistoryBuffer<u16, SAMPLING_BUFFER_LEN_WITH_MARGINS>;
+/// Pulses told apart in a burst, the rest of the buffer is left unsegmented
+pub const MAX_SEGMENTS: usize = 8;
+/// Samples per [`MeasurementObserver::on_sample_block`] call
+pub const OBSERVER_BLOCK_LEN: usize = 32;
+/// Kept of a burst for the result, see [`Measurement::merge_burst`]
+pub const BURST_TRACE_LEN: usize = 256;
 
 #[derive(Clone)]
 pub struct SamplingBuffer<const LEN: usize> {
@@ -60,20 +73,340 @@ impl<const LEN: usize> SamplingBuffer<LEN> {
     }
 }
 
+/// Another pulse that started after the measured one had ended,
+/// e.g. a capping curtain letting light through
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SecondPulse {
+    /// Between the end of the first pulse and the start of this one
+    pub gap_micros: u64,
+    pub duration_micros: u64,
+}
+
+/// Conversions taken back to back right after the shutter opened, far closer together
+/// than the measurement's own samples
+#[derive(Clone)]
+pub struct BurstTrace {
+    pub samples: HistoryBuffer<u16, BURST_TRACE_LEN>,
+    pub sample_interval_nanos: u32,
+    /// Sample the pulse ended at, `None` if it outlasted the burst
+    pub end_index: Option<usize>,
+}
+
+impl BurstTrace {
+    /// Counted from the newest sample like [`MeasurementResult::samples_since_end`]
+    pub fn samples_since_end(&self) -> Option<usize> {
+        self.end_index.map(|index| self.samples.len() - index)
+    }
+}
+
+/// How the exposure time is read off the light pulse, standards differ
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DurationMethod {
+    /// Between the trigger crossings
+    Threshold,
+    /// Full width at half of the peak above the dark level
+    HalfPeak,
+    /// Area above trigger low over the peak height
+    #[default]
+    Integral,
+}
+
+impl DurationMethod {
+    pub const ALL: [DurationMethod; 3] = [
+        DurationMethod::Threshold,
+        DurationMethod::HalfPeak,
+        DurationMethod::Integral,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            DurationMethod::Threshold => "EDGE",
+            DurationMethod::HalfPeak => "50%",
+            DurationMethod::Integral => "AREA",
+        }
+    }
+
+    pub fn next(&self) -> Self {
+        let index = Self::ALL.iter().position(|m| m == self).unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+}
+
 #[derive(Clone)]
 pub struct MeasurementResult {
+    /// Between the trigger crossings
     pub duration_micros: u64,
     pub integrated_duration_micros: u64,
+    pub half_peak_duration_micros: u64,
     pub sample_buffer: ResultBuffer,
     pub samples_since_start: usize,
     pub samples_since_end: usize,
     pub sample_rate: SamplingRate,
+    /// ADC conversions averaged into each sample
+    pub oversampling: u32,
+    pub second_pulse: Option<SecondPulse>,
+    /// From the sync input pulse to the shutter opening
+    pub release_lag_micros: Option<u64>,
+    /// From the shutter opening to the sync input pulse, negative when the pulse came first
+    pub sync_offset_micros: Option<i64>,
+    /// From the release output firing to the shutter opening
+    pub fired_lag_micros: Option<u64>,
+    /// Between two ADC conversions, 0 if unknown
+    pub sample_interval_nanos: u32,
+    /// The ADC saturated while the shutter was open, the integrated duration reads short
+    pub clipped: bool,
+    /// See [`Measurement::merge_burst`]
+    pub burst: Option<BurstTrace>,
+}
+
+impl MeasurementResult {
+    pub fn duration_micros_by(&self, method: DurationMethod) -> u64 {
+        match method {
+            DurationMethod::Threshold => self.duration_micros,
+            DurationMethod::HalfPeak => self.half_peak_duration_micros,
+            DurationMethod::Integral => self.integrated_duration_micros,
+        }
+    }
+
+    /// Number of ADC conversions per stored sample
+    pub fn effective_divisor(&self) -> u32 {
+        self.sample_rate.divisor().saturating_mul(self.oversampling)
+    }
+
+    /// Quantization error of the duration, one stored sample either way. Half of one
+    /// and a burst's interval when the burst timed the closing.
+    pub fn uncertainty_micros(&self) -> u64 {
+        let sample_nanos =
+            (self.sample_interval_nanos as u64).saturating_mul(self.effective_divisor() as u64);
+        match self
+            .burst
+            .as_ref()
+            .filter(|burst| burst.end_index.is_some())
+        {
+            Some(burst) => (sample_nanos / 2 + burst.sample_interval_nanos as u64).div_ceil(1000),
+            None => sample_nanos.div_ceil(1000),
+        }
+    }
+
+    /// Open/close pairs in the sample buffer, split at half of the measured pulse's peak
+    /// so that frames of a burst show up next to the measured one
+    pub fn segments(&self) -> Vec<PulseSegment, MAX_SEGMENTS> {
+        let mut segments = Vec::new();
+        let len = self.sample_buffer.len();
+        let start = len.saturating_sub(self.samples_since_start);
+        let end = len.saturating_sub(self.samples_since_end).max(start);
+
+        let floor = self
+            .sample_buffer
+            .oldest_ordered()
+            .copied()
+            .min()
+            .unwrap_or(0);
+        let peak = self
+            .sample_buffer
+            .oldest_ordered()
+            .skip(start)
+            .take(end - start)
+            .copied()
+            .max()
+            .unwrap_or(floor);
+        if peak <= floor {
+            return segments;
+        }
+        let open_level = floor + (peak - floor) / 2;
+        // Some hysteresis so that noise on the edges doesn't split a pulse
+        let close_level = open_level - (peak - floor) / 8;
+
+        let sample_nanos = self.sample_interval_nanos as u64 * self.effective_divisor() as u64;
+        let mut opened_at = None;
+        for (index, &value) in self.sample_buffer.oldest_ordered().enumerate() {
+            match opened_at {
+                None if value > open_level => opened_at = Some(index),
+                Some(opened) if value < close_level => {
+                    opened_at = None;
+                    if segments
+                        .push(PulseSegment::new(opened, index, sample_nanos))
+                        .is_err()
+                    {
+                        return segments;
+                    }
+                }
+                _ => (),
+            }
+        }
+        // Still open at the end of the buffer
+        if let Some(opened) = opened_at {
+            let _ = segments.push(PulseSegment::new(opened, len, sample_nanos));
+        }
+        segments
+    }
+
+    /// Between the dark level and the measured pulse's peak, interpolated between samples.
+    /// `None` if the sample interval is unknown or either edge runs off the buffer.
+    pub fn edge_times(&self) -> Option<EdgeTimes> {
+        if self.sample_interval_nanos == 0 {
+            return None;
+        }
+        let (older, newer) = self.sample_buffer.as_slices();
+        let at = |index: usize| {
+            older
+                .get(index)
+                .copied()
+                .unwrap_or_else(|| newer[index - older.len()]) as f32
+        };
+        let len = self.sample_buffer.len();
+        let start = len.saturating_sub(self.samples_since_start);
+        let end = len.saturating_sub(self.samples_since_end).max(start);
+
+        let floor = self.sample_buffer.oldest_ordered().copied().min()?;
+        let peak = (start..end).map(at).fold(floor as f32, f32::max);
+        let span = peak - floor as f32;
+        if span <= 0.0 {
+            return None;
+        }
+        let low = floor as f32 + span * 0.1;
+        let high = floor as f32 + span * 0.9;
+
+        let first_high = (start..end).find(|&i| at(i) >= high)?;
+        let last_high = (start..end).rev().find(|&i| at(i) >= high)?;
+        // Out from the plateau to where the light was still dark
+        let rise_from = (0..first_high).rev().find(|&i| at(i) < low)?;
+        let rise_to = (rise_from + 1..len).find(|&i| at(i) >= high)?;
+        let fall_to = (last_high + 1..len).find(|&i| at(i) < low)?;
+        let fall_from = (0..fall_to).rev().find(|&i| at(i) >= high)?;
+
+        // Where `level` falls between the sample at `index` and the next one
+        let crossing = |index: usize, level: f32| {
+            let (a, b) = (at(index), at(index + 1));
+            index as f32 + (level - a) / (b - a)
+        };
+        let sample_micros =
+            self.sample_interval_nanos as f32 * self.effective_divisor() as f32 / 1000.0;
+        let micros = |samples: f32| (samples * sample_micros + 0.5) as u64;
+        Some(EdgeTimes {
+            rise_micros: micros(crossing(rise_to - 1, high) - crossing(rise_from, low)),
+            fall_micros: micros(crossing(fall_to - 1, low) - crossing(fall_from, high)),
+        })
+    }
+}
+
+/// Time the light took to go from 10% to 90% of the pulse's height and back, the
+/// curtains or blades speeding across the sensor
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EdgeTimes {
+    pub rise_micros: u64,
+    pub fall_micros: u64,
+}
+
+/// A stretch of the sample buffer the shutter was open for
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PulseSegment {
+    /// Buffer indices, oldest sample first
+    pub start: usize,
+    pub end: usize,
+    /// 0 if the sample interval is unknown
+    pub duration_micros: u64,
+}
+
+impl PulseSegment {
+    fn new(start: usize, end: usize, sample_nanos: u64) -> Self {
+        Self {
+            start,
+            end,
+            duration_micros: ((end - start) as u64 * sample_nanos + 500) / 1000,
+        }
+    }
+
+    pub fn contains(&self, index: usize) -> bool {
+        (self.start..self.end).contains(&index)
+    }
+}
+
+/// Hears about a measurement as it goes, without locking it. Called from
+/// [`Measurement::step_observed`] in the sampling interrupt, so keep it short.
+pub trait MeasurementObserver<M: LaxMonotonic> {
+    /// The light crossed trigger high
+    fn on_trigger(&mut self, _at: M::Instant) {}
+
+    /// Every stepped sample in order, [`OBSERVER_BLOCK_LEN`] at a time
+    fn on_sample_block(&mut self, _samples: &[u16]) {}
+
+    /// Once per measurement, after the last samples
+    fn on_done(&mut self, _result: &MeasurementResult) {}
+}
+
+/// For [`Measurement::step`]
+pub struct NoObserver;
+
+impl<M: LaxMonotonic> MeasurementObserver<M> for NoObserver {}
+
+/// How a [`Measurement`] samples and triggers, kept through [`Measurement::reset`]
+pub struct MeasurementSetup<M: LaxMonotonic> {
+    /// See [`Measurement::with_oversampling`]
+    pub oversampling: u32,
+    /// See [`Measurement::with_sample_rate`], 0 when unknown
+    pub sample_interval_nanos: u32,
+    /// See [`Measurement::with_sync`]
+    pub wait_for_sync: bool,
+    /// See [`Measurement::with_release`]
+    pub wait_for_release: bool,
+    /// See [`Measurement::with_saturation_level`]
+    pub saturation_level: u16,
+    /// Dark level for [`Measurement::with_auto_trigger_low`]
+    pub auto_trigger_low_from: Option<u16>,
+    /// For [`Measurement::with_response_time`], 0 when off
+    pub response_time_nanos: u32,
+    /// See [`Measurement::with_latency`]
+    pub latency: Option<M::Duration>,
+    /// See [`Measurement::with_burst_capture`]
+    pub burst_capture: bool,
+    /// See [`Measurement::with_max_exposure_samples`]
+    pub max_exposure_samples: usize,
+}
+
+impl<M: LaxMonotonic> Default for MeasurementSetup<M> {
+    fn default() -> Self {
+        Self {
+            oversampling: 1,
+            sample_interval_nanos: 0,
+            wait_for_sync: false,
+            wait_for_release: false,
+            saturation_level: u16::MAX,
+            auto_trigger_low_from: None,
+            response_time_nanos: 0,
+            latency: None,
+            burst_capture: false,
+            max_exposure_samples: MAX_EXPOSURE_SAMPLES,
+        }
+    }
+}
+
+impl<M: LaxMonotonic> Clone for MeasurementSetup<M> {
+    fn clone(&self) -> Self {
+        *self
+    }
 }
 
+impl<M: LaxMonotonic> Copy for MeasurementSetup<M> {}
+
 pub struct Measurement<M: LaxMonotonic> {
     head_buffer: HistoryBuffer<u16, MARGIN_SAMPLES>,
     tail_buffer: HistoryBuffer<u16, MARGIN_SAMPLES>,
     sampling_buffer: SamplingReservoir<u16, SAMPLING_BUFFER_LEN>,
+    setup: MeasurementSetup<M>,
+    triggers: TriggerChannels<M, TRIGGER_CHANNELS>,
+    release_lag_micros: Option<u64>,
+    /// See [`Self::mark_release`], kept when a pulse is invalidated
+    released_at: Option<M::Instant>,
+    fired_lag_micros: Option<u64>,
+    clipped: bool,
+    burst: Option<BurstTrace>,
+    dark_level: u16,
+    /// What [`Self::reset`] goes back to waiting for
+    trigger_high: u16,
+    trigger_low: u16,
+    /// Samples not yet handed to the observer
+    observer_block: Vec<u16, OBSERVER_BLOCK_LEN>,
     state: MeasurementState<M>,
 }
 
@@ -90,6 +423,9 @@ pub enum MeasurementState<M: LaxMonotonic> {
         trigger_low: u16,
         head_buffer_samples: usize,
         samples_since_trigger: usize,
+        trigger_high: u16,
+        /// Same as `trigger_low` unless raised by [`Measurement::with_auto_trigger_low`]
+        end_level: u16,
     },
     Trailing {
         head_buffer_samples: usize,
@@ -97,19 +433,54 @@ pub enum MeasurementState<M: LaxMonotonic> {
         samples_since_end: usize,
         duration_micros: u64,
         integrated_duration_micros: u64,
+        half_peak_duration_micros: u64,
+        ended_at: M::Instant,
+        trigger_high: u16,
+        trigger_low: u16,
+        second_pulse_since: Option<M::Instant>,
+        second_pulse: Option<SecondPulse>,
     },
     Done(MeasurementResult),
+    /// Gave up on an exposure the integral can't hold, see [`Measurement::with_max_exposure_samples`]
+    TooLong {
+        /// How long the shutter had been open by then
+        duration_micros: u64,
+    },
+}
+
+/// Coarse view of [`MeasurementState`] for showing progress
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MeasurementPhase {
+    /// Waiting for the light to cross the trigger level
+    Armed,
+    Exposing,
+    /// Shutter closed, still sampling the tail
+    Finishing,
+    Done,
 }
 
 impl<M: LaxMonotonic> Measurement<M> {
     pub fn new(calibration: CalibrationResult, trigger_thresholds: TriggerThresholds) -> Self {
+        let trigger_high = trigger_thresholds.trigger_high(&calibration);
+        let trigger_low = trigger_thresholds.trigger_low(&calibration);
         Self {
             head_buffer: HistoryBuffer::new(),
             tail_buffer: HistoryBuffer::new(),
             sampling_buffer: SamplingReservoir::new(),
+            setup: MeasurementSetup::default(),
+            triggers: TriggerChannels::new(),
+            release_lag_micros: None,
+            released_at: None,
+            fired_lag_micros: None,
+            clipped: false,
+            burst: None,
+            dark_level: calibration.average,
+            trigger_high,
+            trigger_low,
+            observer_block: Vec::new(),
             state: MeasurementState::Idle {
-                trigger_low: trigger_thresholds.trigger_low(&calibration),
-                trigger_high: trigger_thresholds.trigger_high(&calibration),
+                trigger_high,
+                trigger_low,
             },
         }
     }
@@ -119,22 +490,269 @@ impl<M: LaxMonotonic> Measurement<M> {
             head_buffer: HistoryBuffer::new(),
             tail_buffer: HistoryBuffer::new(),
             sampling_buffer: SamplingReservoir::new(),
+            setup: MeasurementSetup::default(),
+            triggers: TriggerChannels::new(),
+            release_lag_micros: None,
+            released_at: None,
+            fired_lag_micros: None,
+            clipped: false,
+            burst: None,
+            dark_level: 0,
+            // Never triggers until rearmed
+            trigger_high: u16::MAX,
+            trigger_low: u16::MAX,
+            observer_block: Vec::new(),
             state: MeasurementState::Done(MeasurementResult {
                 sample_buffer: HistoryBuffer::new(),
                 duration_micros: ms as u64 * 1000,
                 integrated_duration_micros: ms as u64 * 1000,
+                half_peak_duration_micros: ms as u64 * 1000,
                 samples_since_start: 0,
                 samples_since_end: 0,
                 sample_rate: SamplingRate::new(1),
+                oversampling: 1,
+                second_pulse: None,
+                release_lag_micros: None,
+                sync_offset_micros: None,
+                fired_lag_micros: None,
+                sample_interval_nanos: 0,
+                clipped: false,
+                burst: None,
             }),
         }
     }
 
+    /// Wraps a result obtained elsewhere, e.g. from the input capture timer
+    pub fn from_result(result: MeasurementResult) -> Self {
+        Self {
+            head_buffer: HistoryBuffer::new(),
+            tail_buffer: HistoryBuffer::new(),
+            sampling_buffer: SamplingReservoir::new(),
+            setup: MeasurementSetup {
+                oversampling: result.oversampling,
+                sample_interval_nanos: result.sample_interval_nanos,
+                ..MeasurementSetup::default()
+            },
+            triggers: TriggerChannels::new(),
+            release_lag_micros: result.release_lag_micros,
+            released_at: None,
+            fired_lag_micros: result.fired_lag_micros,
+            clipped: result.clipped,
+            burst: None,
+            dark_level: 0,
+            trigger_high: u16::MAX,
+            trigger_low: u16::MAX,
+            observer_block: Vec::new(),
+            state: MeasurementState::Done(result),
+        }
+    }
+
+    /// Tells the measurement that every step receives an average of `factor` conversions
+    pub fn with_oversampling(mut self, factor: u32) -> Self {
+        self.setup.oversampling = factor;
+        self
+    }
+
+    /// ADC conversion rate before oversampling, used to report the uncertainty
+    pub fn with_sample_rate(mut self, sample_rate_hz: u32) -> Self {
+        self.setup.sample_interval_nanos = 1_000_000_000 / sample_rate_hz;
+        self
+    }
+
+    /// Samples at or above `level` during the open phase mark the result as clipped
+    pub fn with_saturation_level(mut self, level: u16) -> Self {
+        self.setup.saturation_level = level;
+        self
+    }
+
+    /// Once the shutter opens, ends the pulse no lower than half way between `dark_level` and
+    /// the peak so far, so that dim or slowly closing pulses don't linger above trigger low.
+    /// The integrated duration still uses trigger low.
+    pub fn with_auto_trigger_low(mut self, dark_level: u16) -> Self {
+        self.setup.auto_trigger_low_from = Some(dark_level);
+        self
+    }
+
+    /// Time constant of the sensor's first order response. Short pulses that never
+    /// reach their full level read long when integrated, this takes the smear back out.
+    pub fn with_response_time(mut self, time_constant_nanos: u32) -> Self {
+        self.setup.response_time_nanos = time_constant_nanos;
+        self
+    }
+
+    /// Time from the light reaching the sensor to its sample being stepped. Taken off
+    /// the light edges so that they line up with the sync input, durations don't change.
+    pub fn with_latency(mut self, latency: M::Duration) -> Self {
+        self.setup.latency = Some(latency);
+        self
+    }
+
+    /// Asks for a burst of conversions as the shutter opens, for the sampler to take and
+    /// [`Self::merge_burst`] to put in
+    pub fn with_burst_capture(mut self) -> Self {
+        self.setup.burst_capture = true;
+        self
+    }
+
+    /// Gives up on the exposure sooner than [`MAX_EXPOSURE_SAMPLES`], never later
+    pub fn with_max_exposure_samples(mut self, samples: usize) -> Self {
+        self.setup.max_exposure_samples = samples.min(MAX_EXPOSURE_SAMPLES);
+        self
+    }
+
+    /// Ignores light until [`Self::mark_sync`] is called, the result then carries the release lag
+    pub fn with_sync(mut self) -> Self {
+        self.setup.wait_for_sync = true;
+        self
+    }
+
+    /// Timestamps the sync input, armed alongside the light sensor until the result is
+    /// in. Only the first pulse counts.
+    pub fn mark_sync(&mut self) {
+        if !self.is_done() {
+            self.triggers.mark(SYNC_CHANNEL, M::now());
+        }
+    }
+
+    pub fn is_waiting_for_sync(&self) -> bool {
+        self.setup.wait_for_sync && self.triggers.triggered_at(SYNC_CHANNEL).is_none()
+    }
+
+    /// Ignores light until [`Self::mark_release`] is called, the result then carries the
+    /// lag from firing the release
+    pub fn with_release(mut self) -> Self {
+        self.setup.wait_for_release = true;
+        self
+    }
+
+    pub fn is_waiting_for_release(&self) -> bool {
+        self.setup.wait_for_release && self.released_at.is_none()
+    }
+
+    /// When the release output fired, only the first call counts
+    pub fn mark_release(&mut self, at: M::Instant) {
+        if !self.is_done() && self.released_at.is_none() {
+            self.released_at = Some(at);
+        }
+    }
+
+    /// When the light first crossed the trigger level
+    pub fn opened_at(&self) -> Option<M::Instant> {
+        self.triggers.triggered_at(LIGHT_CHANNEL)
+    }
+
     pub fn is_done(&self) -> bool {
-        matches!(self.state, MeasurementState::Done { .. })
+        matches!(
+            self.state,
+            MeasurementState::Done { .. } | MeasurementState::TooLong { .. }
+        )
+    }
+
+    /// Done without a result, how long the shutter had been open when it gave up
+    pub fn too_long_micros(&self) -> Option<u64> {
+        match self.state {
+            MeasurementState::TooLong { duration_micros } => Some(duration_micros),
+            _ => None,
+        }
+    }
+
+    /// Between the shutter opening and the result being ready
+    pub fn is_running(&self) -> bool {
+        matches!(
+            self.state,
+            MeasurementState::Measuring { .. } | MeasurementState::Trailing { .. }
+        )
+    }
+
+    pub fn phase(&self) -> MeasurementPhase {
+        match self.state {
+            MeasurementState::Idle { .. } => MeasurementPhase::Armed,
+            MeasurementState::Measuring { .. } => MeasurementPhase::Exposing,
+            MeasurementState::Trailing { .. } => MeasurementPhase::Finishing,
+            MeasurementState::Done(_) | MeasurementState::TooLong { .. } => MeasurementPhase::Done,
+        }
     }
 
     pub fn step(&mut self, value: u16) {
+        self.step_observed(value, &mut NoObserver);
+    }
+
+    /// [`Self::step`], telling `observer` what happened
+    pub fn step_observed(&mut self, value: u16, observer: &mut impl MeasurementObserver<M>) {
+        let latency = self.setup.latency;
+        // When the light behind `value` arrived
+        self.step_sampled_at(
+            value,
+            || latency.map_or_else(M::now, |latency| M::now() - latency),
+            observer,
+        );
+    }
+
+    /// Steps through a burst of conversions `interval_nanos` apart, the first of them
+    /// sampled at `started_at`, that held the samples up while the shutter was open. One
+    /// per sample interval is stepped like any other, and the first one under the end level
+    /// on top so that the pulse ends to within the burst's interval. The burst itself
+    /// goes into the result.
+    pub fn merge_burst(
+        &mut self,
+        samples: &[u16],
+        started_at: M::Instant,
+        interval_nanos: u32,
+        observer: &mut impl MeasurementObserver<M>,
+    ) {
+        if !matches!(self.state, MeasurementState::Measuring { .. }) || interval_nanos == 0 {
+            return;
+        }
+        let sample_nanos = self
+            .setup
+            .sample_interval_nanos
+            .saturating_mul(self.setup.oversampling);
+        let stride = (sample_nanos / interval_nanos).max(1) as usize;
+        // The shutter opened somewhere in the sample before the trigger. Once the closing
+        // is timed finer than that, the middle of it is the better guess.
+        let half_sample = M::Duration::from_nanos(sample_nanos as u64 / 2);
+        if let MeasurementState::Measuring { since, .. } = &mut self.state {
+            *since = *since - half_sample;
+        }
+        let mut trace = BurstTrace {
+            samples: HistoryBuffer::new(),
+            sample_interval_nanos: interval_nanos,
+            end_index: None,
+        };
+        for (index, &value) in samples.iter().take(BURST_TRACE_LEN).enumerate() {
+            trace.samples.write(value);
+            let ends = matches!(
+                self.state,
+                MeasurementState::Measuring { end_level, .. } if value < end_level
+            );
+            if ends {
+                trace.end_index = Some(index);
+            }
+            if ends || index % stride == 0 {
+                let at = started_at + M::Duration::from_nanos(index as u64 * interval_nanos as u64);
+                self.step_sampled_at(value, || at, observer);
+            }
+        }
+        // Still open, the closing will be as coarse as the opening
+        if let MeasurementState::Measuring { since, .. } = &mut self.state {
+            *since = *since + half_sample;
+        }
+        self.burst = Some(trace);
+    }
+
+    fn step_sampled_at(
+        &mut self,
+        value: u16,
+        sampled_at: impl Fn() -> M::Instant,
+        observer: &mut impl MeasurementObserver<M>,
+    ) {
+        // Full blocks go out before the sample that fills the next one
+        if self.observer_block.push(value).is_err() {
+            observer.on_sample_block(&self.observer_block);
+            self.observer_block.clear();
+            let _ = self.observer_block.push(value);
+        }
+
         match &mut self.state {
             MeasurementState::Idle {
                 trigger_high,
@@ -142,8 +760,15 @@ impl<M: LaxMonotonic> Measurement<M> {
             } => {
                 self.head_buffer.write(value);
 
-                if value > *trigger_high {
-                    let now = M::now();
+                let synced_at = self.triggers.triggered_at(SYNC_CHANNEL);
+                let armed = (!self.setup.wait_for_sync || synced_at.is_some())
+                    && (!self.setup.wait_for_release || self.released_at.is_some());
+                if armed && value > *trigger_high {
+                    let now = sampled_at();
+                    self.triggers.mark(LIGHT_CHANNEL, now);
+                    observer.on_trigger(now);
+                    self.release_lag_micros = synced_at.map(|at| (now - at).to_micros());
+                    self.fired_lag_micros = self.released_at.map(|at| (now - at).to_micros());
 
                     let last_index_below_trigger =
                         HistoryBufferDoubleEndedIterator::new(&self.head_buffer)
@@ -168,7 +793,9 @@ impl<M: LaxMonotonic> Measurement<M> {
                         integrated: head_buf_integrated,
                         head_buffer_samples: head_buf_integrated_samples,
                         samples_since_trigger: 0,
+                        trigger_high: *trigger_high,
                         trigger_low: *trigger_low,
+                        end_level: *trigger_low,
                     };
                 }
             }
@@ -178,9 +805,17 @@ impl<M: LaxMonotonic> Measurement<M> {
                 samples_since_trigger,
                 integrated,
                 peak,
+                trigger_high,
                 trigger_low,
+                end_level,
             } => {
                 *peak = (*peak).max(value);
+                if let Some(dark_level) = self.setup.auto_trigger_low_from {
+                    *end_level = (*end_level).max(dark_level + peak.saturating_sub(dark_level) / 2);
+                }
+                if value >= self.setup.saturation_level {
+                    self.clipped = true;
+                }
                 match self.sampling_buffer.sample(value) {
                     SamplingOutcome::Discarded(_) => (),
                     SamplingOutcome::Consumed => {
@@ -196,20 +831,48 @@ impl<M: LaxMonotonic> Measurement<M> {
                     }
                 }
 
-                if value < *trigger_low {
-                    let t_end = M::now();
+                if self.sampling_buffer.samples_seen() >= self.setup.max_exposure_samples {
+                    self.state = MeasurementState::TooLong {
+                        duration_micros: (sampled_at() - *since).to_micros(),
+                    };
+                    return;
+                }
 
-                    // remove area below threshold
-                    let integrated_value_samples =
-                        *integrated - *samples_since_trigger as u64 * *trigger_low as u64;
+                if value < *end_level {
+                    let t_end = sampled_at();
+                    let duration_micros = (t_end - *since).to_micros();
 
-                    // scale Y to 0-1
-                    let integrated_duration_samples =
-                        integrated_value_samples / (*peak - *trigger_low) as u64;
+                    let Some(mut integrated_duration_micros) = integrated_duration_micros(
+                        *integrated,
+                        *samples_since_trigger,
+                        *peak,
+                        *trigger_low,
+                        duration_micros,
+                    ) else {
+                        self.state = MeasurementState::TooLong { duration_micros };
+                        return;
+                    };
+                    if self.setup.response_time_nanos > 0 {
+                        integrated_duration_micros = SensorResponse {
+                            time_constant_micros: self.setup.response_time_nanos as f32 / 1000.0,
+                            peak: peak.saturating_sub(self.dark_level) as f32,
+                            trigger_low: trigger_low.saturating_sub(self.dark_level).max(1) as f32,
+                            end_level: end_level.saturating_sub(self.dark_level).max(1) as f32,
+                        }
+                        .compensate(integrated_duration_micros);
+                    }
 
-                    let duration_micros = (t_end - *since).to_micros();
-                    let integrated_duration_micros = integrated_duration_samples * duration_micros
-                        / *samples_since_trigger as u64;
+                    // The reservoir holds the whole pulse at an even rate, the share of it
+                    // above half the peak is the width there
+                    let half_peak = self.dark_level + peak.saturating_sub(self.dark_level) / 2;
+                    let above_half_peak = self
+                        .sampling_buffer
+                        .ordered_iter()
+                        .filter(|&&value| value >= half_peak)
+                        .count();
+                    let half_peak_duration_micros = (above_half_peak as u64)
+                        .saturating_mul(duration_micros)
+                        / self.sampling_buffer.len().max(1) as u64;
 
                     self.state = MeasurementState::Trailing {
                         duration_micros,
@@ -217,6 +880,12 @@ impl<M: LaxMonotonic> Measurement<M> {
                         head_buffer_samples: *head_buffer_samples,
                         samples_since_end: 0,
                         integrated_duration_micros,
+                        half_peak_duration_micros,
+                        ended_at: t_end,
+                        trigger_high: *trigger_high,
+                        trigger_low: *end_level,
+                        second_pulse_since: None,
+                        second_pulse: None,
                     }
                 }
             }
@@ -226,15 +895,43 @@ impl<M: LaxMonotonic> Measurement<M> {
                 head_buffer_samples,
                 samples_since_end,
                 integrated_duration_micros,
+                half_peak_duration_micros,
+                ended_at,
+                trigger_high,
+                trigger_low,
+                second_pulse_since,
+                second_pulse,
             } => {
-                if tail_sample_rate.step() {
+                // Keep the tail contiguous if a second pulse holds the measurement open
+                if *samples_since_end < MARGIN_SAMPLES && tail_sample_rate.step() {
                     self.tail_buffer.write(value);
                     *samples_since_end += 1;
                 }
 
+                // Only the first extra pulse is reported
+                if second_pulse.is_none() {
+                    match *second_pulse_since {
+                        None if value > *trigger_high => {
+                            *second_pulse_since = Some(sampled_at());
+                        }
+                        Some(since) => {
+                            let elapsed_micros = (sampled_at() - since).to_micros();
+                            if value < *trigger_low || elapsed_micros >= SECOND_PULSE_TIMEOUT_MICROS
+                            {
+                                *second_pulse = Some(SecondPulse {
+                                    gap_micros: (since - *ended_at).to_micros(),
+                                    duration_micros: elapsed_micros,
+                                });
+                                *second_pulse_since = None;
+                            }
+                        }
+                        None => (),
+                    }
+                }
+
                 let sample_rate = self.sampling_buffer.sampling_rate();
 
-                if *samples_since_end >= MARGIN_SAMPLES {
+                if *samples_since_end >= MARGIN_SAMPLES && second_pulse_since.is_none() {
                     let mut iter = self.sampling_buffer.ordered_iter();
 
                     let mut final_buffer = ResultBuffer::new();
@@ -249,19 +946,100 @@ impl<M: LaxMonotonic> Measurement<M> {
                     self.state = MeasurementState::Done(MeasurementResult {
                         duration_micros: *duration_micros,
                         integrated_duration_micros: *integrated_duration_micros,
+                        half_peak_duration_micros: *half_peak_duration_micros,
                         samples_since_start: self.sampling_buffer.len()
                             + self.tail_buffer.len()
                             + *head_buffer_samples,
                         samples_since_end: self.tail_buffer.len(),
                         sample_buffer: final_buffer,
                         sample_rate: sample_rate.clone(),
+                        oversampling: self.setup.oversampling,
+                        second_pulse: *second_pulse,
+                        release_lag_micros: self.release_lag_micros,
+                        sync_offset_micros: self
+                            .triggers
+                            .offset_micros(LIGHT_CHANNEL, SYNC_CHANNEL),
+                        fired_lag_micros: self.fired_lag_micros,
+                        sample_interval_nanos: self.setup.sample_interval_nanos,
+                        clipped: self.clipped,
+                        burst: self.burst.take(),
                     });
+                    if let MeasurementState::Done(result) = &self.state {
+                        observer.on_sample_block(&self.observer_block);
+                        self.observer_block.clear();
+                        observer.on_done(result);
+                    }
                 }
             }
-            MeasurementState::Done { .. } => (),
+            MeasurementState::Done { .. } | MeasurementState::TooLong { .. } => (),
         }
     }
 
+    /// Drops a pulse in progress after samples went missing and waits for the
+    /// next one, `false` when there was nothing to drop
+    pub fn invalidate(&mut self) -> bool {
+        let (trigger_high, trigger_low) = match self.state {
+            MeasurementState::Measuring {
+                trigger_high,
+                trigger_low,
+                ..
+            }
+            | MeasurementState::Trailing {
+                trigger_high,
+                trigger_low,
+                ..
+            } => (trigger_high, trigger_low),
+            _ => return false,
+        };
+        self.clear_pulse();
+        self.state = MeasurementState::Idle {
+            trigger_high,
+            trigger_low,
+        };
+        true
+    }
+
+    /// Drops whatever was measured or is in progress and waits for the next pulse with the
+    /// same calibration and setup. Reuses the buffers, unlike building a new measurement.
+    pub fn reset(&mut self) {
+        self.clear_pulse();
+        self.released_at = None;
+        self.observer_block.clear();
+        self.state = MeasurementState::Idle {
+            trigger_high: self.trigger_high,
+            trigger_low: self.trigger_low,
+        };
+    }
+
+    /// [`Self::reset`] with a new calibration and setup
+    pub fn rearm(
+        &mut self,
+        calibration: &CalibrationResult,
+        trigger_thresholds: TriggerThresholds,
+        setup: MeasurementSetup<M>,
+    ) {
+        self.trigger_high = trigger_thresholds.trigger_high(calibration);
+        self.trigger_low = trigger_thresholds.trigger_low(calibration);
+        self.dark_level = calibration.average;
+        self.setup = setup;
+        self.reset();
+    }
+
+    pub fn setup(&self) -> &MeasurementSetup<M> {
+        &self.setup
+    }
+
+    fn clear_pulse(&mut self) {
+        self.head_buffer.clear();
+        self.tail_buffer.clear();
+        self.sampling_buffer = SamplingReservoir::new();
+        self.triggers.clear();
+        self.release_lag_micros = None;
+        self.fired_lag_micros = None;
+        self.clipped = false;
+        self.burst = None;
+    }
+
     pub fn take_result(self) -> Option<MeasurementResult> {
         match self.state {
             MeasurementState::Done(result) => Some(result),
@@ -276,3 +1054,231 @@ impl<M: LaxMonotonic> Measurement<M> {
         }
     }
 }
+
+/// Area above trigger low over the peak height, spread over the pulse's duration.
+/// `None` rather than a wrapped number when the pulse is too long to work out.
+fn integrated_duration_micros(
+    integrated: u64,
+    samples: usize,
+    peak: u16,
+    trigger_low: u16,
+    duration_micros: u64,
+) -> Option<u64> {
+    // remove area below threshold, the sample that ended the pulse may dip under it
+    let integrated_value_samples =
+        integrated.saturating_sub((samples as u64).checked_mul(trigger_low as u64)?);
+
+    // scale Y to 0-1
+    let integrated_duration_samples =
+        integrated_value_samples.checked_div(peak.saturating_sub(trigger_low) as u64)?;
+
+    integrated_duration_samples
+        .checked_mul(duration_micros)?
+        .checked_div(samples as u64)
+}
+
+/// Smear of a first order sensor, relative to the dark level
+#[derive(Clone, Copy, Debug)]
+struct SensorResponse {
+    time_constant_micros: f32,
+    peak: f32,
+    trigger_low: f32,
+    /// Where integration stops, above trigger low with auto trigger low
+    end_level: f32,
+}
+
+impl SensorResponse {
+    /// What a light pulse of `actual` length integrates to above trigger low,
+    /// relative to the peak it gets to
+    fn integrated_micros(&self, actual: f32) -> f32 {
+        let (tau, peak, low, end) = (
+            self.time_constant_micros,
+            self.peak,
+            self.trigger_low,
+            self.end_level,
+        );
+        // The light level it was heading for when the shutter closed
+        let level = peak / (1.0 - (-actual / tau).exp());
+        let crossed_low_at = -tau * (1.0 - low / level).ln();
+        let rising = (level - low) * (actual - crossed_low_at);
+        let tail_deficit = tau * (end - low) + low * tau * (peak / end).ln();
+        (rising - tail_deficit) / (peak - low)
+    }
+
+    /// Solved by bisection. Long pulses come out about unchanged, much shorter than the
+    /// time constant they barely change the peak and stay rough.
+    fn compensate(&self, integrated_micros: u64) -> u64 {
+        let integrated = integrated_micros as f32;
+        // Slightly longer than what was integrated at most, the edges are cut off at
+        // trigger low
+        let (mut low, mut high) = (0.0, integrated + self.time_constant_micros);
+        for _ in 0..24 {
+            let mid = (low + high) / 2.0;
+            if self.integrated_micros(mid) < integrated {
+                low = mid;
+            } else {
+                high = mid;
+            }
+        }
+        (((low + high) / 2.0) as u64).max(1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use core::cell::Cell;
+
+    use super::*;
+
+    const DARK: u16 = 200;
+    const SAMPLE_MICROS: u64 = 10;
+    // Same as the firmware defaults
+    const THRESHOLDS: TriggerThresholds = TriggerThresholds {
+        low_ratio: 1.0,
+        high_ratio: 1.0,
+        low_delta: 4096 / 32,
+        high_delta: 4096 / 16,
+    };
+
+    std::thread_local! {
+        static NOW_MICROS: Cell<u64> = const { Cell::new(0) };
+    }
+
+    /// Set by the test, per thread so that tests can run side by side
+    struct TestClock;
+
+    impl LaxMonotonic for TestClock {
+        type Instant = fugit::TimerInstantU64<1_000_000>;
+        type Duration = fugit::TimerDurationU64<1_000_000>;
+
+        fn now() -> Self::Instant {
+            Self::Instant::from_ticks(NOW_MICROS.with(Cell::get))
+        }
+    }
+
+    fn measurement() -> Measurement<TestClock> {
+        NOW_MICROS.with(|now| now.set(0));
+        let calibration = CalibrationResult {
+            average: DARK,
+            min: DARK,
+            max: DARK,
+            covered: DARK,
+        };
+        Measurement::new(calibration, THRESHOLDS).with_sample_rate(1_000_000 / SAMPLE_MICROS as u32)
+    }
+
+    /// Steps `level(t)` every `interval` microseconds until the measurement is done
+    fn run(
+        measurement: &mut Measurement<TestClock>,
+        interval: u64,
+        level: impl Fn(f32) -> f32,
+    ) -> Option<MeasurementResult> {
+        let mut t = 0;
+        while !measurement.is_done() {
+            NOW_MICROS.with(|now| now.set(t));
+            measurement.step((DARK as f32 + level(t as f32)) as u16);
+            t += interval;
+        }
+        measurement.result().cloned()
+    }
+
+    /// Light of `height` for `duration` from 1 ms in, seen through a first order
+    /// sensor with time constant `tau`
+    fn rc_pulse(duration: f32, tau: f32, height: f32) -> impl Fn(f32) -> f32 {
+        move |t| {
+            let t = t - 1000.0;
+            if t < 0.0 {
+                0.0
+            } else if t < duration {
+                height * (1.0 - (-t / tau).exp())
+            } else {
+                height * (1.0 - (-duration / tau).exp()) * (-(t - duration) / tau).exp()
+            }
+        }
+    }
+
+    #[test]
+    fn response_time_compensation_recovers_rc_filtered_pulses() {
+        // As long as the time constant or longer, within 3 % or two samples
+        let within_tolerance = |measured: u64, duration: f32| {
+            (measured as f32 - duration).abs() <= (duration * 0.03).max(2.0 * SAMPLE_MICROS as f32)
+        };
+        let mut uncorrected_off = false;
+        for tau in [50.0, 200.0, 1000.0] {
+            for duration in [tau, 2.0 * tau, 1000.0, 5000.0, 20_000.0] {
+                let pulse = || rc_pulse(duration, tau, 2800.0);
+                let uncorrected = run(&mut measurement(), SAMPLE_MICROS, pulse()).unwrap();
+                uncorrected_off |=
+                    !within_tolerance(uncorrected.integrated_duration_micros, duration);
+
+                let mut corrected = measurement().with_response_time((tau * 1000.0) as u32);
+                let corrected = run(&mut corrected, SAMPLE_MICROS, pulse()).unwrap();
+                assert!(
+                    within_tolerance(corrected.integrated_duration_micros, duration),
+                    "tau {} us, {} us pulse integrated to {} us",
+                    tau,
+                    duration,
+                    corrected.integrated_duration_micros
+                );
+            }
+        }
+        // Otherwise the pulses are too easy to tell anything
+        assert!(uncorrected_off);
+    }
+
+    #[test]
+    fn hour_long_pulse_measures() {
+        const HOUR_MICROS: u64 = 3_600_000_000;
+        // A sample every 10 ms keeps it quick, the reservoir thins them out the same way
+        let mut measurement = measurement();
+        let result = run(&mut measurement, 10_000, |t| {
+            if (1000.0..1000.0 + HOUR_MICROS as f32).contains(&t) {
+                2800.0
+            } else {
+                0.0
+            }
+        })
+        .unwrap();
+        assert!(result.duration_micros.abs_diff(HOUR_MICROS) <= 10_000);
+        assert!(result.integrated_duration_micros.abs_diff(HOUR_MICROS) <= HOUR_MICROS / 100);
+    }
+
+    #[test]
+    fn too_long_at_max_exposure_samples() {
+        // Same reservoir thinning as the full limit, without stepping through 2^30 samples
+        const MAX_SAMPLES: usize = 1 << 16;
+        let mut measurement = measurement().with_max_exposure_samples(MAX_SAMPLES);
+        // Triggers, the samples after it count
+        measurement.step(DARK + 2800);
+        for _ in 1..MAX_SAMPLES {
+            measurement.step(DARK + 2800);
+        }
+        assert_eq!(measurement.phase(), MeasurementPhase::Exposing);
+        assert_eq!(measurement.too_long_micros(), None);
+
+        let open_micros = MAX_SAMPLES as u64 * SAMPLE_MICROS;
+        NOW_MICROS.with(|now| now.set(open_micros));
+        measurement.step(DARK + 2800);
+        assert!(measurement.is_done());
+        assert_eq!(measurement.phase(), MeasurementPhase::Done);
+        assert!(measurement.result().is_none());
+        assert_eq!(measurement.too_long_micros(), Some(open_micros));
+    }
+
+    #[test]
+    fn integrated_duration_at_overflow_edge() {
+        // With a peak of 1 over trigger low the integral is the width in samples
+        let half = u64::MAX / 2;
+        assert_eq!(integrated_duration_micros(half, 1, 1, 0, 2), Some(half * 2));
+        assert_eq!(integrated_duration_micros(half, 1, 1, 0, 3), None);
+        // Trigger low over every sample
+        assert_eq!(
+            integrated_duration_micros(u64::MAX, usize::MAX, 3, 2, 1),
+            None
+        );
+        // No height to scale by
+        assert_eq!(integrated_duration_micros(1000, 10, 300, 300, 100), None);
+    }
+}
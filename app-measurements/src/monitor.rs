@@ -0,0 +1,91 @@
+use core::fmt;
+
+/// Frames are sent about this often while monitoring
+pub const MONITOR_INTERVAL_MS: u32 = 50;
+
+/// One line of the live view stream, the numbers the debug screen shows.
+/// Formatted as `MON <level> <noise> <trigger low> <trigger high> <H|L>`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MonitorFrame {
+    /// Average over the frame
+    pub level: u16,
+    pub min: u16,
+    pub max: u16,
+    pub trigger_low: u16,
+    pub trigger_high: u16,
+    /// Above the high trigger and not yet back under the low one
+    pub triggered: bool,
+}
+
+impl MonitorFrame {
+    pub fn noise(&self) -> u16 {
+        (self.max - self.min) / 2
+    }
+}
+
+impl fmt::Display for MonitorFrame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "MON {} {} {} {} {}\r\n",
+            self.level,
+            self.noise(),
+            self.trigger_low,
+            self.trigger_high,
+            if self.triggered { "H" } else { "L" }
+        )
+    }
+}
+
+/// Decimates polled light levels into [`MonitorFrame`]s, with the same
+/// trigger hysteresis as the debug screen
+#[derive(Clone, Debug, Default)]
+pub struct LevelMonitor {
+    sum: u32,
+    count: u32,
+    min: u16,
+    max: u16,
+    trigger_low: u16,
+    trigger_high: u16,
+    triggered: bool,
+}
+
+impl LevelMonitor {
+    pub fn step(&mut self, value: u16, trigger_low: u16, trigger_high: u16) {
+        if self.count == 0 {
+            self.min = value;
+            self.max = value;
+        }
+        self.sum += value as u32;
+        self.count += 1;
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+        self.trigger_low = trigger_low;
+        self.trigger_high = trigger_high;
+
+        if !self.triggered && value > trigger_high {
+            self.triggered = true;
+        }
+        if self.triggered && value < trigger_low {
+            self.triggered = false;
+        }
+    }
+
+    /// Ends the frame, `None` if nothing was stepped since the last one
+    pub fn take_frame(&mut self) -> Option<MonitorFrame> {
+        if self.count == 0 {
+            return None;
+        }
+        let frame = MonitorFrame {
+            level: (self.sum / self.count) as u16,
+            min: self.min,
+            max: self.max,
+            trigger_low: self.trigger_low,
+            trigger_high: self.trigger_high,
+            triggered: self.triggered,
+        };
+        self.sum = 0;
+        self.count = 0;
+        Some(frame)
+    }
+}
@@ -0,0 +1,41 @@
+/// Averages `factor` consecutive ADC conversions into a single sample,
+/// trading sample rate for effective resolution
+#[derive(Clone, Debug)]
+pub struct Oversampler {
+    factor: u32,
+    sum: u32,
+    count: u32,
+}
+
+impl Oversampler {
+    pub fn new(factor: u32) -> Self {
+        Self {
+            factor: factor.max(1),
+            sum: 0,
+            count: 0,
+        }
+    }
+
+    pub fn factor(&self) -> u32 {
+        self.factor
+    }
+
+    #[inline(always)]
+    pub fn push(&mut self, value: u16) -> Option<u16> {
+        self.sum += value as u32;
+        self.count += 1;
+        if self.count < self.factor {
+            return None;
+        }
+        let average = (self.sum / self.count) as u16;
+        self.sum = 0;
+        self.count = 0;
+        Some(average)
+    }
+}
+
+impl Default for Oversampler {
+    fn default() -> Self {
+        Self::new(1)
+    }
+}
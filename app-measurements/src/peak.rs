@@ -0,0 +1,31 @@
+/// Session minimum and maximum of raw ADC values
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PeakHold {
+    pub min: u16,
+    pub max: u16,
+}
+
+impl PeakHold {
+    #[inline(always)]
+    pub fn step(&mut self, value: u16) {
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.min > self.max
+    }
+}
+
+impl Default for PeakHold {
+    fn default() -> Self {
+        Self {
+            min: u16::MAX,
+            max: 0,
+        }
+    }
+}
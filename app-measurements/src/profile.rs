@@ -0,0 +1,94 @@
+use crate::{DurationMethod, TriggerThresholds};
+
+/// What the menu shows when the settings don't match any of [`TRIGGER_PROFILES`]
+pub const USER_PROFILE_LABEL: &str = "USER";
+/// Threshold deltas of the presets are counted in 12 bits
+const PRESET_ADC_RANGE: u16 = 4096;
+
+/// Everything that decides when a pulse starts and ends and how it's read,
+/// tuned together for one kind of shutter
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TriggerProfile {
+    pub label: &'static str,
+    pub thresholds: TriggerThresholds,
+    /// What the threshold deltas are counted in, see [`TriggerThresholds::rescaled`]
+    pub adc_range: u16,
+    /// ADC conversions averaged into each sample, fewer samples but less noise
+    pub oversampling: u32,
+    pub auto_trigger_low: bool,
+    pub duration_method: DurationMethod,
+    /// Longer ADC sample time for a sensor loaded for dim light
+    pub high_gain: bool,
+}
+
+impl TriggerProfile {
+    /// Thresholds for samples in `adc_range` counts
+    pub fn thresholds_for(&self, adc_range: u16) -> TriggerThresholds {
+        self.thresholds.rescaled(self.adc_range, adc_range)
+    }
+}
+
+pub const TRIGGER_PROFILES: [TriggerProfile; 4] = [
+    // A narrow slit sweeps past the sensor, full sample rate and the area of the pulse
+    TriggerProfile {
+        label: "FOCAL",
+        thresholds: TriggerThresholds {
+            low_ratio: 1.0,
+            high_ratio: 1.0,
+            low_delta: PRESET_ADC_RANGE / 32,
+            high_delta: PRESET_ADC_RANGE / 16,
+        },
+        adc_range: PRESET_ADC_RANGE,
+        oversampling: 1,
+        auto_trigger_low: false,
+        duration_method: DurationMethod::Integral,
+        high_gain: false,
+    },
+    // Blades open and close slowly and unevenly, the pulse ends relative to its peak
+    // and is read at half of it
+    TriggerProfile {
+        label: "LEAF",
+        thresholds: TriggerThresholds {
+            low_ratio: 1.0,
+            high_ratio: 1.0,
+            low_delta: PRESET_ADC_RANGE / 32,
+            high_delta: PRESET_ADC_RANGE / 16,
+        },
+        adc_range: PRESET_ADC_RANGE,
+        oversampling: 4,
+        auto_trigger_low: true,
+        duration_method: DurationMethod::HalfPeak,
+        high_gain: false,
+    },
+    // Square pulses with sharp edges, low thresholds and the time between them
+    TriggerProfile {
+        label: "ELEC",
+        thresholds: TriggerThresholds {
+            low_ratio: 1.0,
+            high_ratio: 1.0,
+            low_delta: PRESET_ADC_RANGE / 64,
+            high_delta: PRESET_ADC_RANGE / 32,
+        },
+        adc_range: PRESET_ADC_RANGE,
+        oversampling: 1,
+        auto_trigger_low: false,
+        duration_method: DurationMethod::Threshold,
+        high_gain: false,
+    },
+    // Large format shutters read through the ground glass, too little light for the
+    // usual thresholds. The noise is averaged down and the pulse area is taken.
+    TriggerProfile {
+        label: "LF",
+        thresholds: TriggerThresholds {
+            low_ratio: 1.0,
+            high_ratio: 1.0,
+            low_delta: PRESET_ADC_RANGE / 256,
+            high_delta: PRESET_ADC_RANGE / 128,
+        },
+        adc_range: PRESET_ADC_RANGE,
+        oversampling: 16,
+        auto_trigger_low: false,
+        duration_method: DurationMethod::Integral,
+        high_gain: true,
+    },
+];
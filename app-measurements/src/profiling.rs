@@ -0,0 +1,108 @@
+/// Execution times of one code section, in CPU cycles
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TimingStats {
+    pub min: u32,
+    pub max: u32,
+    pub count: u32,
+    total: u64,
+}
+
+impl TimingStats {
+    #[inline(always)]
+    pub fn record(&mut self, cycles: u32) {
+        if self.count == 0 || cycles < self.min {
+            self.min = cycles;
+        }
+        self.max = self.max.max(cycles);
+        self.count = self.count.saturating_add(1);
+        self.total += cycles as u64;
+    }
+
+    pub fn mean(&self) -> u32 {
+        match self.count {
+            0 => 0,
+            count => (self.total / count as u64) as u32,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProfiledSection {
+    /// ADC DMA completion handler, runs for every sample
+    Dma,
+    DisplayFrame,
+    /// One pass of the measurement wait loop
+    MeasureLoop,
+}
+
+impl ProfiledSection {
+    pub const ALL: [ProfiledSection; 3] = [
+        ProfiledSection::Dma,
+        ProfiledSection::DisplayFrame,
+        ProfiledSection::MeasureLoop,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ProfiledSection::Dma => "DMA",
+            ProfiledSection::DisplayFrame => "DRAW",
+            ProfiledSection::MeasureLoop => "MEAS",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Profile {
+    clock_hz: u32,
+    /// Cycles available to the DMA handler before the next sample arrives
+    sample_budget_cycles: u32,
+    sections: [TimingStats; 3],
+}
+
+impl Profile {
+    pub const fn new(clock_hz: u32, sample_rate_hz: u32) -> Self {
+        Self {
+            clock_hz,
+            sample_budget_cycles: clock_hz / sample_rate_hz,
+            sections: [TimingStats {
+                min: 0,
+                max: 0,
+                count: 0,
+                total: 0,
+            }; 3],
+        }
+    }
+
+    #[inline(always)]
+    pub fn record(&mut self, section: ProfiledSection, cycles: u32) {
+        self.sections[section as usize].record(cycles);
+    }
+
+    pub fn get(&self, section: ProfiledSection) -> &TimingStats {
+        &self.sections[section as usize]
+    }
+
+    pub fn reset(&mut self) {
+        self.sections = Default::default();
+    }
+
+    pub fn cycles_to_nanos(&self, cycles: u32) -> u64 {
+        match self.clock_hz {
+            0 => 0,
+            clock_hz => cycles as u64 * 1_000_000_000 / clock_hz as u64,
+        }
+    }
+
+    /// Worst case DMA handler time as a share of the sample period
+    pub fn sample_budget_percent(&self) -> Option<u32> {
+        let dma = self.get(ProfiledSection::Dma);
+        if dma.is_empty() || self.sample_budget_cycles == 0 {
+            return None;
+        }
+        Some((dma.max as u64 * 100 / self.sample_budget_cycles as u64) as u32)
+    }
+}
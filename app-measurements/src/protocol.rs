@@ -0,0 +1,333 @@
+use core::fmt;
+
+use heapless::{Deque, Vec};
+
+use crate::{CalibrationResult, MeasurementResult};
+
+const COMMAND_MAX_LEN: usize = 32;
+/// A pasted config brings several lines per USB packet
+const PENDING_REQUESTS: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UsbExport {
+    History,
+    Sequence,
+    Profile,
+    Traces,
+    FocalPlane,
+    /// `STATUS`, fault counters rather than CSV
+    Status,
+    /// `monitor`, starts or stops streaming the light level, see [`crate::MonitorFrame`]
+    Monitor,
+    /// `config dump`, the settings in the format `config load` reads back
+    Config,
+    /// `report`, the test sequence as plain text for a repair ticket, see [`crate::REPORT_COLUMNS`]
+    Report,
+    /// `capabilities?`, what this build supports, see [`Capabilities`]
+    Capabilities,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UsbRequest {
+    Export(UsbExport),
+    /// 1200 baud touch or a DFU detach request
+    Bootloader,
+    /// `MEAS:ARM`, calibrates and waits for the shutter like a button press
+    Arm,
+    /// `SELF:CHECK`, measures an emitter pulse of a known length, see [`crate::LoopbackCheck`]
+    SelfCheck,
+    /// `TOL 1/500 40` overrides the tolerance of a speed in percent,
+    /// `TOL 1/500` puts it back on the band
+    SetTolerance {
+        nominal_micros: u32,
+        percent: Option<u8>,
+    },
+    /// A `key=value` line between `config load` and `end`
+    LoadConfig(ConfigLine),
+    /// `end` of a `config load`
+    ConfigLoaded,
+    /// `SUBSCRIBE` starts pushing [`DeviceEvent`]s, `UNSUBSCRIBE` stops
+    Subscribe(bool),
+}
+
+/// Pushed to a host that sent `SUBSCRIBE` as it happens, until it sends `UNSUBSCRIBE`
+/// or closes the port
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeviceEvent {
+    /// `EVT:MODE <name>`, also right after subscribing
+    Mode(&'static str),
+    /// `EVT:CAL <average> <min> <max>`
+    Calibrated { average: u16, min: u16, max: u16 },
+    /// `EVT:MEAS <duration us> <integrated us> <uncertainty us> <OK|CLIPPED>`
+    Measured {
+        duration_micros: u64,
+        integrated_micros: u64,
+        uncertainty_micros: u64,
+        clipped: bool,
+    },
+}
+
+impl DeviceEvent {
+    pub fn calibrated(calibration: &CalibrationResult) -> Self {
+        Self::Calibrated {
+            average: calibration.average,
+            min: calibration.min,
+            max: calibration.max,
+        }
+    }
+
+    pub fn measured(result: &MeasurementResult) -> Self {
+        Self::Measured {
+            duration_micros: result.duration_micros,
+            integrated_micros: result.integrated_duration_micros,
+            uncertainty_micros: result.uncertainty_micros(),
+            clipped: result.clipped,
+        }
+    }
+}
+
+impl fmt::Display for DeviceEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Mode(name) => write!(f, "EVT:MODE {}\r\n", name),
+            Self::Calibrated { average, min, max } => {
+                write!(f, "EVT:CAL {} {} {}\r\n", average, min, max)
+            }
+            Self::Measured {
+                duration_micros,
+                integrated_micros,
+                uncertainty_micros,
+                clipped,
+            } => write!(
+                f,
+                "EVT:MEAS {} {} {} {}\r\n",
+                duration_micros,
+                integrated_micros,
+                uncertainty_micros,
+                if *clipped { "CLIPPED" } else { "OK" }
+            ),
+        }
+    }
+}
+
+/// The answer to `capabilities?`, sent as `key=value` lines for each of
+/// [`CAPABILITY_KEYS`] between `capabilities` and `end`. Lists are comma separated,
+/// hosts should skip keys they don't know so that later builds can add more.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Capabilities<'a> {
+    pub firmware: &'a str,
+    pub hardware: &'a str,
+    /// As in `EVT:MODE`
+    pub modes: &'a [&'a str],
+    /// Before oversampling
+    pub sample_rate_hz: u32,
+    pub oversampling: &'a [u32],
+    pub adc_bits: &'a [u32],
+    /// A measurement's trace, margins included
+    pub buffer_samples: usize,
+    pub burst_samples: usize,
+    pub history_len: usize,
+    pub trace_history_len: usize,
+    pub accessories: &'a [&'a str],
+    pub focal_plane_sensors: usize,
+    /// Of the linear sensor
+    pub scan_channels: usize,
+    /// Cargo features and commands that some builds leave out
+    pub features: &'a [&'a str],
+}
+
+pub const CAPABILITY_KEYS: [&str; 14] = [
+    "firmware",
+    "hardware",
+    "modes",
+    "sample_rate_hz",
+    "oversampling",
+    "adc_bits",
+    "buffer_samples",
+    "burst_samples",
+    "history",
+    "trace_history",
+    "accessories",
+    "focal_plane_sensors",
+    "scan_channels",
+    "features",
+];
+
+impl Capabilities<'_> {
+    pub fn write_value(&self, key: &str, out: &mut impl fmt::Write) -> fmt::Result {
+        match key {
+            "firmware" => out.write_str(self.firmware),
+            "hardware" => out.write_str(self.hardware),
+            "modes" => write_list(out, self.modes),
+            "sample_rate_hz" => write!(out, "{}", self.sample_rate_hz),
+            "oversampling" => write_list(out, self.oversampling),
+            "adc_bits" => write_list(out, self.adc_bits),
+            "buffer_samples" => write!(out, "{}", self.buffer_samples),
+            "burst_samples" => write!(out, "{}", self.burst_samples),
+            "history" => write!(out, "{}", self.history_len),
+            "trace_history" => write!(out, "{}", self.trace_history_len),
+            "accessories" => write_list(out, self.accessories),
+            "focal_plane_sensors" => write!(out, "{}", self.focal_plane_sensors),
+            "scan_channels" => write!(out, "{}", self.scan_channels),
+            "features" => write_list(out, self.features),
+            _ => Ok(()),
+        }
+    }
+}
+
+fn write_list(out: &mut impl fmt::Write, items: &[impl fmt::Display]) -> fmt::Result {
+    for (index, item) in items.iter().enumerate() {
+        if index > 0 {
+            out.write_char(',')?;
+        }
+        write!(out, "{}", item)?;
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConfigLine {
+    bytes: [u8; COMMAND_MAX_LEN],
+    len: u8,
+    separator: u8,
+}
+
+impl ConfigLine {
+    fn parse(line: &[u8]) -> Option<Self> {
+        core::str::from_utf8(line).ok()?;
+        let separator = line.iter().position(|&c| c == b'=')?;
+        let mut bytes = [0; COMMAND_MAX_LEN];
+        bytes.get_mut(..line.len())?.copy_from_slice(line);
+        Some(Self {
+            bytes,
+            len: line.len() as u8,
+            separator: separator as u8,
+        })
+    }
+
+    pub fn key(&self) -> &str {
+        self.str(0..self.separator as usize)
+    }
+
+    pub fn value(&self) -> &str {
+        self.str(self.separator as usize + 1..self.len as usize)
+    }
+
+    fn str(&self, range: core::ops::Range<usize>) -> &str {
+        // Split at an ASCII '=' of a valid string
+        core::str::from_utf8(&self.bytes[range])
+            .unwrap_or_default()
+            .trim()
+    }
+}
+
+/// Single key commands act right away, longer ones wait for the end of the line.
+/// The simulator serves the same commands over TCP.
+#[derive(Default)]
+pub struct CommandParser {
+    line: Vec<u8, COMMAND_MAX_LEN>,
+    /// Between `config load` and `end`, where lines are settings and single keys aren't commands
+    loading_config: bool,
+    pending: Deque<UsbRequest, PENDING_REQUESTS>,
+}
+
+impl CommandParser {
+    /// The first request in `input`, the rest come from [`Self::next_request`]
+    pub fn feed(&mut self, input: &[u8]) -> Option<UsbRequest> {
+        for &c in input {
+            let shortcut = self.line.is_empty() && !self.loading_config;
+            let parsed = match c {
+                b'\r' | b'\n' => {
+                    let parsed = self.parse_line();
+                    self.line.clear();
+                    parsed
+                }
+                b'h' if shortcut => Some(UsbRequest::Export(UsbExport::History)),
+                b's' if shortcut => Some(UsbRequest::Export(UsbExport::Sequence)),
+                b'p' if shortcut => Some(UsbRequest::Export(UsbExport::Profile)),
+                b't' if shortcut => Some(UsbRequest::Export(UsbExport::Traces)),
+                b'f' if shortcut => Some(UsbRequest::Export(UsbExport::FocalPlane)),
+                _ => {
+                    // Overlong lines are garbage anyway
+                    if self.line.push(c).is_err() {
+                        self.line.clear();
+                    }
+                    None
+                }
+            };
+            if let Some(request) = parsed {
+                // Dropped when the host sends faster than requests get handled
+                let _ = self.pending.push_back(request);
+            }
+        }
+        self.next_request()
+    }
+
+    pub fn next_request(&mut self) -> Option<UsbRequest> {
+        self.pending.pop_front()
+    }
+
+    fn parse_line(&mut self) -> Option<UsbRequest> {
+        let line = self.line.trim_ascii();
+        if self.loading_config {
+            return match line {
+                b"end" => {
+                    self.loading_config = false;
+                    Some(UsbRequest::ConfigLoaded)
+                }
+                // Blank lines and comments
+                b"" => None,
+                line if line.starts_with(b"#") => None,
+                line => ConfigLine::parse(line).map(UsbRequest::LoadConfig),
+            };
+        }
+        match line {
+            b"config load" => {
+                self.loading_config = true;
+                None
+            }
+            b"config dump" => Some(UsbRequest::Export(UsbExport::Config)),
+            line => parse_line(line),
+        }
+    }
+}
+
+fn parse_line(line: &[u8]) -> Option<UsbRequest> {
+    match line.trim_ascii() {
+        b"MEAS:ARM" => Some(UsbRequest::Arm),
+        b"SELF:CHECK" => Some(UsbRequest::SelfCheck),
+        b"STATUS" => Some(UsbRequest::Export(UsbExport::Status)),
+        b"monitor" | b"MONITOR" => Some(UsbRequest::Export(UsbExport::Monitor)),
+        b"report" | b"REPORT" => Some(UsbRequest::Export(UsbExport::Report)),
+        b"capabilities?" | b"CAPABILITIES?" => Some(UsbRequest::Export(UsbExport::Capabilities)),
+        // Upper case only, a lower case `s` starting the line is the sequence export
+        b"SUBSCRIBE" => Some(UsbRequest::Subscribe(true)),
+        b"UNSUBSCRIBE" => Some(UsbRequest::Subscribe(false)),
+        line => parse_tolerance(line.strip_prefix(b"TOL ")?),
+    }
+}
+
+fn parse_tolerance(args: &[u8]) -> Option<UsbRequest> {
+    let mut args = core::str::from_utf8(args).ok()?.split_ascii_whitespace();
+    // Either a fraction like 1/500 or whole seconds
+    let speed = args.next()?;
+    let nominal_micros = match speed.split_once('/') {
+        Some(("1", denominator)) => {
+            let denominator: u32 = denominator.parse().ok().filter(|&d| d > 0)?;
+            (1_000_000 + denominator / 2) / denominator
+        }
+        Some(_) => return None,
+        None => speed.parse::<u32>().ok()?.checked_mul(1_000_000)?,
+    };
+    let percent = match args.next() {
+        Some(percent) => Some(percent.parse().ok()?),
+        None => None,
+    };
+    if args.next().is_some() {
+        return None;
+    }
+    Some(UsbRequest::SetTolerance {
+        nominal_micros,
+        percent,
+    })
+}
@@ -0,0 +1,82 @@
+use core::fmt::{self, Write};
+
+use crate::{SequenceStep, Tolerances, Verdict};
+
+/// Above the rows of [`write_report_row`], the columns line up with them
+pub const REPORT_COLUMNS: &str = "SPEED    NOMINAL US  MEASURED US  DEV %  STOPS  RESULT";
+
+/// One step of the test sequence, a skipped or unmeasured one only has the nominal side
+pub fn write_report_row(
+    out: &mut impl Write,
+    step: &SequenceStep,
+    tolerances: &Tolerances,
+) -> fmt::Result {
+    write!(out, "{:<8}", step.nominal())?;
+    write!(out, "{:>11}", step.nominal_duration_micros())?;
+    let (Some(duration_micros), Some(deviation), Some(stops), Some(verdict)) = (
+        step.duration_micros,
+        step.deviation_percent(),
+        step.deviation_stops(),
+        step.verdict(tolerances),
+    ) else {
+        return write!(out, "{:>13}", "-");
+    };
+    write!(
+        out,
+        "{:>13}{:>+7}{:>+7.2}  {}",
+        duration_micros,
+        deviation,
+        stops,
+        verdict.label()
+    )
+}
+
+/// Totals under the table
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ReportSummary {
+    pub steps: usize,
+    pub passed: usize,
+    pub failed: usize,
+}
+
+impl ReportSummary {
+    pub fn new(steps: &[SequenceStep], tolerances: &Tolerances) -> Self {
+        let mut summary = Self {
+            steps: steps.len(),
+            ..Self::default()
+        };
+        for step in steps {
+            match step.verdict(tolerances) {
+                Some(Verdict::Pass) => summary.passed += 1,
+                Some(Verdict::Fail) => summary.failed += 1,
+                None => (),
+            }
+        }
+        summary
+    }
+
+    pub fn measured(&self) -> usize {
+        self.passed + self.failed
+    }
+
+    /// A single failed speed fails the shutter, `None` until something was measured
+    pub fn verdict(&self) -> Option<Verdict> {
+        match (self.measured(), self.failed) {
+            (0, _) => None,
+            (_, 0) => Some(Verdict::Pass),
+            _ => Some(Verdict::Fail),
+        }
+    }
+
+    pub fn write(&self, out: &mut impl Write) -> fmt::Result {
+        write!(
+            out,
+            "{} OF {} MEASURED, {} PASS, {} FAIL: {}",
+            self.measured(),
+            self.steps,
+            self.passed,
+            self.failed,
+            self.verdict().map_or("INCOMPLETE", |v| v.label())
+        )
+    }
+}
@@ -0,0 +1,272 @@
+use crate::{CalibrationResult, TriggerThresholds};
+
+/// Photodiodes on the rolling shutter accessory
+pub const SCAN_CHANNELS: usize = 8;
+/// Sweeps averaged into each channel's dark level
+const CALIBRATION_SWEEPS: u32 = 256;
+/// Channels that never open or close stop holding the scan open after this long
+const SCAN_TIMEOUT_MICROS: u64 = 1_000_000;
+
+/// Edge times of a single photodiode, relative to the start of the sweeps
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ChannelTiming {
+    pub opened_at_micros: Option<u64>,
+    pub closed_at_micros: Option<u64>,
+}
+
+impl ChannelTiming {
+    pub fn open_micros(&self) -> Option<u64> {
+        self.closed_at_micros?.checked_sub(self.opened_at_micros?)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ScanResult<const N: usize> {
+    pub channels: [ChannelTiming; N],
+    /// Photodiode spacing along the scan direction
+    pub pitch_um: u32,
+    /// Along the scan direction
+    pub frame_height_um: u32,
+    pub sweep_interval_micros: u32,
+}
+
+impl<const N: usize> ScanResult<N> {
+    /// Least squares slope of the opening times across the channels, in microseconds
+    /// per channel, positive when channel 0 opens first
+    fn opening_slope(&self) -> Option<(i64, i64)> {
+        let points = || {
+            self.channels
+                .iter()
+                .enumerate()
+                .filter_map(|(index, c)| Some((index as i64, c.opened_at_micros? as i64)))
+        };
+        let count = points().count() as i64;
+        if count < 2 {
+            return None;
+        }
+        let (sum_x, sum_t) = points().fold((0, 0), |(sx, st), (x, t)| (sx + x, st + t));
+        let (numerator, denominator) = points().fold((0, 0), |(num, den), (x, t)| {
+            let dx = x * count - sum_x;
+            (num + dx * (t * count - sum_t), den + dx * dx)
+        });
+        match denominator {
+            0 => None,
+            denominator => Some((numerator, denominator)),
+        }
+    }
+
+    /// Time for the opening edge to cross the full frame height
+    pub fn scan_time_micros(&self) -> Option<u64> {
+        let (numerator, denominator) = self.opening_slope()?;
+        Some(
+            numerator.unsigned_abs() * self.frame_height_um as u64
+                / (denominator.unsigned_abs() * self.pitch_um.max(1) as u64),
+        )
+    }
+
+    /// `true` when the scan runs from channel 0 towards the last one
+    pub fn is_forward(&self) -> bool {
+        self.opening_slope()
+            .is_none_or(|(numerator, _)| numerator >= 0)
+    }
+
+    /// Mean time each line of the frame is exposed for
+    pub fn exposure_micros(&self) -> Option<u64> {
+        let (sum, count) = self
+            .channels
+            .iter()
+            .filter_map(ChannelTiming::open_micros)
+            .fold((0, 0), |(sum, count), micros| (sum + micros, count + 1));
+        match count {
+            0 => None,
+            count => Some(sum / count),
+        }
+    }
+
+    /// Height of the exposed band travelling across the frame
+    pub fn slit_width_um(&self) -> Option<u64> {
+        let scan_time_micros = self.scan_time_micros()?;
+        if scan_time_micros == 0 {
+            return None;
+        }
+        Some(self.exposure_micros()? * self.frame_height_um as u64 / scan_time_micros)
+    }
+
+    /// One sweep either way on every edge
+    pub fn uncertainty_micros(&self) -> u64 {
+        self.sweep_interval_micros as u64
+    }
+}
+
+#[derive(Clone, Debug)]
+enum ScanState<const N: usize> {
+    Calibrating {
+        sums: [u32; N],
+        mins: [u16; N],
+        maxs: [u16; N],
+    },
+    /// Waiting for the first channel to open
+    Armed,
+    Scanning {
+        started_at_micros: u64,
+    },
+    Done,
+}
+
+/// Fuses the channels of a photodiode array into rolling shutter timings.
+/// Sampled in sweeps, all channels read one after another at a fixed rate.
+#[derive(Clone, Debug)]
+pub struct ScanMeasurement<const N: usize> {
+    trigger_thresholds: TriggerThresholds,
+    sweep_interval_micros: u32,
+    pitch_um: u32,
+    frame_height_um: u32,
+    sweeps: u32,
+    trigger_high: [u16; N],
+    trigger_low: [u16; N],
+    last_values: [u16; N],
+    channels: [ChannelTiming; N],
+    state: ScanState<N>,
+    result: Option<ScanResult<N>>,
+}
+
+impl<const N: usize> ScanMeasurement<N> {
+    pub fn new(
+        trigger_thresholds: TriggerThresholds,
+        sweep_rate_hz: u32,
+        pitch_um: u32,
+        frame_height_um: u32,
+    ) -> Self {
+        Self {
+            trigger_thresholds,
+            sweep_interval_micros: 1_000_000 / sweep_rate_hz.max(1),
+            pitch_um,
+            frame_height_um,
+            sweeps: 0,
+            trigger_high: [u16::MAX; N],
+            trigger_low: [u16::MAX; N],
+            last_values: [0; N],
+            channels: [ChannelTiming::default(); N],
+            state: ScanState::Calibrating {
+                sums: [0; N],
+                mins: [u16::MAX; N],
+                maxs: [0; N],
+            },
+            result: None,
+        }
+    }
+
+    pub fn step(&mut self, values: &[u16; N]) {
+        self.sweeps += 1;
+        self.last_values = *values;
+        let now_micros = self.sweeps as u64 * self.sweep_interval_micros as u64;
+
+        match self.state {
+            ScanState::Calibrating {
+                ref mut sums,
+                ref mut mins,
+                ref mut maxs,
+            } => {
+                for (index, &value) in values.iter().enumerate() {
+                    sums[index] += value as u32;
+                    mins[index] = mins[index].min(value);
+                    maxs[index] = maxs[index].max(value);
+                }
+                if self.sweeps >= CALIBRATION_SWEEPS {
+                    for index in 0..N {
+                        let calibration = CalibrationResult {
+                            average: (sums[index] / self.sweeps) as u16,
+                            min: mins[index],
+                            max: maxs[index],
+                            covered: 0,
+                        };
+                        self.trigger_high[index] =
+                            self.trigger_thresholds.trigger_high(&calibration);
+                        self.trigger_low[index] = self.trigger_thresholds.trigger_low(&calibration);
+                    }
+                    self.state = ScanState::Armed;
+                }
+            }
+            ScanState::Armed | ScanState::Scanning { .. } => {
+                self.step_channels(values, now_micros);
+
+                if let ScanState::Armed = self.state {
+                    if self.channels.iter().any(|c| c.opened_at_micros.is_some()) {
+                        self.state = ScanState::Scanning {
+                            started_at_micros: now_micros,
+                        };
+                    }
+                }
+
+                if let ScanState::Scanning { started_at_micros } = self.state {
+                    let all_closed = self.channels.iter().all(|c| c.closed_at_micros.is_some());
+                    if all_closed || now_micros - started_at_micros > SCAN_TIMEOUT_MICROS {
+                        self.finish();
+                    }
+                }
+            }
+            ScanState::Done => (),
+        }
+    }
+
+    fn step_channels(&mut self, values: &[u16; N], now_micros: u64) {
+        for (index, &value) in values.iter().enumerate() {
+            let channel = &mut self.channels[index];
+            match (channel.opened_at_micros, channel.closed_at_micros) {
+                (None, _) if value > self.trigger_high[index] => {
+                    channel.opened_at_micros = Some(now_micros);
+                }
+                (Some(_), None) if value < self.trigger_low[index] => {
+                    channel.closed_at_micros = Some(now_micros);
+                }
+                _ => (),
+            }
+        }
+    }
+
+    fn finish(&mut self) {
+        self.result = Some(ScanResult {
+            channels: self.channels,
+            pitch_um: self.pitch_um,
+            frame_height_um: self.frame_height_um,
+            sweep_interval_micros: self.sweep_interval_micros,
+        });
+        self.state = ScanState::Done;
+    }
+
+    /// Drops the edges of a scan in progress and arms again, `false` when there
+    /// was nothing to drop
+    pub fn invalidate(&mut self) -> bool {
+        if !self.is_scanning() {
+            return false;
+        }
+        self.channels = [ChannelTiming::default(); N];
+        self.state = ScanState::Armed;
+        true
+    }
+
+    pub fn is_calibrating(&self) -> bool {
+        matches!(self.state, ScanState::Calibrating { .. })
+    }
+
+    pub fn is_scanning(&self) -> bool {
+        matches!(self.state, ScanState::Scanning { .. })
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.result.is_some()
+    }
+
+    /// Most recent sweep, for live level display
+    pub fn last_values(&self) -> &[u16; N] {
+        &self.last_values
+    }
+
+    pub fn result(&self) -> Option<&ScanResult<N>> {
+        self.result.as_ref()
+    }
+
+    pub fn take_result(self) -> Option<ScanResult<N>> {
+        self.result
+    }
+}
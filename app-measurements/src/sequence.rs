@@ -0,0 +1,191 @@
+use heapless::Vec;
+
+use crate::util::KNOWN_SHUTTER_DURATIONS;
+use crate::{ShutterSpeed, Tolerances, Verdict};
+
+pub const SEQUENCE_MAX_LEN: usize = KNOWN_SHUTTER_DURATIONS.len();
+/// Shots of a single step with [`OutlierRejection::MedianOf5`]
+pub const SEQUENCE_MAX_SHOTS: usize = 5;
+
+/// Repeats every step and keeps the middle of the shots, so that a single
+/// misfire doesn't end up as the speed of that dial setting
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OutlierRejection {
+    #[default]
+    Off,
+    MedianOf3,
+    MedianOf5,
+}
+
+impl OutlierRejection {
+    pub const ALL: [OutlierRejection; 3] = [
+        OutlierRejection::Off,
+        OutlierRejection::MedianOf3,
+        OutlierRejection::MedianOf5,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            OutlierRejection::Off => "OFF",
+            OutlierRejection::MedianOf3 => "MED3",
+            OutlierRejection::MedianOf5 => "MED5",
+        }
+    }
+
+    pub fn next(&self) -> Self {
+        let index = Self::ALL.iter().position(|r| r == self).unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+
+    pub fn shots(&self) -> usize {
+        match self {
+            OutlierRejection::Off => 1,
+            OutlierRejection::MedianOf3 => 3,
+            OutlierRejection::MedianOf5 => SEQUENCE_MAX_SHOTS,
+        }
+    }
+}
+
+/// Mean of the shots without the shortest and the longest one, which is the median
+/// for three. Fewer than three are averaged as they are.
+pub fn trimmed_mean_micros(shots: &[u64]) -> Option<u64> {
+    let mut sorted = Vec::<u64, SEQUENCE_MAX_SHOTS>::new();
+    for &shot in shots.iter().take(SEQUENCE_MAX_SHOTS) {
+        let _ = sorted.push(shot);
+    }
+    sorted.sort_unstable();
+    let kept = if sorted.len() >= 3 {
+        &sorted[1..sorted.len() - 1]
+    } else {
+        &sorted[..]
+    };
+    if kept.is_empty() {
+        return None;
+    }
+    Some(kept.iter().sum::<u64>() / kept.len() as u64)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SequenceStep {
+    /// Index into [`KNOWN_SHUTTER_DURATIONS`]
+    pub nominal_speed: usize,
+    /// `None` until measured or if skipped, the trimmed mean with [`OutlierRejection`]
+    pub duration_micros: Option<u64>,
+}
+
+impl SequenceStep {
+    pub fn nominal_duration_micros(&self) -> u64 {
+        self.nominal().micros()
+    }
+
+    pub fn nominal(&self) -> ShutterSpeed {
+        ShutterSpeed::from_secs(KNOWN_SHUTTER_DURATIONS[self.nominal_speed])
+    }
+
+    /// Positive if the shutter is slower than nominal
+    pub fn deviation_percent(&self) -> Option<i32> {
+        self.nominal().deviation_percent(self.duration_micros?)
+    }
+
+    /// Positive if the shutter is slower than nominal, so the film got that much more light
+    pub fn deviation_stops(&self) -> Option<f32> {
+        self.nominal().deviation_stops(self.duration_micros?)
+    }
+
+    pub fn verdict(&self, tolerances: &Tolerances) -> Option<Verdict> {
+        Some(tolerances.verdict(self.nominal_duration_micros() as u32, self.duration_micros?))
+    }
+}
+
+/// A guided run through a list of nominal speeds, one measurement each or
+/// several with [`OutlierRejection`]
+#[derive(Clone, Debug, Default)]
+pub struct TestSequence {
+    steps: Vec<SequenceStep, SEQUENCE_MAX_LEN>,
+    position: usize,
+    rejection: OutlierRejection,
+    /// Of the current step, not kept across resets
+    shots: Vec<u64, SEQUENCE_MAX_SHOTS>,
+}
+
+impl TestSequence {
+    /// `speeds` are indices into [`KNOWN_SHUTTER_DURATIONS`], invalid ones are dropped
+    pub fn new(speeds: &[usize]) -> Self {
+        let mut steps = Vec::new();
+        for &nominal_speed in speeds
+            .iter()
+            .filter(|&&i| i < KNOWN_SHUTTER_DURATIONS.len())
+        {
+            let _ = steps.push(SequenceStep {
+                nominal_speed,
+                duration_micros: None,
+            });
+        }
+        Self {
+            steps,
+            ..Self::default()
+        }
+    }
+
+    pub fn with_rejection(mut self, rejection: OutlierRejection) -> Self {
+        self.rejection = rejection;
+        self
+    }
+
+    /// Picks a sequence up where it was left, e.g. after a reset
+    pub fn resume(steps: &[SequenceStep], position: usize) -> Self {
+        let mut sequence = Self::default();
+        for step in steps
+            .iter()
+            .filter(|step| step.nominal_speed < KNOWN_SHUTTER_DURATIONS.len())
+        {
+            let _ = sequence.steps.push(*step);
+        }
+        sequence.position = position.min(sequence.steps.len());
+        sequence
+    }
+
+    pub fn steps(&self) -> &[SequenceStep] {
+        &self.steps
+    }
+
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    pub fn rejection(&self) -> OutlierRejection {
+        self.rejection
+    }
+
+    /// Taken so far for the current step
+    pub fn shots(&self) -> &[u64] {
+        &self.shots
+    }
+
+    pub fn current(&self) -> Option<SequenceStep> {
+        self.steps.get(self.position).copied()
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.position >= self.steps.len()
+    }
+
+    /// Moves on once the step has all of its shots
+    pub fn record(&mut self, duration_micros: u64) {
+        let Some(step) = self.steps.get_mut(self.position) else {
+            return;
+        };
+        let _ = self.shots.push(duration_micros);
+        if self.shots.len() >= self.rejection.shots() {
+            step.duration_micros = trimmed_mean_micros(&self.shots);
+            self.shots.clear();
+            self.position += 1;
+        }
+    }
+
+    /// Drops the shots taken so far for the step
+    pub fn skip(&mut self) {
+        self.shots.clear();
+        self.position = (self.position + 1).min(self.steps.len());
+    }
+}
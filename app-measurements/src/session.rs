@@ -0,0 +1,81 @@
+use heapless::Vec;
+
+use crate::util::KNOWN_SHUTTER_DURATIONS;
+use crate::{SequenceStep, TestSequence, SEQUENCE_MAX_LEN};
+
+/// Words kept across resets, all but the last of the F401's 20 RTC backup registers.
+/// The last one holds the [`crate::DisplayGeometry`].
+pub const SESSION_WORDS: usize = 19;
+/// Changes with the layout so that older firmware's sessions are ignored
+const MAGIC: u32 = 0x5e55_0100;
+const MAGIC_MASK: u32 = 0xffff_ff00;
+const HEADER_WORDS: usize = 2;
+const SEQUENCE_PRESENT: u32 = 1 << 31;
+const SPEED_MASK: u32 = 0x00ff_ffff;
+
+/// What the firmware was doing before a reset. Sequence steps past the
+/// first `SESSION_WORDS - 2` only keep their nominal speed.
+#[derive(Clone, Debug)]
+pub struct Session {
+    /// Firmware specific mode id
+    pub mode: u8,
+    pub sequence: Option<TestSequence>,
+}
+
+impl Session {
+    pub fn encode(&self) -> [u32; SESSION_WORDS] {
+        let mut words = [0; SESSION_WORDS];
+        words[0] = MAGIC | self.mode as u32;
+
+        if let Some(sequence) = self.sequence.as_ref().filter(|s| is_encodable(s)) {
+            let speeds = sequence
+                .steps()
+                .iter()
+                .fold(0u32, |mask, step| mask | 1 << step.nominal_speed);
+            words[1] = SEQUENCE_PRESENT | (sequence.position() as u32) << 24 | speeds;
+            for (word, step) in words[HEADER_WORDS..].iter_mut().zip(sequence.steps()) {
+                *word = step
+                    .duration_micros
+                    .map_or(0, |micros| micros.clamp(1, u32::MAX as u64) as u32);
+            }
+        }
+        words
+    }
+
+    pub fn decode(words: &[u32; SESSION_WORDS]) -> Option<Self> {
+        if words[0] & MAGIC_MASK != MAGIC {
+            return None;
+        }
+
+        let sequence = (words[1] & SEQUENCE_PRESENT != 0).then(|| {
+            let speeds = words[1] & SPEED_MASK;
+            let position = (words[1] & !SEQUENCE_PRESENT) >> 24;
+            let mut steps = Vec::<SequenceStep, SEQUENCE_MAX_LEN>::new();
+            for nominal_speed in (0..KNOWN_SHUTTER_DURATIONS.len()).filter(|i| speeds & 1 << i != 0)
+            {
+                let duration_micros = words
+                    .get(HEADER_WORDS + steps.len())
+                    .filter(|&&micros| micros != 0)
+                    .map(|&micros| micros as u64);
+                let _ = steps.push(SequenceStep {
+                    nominal_speed,
+                    duration_micros,
+                });
+            }
+            TestSequence::resume(&steps, position as usize)
+        });
+
+        Some(Self {
+            mode: words[0] as u8,
+            sequence,
+        })
+    }
+}
+
+/// The speeds are stored as a bit mask, which only holds strictly ascending ones
+fn is_encodable(sequence: &TestSequence) -> bool {
+    sequence
+        .steps()
+        .windows(2)
+        .all(|pair| pair[0].nominal_speed < pair[1].nominal_speed)
+}
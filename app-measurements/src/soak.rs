@@ -0,0 +1,70 @@
+/// One exposure caught while soaking
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SoakEvent {
+    /// Since the soak started
+    pub at_millis: u32,
+    pub duration_micros: u64,
+}
+
+/// Running summary of a long unattended run, only the extremes are kept so
+/// it can go on for hours
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SoakLog {
+    count: u32,
+    total_micros: u64,
+    fastest: Option<SoakEvent>,
+    slowest: Option<SoakEvent>,
+    last: Option<SoakEvent>,
+}
+
+impl SoakLog {
+    pub fn record(&mut self, event: SoakEvent) {
+        self.count += 1;
+        self.total_micros = self.total_micros.saturating_add(event.duration_micros);
+        if self
+            .fastest
+            .is_none_or(|fastest| event.duration_micros < fastest.duration_micros)
+        {
+            self.fastest = Some(event);
+        }
+        if self
+            .slowest
+            .is_none_or(|slowest| event.duration_micros > slowest.duration_micros)
+        {
+            self.slowest = Some(event);
+        }
+        self.last = Some(event);
+    }
+
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+
+    pub fn fastest(&self) -> Option<SoakEvent> {
+        self.fastest
+    }
+
+    pub fn slowest(&self) -> Option<SoakEvent> {
+        self.slowest
+    }
+
+    pub fn last(&self) -> Option<SoakEvent> {
+        self.last
+    }
+
+    pub fn mean_micros(&self) -> Option<u64> {
+        (self.count > 0).then(|| self.total_micros / self.count as u64)
+    }
+
+    /// Slowest over fastest in percent, how much the shutter wandered
+    pub fn spread_percent(&self) -> Option<u32> {
+        let (fastest, slowest) = (self.fastest?, self.slowest?);
+        if fastest.duration_micros == 0 {
+            return None;
+        }
+        Some(
+            ((slowest.duration_micros - fastest.duration_micros) * 100 / fastest.duration_micros)
+                as u32,
+        )
+    }
+}
@@ -0,0 +1,107 @@
+use core::fmt::{self, Write};
+
+use heapless::String;
+use micromath::F32Ext;
+
+use crate::util::KNOWN_SHUTTER_DURATIONS;
+
+/// Long enough for "1/" and any `u32`
+pub const SPEED_LABEL_LEN: usize = 12;
+
+/// An exposure time, either a dial setting or a measured one.
+///
+/// Displays the way cameras mark it, "1/125" below a second and "2S" above,
+/// which pads like a `str` so it can go straight into a table column.
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+pub struct ShutterSpeed {
+    secs: f32,
+}
+
+impl ShutterSpeed {
+    pub const fn from_secs(secs: f32) -> Self {
+        Self { secs }
+    }
+
+    pub fn from_micros(micros: u64) -> Self {
+        Self::from_secs(micros as f32 / 1_000_000.0)
+    }
+
+    /// A 1/`denominator` setting, like the custom speeds of annotations
+    pub fn from_denominator(denominator: u32) -> Self {
+        Self::from_secs(1.0 / denominator as f32)
+    }
+
+    /// Entry of [`KNOWN_SHUTTER_DURATIONS`], `None` past its end
+    pub fn known(index: usize) -> Option<Self> {
+        KNOWN_SHUTTER_DURATIONS
+            .get(index)
+            .copied()
+            .map(Self::from_secs)
+    }
+
+    pub fn secs(&self) -> f32 {
+        self.secs
+    }
+
+    pub fn micros(&self) -> u64 {
+        (self.secs * 1_000_000.0) as u64
+    }
+
+    /// Below a second, what gets the "1/" in front on the dial
+    pub fn is_fraction(&self) -> bool {
+        self.secs < 1.0
+    }
+
+    /// The number engraved on the dial, seconds or the denominator
+    pub fn dial_number(&self) -> u32 {
+        if self.is_fraction() {
+            (1.0 / self.secs + 0.5) as u32
+        } else {
+            (self.secs + 0.5) as u32
+        }
+    }
+
+    pub fn label(&self) -> String<SPEED_LABEL_LEN> {
+        let mut s = String::new();
+        if self.is_fraction() {
+            let _ = write!(s, "1/{}", self.dial_number());
+        } else {
+            let _ = write!(s, "{}S", self.dial_number());
+        }
+        s
+    }
+
+    /// Faster than one second, negative for long exposures. The ruler is laid out on this.
+    pub fn stops(&self) -> f32 {
+        (1.0 / self.secs).log2()
+    }
+
+    /// How much more light this lets in than `other`
+    pub fn stops_over(&self, other: ShutterSpeed) -> f32 {
+        (self.secs / other.secs).log2()
+    }
+
+    /// Of a measured exposure against this nominal speed, positive if the shutter is slower
+    pub fn deviation_percent(&self, measured_micros: u64) -> Option<i32> {
+        let nominal = self.micros() as i64;
+        if nominal == 0 {
+            return None;
+        }
+        Some(((measured_micros as i64 - nominal) * 100 / nominal) as i32)
+    }
+
+    /// Of a measured exposure against this nominal speed, positive if the shutter is slower
+    /// so the film got that much more light
+    pub fn deviation_stops(&self, measured_micros: u64) -> Option<f32> {
+        if measured_micros == 0 || self.micros() == 0 {
+            return None;
+        }
+        Some((measured_micros as f32 / self.micros() as f32).log2())
+    }
+}
+
+impl fmt::Display for ShutterSpeed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(&self.label())
+    }
+}
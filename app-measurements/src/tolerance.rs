@@ -0,0 +1,97 @@
+use heapless::Vec;
+
+pub const MAX_TOLERANCE_OVERRIDES: usize = 8;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Verdict {
+    Pass,
+    Fail,
+}
+
+impl Verdict {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Verdict::Pass => "PASS",
+            Verdict::Fail => "FAIL",
+        }
+    }
+}
+
+/// Allowed deviation from the nominal speed in percent either way. Service manuals
+/// allow more from around 1/500 down, where focal plane curtains are hard to time.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Tolerances {
+    pub percent: u8,
+    /// Replaces `percent` for speeds at least as fast as `fast_from_micros`
+    pub fast_percent: u8,
+    pub fast_from_micros: u32,
+    /// Nominal duration and percent, for speeds the manual lists separately
+    overrides: Vec<(u32, u8), MAX_TOLERANCE_OVERRIDES>,
+}
+
+impl Tolerances {
+    pub fn new(percent: u8, fast_percent: u8, fast_from_micros: u32) -> Self {
+        Self {
+            percent,
+            fast_percent,
+            fast_from_micros,
+            overrides: Vec::new(),
+        }
+    }
+
+    pub fn percent_for(&self, nominal_micros: u32) -> u8 {
+        match self.find_override(nominal_micros) {
+            Some(index) => self.overrides[index].1,
+            None if nominal_micros <= self.fast_from_micros => self.fast_percent,
+            None => self.percent,
+        }
+    }
+
+    pub fn verdict(&self, nominal_micros: u32, measured_micros: u64) -> Verdict {
+        let deviation = measured_micros.abs_diff(nominal_micros as u64) * 100;
+        if deviation <= self.percent_for(nominal_micros) as u64 * nominal_micros as u64 {
+            Verdict::Pass
+        } else {
+            Verdict::Fail
+        }
+    }
+
+    /// Replaces an override for the same speed, `false` if all slots are taken
+    pub fn set_override(&mut self, nominal_micros: u32, percent: u8) -> bool {
+        match self.find_override(nominal_micros) {
+            Some(index) => {
+                self.overrides[index] = (nominal_micros, percent);
+                true
+            }
+            None => self.overrides.push((nominal_micros, percent)).is_ok(),
+        }
+    }
+
+    pub fn clear_override(&mut self, nominal_micros: u32) {
+        if let Some(index) = self.find_override(nominal_micros) {
+            self.overrides.swap_remove(index);
+        }
+    }
+
+    pub fn clear_overrides(&mut self) {
+        self.overrides.clear();
+    }
+
+    pub fn overrides(&self) -> &[(u32, u8)] {
+        &self.overrides
+    }
+
+    // Speed tables round differently, 1/60 is 16666 or 16667 us depending on who asks
+    fn find_override(&self, nominal_micros: u32) -> Option<usize> {
+        self.overrides
+            .iter()
+            .position(|&(micros, _)| micros.abs_diff(nominal_micros) * 100 <= micros)
+    }
+}
+
+impl Default for Tolerances {
+    /// ±20 %, ±30 % from 1/500 on
+    fn default() -> Self {
+        Self::new(20, 30, 2_000)
+    }
+}
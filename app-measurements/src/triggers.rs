@@ -0,0 +1,50 @@
+use crate::util::{LaxDuration, LaxMonotonic};
+
+/// The light sensor and the sync contact
+pub const TRIGGER_CHANNELS: usize = 2;
+pub const LIGHT_CHANNEL: usize = 0;
+pub const SYNC_CHANNEL: usize = 1;
+
+/// First trigger time of each of `N` independently armed inputs
+pub struct TriggerChannels<M: LaxMonotonic, const N: usize> {
+    triggered_at: [Option<M::Instant>; N],
+}
+
+impl<M: LaxMonotonic, const N: usize> TriggerChannels<M, N> {
+    pub fn new() -> Self {
+        Self {
+            triggered_at: [None; N],
+        }
+    }
+
+    /// Later triggers of the same channel are ignored
+    pub fn mark(&mut self, channel: usize, at: M::Instant) {
+        if let Some(slot @ None) = self.triggered_at.get_mut(channel) {
+            *slot = Some(at);
+        }
+    }
+
+    pub fn triggered_at(&self, channel: usize) -> Option<M::Instant> {
+        *self.triggered_at.get(channel)?
+    }
+
+    /// From `from` triggering to `to` triggering, negative when `to` came first
+    pub fn offset_micros(&self, from: usize, to: usize) -> Option<i64> {
+        let (from, to) = (self.triggered_at(from)?, self.triggered_at(to)?);
+        Some(if to >= from {
+            (to - from).to_micros() as i64
+        } else {
+            -((from - to).to_micros() as i64)
+        })
+    }
+
+    pub fn clear(&mut self) {
+        self.triggered_at = [None; N];
+    }
+}
+
+impl<M: LaxMonotonic, const N: usize> Default for TriggerChannels<M, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
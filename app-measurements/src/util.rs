@@ -8,24 +8,38 @@ use rtic_monotonics::Monotonic;
 
 pub trait LaxDuration {
     fn to_micros(&self) -> u64;
+    /// Rounded down to the clock's resolution
+    fn from_nanos(nanos: u64) -> Self;
 }
 
 impl LaxDuration for fugit::MicrosDurationU32 {
     fn to_micros(&self) -> u64 {
         self.to_micros() as u64
     }
+
+    fn from_nanos(nanos: u64) -> Self {
+        Self::micros((nanos / 1000) as u32)
+    }
 }
 
 impl LaxDuration for fugit::Duration<u32, 1, 1000> {
     fn to_micros(&self) -> u64 {
         self.to_micros() as u64
     }
+
+    fn from_nanos(nanos: u64) -> Self {
+        Self::millis((nanos / 1_000_000) as u32)
+    }
 }
 
 impl<const CLK: u32> LaxDuration for fugit::TimerDurationU64<CLK> {
     fn to_micros(&self) -> u64 {
         self.to_micros()
     }
+
+    fn from_nanos(nanos: u64) -> Self {
+        Self::nanos(nanos)
+    }
 }
 
 pub trait LaxMonotonic {
@@ -34,7 +48,7 @@ pub trait LaxMonotonic {
         + core::ops::Add<Self::Duration, Output = Self::Instant>
         + core::ops::Sub<Self::Duration, Output = Self::Instant>
         + core::ops::Sub<Self::Instant, Output = Self::Duration>;
-    type Duration: LaxDuration;
+    type Duration: LaxDuration + Copy;
     fn now() -> Self::Instant;
 }
 
@@ -61,6 +75,44 @@ impl<const CLK: u32> LaxMonotonic for CycleCounterClock<CLK> {
     }
 }
 
+/// An instant on a fine clock to do something at. The caller sleeps on a coarse timer
+/// through most of [`Deadline::remaining`], then [`Deadline::spin`]s out the rest.
+pub struct Deadline<M: LaxMonotonic> {
+    at: M::Instant,
+}
+
+impl<M: LaxMonotonic> Deadline<M> {
+    pub fn after(delay: M::Duration) -> Self {
+        Self {
+            at: M::now() + delay,
+        }
+    }
+
+    pub fn until(at: M::Instant) -> Self {
+        Self { at }
+    }
+
+    pub fn at(&self) -> M::Instant {
+        self.at
+    }
+
+    /// `None` once it has passed
+    pub fn remaining(&self) -> Option<M::Duration> {
+        let now = M::now();
+        (now < self.at).then(|| self.at - now)
+    }
+
+    /// Busy waits until the deadline, returns when it got there
+    pub fn spin(&self) -> M::Instant {
+        loop {
+            let now = M::now();
+            if now >= self.at {
+                return now;
+            }
+        }
+    }
+}
+
 pub struct HistoryBufferDoubleEndedIterator<'a, T, const N: usize> {
     buf: &'a HistoryBuffer<T, N>,
     cur: usize,
@@ -116,6 +168,8 @@ impl<T, const N: usize> DoubleEndedIterator for HistoryBufferDoubleEndedIterator
     }
 }
 
+/// Full stops from 64 s to 1/16000 in seconds, longest first. Sequence steps, annotations
+/// and the session speed masks refer to speeds by their index here, see [`crate::ShutterSpeed::known`].
 pub const KNOWN_SHUTTER_DURATIONS: [f32; 21] = [
     64.0,
     32.0,
@@ -140,14 +194,126 @@ pub const KNOWN_SHUTTER_DURATIONS: [f32; 21] = [
     1.0 / 16000.0,
 ];
 
-pub fn get_closest_shutter_speed(duration: f32) -> f32 {
-    let mut best_match = 1.0;
-    for d in KNOWN_SHUTTER_DURATIONS.iter() {
-        if (d - duration).abs() < (best_match - duration).abs() {
-            best_match = *d;
+/// Older leaf shutters, e.g. the Compur series
+const LEAF_SHUTTER_DURATIONS: [f32; 9] = [
+    1.0,
+    1.0 / 2.0,
+    1.0 / 5.0,
+    1.0 / 10.0,
+    1.0 / 25.0,
+    1.0 / 50.0,
+    1.0 / 100.0,
+    1.0 / 200.0,
+    1.0 / 400.0,
+];
+
+/// 24 fps at shutter angles from 360° down to 22.5°
+const CINE_DURATIONS: [f32; 7] = [
+    1.0 / 24.0,
+    1.0 / 32.0,
+    1.0 / 48.0,
+    1.0 / 64.0,
+    1.0 / 96.0,
+    1.0 / 192.0,
+    1.0 / 384.0,
+];
+
+const THIRD_STOP_DURATIONS: [f32; 39] = [
+    1.0,
+    1.0 / 2.0,
+    1.0 / 4.0,
+    1.0 / 5.0,
+    1.0 / 6.0,
+    1.0 / 8.0,
+    1.0 / 10.0,
+    1.0 / 13.0,
+    1.0 / 15.0,
+    1.0 / 20.0,
+    1.0 / 25.0,
+    1.0 / 30.0,
+    1.0 / 40.0,
+    1.0 / 50.0,
+    1.0 / 60.0,
+    1.0 / 80.0,
+    1.0 / 100.0,
+    1.0 / 125.0,
+    1.0 / 160.0,
+    1.0 / 200.0,
+    1.0 / 250.0,
+    1.0 / 320.0,
+    1.0 / 400.0,
+    1.0 / 500.0,
+    1.0 / 640.0,
+    1.0 / 800.0,
+    1.0 / 1000.0,
+    1.0 / 1250.0,
+    1.0 / 1600.0,
+    1.0 / 2000.0,
+    1.0 / 2500.0,
+    1.0 / 3200.0,
+    1.0 / 4000.0,
+    1.0 / 5000.0,
+    1.0 / 6400.0,
+    1.0 / 8000.0,
+    1.0 / 10000.0,
+    1.0 / 12800.0,
+    1.0 / 16000.0,
+];
+
+/// Nominal speeds results are matched against and the ruler shows. Annotations
+/// and test sequences always index into [`KNOWN_SHUTTER_DURATIONS`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SpeedTable {
+    /// Full stops from 64 s to 1/16000
+    #[default]
+    Standard,
+    Leaf,
+    Cine,
+    /// Modern cameras, third stops from 1/4 to 1/16000 and full ones above
+    ThirdStop,
+}
+
+impl SpeedTable {
+    pub const ALL: [SpeedTable; 4] = [
+        SpeedTable::Standard,
+        SpeedTable::Leaf,
+        SpeedTable::Cine,
+        SpeedTable::ThirdStop,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            SpeedTable::Standard => "FULL",
+            SpeedTable::Leaf => "LEAF",
+            SpeedTable::Cine => "CINE",
+            SpeedTable::ThirdStop => "1/3",
+        }
+    }
+
+    pub fn next(&self) -> Self {
+        let index = Self::ALL.iter().position(|t| t == self).unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+
+    /// In seconds, longest first
+    pub fn durations(&self) -> &'static [f32] {
+        match self {
+            SpeedTable::Standard => &KNOWN_SHUTTER_DURATIONS,
+            SpeedTable::Leaf => &LEAF_SHUTTER_DURATIONS,
+            SpeedTable::Cine => &CINE_DURATIONS,
+            SpeedTable::ThirdStop => &THIRD_STOP_DURATIONS,
+        }
+    }
+
+    pub fn closest(&self, duration: f32) -> f32 {
+        let mut best_match = self.durations()[0];
+        for d in self.durations().iter() {
+            if (d - duration).abs() < (best_match - duration).abs() {
+                best_match = *d;
+            }
         }
+        best_match
     }
-    best_match
 }
 
 #[allow(dead_code)]
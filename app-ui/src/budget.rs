@@ -0,0 +1,55 @@
+/// How many draw steps a frame may take. A step is roughly one font render,
+/// heavy redraws stop when it runs out and pick up on the next frame.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DrawBudget {
+    steps: usize,
+}
+
+impl DrawBudget {
+    pub const UNLIMITED: Self = Self { steps: usize::MAX };
+
+    pub const fn new(steps: usize) -> Self {
+        Self { steps }
+    }
+
+    fn take(&mut self) -> bool {
+        if self.steps == 0 {
+            return false;
+        }
+        self.steps -= 1;
+        true
+    }
+}
+
+/// Where a redraw split into numbered steps got to
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DrawProgress {
+    next: usize,
+}
+
+impl DrawProgress {
+    pub fn restart(&mut self) {
+        self.next = 0;
+    }
+
+    pub fn is_complete(&self, len: usize) -> bool {
+        self.next >= len
+    }
+
+    /// Indices of the steps this frame can afford, starting after the last
+    /// one drawn. A step counts as drawn once yielded, a failed frame
+    /// redraws from scratch anyway.
+    pub fn steps<'a>(
+        &'a mut self,
+        len: usize,
+        budget: &'a mut DrawBudget,
+    ) -> impl Iterator<Item = usize> + 'a {
+        core::iter::from_fn(move || {
+            if self.next >= len || !budget.take() {
+                return None;
+            }
+            self.next += 1;
+            Some(self.next - 1)
+        })
+    }
+}
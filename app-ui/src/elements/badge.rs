@@ -5,7 +5,7 @@ use embedded_graphics::pixelcolor::Rgb565;
 use u8g2_fonts::types::{FontColor, HorizontalAlignment, VerticalPosition};
 
 use crate::fonts::SMALL_FONT;
-use crate::util::delay_ms;
+use crate::util::{delay_ms, font_error};
 use crate::AppDrawTarget;
 
 pub async fn draw_badge<D: AppDrawTarget<E>, E: Debug>(
@@ -14,7 +14,7 @@ pub async fn draw_badge<D: AppDrawTarget<E>, E: Debug>(
     text: &str,
     fg: Rgb565,
     bg: Rgb565,
-) {
+) -> Result<(), E> {
     SMALL_FONT
         .render_aligned(
             text,
@@ -24,7 +24,7 @@ pub async fn draw_badge<D: AppDrawTarget<E>, E: Debug>(
             FontColor::WithBackground { fg: bg, bg: fg },
             display,
         )
-        .unwrap();
+        .map_err(font_error)?;
 
     display.hint_refresh();
     delay_ms(50).await;
@@ -38,5 +38,6 @@ pub async fn draw_badge<D: AppDrawTarget<E>, E: Debug>(
             FontColor::WithBackground { fg, bg },
             display,
         )
-        .unwrap();
+        .map_err(font_error)?;
+    Ok(())
 }
@@ -0,0 +1,61 @@
+use core::fmt::Debug;
+
+use embedded_graphics::geometry::{Point, Size};
+use embedded_graphics::pixelcolor::Rgb565;
+use embedded_graphics::primitives::Rectangle;
+use u8g2_fonts::types::{FontColor, HorizontalAlignment, VerticalPosition};
+
+use crate::fonts::TINY_FONT;
+use crate::util::font_error;
+use crate::{config as cfg, AppDrawTarget};
+
+const BANNER_HEIGHT: u32 = 14;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+impl Severity {
+    pub fn color(&self) -> Rgb565 {
+        match self {
+            Severity::Info => cfg::COLOR_BANNER_INFO,
+            Severity::Warning => cfg::COLOR_BANNER_WARNING,
+            Severity::Error => cfg::COLOR_BANNER_ERROR,
+        }
+    }
+}
+
+/// Notice along the top edge for things that don't need a screen of their own.
+/// Drawn over the current screen, which needs a redraw once it goes away.
+pub struct Banner<'a> {
+    text: &'a str,
+    severity: Severity,
+}
+
+impl<'a> Banner<'a> {
+    pub fn new(text: &'a str, severity: Severity) -> Self {
+        Self { text, severity }
+    }
+
+    pub fn draw<D: AppDrawTarget<E>, E: Debug>(&self, display: &mut D) -> Result<(), E> {
+        let area = Rectangle::new(
+            Point::zero(),
+            Size::new(display.bounding_box().size.width, BANNER_HEIGHT),
+        );
+        display.fill_solid(&area, self.severity.color())?;
+        TINY_FONT
+            .render_aligned(
+                self.text,
+                area.center(),
+                VerticalPosition::Center,
+                HorizontalAlignment::Center,
+                FontColor::Transparent(cfg::COLOR_BACKGROUND),
+                display,
+            )
+            .map_err(font_error)?;
+        Ok(())
+    }
+}
@@ -1,34 +1,88 @@
 use core::fmt::Debug;
+use core::ops::Range;
 
+use app_measurements::PulseSegment;
 use embedded_graphics::geometry::{Point, Size};
 use embedded_graphics::primitives::{Line, Primitive, PrimitiveStyleBuilder, Rectangle};
 use embedded_graphics::Drawable;
-use heapless::{HistoryBuffer, String};
+use heapless::HistoryBuffer;
 #[cfg(feature = "cortex-m")]
 use micromath::F32Ext;
 use u8g2_fonts::types::{FontColor, HorizontalAlignment, VerticalPosition};
-use ufmt::uwrite;
 
 use crate::config::COLOR_BACKGROUND;
 use crate::fonts::TINY_FONT;
+use crate::format::micros_to_string;
+use crate::util::font_error;
 use crate::{config as cfg, AppDrawTarget};
 
+pub const MAX_ZOOM_LEVEL: u32 = 3;
+// Pan steps per visible window
+const PAN_STEPS: isize = 4;
+
+/// Zoom and pan state of the results chart, kept separately from the result itself
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ChartViewport {
+    /// Each level halves the visible window, 0 shows the whole buffer
+    pub zoom_level: u32,
+    /// In fractions of the visible window, relative to the focus point
+    pub pan: isize,
+}
+
+impl ChartViewport {
+    pub fn zoom_factor(&self) -> usize {
+        1 << self.zoom_level
+    }
+
+    pub fn is_zoomed(&self) -> bool {
+        self.zoom_level > 0
+    }
+
+    /// Steps through the zoom levels and wraps back to the full view
+    pub fn cycle_zoom(&mut self) {
+        self.zoom_level = (self.zoom_level + 1) % (MAX_ZOOM_LEVEL + 1);
+        self.pan = 0;
+    }
+
+    pub fn pan(&mut self, delta: isize) {
+        // Far enough to reach either end of the buffer from any focus point
+        let limit = PAN_STEPS * self.zoom_factor() as isize;
+        self.pan = (self.pan + delta).clamp(-limit, limit);
+    }
+
+    /// Sample range to show out of `len`, centered on `focus` before panning
+    pub fn window(&self, len: usize, focus: usize) -> Range<usize> {
+        if !self.is_zoomed() || len == 0 {
+            return 0..len;
+        }
+        let window_len = (len / self.zoom_factor()).max(1);
+        let center = focus as isize + self.pan * window_len as isize / PAN_STEPS;
+        let start = (center - window_len as isize / 2).clamp(0, (len - window_len) as isize);
+        start as usize..start as usize + window_len
+    }
+}
+
 #[allow(clippy::too_many_arguments)]
 pub fn draw_chart<const LEN: usize, D: AppDrawTarget<E>, E: Debug>(
     display: &mut D,
     chart: &HistoryBuffer<u16, LEN>,
+    window: Range<usize>,
     graph_y: i32,
+    graph_height: u32,
     samples_since_start: Option<usize>,
     samples_since_end: Option<usize>,
+    segments: &[PulseSegment],
     raw_micros: u64,
     integrated_micros: u64,
     clear: bool,
-) {
+) -> Result<(), E> {
     let padding = 10;
 
-    let len = chart.len();
+    let window = window.start.min(chart.len())..window.end.min(chart.len());
+    let len = window.len();
     let max_width = display.bounding_box().size.width - padding * 2;
 
+    // Scaled to the whole buffer so that the level doesn't jump while panning
     let mut y_min = *chart.iter().min().unwrap_or(&0);
     let mut y_max = *chart.iter().max().unwrap_or(&0).max(&(y_min + 1));
 
@@ -41,111 +95,145 @@ pub fn draw_chart<const LEN: usize, D: AppDrawTarget<E>, E: Debug>(
     // Leave some space below the baseline
     y_min = y_min.saturating_sub((y_max - y_min) / 5);
 
-    let chunk_size = ((len as f32 / max_width as f32).ceil() as u32).max(1);
-    let mut i = 0;
-    let mut done = false;
-    let mut iter = chart.oldest_ordered();
+    let chunk_size = ((len as f32 / max_width as f32).ceil() as usize).max(1);
+    let mut iter = chart.oldest_ordered().skip(window.start).take(len);
 
-    // Center the chart
-    let width = chart.len() as u32 / chunk_size;
+    // Narrow windows get stretched across the full width
+    let width = if len >= max_width as usize {
+        (len / chunk_size) as u32
+    } else {
+        max_width
+    };
 
+    // Center the chart
     let graph_rect = Rectangle::new(
         Point::new(
             (display.bounding_box().size.width / 2 - width / 2) as i32,
             graph_y,
         ),
-        Size::new(width, 20),
+        Size::new(width, graph_height),
     );
 
     if clear {
-        display
-            .fill_solid(&graph_rect, cfg::COLOR_BACKGROUND)
-            .unwrap();
+        display.fill_solid(&graph_rect, cfg::COLOR_BACKGROUND)?;
     }
 
-    let xy_to_coords = |x: u16, y: u16| {
-        let x = x / chunk_size as u16;
-        let y = (y - y_min) as i32;
+    let graph_bottom = graph_rect.bottom_right().unwrap().y;
+    let index_to_x = |index: usize| {
+        let offset = index.clamp(window.start, window.end) - window.start;
+        graph_rect.top_left.x + (offset * width as usize / len.max(1)) as i32
+    };
+    let value_to_y = |value: u16| {
+        let y = (value - y_min) as i32;
+        graph_bottom - y * graph_rect.size.height as i32 / (y_max - y_min) as i32
+    };
 
-        let y = y * graph_rect.size.height as i32 / (y_max - y_min) as i32;
+    let start_idx = samples_since_start.map(|s| chart.len().saturating_sub(s));
+    let end_idx = samples_since_end.map(|s| chart.len().saturating_sub(s));
 
-        let x = x as i32 + graph_rect.top_left.x;
-        let y = graph_rect.bottom_right().unwrap().y - y;
-        (x, y)
+    // A single pulse is already marked as the exposure, segments only matter for bursts
+    let segments = if segments.len() > 1 { segments } else { &[] };
+    let is_measured = |segment: &PulseSegment| {
+        segment.start < end_idx.unwrap_or(chart.len()) && segment.end > start_idx.unwrap_or(0)
     };
+    let segment_color = |index: usize| cfg::COLOR_SEGMENTS[index % cfg::COLOR_SEGMENTS.len()];
 
-    while !done {
+    let mut sample_index = window.start;
+    loop {
         let mut sum = 0;
         let mut count = 0;
-        for _ in 0..chunk_size {
-            if let Some(x) = iter.next() {
-                sum += x;
-                count += 1;
-            } else {
-                done = true;
-                break;
-            }
+        for x in iter.by_ref().take(chunk_size) {
+            sum += *x as u32;
+            count += 1;
         }
         if count == 0 {
             break;
         }
-        let avg: u16 = sum / count;
-
-        let sample_index = i * chunk_size as u16;
-        let is_integrated = sample_index
-            > chart.len() as u16 - samples_since_start.unwrap_or(0) as u16
-            && sample_index < chart.len() as u16 - samples_since_end.unwrap_or(0) as u16;
-
-        let (x, y) = xy_to_coords(sample_index, avg);
-
-        display
-            .fill_solid(
-                &Rectangle::with_corners(
-                    Point::new(x, y),
-                    Point::new(x, graph_rect.bottom_right().unwrap().y),
-                ),
-                if is_integrated {
-                    cfg::COLOR_CHART_2
-                } else {
-                    cfg::COLOR_CHART_1
-                },
-            )
-            .unwrap();
-        display
-            .fill_solid(
-                &Rectangle::new(Point::new(x, y), Size::new(2, 2)),
-                if is_integrated {
-                    cfg::COLOR_CHART_3
-                } else {
-                    cfg::COLOR_CHART_2
-                },
-            )
-            .unwrap();
-
-        i += 1;
+        let avg = (sum / count) as u16;
+
+        let is_integrated =
+            sample_index > start_idx.unwrap_or(0) && sample_index < end_idx.unwrap_or(chart.len());
+        let burst_segment = segments
+            .iter()
+            .position(|s| s.contains(sample_index) && !is_measured(s));
+
+        let x = index_to_x(sample_index);
+        let next_x = index_to_x(sample_index + count as usize).max(x + 1);
+        let y = value_to_y(avg);
+
+        display.fill_solid(
+            &Rectangle::with_corners(Point::new(x, y), Point::new(next_x - 1, graph_bottom)),
+            if is_integrated {
+                cfg::COLOR_CHART_2
+            } else {
+                cfg::COLOR_CHART_1
+            },
+        )?;
+        display.fill_solid(
+            &Rectangle::new(Point::new(x, y), Size::new((next_x - x).max(2) as u32, 2)),
+            match burst_segment {
+                Some(index) => segment_color(index),
+                None if is_integrated => cfg::COLOR_CHART_3,
+                None => cfg::COLOR_CHART_2,
+            },
+        )?;
+
+        sample_index += count as usize;
     }
 
-    let mut start_x = None;
-    let mut end_x = None;
-
-    let graph_bottom = graph_rect.bottom_right().unwrap().y;
-    if let Some(samples_since_start) = samples_since_start {
-        let start_idx = chart.len() - samples_since_start;
-        if let Some(start_y) = chart.get(start_idx) {
-            start_x = Some(xy_to_coords(start_idx as u16, *start_y).0);
+    let mut last_label_end = i32::MIN;
+    for (index, segment) in segments.iter().enumerate() {
+        if is_measured(segment)
+            || segment.duration_micros == 0
+            || segment.end <= window.start
+            || segment.start >= window.end
+        {
+            continue;
+        }
+        let label = micros_to_string(segment.duration_micros);
+        let position = Point::new(
+            (index_to_x(segment.start) + index_to_x(segment.end)) / 2,
+            graph_y,
+        );
+        let Ok(Some(bounds)) = TINY_FONT.get_rendered_dimensions_aligned(
+            &label[..],
+            position,
+            VerticalPosition::Top,
+            HorizontalAlignment::Center,
+        ) else {
+            continue;
+        };
+        // Narrow frames of a fast burst would pile their labels up
+        if bounds.top_left.x <= last_label_end
+            || bounds.top_left.x < graph_rect.top_left.x
+            || bounds.top_left.x + bounds.size.width as i32 > graph_rect.top_left.x + width as i32
+        {
+            continue;
         }
+        last_label_end = bounds.top_left.x + bounds.size.width as i32;
+        TINY_FONT
+            .render_aligned(
+                &label[..],
+                position,
+                VerticalPosition::Top,
+                HorizontalAlignment::Center,
+                FontColor::WithBackground {
+                    fg: segment_color(index),
+                    bg: cfg::COLOR_BACKGROUND,
+                },
+                display,
+            )
+            .map_err(font_error)?;
     }
 
-    if let Some(samples_since_end) = samples_since_end {
-        let end_idx = chart.len() - samples_since_end;
-        if let Some(end_y) = chart.get(end_idx) {
-            end_x = Some(xy_to_coords(end_idx as u16, *end_y).0);
+    if let (Some(start_idx), Some(end_idx)) = (start_idx, end_idx) {
+        // Nothing to mark if the exposure is entirely off screen
+        if end_idx < window.start || start_idx >= window.end {
+            return Ok(());
         }
-    }
 
-    if let (Some(start_x), Some(end_x)) = (start_x, end_x) {
-        let start_x = start_x.min(end_x);
-        let end_x = start_x.max(end_x);
+        let start_x = index_to_x(start_idx.min(end_idx));
+        let end_x = index_to_x(start_idx.max(end_idx));
 
         let line_y = graph_bottom + 7;
 
@@ -156,14 +244,15 @@ pub fn draw_chart<const LEN: usize, D: AppDrawTarget<E>, E: Debug>(
 
         Line::new(Point::new(start_x, line_y), Point::new(end_x, line_y))
             .into_styled(line_style)
-            .draw(display)
-            .unwrap();
+            .draw(display)?;
 
-        for x in [start_x, end_x] {
+        for (index, x) in [(start_idx, start_x), (end_idx, end_x)] {
+            if !window.contains(&index) {
+                continue;
+            }
             Line::new(Point::new(x, line_y - 3), Point::new(x, line_y + 3))
                 .into_styled(line_style)
-                .draw(display)
-                .unwrap();
+                .draw(display)?;
         }
 
         // display
@@ -189,7 +278,7 @@ pub fn draw_chart<const LEN: usize, D: AppDrawTarget<E>, E: Debug>(
                 },
                 display,
             )
-            .unwrap();
+            .map_err(font_error)?;
 
         TINY_FONT
             .with_line_height(20)
@@ -201,19 +290,7 @@ pub fn draw_chart<const LEN: usize, D: AppDrawTarget<E>, E: Debug>(
                 FontColor::Transparent(COLOR_BACKGROUND),
                 display,
             )
-            .unwrap();
+            .map_err(font_error)?;
     }
-}
-
-fn micros_to_string(micros: u64) -> String<128> {
-    let mut s = String::<128>::default();
-
-    if micros > 10000 {
-        let millis = micros / 1000;
-        uwrite!(s, " {} ms ", millis).unwrap();
-    } else {
-        uwrite!(s, " {} us ", micros).unwrap();
-    };
-
-    s
+    Ok(())
 }
@@ -1,3 +1,9 @@
 pub mod badge;
+pub mod banner;
 pub mod chart;
+pub mod offscreen;
+pub mod pager;
+pub mod progress;
 pub mod ruler;
+pub mod spinner;
+pub mod toast;
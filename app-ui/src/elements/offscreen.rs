@@ -0,0 +1,43 @@
+use embedded_graphics::draw_target::{DrawTarget, DrawTargetExt, Translated};
+use embedded_graphics::pixelcolor::Rgb565;
+use embedded_graphics::primitives::Rectangle;
+use embedded_graphics_framebuf::FrameBuf;
+
+/// Part of the screen drawn in memory and sent to the display in one transfer, so
+/// readouts repainted every frame don't show half drawn. `N` is the area's width
+/// times its height, it lives on the stack while drawing.
+pub struct OffscreenRegion<const N: usize> {
+    area: Rectangle,
+    buffer: FrameBuf<Rgb565, [Rgb565; N]>,
+}
+
+impl<const N: usize> OffscreenRegion<N> {
+    pub fn new(area: Rectangle, background: Rgb565) -> Self {
+        Self {
+            area,
+            buffer: FrameBuf::new(
+                [background; N],
+                area.size.width as usize,
+                area.size.height as usize,
+            ),
+        }
+    }
+
+    pub fn area(&self) -> Rectangle {
+        self.area
+    }
+
+    /// Relative to the top left of the area
+    pub fn buffer(&mut self) -> &mut FrameBuf<Rgb565, [Rgb565; N]> {
+        &mut self.buffer
+    }
+
+    /// In display coordinates, whatever falls outside the area is dropped
+    pub fn target(&mut self) -> Translated<'_, FrameBuf<Rgb565, [Rgb565; N]>> {
+        self.buffer.translated(-self.area.top_left)
+    }
+
+    pub fn blit<D: DrawTarget<Color = Rgb565>>(&self, display: &mut D) -> Result<(), D::Error> {
+        display.fill_contiguous(&self.area, self.buffer.data)
+    }
+}
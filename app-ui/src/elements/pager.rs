@@ -0,0 +1,27 @@
+use core::fmt::Debug;
+
+use embedded_graphics::geometry::Point;
+use embedded_graphics::primitives::{Circle, PrimitiveStyle, StyledDrawable};
+
+use crate::{config as cfg, AppDrawTarget};
+
+pub fn draw_page_indicator<D: AppDrawTarget<E>, E: Debug>(
+    display: &mut D,
+    center: Point,
+    count: usize,
+    active: usize,
+) -> Result<(), E> {
+    let spacing = 8;
+    let left = center.x - (count as i32 - 1) * spacing / 2;
+
+    for i in 0..count {
+        let color = if i == active {
+            cfg::COLOR_RESULT_VALUE
+        } else {
+            cfg::COLOR_RESULT_VALUE_INACTIVE
+        };
+        Circle::with_center(Point::new(left + i as i32 * spacing, center.y), 5)
+            .draw_styled(&PrimitiveStyle::with_fill(color), display)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,52 @@
+use core::fmt::Debug;
+
+use embedded_graphics::geometry::{Point, Size};
+use embedded_graphics::pixelcolor::Rgb565;
+use embedded_graphics::primitives::Rectangle;
+
+use crate::{config as cfg, AppDrawTarget};
+
+/// Horizontal bar filling up from the left. Both parts are drawn every time,
+/// so it updates in place without clearing.
+pub struct ProgressBar {
+    area: Rectangle,
+    color: Rgb565,
+}
+
+impl ProgressBar {
+    pub fn new(area: Rectangle) -> Self {
+        Self {
+            area,
+            color: cfg::COLOR_LEVEL,
+        }
+    }
+
+    pub fn with_color(mut self, color: Rgb565) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// Never quite empty, a sliver stays to show where the bar is
+    pub fn draw<D: AppDrawTarget<E>, E: Debug>(
+        &self,
+        display: &mut D,
+        value: u32,
+        max: u32,
+    ) -> Result<(), E> {
+        let Size { width, height } = self.area.size;
+        let filled = (value.min(max) as u64 * width as u64 / max.max(1) as u64).max(1) as u32;
+        let filled = filled.min(width);
+        display.fill_solid(
+            &Rectangle::new(self.area.top_left, Size::new(filled, height)),
+            self.color,
+        )?;
+        display.fill_solid(
+            &Rectangle::new(
+                self.area.top_left + Point::new(filled as i32, 0),
+                Size::new(width - filled, height),
+            ),
+            cfg::COLOR_RESULT_VALUE_INACTIVE,
+        )?;
+        Ok(())
+    }
+}
@@ -1,34 +1,153 @@
 use core::fmt::Debug;
 
-use app_measurements::util::{get_closest_shutter_speed, KNOWN_SHUTTER_DURATIONS};
+use app_measurements::util::SpeedTable;
+use app_measurements::ShutterSpeed;
 use embedded_graphics::geometry::{Point, Size};
 use embedded_graphics::pixelcolor::{Rgb565, RgbColor, WebColors};
-use embedded_graphics::primitives::Rectangle;
+use embedded_graphics::primitives::{Primitive, PrimitiveStyle, Rectangle, Triangle};
+use embedded_graphics::Drawable;
 use heapless::String;
-#[cfg(feature = "cortex-m")]
-use micromath::F32Ext;
 use u8g2_fonts::types::{FontColor, VerticalPosition};
 use ufmt::uwrite;
 
 use crate::fonts::TINY_FONT;
+use crate::util::font_error;
 use crate::{config as cfg, AppDrawTarget};
 
-pub fn draw_speed_ruler<D: AppDrawTarget<E>, E: Debug>(
-    display: &mut D,
+const RULER_HEIGHT: i32 = 5;
+/// Per stop
+const STOP_WIDTH: f32 = 30.0;
+/// Below the speed labels
+const CURSOR_OFFSET_Y: i32 = 13;
+const CURSOR_HEIGHT: i32 = 5;
+
+/// Which table speed the encoder points at on the results ruler, kept separately from
+/// the result like [`crate::ChartViewport`]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RulerCursor {
+    /// Turning moves the cursor instead of the pages
+    pub active: bool,
+    /// Into [`SpeedTable::durations`]
+    pub index: usize,
+    len: usize,
+}
+
+impl RulerCursor {
+    /// Starting out on the speed at `index` of a table `len` long
+    pub fn new(index: usize, len: usize) -> Self {
+        Self {
+            active: false,
+            index,
+            len,
+        }
+    }
+
+    pub fn toggle(&mut self) {
+        self.active = !self.active;
+    }
+
+    /// Towards faster speeds for positive `delta`, stopping at either end
+    pub fn step(&mut self, delta: isize) {
+        self.index =
+            (self.index as isize + delta).clamp(0, self.len.saturating_sub(1) as isize) as usize;
+    }
+}
+
+/// Laid out once around the measured time, so that the cursor can be drawn over it later
+#[derive(Clone, Copy, Debug)]
+pub struct SpeedRuler {
     origin: Point,
+    width: u32,
+    /// Puts the measured time in the middle
+    x_offset: i32,
     actual_duration_secs: f32,
-) {
-    let width = display.bounding_box().size.width;
-    let ruler_height = 5;
+    speeds: SpeedTable,
+    // What the shutter is marked as, the closest table speed otherwise
+    nominal_duration_secs: Option<f32>,
+}
+
+impl SpeedRuler {
+    pub fn new(
+        origin: Point,
+        width: u32,
+        actual_duration_secs: f32,
+        speeds: SpeedTable,
+        nominal_duration_secs: Option<f32>,
+    ) -> Self {
+        let mut ruler = Self {
+            origin,
+            width,
+            x_offset: 0,
+            actual_duration_secs,
+            speeds,
+            nominal_duration_secs,
+        };
+        ruler.x_offset = width as i32 / 2 - ruler.x_for(actual_duration_secs);
+        ruler
+    }
+
+    /// Where the tick for `duration_secs` goes, off screen for the far ends of the table
+    pub fn x_for(&self, duration_secs: f32) -> i32 {
+        self.origin.x
+            + self.x_offset
+            + (ShutterSpeed::from_secs(duration_secs).stops() * STOP_WIDTH) as i32
+    }
+
+    /// Table speed the measured time is compared with unless a cursor says otherwise
+    pub fn best_match(&self) -> f32 {
+        self.nominal_duration_secs
+            .unwrap_or_else(|| self.speeds.closest(self.actual_duration_secs))
+    }
 
-    let duration_to_x_offset = |d: f32| ((1.0 / d).log2() * 30.0) as i32;
+    /// Index of [`Self::best_match`] in the table, or the table speed closest to it
+    pub fn best_match_index(&self) -> usize {
+        let best_match = self.speeds.closest(self.best_match());
+        self.speeds
+            .durations()
+            .iter()
+            .position(|&d| d == best_match)
+            .unwrap_or(0)
+    }
 
-    let actual_x = origin.x + duration_to_x_offset(actual_duration_secs);
+    /// A mark under the tick for `duration_secs`, replacing the previous one
+    pub fn draw_cursor<D: AppDrawTarget<E>, E: Debug>(
+        &self,
+        display: &mut D,
+        duration_secs: Option<f32>,
+    ) -> Result<(), E> {
+        let y = self.origin.y + CURSOR_OFFSET_Y;
+        display.fill_solid(
+            &Rectangle::new(
+                Point::new(0, y),
+                Size::new(self.width, CURSOR_HEIGHT as u32 + 1),
+            ),
+            cfg::COLOR_BACKGROUND,
+        )?;
+        let Some(duration_secs) = duration_secs else {
+            return Ok(());
+        };
+        let x = self.x_for(duration_secs);
+        Triangle::new(
+            Point::new(x, y),
+            Point::new(x - CURSOR_HEIGHT, y + CURSOR_HEIGHT),
+            Point::new(x + CURSOR_HEIGHT, y + CURSOR_HEIGHT),
+        )
+        .into_styled(PrimitiveStyle::with_fill(cfg::COLOR_NEAREST_SPEED))
+        .draw(display)
+    }
 
-    let overall_x_offset = width as i32 / 2 - actual_x;
+    pub fn draw<D: AppDrawTarget<E>, E: Debug>(&self, display: &mut D) -> Result<(), E> {
+        let Self {
+            origin,
+            width,
+            actual_duration_secs,
+            speeds,
+            nominal_duration_secs,
+            ..
+        } = *self;
+        let ruler_height = RULER_HEIGHT;
 
-    display
-        .fill_contiguous(
+        display.fill_contiguous(
             &Rectangle::new(
                 origin - Point::new(0, ruler_height),
                 Size::new(width - 1, ruler_height as u32),
@@ -42,63 +161,64 @@ pub fn draw_speed_ruler<D: AppDrawTarget<E>, E: Debug>(
             .iter()
             .cycle()
             .cloned(),
-        )
-        .unwrap();
+        )?;
 
-    display
-        .fill_solid(
+        display.fill_solid(
             &Rectangle::new(origin, Size::new(width, 1)),
             cfg::COLOR_RULER,
-        )
-        .unwrap();
-    display
-        .fill_solid(
+        )?;
+        display.fill_solid(
             &Rectangle::new(origin + Point::new(0, -ruler_height), Size::new(width, 1)),
             cfg::COLOR_RULER,
-        )
-        .unwrap();
-
-    let best_match = get_closest_shutter_speed(actual_duration_secs);
-
-    for (duration, bottom) in KNOWN_SHUTTER_DURATIONS
-        .iter()
-        .map(|x| (x, true))
-        .chain([(&actual_duration_secs, false)].iter().copied())
-    {
-        let x = origin.x + overall_x_offset + duration_to_x_offset(*duration);
-        let y = origin.y;
-        let mut s = String::<128>::default();
-        s.clear();
-        let mut color = if duration >= &1.0 {
-            uwrite!(s, " {} ", duration.round() as u32).unwrap();
-            Rgb565::CSS_ORANGE
-        } else {
-            uwrite!(s, " {} ", (1.0 / duration).round() as u32).unwrap();
-            Rgb565::CSS_PALE_GREEN
-        };
+        )?;
 
-        if actual_duration_secs == *duration {
-            color = cfg::COLOR_RESULT_VALUE;
-        }
-        if best_match == *duration {
-            color = cfg::COLOR_NEAREST_SPEED;
-        }
+        let best_match = self.best_match();
+        // A custom speed that isn't in the table still gets its own tick
+        let extra_nominal = nominal_duration_secs.filter(|d| !speeds.durations().contains(d));
+        // Dense tables don't leave room to label every speed
+        let mut last_label_end = i32::MIN;
 
-        let label_size = TINY_FONT
-            .get_rendered_dimensions(&s[..], Point::zero(), VerticalPosition::Top)
-            .unwrap();
-        let label_origin = Point::new(
-            x - label_size.bounding_box.unwrap().size.width as i32 / 2,
-            if bottom { y + 3 } else { y - ruler_height - 11 },
-        );
-
-        let label_off_screen = label_origin.x + label_size.bounding_box.unwrap().size.width as i32
-            > width as i32
-            || label_origin.x < 0;
-
-        if x > 1 && x < width as i32 - 2 {
-            display
-                .fill_solid(
+        for (duration, bottom) in speeds
+            .durations()
+            .iter()
+            .map(|x| (x, true))
+            .chain(extra_nominal.as_ref().map(|x| (x, true)))
+            .chain([(&actual_duration_secs, false)].iter().copied())
+        {
+            let x = self.x_for(*duration);
+            let y = origin.y;
+            let mut s = String::<128>::default();
+            s.clear();
+            let speed = ShutterSpeed::from_secs(*duration);
+            uwrite!(s, " {} ", speed.dial_number()).unwrap();
+            let mut color = if speed.is_fraction() {
+                Rgb565::CSS_PALE_GREEN
+            } else {
+                Rgb565::CSS_ORANGE
+            };
+
+            if actual_duration_secs == *duration {
+                color = cfg::COLOR_RESULT_VALUE;
+            }
+            if best_match == *duration {
+                color = cfg::COLOR_NEAREST_SPEED;
+            }
+
+            let label_size = TINY_FONT
+                .get_rendered_dimensions(&s[..], Point::zero(), VerticalPosition::Top)
+                .unwrap();
+            let label_origin = Point::new(
+                x - label_size.bounding_box.unwrap().size.width as i32 / 2,
+                if bottom { y + 3 } else { y - ruler_height - 11 },
+            );
+
+            let label_end = label_origin.x + label_size.bounding_box.unwrap().size.width as i32;
+            let label_off_screen = label_end > width as i32
+                || label_origin.x < 0
+                || (bottom && best_match != *duration && label_origin.x < last_label_end);
+
+            if x > 1 && x < width as i32 - 2 {
+                display.fill_solid(
                     &Rectangle::new(
                         Point::new(x - 1, y - ruler_height)
                             + if bottom {
@@ -112,33 +232,37 @@ pub fn draw_speed_ruler<D: AppDrawTarget<E>, E: Debug>(
                         ),
                     ),
                     color,
+                )?;
+            }
+
+            if label_off_screen {
+                continue;
+            }
+            if bottom {
+                last_label_end = label_end;
+            }
+            TINY_FONT
+                .render(
+                    &s[..],
+                    label_origin,
+                    VerticalPosition::Top,
+                    FontColor::WithBackground {
+                        bg: color,
+                        fg: Rgb565::BLACK,
+                    },
+                    display,
                 )
-                .unwrap();
+                .map_err(font_error)?;
         }
 
-        if label_off_screen {
-            continue;
-        }
-        TINY_FONT
-            .render(
-                &s[..],
-                label_origin,
-                VerticalPosition::Top,
-                FontColor::WithBackground {
-                    bg: color,
-                    fg: Rgb565::BLACK,
-                },
-                display,
-            )
-            .unwrap();
+        // Pointer::new(
+        //     Point::new(self.x_for(actual_duration_secs) - 2, origin.y - ruler_height - 1),
+        //     12,
+        //     false,
+        //     Rgb565::WHITE,
+        // )
+        // .draw(display)
+        // .unwrap();
+        Ok(())
     }
-
-    // Pointer::new(
-    //     Point::new(overall_x_offset + actual_x - 2, origin.y - ruler_height - 1),
-    //     12,
-    //     false,
-    //     Rgb565::WHITE,
-    // )
-    // .draw(display)
-    // .unwrap();
 }
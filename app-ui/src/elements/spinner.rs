@@ -0,0 +1,84 @@
+use core::fmt::Write;
+
+/// Number edited with the encoder. Written at a fixed width, so that it can be
+/// redrawn in place whatever the value.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Spinner {
+    pub value: u16,
+    min: u16,
+    max: u16,
+    step: u16,
+    digits: usize,
+    unit: &'static str,
+    /// Written instead of 0
+    zero_label: Option<&'static str>,
+}
+
+impl Spinner {
+    pub fn new(value: u16, min: u16, max: u16) -> Self {
+        let mut digits = 1;
+        while 10u32.pow(digits as u32) <= max as u32 {
+            digits += 1;
+        }
+        Self {
+            value: value.clamp(min, max),
+            min,
+            max,
+            step: 1,
+            digits,
+            unit: "",
+            zero_label: None,
+        }
+    }
+
+    pub fn with_step(mut self, step: u16) -> Self {
+        self.step = step.max(1);
+        self
+    }
+
+    /// Wider than the maximum needs, to line up with other values
+    pub fn with_digits(mut self, digits: usize) -> Self {
+        self.digits = digits;
+        self
+    }
+
+    pub fn with_unit(mut self, unit: &'static str) -> Self {
+        self.unit = unit;
+        self
+    }
+
+    /// For settings that 0 turns off
+    pub fn with_zero_label(mut self, label: &'static str) -> Self {
+        self.zero_label = Some(label);
+        self
+    }
+
+    /// By encoder detents, stops at either end
+    pub fn adjust(&mut self, delta: i32) -> u16 {
+        self.value = (self.value as i32 + delta * self.step as i32)
+            .clamp(self.min as i32, self.max as i32) as u16;
+        self.value
+    }
+
+    pub fn write(&self, s: &mut impl Write) {
+        match self.zero_label {
+            Some(label) if self.value == 0 => {
+                write!(
+                    s,
+                    "{:<width$}",
+                    label,
+                    width = self.digits + self.unit.len()
+                )
+                .unwrap();
+            }
+            _ => write!(
+                s,
+                "{:>digits$}{}",
+                self.value,
+                self.unit,
+                digits = self.digits
+            )
+            .unwrap(),
+        }
+    }
+}
@@ -0,0 +1,53 @@
+use core::fmt::Debug;
+
+use embedded_graphics::geometry::{Point, Size};
+use embedded_graphics::pixelcolor::Rgb565;
+use embedded_graphics::primitives::Rectangle;
+use u8g2_fonts::types::{FontColor, HorizontalAlignment, VerticalPosition};
+
+use crate::fonts::TINY_FONT;
+use crate::util::font_error;
+use crate::{config as cfg, AppDrawTarget};
+
+const TOAST_HEIGHT: u32 = 14;
+
+/// Banner along the bottom edge, e.g. for errors that the app recovered from.
+/// Drawn over the current screen, which needs a redraw once it goes away.
+pub struct Toast<'a> {
+    text: &'a str,
+    color: Rgb565,
+}
+
+impl<'a> Toast<'a> {
+    pub fn new(text: &'a str) -> Self {
+        Self {
+            text,
+            color: cfg::COLOR_TOAST,
+        }
+    }
+
+    pub fn with_color(mut self, color: Rgb565) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub fn draw<D: AppDrawTarget<E>, E: Debug>(&self, display: &mut D) -> Result<(), E> {
+        let size = display.bounding_box().size;
+        let area = Rectangle::new(
+            Point::new(0, (size.height - TOAST_HEIGHT) as i32),
+            Size::new(size.width, TOAST_HEIGHT),
+        );
+        display.fill_solid(&area, self.color)?;
+        TINY_FONT
+            .render_aligned(
+                self.text,
+                area.center(),
+                VerticalPosition::Center,
+                HorizontalAlignment::Center,
+                FontColor::Transparent(cfg::COLOR_BACKGROUND),
+                display,
+            )
+            .map_err(font_error)?;
+        Ok(())
+    }
+}
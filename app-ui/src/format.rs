@@ -1,5 +1,6 @@
 use core::fmt::Debug;
 
+use heapless::String;
 use ufmt::{uWrite, uwrite};
 
 pub fn write_fraction<E: Debug, W: uWrite<Error = E>>(s: &mut W, fraction: f32) {
@@ -10,3 +11,16 @@ pub fn write_fraction<E: Debug, W: uWrite<Error = E>>(s: &mut W, fraction: f32)
         uwrite!(s, ".{}", fr as u32).unwrap();
     }
 }
+
+pub fn micros_to_string(micros: u64) -> String<128> {
+    let mut s = String::<128>::default();
+
+    if micros > 10000 {
+        let millis = micros / 1000;
+        uwrite!(s, " {} ms ", millis).unwrap();
+    } else {
+        uwrite!(s, " {} us ", micros).unwrap();
+    };
+
+    s
+}
@@ -1,31 +1,55 @@
+use core::fmt::Debug;
+
 use embedded_graphics::draw_target::DrawTarget;
-use embedded_graphics::geometry::Dimensions;
+use embedded_graphics::geometry::{Dimensions, Point, Size};
 use embedded_graphics::pixelcolor::{IntoStorage, Rgb565, RgbColor};
 use embedded_graphics::primitives::{PointsIter, Rectangle};
 use embedded_graphics::Pixel;
+use heapless::Vec;
 
+use crate::config::{COLOR_BACKGROUND, COLOR_MENU_ACTION};
+use crate::util::delay_ms;
 use crate::{AppDrawTarget, HintRefresh};
 
+const TRANSITION_STEPS: i32 = 4;
+const TRANSITION_STEP_MS: u32 = 30;
+/// Interlaced line order, spread out so that the fade looks even
+const FADE_LINES: [i32; TRANSITION_STEPS as usize] = [0, 2, 1, 3];
+
+/// The checkerboard flips at this pace whatever the frame rate
+const FLIP_PERIOD_MS: u32 = 100;
+/// Brightness swing at full intensity, out of 255
+const MAX_DELTA: u16 = 50;
+pub const FX_MAX_EXCLUSIONS: usize = 4;
+
 pub struct FX<'a, DT: AppDrawTarget<E>, E> {
     target: &'a mut DT,
-    params: FXParams,
+    params: &'a FXParams,
     _p: core::marker::PhantomData<E>,
 }
 
-#[derive(Copy, Clone)]
+#[derive(Clone, Debug)]
 pub struct FXParams {
-    t: u32,
+    phase: u32,
+    /// Per channel swing in Rgb565 units
+    delta: [u8; 3],
+    exclusions: Vec<Rectangle, FX_MAX_EXCLUSIONS>,
 }
 
-#[allow(clippy::derivable_impls)]
 impl Default for FXParams {
     fn default() -> Self {
-        Self { t: 0 }
+        let mut params = Self {
+            phase: 0,
+            delta: [0; 3],
+            exclusions: Vec::new(),
+        };
+        params.set_intensity(100);
+        params
     }
 }
 
 impl<'a, DT: AppDrawTarget<E>, E> FX<'a, DT, E> {
-    pub fn new(target: &'a mut DT, params: FXParams) -> Self {
+    pub fn new(target: &'a mut DT, params: &'a FXParams) -> Self {
         Self {
             target,
             params,
@@ -37,39 +61,138 @@ impl<'a, DT: AppDrawTarget<E>, E> FX<'a, DT, E> {
         self.target
     }
 
-    fn map_pixel(mut p: Pixel<Rgb565>, params: FXParams) -> Rgb565 {
-        if p.1.into_storage() == 0 {
+    fn map_pixel(mut p: Pixel<Rgb565>, params: &FXParams) -> Rgb565 {
+        if p.1.into_storage() == 0 || params.delta == [0; 3] || params.is_excluded(p.0) {
             return p.1;
         }
-        let is_odd = (p.0.x % 2 == 1) ^ (p.0.y % 2 == 1) ^ (params.t % 2 == 1);
-        const D: u16 = 50;
-        const DR: u8 = (D * Rgb565::MAX_R as u16 / 255) as u8;
-        const DG: u8 = (D * Rgb565::MAX_G as u16 / 255) as u8;
-        const DB: u8 = (D * Rgb565::MAX_B as u16 / 255) as u8;
+        let is_odd = (p.0.x % 2 == 1) ^ (p.0.y % 2 == 1) ^ (params.phase % 2 == 1);
+        let [dr, dg, db] = params.delta;
         if is_odd {
             p.1 = Rgb565::new(
-                p.1.r().saturating_sub(DR),
-                p.1.g().saturating_sub(DG),
-                p.1.b().saturating_sub(DB),
+                p.1.r().saturating_sub(dr),
+                p.1.g().saturating_sub(dg),
+                p.1.b().saturating_sub(db),
             );
         } else {
             p.1 = Rgb565::new(
-                (p.1.r() + DR).min(Rgb565::MAX_R),
-                (p.1.g() + DG).min(Rgb565::MAX_G),
-                (p.1.b() + DB).min(Rgb565::MAX_B),
+                (p.1.r() + dr).min(Rgb565::MAX_R),
+                (p.1.g() + dg).min(Rgb565::MAX_G),
+                (p.1.b() + db).min(Rgb565::MAX_B),
             );
         }
         p.1
     }
+}
+
+impl FXParams {
+    pub fn set_time(&mut self, animation_time_ms: u32) {
+        self.phase = animation_time_ms / FLIP_PERIOD_MS;
+    }
 
-    pub fn step_params(&mut self) {
-        self.params.step();
+    pub fn set_intensity(&mut self, percent: u8) {
+        let delta = MAX_DELTA * percent.min(100) as u16 / 100;
+        self.delta = [Rgb565::MAX_R, Rgb565::MAX_G, Rgb565::MAX_B]
+            .map(|max| (delta * max as u16 / 255) as u8);
+    }
+
+    /// Areas drawn as they are, e.g. to keep text crisp. Only the first
+    /// [`FX_MAX_EXCLUSIONS`] are kept.
+    pub fn set_exclusions(&mut self, areas: &[Rectangle]) {
+        self.exclusions.clear();
+        for area in areas.iter().take(FX_MAX_EXCLUSIONS) {
+            let _ = self.exclusions.push(*area);
+        }
+    }
+
+    fn is_excluded(&self, point: Point) -> bool {
+        self.exclusions.iter().any(|area| area.contains(point))
     }
 }
 
-impl FXParams {
-    pub fn step(&mut self) {
-        self.t += 1;
+/// Played by [`crate::ScreenStack`] when the screen changes, only with the `effects` feature
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Transition {
+    #[default]
+    Off,
+    /// Wipes the old screen off sideways, backwards when a screen is popped
+    Slide,
+    /// Blanks the old screen a few interlaced lines at a time
+    Fade,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum TransitionDirection {
+    Forward,
+    Back,
+}
+
+impl Transition {
+    pub const ALL: [Transition; 3] = [Transition::Off, Transition::Slide, Transition::Fade];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Transition::Off => "OFF",
+            Transition::Slide => "SLIDE",
+            Transition::Fade => "FADE",
+        }
+    }
+
+    pub fn next(&self) -> Self {
+        let index = Self::ALL.iter().position(|t| t == self).unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+
+    /// Covers up the old screen before the next one draws itself
+    pub(crate) async fn draw<DT: AppDrawTarget<E>, E: Debug>(
+        &self,
+        display: &mut DT,
+        direction: TransitionDirection,
+    ) -> Result<(), E> {
+        if !cfg!(feature = "effects") || *self == Transition::Off {
+            return Ok(());
+        }
+
+        let area = display.bounding_box();
+        let size = area.size;
+        let (width, height) = (size.width as i32, size.height as i32);
+        let band = width / TRANSITION_STEPS + 1;
+
+        for step in 0..TRANSITION_STEPS {
+            match self {
+                Transition::Off => (),
+                Transition::Slide => {
+                    let (x, edge_x) = match direction {
+                        TransitionDirection::Forward => (step * band, (step + 1) * band),
+                        TransitionDirection::Back => {
+                            (width - (step + 1) * band, width - (step + 1) * band - 1)
+                        }
+                    };
+                    display.fill_solid(
+                        &Rectangle::new(Point::new(x, 0), Size::new(band as u32, size.height))
+                            .intersection(&area),
+                        COLOR_BACKGROUND,
+                    )?;
+                    // Falls into the next band, which covers it again
+                    if step < TRANSITION_STEPS - 1 {
+                        display.fill_solid(
+                            &Rectangle::new(Point::new(edge_x, 0), Size::new(1, size.height)),
+                            COLOR_MENU_ACTION,
+                        )?;
+                    }
+                }
+                Transition::Fade => {
+                    for y in (FADE_LINES[step as usize]..height).step_by(FADE_LINES.len()) {
+                        display.fill_solid(
+                            &Rectangle::new(Point::new(0, y), Size::new(size.width, 1)),
+                            COLOR_BACKGROUND,
+                        )?;
+                    }
+                }
+            }
+            display.hint_refresh();
+            delay_ms(TRANSITION_STEP_MS).await;
+        }
+        Ok(())
     }
 }
 
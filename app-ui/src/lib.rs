@@ -2,12 +2,15 @@
 
 use embedded_graphics::draw_target::DrawTarget;
 use embedded_graphics::pixelcolor::Rgb565;
+use embedded_graphics::primitives::Rectangle;
 
+mod budget;
 mod config;
 mod elements;
 pub mod fonts;
 mod format;
 mod fx;
+mod palette;
 pub mod panic;
 mod primitives;
 mod screens;
@@ -15,16 +18,35 @@ mod util;
 
 pub use elements::*;
 pub use screens::{
-    BootScreen, CalibrationScreen, DebugScreen, DrawFrameContext, MeasurementScreen, MenuScreen,
-    NoAccessoryScreen, ResultsScreen, Screen, Screens, StartScreen, UpdateScreen,
+    AboutScreen, AnnotationEditor, AnnotationField, AnnotationScreen, BeeperTuner,
+    BeeperTuningScreen, BootDetails, BootScreen, BuildInfo, CalibrationScreen, ClockFaultScreen,
+    CounterScreen, DebugPage, DebugScreen, DisplayGeometryEditor, DisplayGeometryScreen,
+    DrawFrameContext, FocalPlaneScreen, GainScreen, MeasurementScreen, MemoryUsage, MenuItem,
+    MenuPage, MenuScreen, MenuValue, Navigation, NoAccessoryScreen, ResultsScreen, ResumeScreen,
+    ScanScreen, Screen, ScreenStack, Screens, SelfCheckScreen, SensorFaultScreen, SequenceScreen,
+    SoakScreen, StartScreen, ThresholdEditor, ThresholdSelection, UpdateScreen, BEEPER_OFFSETS,
+    MEMORY_BUFFERS, NAVIGATION_DEPTH,
 };
 
 pub trait HintRefresh {
     fn hint_refresh(&mut self);
+
+    /// Areas of the current screen to keep out of the FX modulation
+    fn hint_fx_exclusions(&mut self, _areas: &[Rectangle]) {}
 }
 
+/// Screens draw in Rgb565, other panels go through a [`PaletteTarget`]
 pub trait AppDrawTarget<E>: DrawTarget<Color = Rgb565, Error = E> + HintRefresh {}
 impl<E, D: DrawTarget<Color = Rgb565, Error = E> + HintRefresh> AppDrawTarget<E> for D {}
 
 pub use badge::draw_badge;
-pub use fx::{FXParams, FX};
+pub use banner::{Banner, Severity};
+pub use budget::{DrawBudget, DrawProgress};
+pub use chart::ChartViewport;
+pub use fx::{FXParams, Transition, FX, FX_MAX_EXCLUSIONS};
+pub use offscreen::OffscreenRegion;
+pub use palette::{Monochrome, Palette, PaletteTarget};
+pub use progress::ProgressBar;
+pub use ruler::{RulerCursor, SpeedRuler};
+pub use spinner::Spinner;
+pub use toast::Toast;
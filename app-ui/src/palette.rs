@@ -0,0 +1,112 @@
+use embedded_graphics::draw_target::DrawTarget;
+use embedded_graphics::geometry::Dimensions;
+use embedded_graphics::pixelcolor::{BinaryColor, PixelColor, Rgb565, RgbColor};
+use embedded_graphics::primitives::Rectangle;
+use embedded_graphics::Pixel;
+
+use crate::HintRefresh;
+
+/// Turns the colors screens draw with into whatever the panel takes
+pub trait Palette<C: PixelColor> {
+    fn map(&self, color: Rgb565) -> C;
+}
+
+/// Lights up everything at least as bright as `threshold`, pairs with the
+/// `monochrome` theme where every color is either black or white
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Monochrome {
+    /// Luma out of 255
+    pub threshold: u8,
+}
+
+impl Default for Monochrome {
+    fn default() -> Self {
+        // Keeps the dimmest chart and inactive colors of the color theme visible
+        Self { threshold: 16 }
+    }
+}
+
+impl Palette<BinaryColor> for Monochrome {
+    fn map(&self, color: Rgb565) -> BinaryColor {
+        let r = (color.r() as u32) << 3;
+        let g = (color.g() as u32) << 2;
+        let b = (color.b() as u32) << 3;
+        let luma = (77 * r + 150 * g + 29 * b) >> 8;
+        if luma >= self.threshold as u32 {
+            BinaryColor::On
+        } else {
+            BinaryColor::Off
+        }
+    }
+}
+
+/// Lets a display of any color type stand in as an [`crate::AppDrawTarget`]
+pub struct PaletteTarget<D, P> {
+    target: D,
+    palette: P,
+}
+
+impl<D: DrawTarget, P: Palette<D::Color>> PaletteTarget<D, P> {
+    pub fn new(target: D, palette: P) -> Self {
+        Self { target, palette }
+    }
+
+    pub fn inner(&mut self) -> &mut D {
+        &mut self.target
+    }
+
+    pub fn into_inner(self) -> D {
+        self.target
+    }
+}
+
+impl<D: HintRefresh, P> HintRefresh for PaletteTarget<D, P> {
+    fn hint_refresh(&mut self) {
+        self.target.hint_refresh();
+    }
+
+    fn hint_fx_exclusions(&mut self, areas: &[Rectangle]) {
+        self.target.hint_fx_exclusions(areas);
+    }
+}
+
+impl<D: DrawTarget, P: Palette<D::Color>> DrawTarget for PaletteTarget<D, P> {
+    type Color = Rgb565;
+    type Error = D::Error;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        let palette = &self.palette;
+        self.target.draw_iter(
+            pixels
+                .into_iter()
+                .map(|Pixel(point, color)| Pixel(point, palette.map(color))),
+        )
+    }
+
+    fn fill_contiguous<I>(&mut self, area: &Rectangle, colors: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Self::Color>,
+    {
+        let palette = &self.palette;
+        self.target
+            .fill_contiguous(area, colors.into_iter().map(|color| palette.map(color)))
+    }
+
+    // Mapped once instead of per pixel, these are most of what gets drawn
+    fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Self::Error> {
+        self.target.fill_solid(area, self.palette.map(color))
+    }
+
+    fn clear(&mut self, color: Self::Color) -> Result<(), Self::Error> {
+        self.target.clear(self.palette.map(color))
+    }
+}
+
+impl<D: DrawTarget, P> Dimensions for PaletteTarget<D, P> {
+    fn bounding_box(&self) -> Rectangle {
+        self.target.bounding_box()
+    }
+}
@@ -0,0 +1,174 @@
+use core::fmt::{Debug, Write};
+
+use embedded_graphics::geometry::{Point, Size};
+use embedded_graphics::pixelcolor::{Rgb565, RgbColor, WebColors};
+use embedded_graphics::primitives::Rectangle;
+use heapless::String;
+use u8g2_fonts::types::{FontColor, HorizontalAlignment, VerticalPosition};
+
+use super::{DrawFrameContext, Navigation, Screen};
+use crate::fonts::TINY_FONT;
+use crate::util::font_error;
+use crate::{config, draw_badge, AppDrawTarget};
+
+/// Baked in by the firmware build script
+#[derive(Clone, Copy, Debug)]
+pub struct BuildInfo {
+    pub version: &'static str,
+    pub git_hash: &'static str,
+    pub build_date: &'static str,
+    pub hal_version: &'static str,
+    pub rtic_version: &'static str,
+}
+
+/// What the bootloader recorded on the way in
+#[derive(Clone, Copy, Debug)]
+pub struct BootDetails {
+    pub boot_count: u32,
+    pub reset_cause: &'static str,
+    /// `None` when the image was flashed without a checksum
+    pub app_crc_valid: Option<bool>,
+}
+
+pub struct AboutScreen<DT, E> {
+    pub uptime_secs: u32,
+    pub measurement_count: u32,
+    pub boot: Option<BootDetails>,
+    /// Any input closes the page, back to the menu
+    pub closed: bool,
+    build: BuildInfo,
+    hardware: String<24>,
+    drawn: Option<(u32, u32)>,
+    _phantom: core::marker::PhantomData<(DT, E)>,
+}
+
+const ROWS_Y: i32 = 30;
+const ROW_HEIGHT: i32 = 13;
+const MARGIN: i32 = 4;
+const COLOR: Rgb565 = Rgb565::CSS_PALE_GOLDENROD;
+const ROWS: i32 = 10;
+/// Spans the full width on any display
+const TEXT_AREA: [Rectangle; 1] = [Rectangle::new(
+    Point::new(0, ROWS_Y),
+    Size::new(u16::MAX as u32, (ROWS * ROW_HEIGHT) as u32),
+)];
+
+impl<DT: AppDrawTarget<E>, E: Debug> AboutScreen<DT, E> {
+    pub fn new(build: BuildInfo, hardware: &str) -> Self {
+        let mut s = String::new();
+        let _ = s.push_str(hardware);
+        Self {
+            uptime_secs: 0,
+            measurement_count: 0,
+            boot: None,
+            build,
+            hardware: s,
+            closed: false,
+            drawn: None,
+            _phantom: core::marker::PhantomData,
+        }
+    }
+
+    fn draw_row(display: &mut DT, index: i32, name: &str, value: &str) -> Result<(), E> {
+        let y = ROWS_Y + index * ROW_HEIGHT;
+        TINY_FONT
+            .render(
+                name,
+                Point::new(MARGIN, y),
+                VerticalPosition::Top,
+                FontColor::WithBackground {
+                    fg: config::COLOR_BACKGROUND,
+                    bg: COLOR,
+                },
+                display,
+            )
+            .map_err(font_error)?;
+        TINY_FONT
+            .render_aligned(
+                value,
+                Point::new(display.bounding_box().size.width as i32 - MARGIN, y),
+                VerticalPosition::Top,
+                HorizontalAlignment::Right,
+                FontColor::WithBackground {
+                    fg: config::COLOR_RESULT_VALUE,
+                    bg: config::COLOR_BACKGROUND,
+                },
+                display,
+            )
+            .map_err(font_error)?;
+        Ok(())
+    }
+}
+
+impl<DT: AppDrawTarget<E>, E: Debug> Screen<DT, E> for AboutScreen<DT, E> {
+    async fn draw_init(&mut self, display: &mut DT) -> Result<(), E> {
+        display.clear(config::COLOR_BACKGROUND)?;
+        self.drawn = None;
+
+        draw_badge(
+            display,
+            Point::new(display.bounding_box().center().x, 8),
+            " ABOUT ",
+            Rgb565::BLACK,
+            COLOR,
+        )
+        .await?;
+
+        let rows = [
+            (" VERSION ", self.build.version),
+            (" GIT ", self.build.git_hash),
+            (" BUILT ", self.build.build_date),
+            (" HAL ", self.build.hal_version),
+            (" RTIC ", self.build.rtic_version),
+            (" MCU ", &self.hardware[..]),
+        ];
+        for (index, (name, value)) in rows.iter().enumerate() {
+            Self::draw_row(display, index as i32, name, value)?;
+        }
+
+        let mut s = String::<32>::default();
+        match self.boot {
+            Some(boot) => write!(s, "{} {}", boot.boot_count, boot.reset_cause).unwrap(),
+            None => s.push_str("--").unwrap(),
+        }
+        Self::draw_row(display, 6, " BOOTS ", &s[..])?;
+        let crc = match self.boot.and_then(|boot| boot.app_crc_valid) {
+            Some(true) => "OK",
+            Some(false) => "BAD",
+            None => "--",
+        };
+        Self::draw_row(display, 7, " APP CRC ", crc)?;
+        Ok(())
+    }
+
+    async fn draw_frame(&mut self, display: &mut DT, _cx: DrawFrameContext) -> Result<(), E> {
+        let state = (self.uptime_secs, self.measurement_count);
+        if self.drawn == Some(state) {
+            return Ok(());
+        }
+        self.drawn = Some(state);
+
+        // Padded so that shorter values cover the previous ones
+        let mut s = String::<32>::default();
+        let (hours, minutes, seconds) = (
+            self.uptime_secs / 3600,
+            self.uptime_secs / 60 % 60,
+            self.uptime_secs % 60,
+        );
+        write!(s, "{:>4}:{:02}:{:02}", hours, minutes, seconds).unwrap();
+        Self::draw_row(display, 8, " UPTIME ", &s[..])?;
+
+        s.clear();
+        write!(s, "{:>10}", self.measurement_count).unwrap();
+        Self::draw_row(display, 9, " MEASURED ", &s[..])?;
+        Ok(())
+    }
+
+    fn take_navigation(&mut self) -> Option<Navigation<DT, E>> {
+        self.closed.then_some(Navigation::Pop)
+    }
+
+    fn fx_exclusions(&self) -> &[Rectangle] {
+        &TEXT_AREA
+    }
+}
@@ -0,0 +1,268 @@
+use core::fmt::Debug;
+
+use app_measurements::util::{SpeedTable, KNOWN_SHUTTER_DURATIONS};
+use app_measurements::{Annotation, CameraSlot};
+use embedded_graphics::geometry::{Point, Size};
+use embedded_graphics::pixelcolor::{Rgb565, RgbColor};
+use embedded_graphics::primitives::Rectangle;
+use heapless::String;
+use u8g2_fonts::types::{FontColor, HorizontalAlignment, VerticalPosition};
+use ufmt::uwrite;
+
+use super::{DrawFrameContext, Navigation, Screen};
+use crate::fonts::{SMALL_FONT, TINY_FONT};
+use crate::ruler::SpeedRuler;
+use crate::util::font_error;
+use crate::{config as cfg, AppDrawTarget};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AnnotationField {
+    Speed,
+    /// 1/N entered directly, for speeds that aren't in any table
+    CustomSpeed,
+    Camera,
+}
+
+/// The most a custom speed goes up to, 1/16000 like the tables
+const CUSTOM_SPEED_MAX: i32 = 16000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AnnotationEditor {
+    pub annotation: Annotation,
+    pub field: AnnotationField,
+}
+
+impl AnnotationEditor {
+    pub fn adjust(&mut self, delta: isize) {
+        match self.field {
+            AnnotationField::Speed => {
+                self.annotation.custom_speed = None;
+                // Position 0 means "no nominal speed"
+                let len = KNOWN_SHUTTER_DURATIONS.len() as isize + 1;
+                let position = self.annotation.nominal_speed.map_or(0, |i| i as isize + 1);
+                let position = (position + delta).rem_euclid(len);
+                self.annotation.nominal_speed = (position > 0).then(|| position as usize - 1);
+            }
+            AnnotationField::CustomSpeed => {
+                // Starts from the speed picked from the table, below 1/1 turns it off
+                let mut denominator = match self.annotation.nominal() {
+                    Some(speed) if speed.is_fraction() => speed.dial_number() as i32,
+                    Some(_) => 1,
+                    None => 0,
+                };
+                for _ in 0..delta.unsigned_abs() {
+                    let step = custom_speed_step(denominator + delta.signum() as i32);
+                    denominator += step * delta.signum() as i32;
+                }
+                self.annotation.custom_speed =
+                    (denominator > 0).then(|| denominator.min(CUSTOM_SPEED_MAX) as u16);
+            }
+            AnnotationField::Camera => {
+                let len = CameraSlot::ALL.len() as isize;
+                let position = CameraSlot::ALL
+                    .iter()
+                    .position(|c| *c == self.annotation.camera)
+                    .unwrap_or(0) as isize;
+                self.annotation.camera =
+                    CameraSlot::ALL[(position + delta).rem_euclid(len) as usize];
+            }
+        }
+    }
+
+    /// Moves on to the next field, returns `true` once all have been visited
+    pub fn select_next(&mut self) -> bool {
+        match self.field {
+            AnnotationField::Speed => {
+                self.field = AnnotationField::CustomSpeed;
+                false
+            }
+            AnnotationField::CustomSpeed => {
+                self.field = AnnotationField::Camera;
+                false
+            }
+            AnnotationField::Camera => {
+                self.field = AnnotationField::Speed;
+                true
+            }
+        }
+    }
+}
+
+/// Single steps at the slow end, coarser ones further up so that 1/4000 is
+/// still in reach of the knob
+fn custom_speed_step(denominator: i32) -> i32 {
+    match denominator {
+        ..=30 => 1,
+        31..=200 => 5,
+        201..=1000 => 25,
+        1001..=4000 => 100,
+        _ => 500,
+    }
+}
+
+impl Default for AnnotationEditor {
+    fn default() -> Self {
+        Self {
+            annotation: Annotation::default(),
+            field: AnnotationField::Speed,
+        }
+    }
+}
+
+pub struct AnnotationScreen<DT, E> {
+    pub editor: AnnotationEditor,
+    /// Left with a long press, finishing the last field goes to the start screen instead
+    pub closed: bool,
+    drawn: Option<AnnotationEditor>,
+    _phantom: core::marker::PhantomData<(DT, E)>,
+}
+
+impl<DT: AppDrawTarget<E>, E: Debug> Screen<DT, E> for AnnotationScreen<DT, E> {
+    async fn draw_init(&mut self, display: &mut DT) -> Result<(), E> {
+        display.clear(cfg::COLOR_BACKGROUND)?;
+        self.drawn = None;
+
+        TINY_FONT
+            .render_aligned(
+                " PRESS TO CONFIRM ",
+                Point::new(
+                    display.bounding_box().center().x,
+                    display.bounding_box().size.height as i32 - 15,
+                ),
+                VerticalPosition::Top,
+                HorizontalAlignment::Center,
+                FontColor::WithBackground {
+                    fg: cfg::COLOR_RESULT_VALUE,
+                    bg: cfg::COLOR_BACKGROUND,
+                },
+                display,
+            )
+            .map_err(font_error)?;
+        Ok(())
+    }
+
+    async fn draw_frame(&mut self, display: &mut DT, _cx: DrawFrameContext) -> Result<(), E> {
+        if self.drawn == Some(self.editor) {
+            return Ok(());
+        }
+        self.drawn = Some(self.editor);
+
+        let width = display.bounding_box().size.width;
+        let center_x = width as i32 / 2;
+
+        let custom = self.editor.field == AnnotationField::CustomSpeed
+            || self.editor.annotation.custom_speed.is_some();
+        // The two labels differ in width
+        display.fill_solid(
+            &Rectangle::new(Point::new(0, 5), Size::new(width, 12)),
+            cfg::COLOR_BACKGROUND,
+        )?;
+        draw_label(
+            display,
+            Point::new(center_x, 5),
+            if custom {
+                " CUSTOM SPEED "
+            } else {
+                " NOMINAL SPEED "
+            },
+            self.editor.field != AnnotationField::Camera,
+        )?;
+
+        let label = self.editor.annotation.nominal().map(|speed| speed.label());
+        let mut s = String::<128>::default();
+        uwrite!(s, "  {}  ", label.as_deref().unwrap_or("ANY")).unwrap();
+        SMALL_FONT
+            .render_aligned(
+                &s[..],
+                Point::new(center_x, 20),
+                VerticalPosition::Top,
+                HorizontalAlignment::Center,
+                FontColor::WithBackground {
+                    fg: cfg::COLOR_RESULT_VALUE,
+                    bg: cfg::COLOR_BACKGROUND,
+                },
+                display,
+            )
+            .map_err(font_error)?;
+
+        display.fill_solid(
+            &Rectangle::new(Point::new(0, 45), Size::new(width, 40)),
+            cfg::COLOR_BACKGROUND,
+        )?;
+        if let Some(duration) = self.editor.annotation.nominal_duration() {
+            SpeedRuler::new(
+                Point::new(0, 70),
+                width,
+                duration,
+                SpeedTable::Standard,
+                None,
+            )
+            .draw(display)?;
+        }
+
+        draw_label(
+            display,
+            Point::new(center_x, 95),
+            " CAMERA ",
+            self.editor.field == AnnotationField::Camera,
+        )?;
+
+        s.clear();
+        uwrite!(s, "  {}  ", self.editor.annotation.camera.label()).unwrap();
+        SMALL_FONT
+            .render_aligned(
+                &s[..],
+                Point::new(center_x, 110),
+                VerticalPosition::Top,
+                HorizontalAlignment::Center,
+                FontColor::WithBackground {
+                    fg: cfg::COLOR_RESULT_VALUE,
+                    bg: cfg::COLOR_BACKGROUND,
+                },
+                display,
+            )
+            .map_err(font_error)?;
+        Ok(())
+    }
+
+    fn take_navigation(&mut self) -> Option<Navigation<DT, E>> {
+        self.closed.then_some(Navigation::Pop)
+    }
+}
+
+fn draw_label<D: AppDrawTarget<E>, E: Debug>(
+    display: &mut D,
+    origin: Point,
+    label: &str,
+    active: bool,
+) -> Result<(), E> {
+    TINY_FONT
+        .render_aligned(
+            label,
+            origin,
+            VerticalPosition::Top,
+            HorizontalAlignment::Center,
+            FontColor::WithBackground {
+                bg: if active {
+                    cfg::COLOR_MENU_ACTION
+                } else {
+                    cfg::COLOR_RESULT_VALUE_INACTIVE
+                },
+                fg: Rgb565::BLACK,
+            },
+            display,
+        )
+        .map_err(font_error)?;
+    Ok(())
+}
+
+impl<DT: AppDrawTarget<E>, E: Debug> Default for AnnotationScreen<DT, E> {
+    fn default() -> Self {
+        Self {
+            editor: AnnotationEditor::default(),
+            closed: false,
+            drawn: None,
+            _phantom: core::marker::PhantomData,
+        }
+    }
+}
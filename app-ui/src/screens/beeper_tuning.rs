@@ -0,0 +1,171 @@
+use core::fmt::Debug;
+use core::ops::RangeInclusive;
+
+use embedded_graphics::geometry::Point;
+use embedded_graphics::prelude::Dimensions;
+use heapless::String;
+use u8g2_fonts::types::{FontColor, HorizontalAlignment, VerticalPosition};
+use ufmt::uwrite;
+
+use super::{DrawFrameContext, Screen};
+use crate::fonts::{SMALL_FONT, TINY_FONT};
+use crate::util::font_error;
+use crate::{config as cfg, draw_badge, AppDrawTarget};
+
+/// Semitones the tunes can be moved by, an octave down to two up
+pub const BEEPER_OFFSETS: RangeInclusive<i8> = -12..=24;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BeeperTuner {
+    /// The one playing
+    pub offset: i8,
+    /// Steps up by itself until the knob takes over
+    pub sweeping: bool,
+}
+
+impl BeeperTuner {
+    /// Sweeps from the bottom
+    pub fn new() -> Self {
+        Self {
+            offset: *BEEPER_OFFSETS.start(),
+            sweeping: true,
+        }
+    }
+
+    /// The next one up while sweeping, back to the bottom past the top
+    pub fn sweep(&mut self) {
+        if !self.sweeping {
+            return;
+        }
+        self.offset = if self.offset >= *BEEPER_OFFSETS.end() {
+            *BEEPER_OFFSETS.start()
+        } else {
+            self.offset + 1
+        };
+    }
+
+    /// Stops the sweep
+    pub fn adjust(&mut self, delta: isize) {
+        self.sweeping = false;
+        self.offset = (self.offset as isize + delta).clamp(
+            *BEEPER_OFFSETS.start() as isize,
+            *BEEPER_OFFSETS.end() as isize,
+        ) as i8;
+    }
+}
+
+impl Default for BeeperTuner {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Plays a tone at each offset in turn, piezo buzzers are much louder near their resonance
+pub struct BeeperTuningScreen<DT, E> {
+    pub tuner: BeeperTuner,
+    /// Of the tone playing
+    pub frequency_hz: u32,
+    /// What the tunes use until another is picked
+    pub stored_offset: i8,
+    drawn: Option<(BeeperTuner, u32)>,
+    _phantom: core::marker::PhantomData<(DT, E)>,
+}
+
+impl<DT: AppDrawTarget<E>, E: Debug> BeeperTuningScreen<DT, E> {
+    pub fn new(stored_offset: i8) -> Self {
+        Self {
+            tuner: BeeperTuner::new(),
+            frequency_hz: 0,
+            stored_offset,
+            drawn: None,
+            _phantom: core::marker::PhantomData,
+        }
+    }
+}
+
+impl<DT: AppDrawTarget<E>, E: Debug> Screen<DT, E> for BeeperTuningScreen<DT, E> {
+    async fn draw_init(&mut self, display: &mut DT) -> Result<(), E> {
+        display.clear(cfg::COLOR_BACKGROUND)?;
+        self.drawn = None;
+        let center_x = display.bounding_box().center().x;
+        let height = display.bounding_box().size.height as i32;
+
+        draw_badge(
+            display,
+            Point::new(center_x, 14),
+            " BEEPER ",
+            cfg::COLOR_BACKGROUND,
+            cfg::COLOR_MENU_ACTION,
+        )
+        .await?;
+
+        let mut stored = String::<16>::default();
+        uwrite!(stored, "NOW {}", &semitones(self.stored_offset)[..]).unwrap();
+        for (line, y) in [
+            (&stored[..], 30),
+            ("PRESS AT THE LOUDEST", height - 30),
+            ("TURN TO PICK BY HAND", height - 18),
+        ] {
+            TINY_FONT
+                .render_aligned(
+                    line,
+                    Point::new(center_x, y),
+                    VerticalPosition::Top,
+                    HorizontalAlignment::Center,
+                    FontColor::Transparent(cfg::COLOR_RESULT_VALUE_INACTIVE),
+                    display,
+                )
+                .map_err(font_error)?;
+        }
+        Ok(())
+    }
+
+    async fn draw_frame(&mut self, display: &mut DT, _cx: DrawFrameContext) -> Result<(), E> {
+        if self.drawn == Some((self.tuner, self.frequency_hz)) {
+            return Ok(());
+        }
+        self.drawn = Some((self.tuner, self.frequency_hz));
+        let center_x = display.bounding_box().center().x;
+
+        let mut offset = String::<16>::default();
+        uwrite!(offset, "  {}  ", &semitones(self.tuner.offset)[..]).unwrap();
+        let mut frequency = String::<16>::default();
+        uwrite!(frequency, "  {} HZ  ", self.frequency_hz).unwrap();
+        let state = if self.tuner.sweeping {
+            " SWEEPING "
+        } else {
+            "  BY HAND "
+        };
+
+        for (line, y, color) in [
+            (&offset[..], 55, cfg::COLOR_RESULT_VALUE),
+            (&frequency[..], 75, cfg::COLOR_RESULT_VALUE),
+            (state, 95, cfg::COLOR_MENU_ACTION),
+        ] {
+            SMALL_FONT
+                .render_aligned(
+                    line,
+                    Point::new(center_x, y),
+                    VerticalPosition::Top,
+                    HorizontalAlignment::Center,
+                    FontColor::WithBackground {
+                        fg: color,
+                        bg: cfg::COLOR_BACKGROUND,
+                    },
+                    display,
+                )
+                .map_err(font_error)?;
+        }
+        Ok(())
+    }
+}
+
+/// With the sign either way
+fn semitones(offset: i8) -> String<4> {
+    let mut s = String::new();
+    if offset > 0 {
+        s.push('+').unwrap();
+    }
+    uwrite!(s, "{}", offset).unwrap();
+    s
+}
@@ -14,14 +14,12 @@ pub struct BootScreen<DT, E> {
 }
 
 impl<DT: AppDrawTarget<E>, E: Debug> Screen<DT, E> for BootScreen<DT, E> {
-    async fn draw_init(&mut self, display: &mut DT) {
+    async fn draw_init(&mut self, display: &mut DT) -> Result<(), E> {
         let x = (display.bounding_box().size.width / 2) as i32;
         let height = display.bounding_box().size.height;
         let y = (height / 2) as i32;
 
-        Cross::new(Point::new(x, y + 5), 10, Rgb565::RED)
-            .draw(display)
-            .unwrap();
+        Cross::new(Point::new(x, y + 5), 10, Rgb565::RED).draw(display)?;
         delay_ms(50).await;
         draw_badge(
             display,
@@ -30,7 +28,7 @@ impl<DT: AppDrawTarget<E>, E: Debug> Screen<DT, E> for BootScreen<DT, E> {
             Rgb565::CSS_GRAY,
             Rgb565::BLACK,
         )
-        .await;
+        .await?;
         draw_badge(
             display,
             Point::new(x, y),
@@ -38,10 +36,8 @@ impl<DT: AppDrawTarget<E>, E: Debug> Screen<DT, E> for BootScreen<DT, E> {
             Rgb565::WHITE,
             Rgb565::BLACK,
         )
-        .await;
-        Cross::new(Point::new(x, y + 5), 15, Rgb565::WHITE)
-            .draw(display)
-            .unwrap();
+        .await?;
+        Cross::new(Point::new(x, y + 5), 15, Rgb565::WHITE).draw(display)?;
         delay_ms(50).await;
         draw_badge(
             display,
@@ -50,11 +46,14 @@ impl<DT: AppDrawTarget<E>, E: Debug> Screen<DT, E> for BootScreen<DT, E> {
             Rgb565::BLACK,
             Rgb565::WHITE,
         )
-        .await;
+        .await?;
         delay_ms(150).await;
+        Ok(())
     }
 
-    async fn draw_frame(&mut self, _display: &mut DT, _cx: DrawFrameContext) {}
+    async fn draw_frame(&mut self, _display: &mut DT, _cx: DrawFrameContext) -> Result<(), E> {
+        Ok(())
+    }
 }
 
 impl<DT: AppDrawTarget<E>, E: Debug> Default for BootScreen<DT, E> {
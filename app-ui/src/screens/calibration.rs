@@ -1,60 +1,146 @@
 use core::fmt::{Debug, Write};
 
-use embedded_graphics::geometry::Point;
-use embedded_graphics::pixelcolor::{Rgb565, RgbColor};
-use embedded_graphics::primitives::{Circle, PrimitiveStyle, StyledDrawable};
+use app_measurements::CalibrationStage;
+use embedded_graphics::geometry::{Point, Size};
+use embedded_graphics::primitives::{Circle, PrimitiveStyle, Rectangle, StyledDrawable};
 use heapless::String;
+use u8g2_fonts::types::{FontColor, HorizontalAlignment, VerticalPosition};
 
 use super::{DrawFrameContext, Screen};
-use crate::{draw_badge, AppDrawTarget};
+use crate::fonts::TINY_FONT;
+use crate::util::font_error;
+use crate::{config as cfg, draw_badge, AppDrawTarget, ProgressBar};
+
+const PROGRESS_WIDTH: u32 = 80;
+const PROGRESS_HEIGHT: u32 = 6;
 
 pub struct CalibrationScreen<DT, E> {
+    stage: Option<CalibrationStage>,
     progress: u8,
+    remaining_ms: Option<u32>,
     _phantom: core::marker::PhantomData<(DT, E)>,
 }
 
 impl<DT: AppDrawTarget<E>, E: Debug> Screen<DT, E> for CalibrationScreen<DT, E> {
-    async fn draw_init(&mut self, display: &mut DT) {
-        display.clear(Rgb565::BLACK).unwrap();
+    async fn draw_init(&mut self, display: &mut DT) -> Result<(), E> {
+        display.clear(cfg::COLOR_BACKGROUND)?;
 
         draw_badge(
             display,
             display.bounding_box().center() - Point::new(0, 30),
             " CALIBRATING ",
-            Rgb565::BLACK,
-            Rgb565::YELLOW,
+            cfg::COLOR_BACKGROUND,
+            cfg::COLOR_CALIBRATION,
         )
-        .await;
+        .await?;
+
+        TINY_FONT
+            .render_aligned(
+                " PRESS TO CANCEL ",
+                Point::new(
+                    display.bounding_box().center().x,
+                    display.bounding_box().size.height as i32 - 15,
+                ),
+                VerticalPosition::Top,
+                HorizontalAlignment::Center,
+                FontColor::WithBackground {
+                    fg: cfg::COLOR_CALIBRATION,
+                    bg: cfg::COLOR_BACKGROUND,
+                },
+                display,
+            )
+            .map_err(font_error)?;
+        Ok(())
     }
 
-    async fn draw_frame(&mut self, display: &mut DT, _cx: DrawFrameContext) {
+    async fn draw_frame(&mut self, display: &mut DT, _cx: DrawFrameContext) -> Result<(), E> {
+        let center = display.bounding_box().center();
+
+        // All the same width, each one covers the last
+        let prompt = match self.stage {
+            Some(CalibrationStage::Cover) => "  COVER THE SENSOR  ",
+            Some(CalibrationStage::Covered) => "  KEEP IT COVERED   ",
+            Some(CalibrationStage::Uncover) => " UNCOVER THE SENSOR ",
+            Some(CalibrationStage::Ambient) => "   AMBIENT LEVEL    ",
+            Some(CalibrationStage::SpotCheck) | None => "                    ",
+        };
+        TINY_FONT
+            .render_aligned(
+                prompt,
+                center - Point::new(0, 48),
+                VerticalPosition::Top,
+                HorizontalAlignment::Center,
+                FontColor::WithBackground {
+                    fg: cfg::COLOR_CALIBRATION,
+                    bg: cfg::COLOR_BACKGROUND,
+                },
+                display,
+            )
+            .map_err(font_error)?;
+
+        ProgressBar::new(Rectangle::with_center(
+            center + Point::new(0, 30),
+            Size::new(PROGRESS_WIDTH, PROGRESS_HEIGHT),
+        ))
+        .with_color(cfg::COLOR_CALIBRATION)
+        .draw(display, self.progress as u32, 100)?;
+
         let mut s = String::<128>::default();
-        write!(s, " {}%", self.progress).unwrap();
+        if let Some(remaining_ms) = self.remaining_ms {
+            write!(s, " {:>4} MS LEFT ", remaining_ms).unwrap();
+        } else {
+            write!(s, "{:14}", "").unwrap();
+        }
+
+        TINY_FONT
+            .render_aligned(
+                &s[..],
+                center + Point::new(0, 45),
+                VerticalPosition::Top,
+                HorizontalAlignment::Center,
+                FontColor::WithBackground {
+                    fg: cfg::COLOR_CALIBRATION,
+                    bg: cfg::COLOR_BACKGROUND,
+                },
+                display,
+            )
+            .map_err(font_error)?;
 
-        let center = display.bounding_box().center();
         let sz = ((100 - self.progress) / 4) as u32;
 
-        Circle::with_center(center, sz)
-            .draw_styled(&PrimitiveStyle::with_stroke(Rgb565::YELLOW, 2), display)
-            .unwrap();
+        Circle::with_center(center, sz).draw_styled(
+            &PrimitiveStyle::with_stroke(cfg::COLOR_CALIBRATION, 2),
+            display,
+        )?;
 
-        Circle::with_center(center, sz + 2)
-            .draw_styled(&PrimitiveStyle::with_stroke(Rgb565::BLACK, 2), display)
-            .unwrap();
+        Circle::with_center(center, sz + 2).draw_styled(
+            &PrimitiveStyle::with_stroke(cfg::COLOR_BACKGROUND, 2),
+            display,
+        )?;
+        Ok(())
     }
 }
 
 impl<DT: AppDrawTarget<E>, E: Debug> Default for CalibrationScreen<DT, E> {
     fn default() -> Self {
         Self {
+            stage: None,
             progress: 0,
+            remaining_ms: None,
             _phantom: core::marker::PhantomData,
         }
     }
 }
 
 impl<DT, E> CalibrationScreen<DT, E> {
-    pub fn step(&mut self, progress: Option<u8>) {
+    pub fn step(
+        &mut self,
+        stage: Option<CalibrationStage>,
+        progress: Option<u8>,
+        remaining_ms: Option<u32>,
+    ) {
+        self.stage = stage;
         self.progress = progress.unwrap_or(100);
+        self.remaining_ms = remaining_ms;
     }
 }
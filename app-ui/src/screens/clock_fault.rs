@@ -0,0 +1,77 @@
+use core::fmt::Debug;
+
+use app_measurements::ClockFault;
+use embedded_graphics::geometry::Point;
+use embedded_graphics::pixelcolor::{Rgb565, RgbColor};
+use embedded_graphics::prelude::Dimensions;
+use heapless::String;
+use u8g2_fonts::types::{FontColor, HorizontalAlignment, VerticalPosition};
+use ufmt::uwrite;
+
+use super::{DrawFrameContext, Screen};
+use crate::fonts::TINY_FONT;
+use crate::util::font_error;
+use crate::{config, draw_badge, AppDrawTarget};
+
+/// Found by the startup check, measuring still works but the times may be off
+pub struct ClockFaultScreen<DT, E> {
+    pub fault: ClockFault,
+    _phantom: core::marker::PhantomData<(DT, E)>,
+}
+
+impl<DT: AppDrawTarget<E>, E: Debug> ClockFaultScreen<DT, E> {
+    pub fn new(fault: ClockFault) -> Self {
+        Self {
+            fault,
+            _phantom: core::marker::PhantomData,
+        }
+    }
+}
+
+impl<DT: AppDrawTarget<E>, E: Debug> Screen<DT, E> for ClockFaultScreen<DT, E> {
+    async fn draw_init(&mut self, display: &mut DT) -> Result<(), E> {
+        display.clear(Rgb565::BLACK)?;
+        let center = display.bounding_box().center();
+
+        draw_badge(
+            display,
+            center - Point::new(0, 40),
+            " CLOCK FAULT ",
+            Rgb565::BLACK,
+            config::COLOR_BANNER_WARNING,
+        )
+        .await?;
+
+        let mut s = String::<32>::default();
+        match self.fault {
+            ClockFault::NoCrystal => uwrite!(s, " CRYSTAL NOT RUNNING ").unwrap(),
+            ClockFault::WrongRate { measured_hz } => {
+                uwrite!(s, " CORE AT {} KHZ ", measured_hz / 1000).unwrap()
+            }
+        }
+        for (line, y) in [
+            (&s[..], -15),
+            (" TIMES MAY BE OFF ", 0),
+            (" PRESS TO CONTINUE ", 30),
+        ] {
+            TINY_FONT
+                .render_aligned(
+                    line,
+                    center + Point::new(0, y),
+                    VerticalPosition::Top,
+                    HorizontalAlignment::Center,
+                    FontColor::WithBackground {
+                        fg: config::COLOR_BANNER_WARNING,
+                        bg: Rgb565::BLACK,
+                    },
+                    display,
+                )
+                .map_err(font_error)?;
+        }
+        Ok(())
+    }
+
+    async fn draw_frame(&mut self, _display: &mut DT, _cx: DrawFrameContext) -> Result<(), E> {
+        Ok(())
+    }
+}
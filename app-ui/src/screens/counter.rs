@@ -0,0 +1,109 @@
+use core::fmt::{Debug, Write};
+
+use embedded_graphics::geometry::Point;
+use embedded_graphics::pixelcolor::{Rgb565, RgbColor, WebColors};
+use heapless::String;
+use u8g2_fonts::types::{FontColor, HorizontalAlignment, VerticalPosition};
+
+use super::{DrawFrameContext, Screen};
+use crate::fonts::{LARGE_DIGIT_FONT, SMALL_FONT, TINY_FONT};
+use crate::util::font_error;
+use crate::{draw_badge, AppDrawTarget};
+
+pub struct CounterScreen<DT, E> {
+    pub count: u32,
+    pub events_per_minute: Option<u32>,
+    drawn: Option<(u32, Option<u32>)>,
+    _phantom: core::marker::PhantomData<(DT, E)>,
+}
+
+impl<DT: AppDrawTarget<E>, E: Debug> Screen<DT, E> for CounterScreen<DT, E> {
+    async fn draw_init(&mut self, display: &mut DT) -> Result<(), E> {
+        display.clear(Rgb565::BLACK)?;
+        self.drawn = None;
+
+        draw_badge(
+            display,
+            Point::new(display.bounding_box().center().x, 25),
+            " COUNTER ",
+            Rgb565::BLACK,
+            Rgb565::CSS_TURQUOISE,
+        )
+        .await?;
+
+        TINY_FONT
+            .render_aligned(
+                " PRESS TO RESET ",
+                Point::new(
+                    display.bounding_box().center().x,
+                    display.bounding_box().size.height as i32 - 15,
+                ),
+                VerticalPosition::Top,
+                HorizontalAlignment::Center,
+                FontColor::WithBackground {
+                    fg: Rgb565::CSS_TURQUOISE,
+                    bg: Rgb565::BLACK,
+                },
+                display,
+            )
+            .map_err(font_error)?;
+        Ok(())
+    }
+
+    async fn draw_frame(&mut self, display: &mut DT, _cx: DrawFrameContext) -> Result<(), E> {
+        let state = (self.count, self.events_per_minute);
+        if self.drawn == Some(state) {
+            return Ok(());
+        }
+        self.drawn = Some(state);
+
+        let center = display.bounding_box().center();
+
+        let mut s = String::<128>::default();
+        write!(s, "{:^7}", self.count).unwrap();
+        LARGE_DIGIT_FONT
+            .render_aligned(
+                &s[..],
+                center - Point::new(0, 5),
+                VerticalPosition::Center,
+                HorizontalAlignment::Center,
+                FontColor::WithBackground {
+                    fg: Rgb565::WHITE,
+                    bg: Rgb565::BLACK,
+                },
+                display,
+            )
+            .map_err(font_error)?;
+
+        s.clear();
+        match self.events_per_minute {
+            Some(epm) => write!(s, " {:>5} / MIN ", epm).unwrap(),
+            None => write!(s, "{:13}", "").unwrap(),
+        }
+        SMALL_FONT
+            .render_aligned(
+                &s[..],
+                center + Point::new(0, 25),
+                VerticalPosition::Top,
+                HorizontalAlignment::Center,
+                FontColor::WithBackground {
+                    fg: Rgb565::CSS_PALE_GOLDENROD,
+                    bg: Rgb565::BLACK,
+                },
+                display,
+            )
+            .map_err(font_error)?;
+        Ok(())
+    }
+}
+
+impl<DT: AppDrawTarget<E>, E: Debug> Default for CounterScreen<DT, E> {
+    fn default() -> Self {
+        Self {
+            count: 0,
+            events_per_minute: None,
+            drawn: None,
+            _phantom: core::marker::PhantomData,
+        }
+    }
+}
@@ -1,6 +1,8 @@
 use core::fmt::{Debug, Write};
 
-use app_measurements::{CalibrationResult, TriggerThresholds};
+use app_measurements::{
+    AdcFaults, CalibrationResult, PeakHold, Profile, ProfiledSection, TriggerThresholds,
+};
 use eg_seven_segment::SevenSegmentStyleBuilder;
 use embedded_graphics::draw_target::DrawTarget;
 use embedded_graphics::geometry::{Point, Size};
@@ -8,7 +10,6 @@ use embedded_graphics::pixelcolor::{Rgb565, RgbColor};
 use embedded_graphics::primitives::Rectangle;
 use embedded_graphics::text::Text;
 use embedded_graphics::Drawable;
-use embedded_graphics_framebuf::FrameBuf;
 use heapless::{HistoryBuffer, String};
 use u8g2_fonts::types::{FontColor, HorizontalAlignment, VerticalPosition};
 use ufmt::uwrite;
@@ -16,24 +17,222 @@ use ufmt::uwrite;
 use super::{DrawFrameContext, Screen};
 use crate::fonts::{SMALL_FONT, TINY_FONT};
 use crate::primitives::Pointer;
-use crate::{config as cfg, AppDrawTarget};
+use crate::util::font_error;
+use crate::{config as cfg, draw_badge, AppDrawTarget, DrawProgress, OffscreenRegion};
+
+/// Peaks, level, bar, calibration, indicators, noise and both thresholds
+const LIVE_STEPS: usize = 8;
+/// Header, a row per section, sample budget, faults and the reset hint
+const STATS_STEPS: usize = ProfiledSection::ALL.len() + 4;
+/// Statics, stack, heap and a row per buffer
+const MEMORY_STEPS: usize = MEMORY_BUFFERS + 3;
+/// The buffers worth watching when something has to grow
+pub const MEMORY_BUFFERS: usize = 7;
+
+/// What the debug screen shows, the pages after the first one follow the thresholds
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DebugPage {
+    /// Light level and thresholds
+    #[default]
+    Live,
+    /// The timing profile
+    Timing,
+    Memory,
+}
+
+/// Where the RAM went, measured by the firmware itself
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MemoryUsage {
+    pub ram_bytes: u32,
+    /// Everything fixed at link time, the buffers included
+    pub static_bytes: u32,
+    /// Left for the stack above the statics
+    pub stack_bytes: u32,
+    /// Deepest the stack has been since boot
+    pub stack_peak_bytes: u32,
+    pub buffers: [(&'static str, u32); MEMORY_BUFFERS],
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ThresholdSelection {
+    Low,
+    High,
+}
+
+#[derive(Clone, Debug)]
+pub struct ThresholdEditor {
+    pub selection: ThresholdSelection,
+    pub low: u16,
+    pub high: u16,
+    pub changed: bool,
+    calibration: CalibrationResult,
+}
+
+impl ThresholdEditor {
+    pub fn new(calibration: &CalibrationResult, trigger_thresholds: &TriggerThresholds) -> Self {
+        Self {
+            selection: ThresholdSelection::Low,
+            low: trigger_thresholds.trigger_low(calibration),
+            high: trigger_thresholds.trigger_high(calibration),
+            changed: false,
+            calibration: calibration.clone(),
+        }
+    }
+
+    pub fn adjust(&mut self, delta: i32, max_value: u16) {
+        match self.selection {
+            ThresholdSelection::Low => {
+                self.low = (self.low as i32 + delta).clamp(0, self.high as i32 - 1) as u16;
+            }
+            ThresholdSelection::High => {
+                self.high =
+                    (self.high as i32 + delta).clamp(self.low as i32 + 1, max_value as i32) as u16;
+            }
+        }
+        self.changed = true;
+    }
+
+    /// Moves on to the next threshold, returns `true` once both have been visited
+    pub fn select_next(&mut self) -> bool {
+        match self.selection {
+            ThresholdSelection::Low => {
+                self.selection = ThresholdSelection::High;
+                false
+            }
+            ThresholdSelection::High => {
+                self.selection = ThresholdSelection::Low;
+                true
+            }
+        }
+    }
+
+    pub fn thresholds(&self) -> TriggerThresholds {
+        TriggerThresholds::from_levels(&self.calibration, self.low, self.high)
+    }
+}
+
+impl Default for ThresholdEditor {
+    fn default() -> Self {
+        Self {
+            selection: ThresholdSelection::Low,
+            low: 0,
+            high: 1,
+            changed: false,
+            calibration: CalibrationResult::default(),
+        }
+    }
+}
 
 pub struct DebugScreen<DT, E> {
+    pub editor: ThresholdEditor,
+    pub peak_hold: PeakHold,
+    pub display_failures: u32,
+    pub adc_faults: AdcFaults,
+    pub page: DebugPage,
+    pub profile: Profile,
+    /// Only kept current while its page is up
+    pub memory: MemoryUsage,
+    drawn_page: Option<DebugPage>,
+    progress: DrawProgress,
     adc_history: HistoryBuffer<u16, 1000>,
     is_triggered: bool,
     calibration: CalibrationResult,
-    threshold_low: u16,
-    threshold_high: u16,
     max_value: u16,
     _phantom: core::marker::PhantomData<(DT, E)>,
 }
 
 impl<DT: AppDrawTarget<E>, E: Debug> Screen<DT, E> for DebugScreen<DT, E> {
-    async fn draw_init(&mut self, display: &mut DT) {
-        display.clear(Rgb565::BLACK).unwrap();
+    async fn draw_init(&mut self, display: &mut DT) -> Result<(), E> {
+        display.clear(Rgb565::BLACK)?;
+        self.drawn_page = None;
+        Ok(())
     }
 
-    async fn draw_frame(&mut self, display: &mut DT, _cx: DrawFrameContext) {
+    async fn draw_frame(&mut self, display: &mut DT, cx: DrawFrameContext) -> Result<(), E> {
+        if self.drawn_page != Some(self.page) {
+            display.clear(Rgb565::BLACK)?;
+            let title = match self.page {
+                DebugPage::Live => None,
+                DebugPage::Timing => Some(" TIMING "),
+                DebugPage::Memory => Some(" MEMORY "),
+            };
+            if let Some(title) = title {
+                draw_badge(
+                    display,
+                    Point::new(display.bounding_box().center().x, 0),
+                    title,
+                    Rgb565::BLACK,
+                    cfg::COLOR_PEAK,
+                )
+                .await?;
+            }
+            self.drawn_page = Some(self.page);
+            self.progress.restart();
+        }
+
+        // Everything here is live, start over once the last step is out
+        let len = match self.page {
+            DebugPage::Live => LIVE_STEPS,
+            DebugPage::Timing => STATS_STEPS,
+            DebugPage::Memory => MEMORY_STEPS,
+        };
+        if self.progress.is_complete(len) {
+            self.progress.restart();
+        }
+        let mut budget = cx.budget;
+        let mut progress = self.progress;
+        for step in progress.steps(len, &mut budget) {
+            match self.page {
+                DebugPage::Live => self.draw_live(display, step)?,
+                DebugPage::Timing => self.draw_stats(display, step)?,
+                DebugPage::Memory => self.draw_memory(display, step)?,
+            }
+        }
+        self.progress = progress;
+        Ok(())
+    }
+}
+
+impl<DT: AppDrawTarget<E>, E: Debug> DebugScreen<DT, E> {
+    pub fn new(
+        calibration: CalibrationResult,
+        trigger_thresholds: TriggerThresholds,
+        max_value: u16,
+    ) -> Self {
+        Self {
+            editor: ThresholdEditor::new(&calibration, &trigger_thresholds),
+            peak_hold: PeakHold::default(),
+            display_failures: 0,
+            adc_faults: AdcFaults::default(),
+            page: DebugPage::Live,
+            profile: Profile::default(),
+            memory: MemoryUsage::default(),
+            drawn_page: None,
+            progress: DrawProgress::default(),
+            adc_history: HistoryBuffer::new(),
+            is_triggered: false,
+            calibration,
+            max_value,
+            _phantom: core::marker::PhantomData,
+        }
+    }
+
+    pub fn step(&mut self, adc_value: u16) {
+        self.adc_history.write(adc_value);
+
+        if !self.is_triggered && adc_value > self.editor.high {
+            self.is_triggered = true;
+        }
+        if self.is_triggered && adc_value < self.editor.low {
+            self.is_triggered = false;
+        }
+    }
+
+    pub fn last_adc_value(&self) -> u16 {
+        *self.adc_history.oldest_ordered().last().unwrap_or(&0)
+    }
+
+    fn draw_live(&mut self, display: &mut DT, step: usize) -> Result<(), E> {
         let recent_samples = self.adc_history.len().min(10);
         let (avg_adc_value, min_adc_value, max_adc_value) = {
             let recent_iter = || {
@@ -49,114 +248,399 @@ impl<DT: AppDrawTarget<E>, E: Debug> Screen<DT, E> for DebugScreen<DT, E> {
         };
 
         let ll_origin = Point::new(display.bounding_box().size.width as i32 / 2, 60);
-        self.draw_light_value(display, ll_origin, avg_adc_value);
-
         let bar_origin = Point::new(5, ll_origin.y);
-        self.draw_bar(
-            display,
-            bar_origin,
-            avg_adc_value,
-            min_adc_value,
-            max_adc_value,
-        );
-
         let calibration_origin = bar_origin + Point::new(0, 40);
-        self.draw_value(
-            display,
-            calibration_origin,
-            " CALIBRATED TO ",
-            self.calibration.average,
-            cfg::COLOR_CALIBRATION,
-        );
-
         let indicator_origin = calibration_origin + Point::new(100, 0);
+        let noise_origin = calibration_origin + Point::new(0, 33);
+
+        match step {
+            0 => self.draw_peaks(display)?,
+            1 => self.draw_light_value(display, ll_origin, avg_adc_value)?,
+            2 => self.draw_bar(
+                display,
+                bar_origin,
+                avg_adc_value,
+                min_adc_value,
+                max_adc_value,
+            )?,
+            3 => self.draw_value(
+                display,
+                calibration_origin,
+                " CALIBRATED TO ",
+                self.calibration.average,
+                cfg::COLOR_CALIBRATION,
+                false,
+            )?,
+            4 => {
+                for (label, offset, active) in [
+                    (" OVER  ", 0, self.is_triggered),
+                    (" UNDER ", 10, !self.is_triggered),
+                ] {
+                    TINY_FONT
+                        .render_aligned(
+                            label,
+                            indicator_origin + Point::new(0, offset),
+                            VerticalPosition::Top,
+                            HorizontalAlignment::Center,
+                            FontColor::WithBackground {
+                                fg: cfg::COLOR_BACKGROUND,
+                                bg: if active {
+                                    cfg::COLOR_RESULT_VALUE
+                                } else {
+                                    cfg::COLOR_RESULT_VALUE_INACTIVE
+                                },
+                            },
+                            display,
+                        )
+                        .map_err(font_error)?;
+                }
+            }
+            5 => self.draw_value(
+                display,
+                noise_origin,
+                " NOISE ",
+                (max_adc_value - min_adc_value) / 2,
+                cfg::COLOR_NOISE,
+                false,
+            )?,
+            6 => self.draw_value(
+                display,
+                noise_origin + Point::new(79, 0),
+                " TRIG H ",
+                self.editor.high,
+                cfg::COLOR_TRIGGER_HIGH,
+                self.editor.selection == ThresholdSelection::High,
+            )?,
+            _ => self.draw_value(
+                display,
+                noise_origin + Point::new(37, 0),
+                " TRIG L ",
+                self.editor.low,
+                cfg::COLOR_TRIGGER_LOW,
+                self.editor.selection == ThresholdSelection::Low,
+            )?,
+        }
+        Ok(())
+    }
+
+    fn draw_peaks(&mut self, display: &mut DT) -> Result<(), E> {
+        let mut s = String::<128>::default();
+        let color = FontColor::WithBackground {
+            fg: cfg::COLOR_PEAK,
+            bg: cfg::COLOR_BACKGROUND,
+        };
+
+        if self.peak_hold.is_empty() {
+            write!(s, "{:10}", "").unwrap();
+        } else {
+            write!(s, "MIN {:<4} ", self.peak_hold.min).unwrap();
+        }
         TINY_FONT
-            .render_aligned(
-                " OVER  ",
-                indicator_origin,
+            .render(
+                &s[..],
+                Point::new(0, 0),
                 VerticalPosition::Top,
-                HorizontalAlignment::Center,
-                FontColor::WithBackground {
-                    fg: cfg::COLOR_BACKGROUND,
-                    bg: if self.is_triggered {
-                        cfg::COLOR_RESULT_VALUE
-                    } else {
-                        cfg::COLOR_RESULT_VALUE_INACTIVE
-                    },
-                },
+                color,
                 display,
             )
-            .unwrap();
+            .map_err(font_error)?;
+
+        // Display bus errors that were recovered from
+        if self.display_failures > 0 {
+            s.clear();
+            write!(s, "E{}", self.display_failures.min(999)).unwrap();
+            TINY_FONT
+                .render_aligned(
+                    &s[..],
+                    Point::new(display.bounding_box().center().x, 0),
+                    VerticalPosition::Top,
+                    HorizontalAlignment::Center,
+                    FontColor::WithBackground {
+                        fg: cfg::COLOR_BACKGROUND,
+                        bg: cfg::COLOR_RESULT_BAD,
+                    },
+                    display,
+                )
+                .map_err(font_error)?;
+        }
 
+        s.clear();
+        if self.peak_hold.is_empty() {
+            write!(s, "{:10}", "").unwrap();
+        } else {
+            write!(s, " MAX {:>4}", self.peak_hold.max).unwrap();
+        }
         TINY_FONT
             .render_aligned(
-                " UNDER ",
-                indicator_origin + Point::new(0, 10),
+                &s[..],
+                Point::new(display.bounding_box().size.width as i32, 0),
                 VerticalPosition::Top,
-                HorizontalAlignment::Center,
-                FontColor::WithBackground {
-                    fg: cfg::COLOR_BACKGROUND,
-                    bg: if !self.is_triggered {
-                        cfg::COLOR_RESULT_VALUE
-                    } else {
-                        cfg::COLOR_RESULT_VALUE_INACTIVE
-                    },
-                },
+                HorizontalAlignment::Right,
+                color,
                 display,
             )
-            .unwrap();
-
-        let noise_origin = calibration_origin + Point::new(0, 33);
-        let noise = (max_adc_value - min_adc_value) / 2;
-        self.draw_value(display, noise_origin, " NOISE ", noise, cfg::COLOR_NOISE);
-
-        self.draw_value(
-            display,
-            noise_origin + Point::new(79, 0),
-            " TRIG H ",
-            self.threshold_high,
-            cfg::COLOR_TRIGGER_HIGH,
-        );
-
-        self.draw_value(
-            display,
-            noise_origin + Point::new(37, 0),
-            " TRIG L ",
-            self.threshold_low,
-            cfg::COLOR_TRIGGER_LOW,
-        );
+            .map_err(font_error)?;
+        Ok(())
     }
-}
 
-impl<DT: AppDrawTarget<E>, E: Debug> DebugScreen<DT, E> {
-    pub fn new(calibration: CalibrationResult, trigger_thresholds: TriggerThresholds, max_value: u16) -> Self {
-        Self {
-            adc_history: HistoryBuffer::new(),
-            is_triggered: false,
-            threshold_low: trigger_thresholds.trigger_low(&calibration),
-            threshold_high: trigger_thresholds.trigger_high(&calibration),
-            calibration,
-            max_value,
-            _phantom: core::marker::PhantomData,
-        }
-    }
+    fn draw_stats(&mut self, display: &mut DT, step: usize) -> Result<(), E> {
+        const LABEL_WIDTH: i32 = 30;
+        const COLUMN_WIDTH: i32 = 34;
+        const ROW_HEIGHT: i32 = 14;
 
-    pub fn step(&mut self, adc_value: u16) {
-        self.adc_history.write(adc_value);
+        let mut s = String::<128>::default();
+        let header_y = 22;
+        // Worst case DMA handler time against the ADC sample period
+        let budget_origin = Point::new(0, header_y + ROW_HEIGHT * 5);
+        let sections = ProfiledSection::ALL.len();
+
+        match step {
+            0 => {
+                let columns = [" US ", "MIN", "AVG", "MAX"];
+                for (index, label) in columns.iter().enumerate() {
+                    let (x, alignment) = match index {
+                        0 => (0, HorizontalAlignment::Left),
+                        _ => (
+                            LABEL_WIDTH + COLUMN_WIDTH * index as i32 - 2,
+                            HorizontalAlignment::Right,
+                        ),
+                    };
+                    TINY_FONT
+                        .render_aligned(
+                            *label,
+                            Point::new(x, header_y),
+                            VerticalPosition::Top,
+                            alignment,
+                            FontColor::WithBackground {
+                                fg: cfg::COLOR_PEAK,
+                                bg: cfg::COLOR_BACKGROUND,
+                            },
+                            display,
+                        )
+                        .map_err(font_error)?;
+                }
+            }
+            row if row <= sections => {
+                let section = ProfiledSection::ALL[row - 1];
+                let y = header_y + ROW_HEIGHT * row as i32;
+                let stats = self.profile.get(section);
+
+                TINY_FONT
+                    .render(
+                        section.label(),
+                        Point::new(0, y),
+                        VerticalPosition::Top,
+                        FontColor::WithBackground {
+                            fg: cfg::COLOR_RESULT_VALUE,
+                            bg: cfg::COLOR_BACKGROUND,
+                        },
+                        display,
+                    )
+                    .map_err(font_error)?;
 
-        if !self.is_triggered && adc_value > self.threshold_high {
-            self.is_triggered = true;
-        }
-        if self.is_triggered && adc_value < self.threshold_low {
-            self.is_triggered = false;
+                for (column, cycles) in [stats.min, stats.mean(), stats.max].iter().enumerate() {
+                    s.clear();
+                    if stats.is_empty() {
+                        write!(s, "{:>6}", "-").unwrap();
+                    } else {
+                        let nanos = self.profile.cycles_to_nanos(*cycles);
+                        if nanos < 100_000 {
+                            write!(s, "{:>4}.{}", nanos / 1000, nanos / 100 % 10).unwrap();
+                        } else {
+                            write!(s, "{:>6}", nanos / 1000).unwrap();
+                        }
+                    }
+                    TINY_FONT
+                        .render_aligned(
+                            &s[..],
+                            Point::new(LABEL_WIDTH + COLUMN_WIDTH * (column as i32 + 1) - 2, y),
+                            VerticalPosition::Top,
+                            HorizontalAlignment::Right,
+                            FontColor::WithBackground {
+                                fg: cfg::COLOR_RESULT_VALUE,
+                                bg: cfg::COLOR_BACKGROUND,
+                            },
+                            display,
+                        )
+                        .map_err(font_error)?;
+                }
+            }
+            n if n == sections + 1 => {
+                TINY_FONT
+                    .render(
+                        " SAMPLE BUDGET ",
+                        budget_origin,
+                        VerticalPosition::Top,
+                        FontColor::WithBackground {
+                            fg: Rgb565::BLACK,
+                            bg: cfg::COLOR_PEAK,
+                        },
+                        display,
+                    )
+                    .map_err(font_error)?;
+
+                s.clear();
+                let color = match self.profile.sample_budget_percent() {
+                    Some(percent) => {
+                        write!(s, "{:>3}% ", percent).unwrap();
+                        match percent {
+                            0..=49 => cfg::COLOR_RESULT_GOOD,
+                            50..=79 => cfg::COLOR_RESULT_FAIR,
+                            _ => cfg::COLOR_RESULT_BAD,
+                        }
+                    }
+                    None => {
+                        write!(s, "{:5}", "").unwrap();
+                        cfg::COLOR_RESULT_VALUE
+                    }
+                };
+                SMALL_FONT
+                    .render(
+                        &s[..],
+                        budget_origin + Point::new(1, 12),
+                        VerticalPosition::Top,
+                        FontColor::WithBackground {
+                            fg: color,
+                            bg: cfg::COLOR_BACKGROUND,
+                        },
+                        display,
+                    )
+                    .map_err(font_error)?;
+            }
+            n if n == sections + 2 => {
+                // Sampling faults next to the budget, DMA errors, ADC overruns and dropped pulses
+                for (row, (label, count)) in [
+                    ("DMA", self.adc_faults.transfer_errors),
+                    ("OVR", self.adc_faults.overruns),
+                    ("DROP", self.adc_faults.invalidated),
+                ]
+                .into_iter()
+                .enumerate()
+                {
+                    s.clear();
+                    write!(s, "{} {:>4}", label, count.min(9999)).unwrap();
+                    TINY_FONT
+                        .render_aligned(
+                            &s[..],
+                            Point::new(
+                                display.bounding_box().size.width as i32,
+                                budget_origin.y + row as i32 * 10,
+                            ),
+                            VerticalPosition::Top,
+                            HorizontalAlignment::Right,
+                            FontColor::WithBackground {
+                                fg: if count > 0 {
+                                    cfg::COLOR_RESULT_BAD
+                                } else {
+                                    cfg::COLOR_RESULT_VALUE
+                                },
+                                bg: cfg::COLOR_BACKGROUND,
+                            },
+                            display,
+                        )
+                        .map_err(font_error)?;
+                }
+            }
+            _ => {
+                TINY_FONT
+                    .render_aligned(
+                        " HOLD TO RESET ",
+                        Point::new(
+                            display.bounding_box().center().x,
+                            display.bounding_box().size.height as i32 - 15,
+                        ),
+                        VerticalPosition::Top,
+                        HorizontalAlignment::Center,
+                        FontColor::WithBackground {
+                            fg: cfg::COLOR_PEAK,
+                            bg: cfg::COLOR_BACKGROUND,
+                        },
+                        display,
+                    )
+                    .map_err(font_error)?;
+            }
         }
+        Ok(())
     }
 
-    pub fn last_adc_value(&self) -> u16 {
-        *self.adc_history.oldest_ordered().last().unwrap_or(&0)
+    fn draw_memory(&mut self, display: &mut DT, step: usize) -> Result<(), E> {
+        const ROW_HEIGHT: i32 = 11;
+        const BUFFERS_Y: i32 = 62;
+
+        let width = display.bounding_box().size.width as i32;
+        let memory = self.memory;
+        let mut s = String::<32>::default();
+        // Red once three quarters are gone, what the next buffer would eat into
+        let (label, y, color) = match step {
+            0 => {
+                write_kib(&mut s, memory.static_bytes);
+                s.push_str(" OF ").unwrap();
+                write_kib(&mut s, memory.ram_bytes);
+                ("STATIC", 22, cfg::COLOR_RESULT_VALUE)
+            }
+            1 => {
+                write_kib(&mut s, memory.stack_peak_bytes);
+                s.push_str(" OF ").unwrap();
+                write_kib(&mut s, memory.stack_bytes);
+                let color = if memory.stack_peak_bytes * 4 > memory.stack_bytes * 3 {
+                    cfg::COLOR_RESULT_BAD
+                } else {
+                    cfg::COLOR_RESULT_GOOD
+                };
+                ("STACK PEAK", 22 + ROW_HEIGHT, color)
+            }
+            // No global allocator, see the firmware's main.rs
+            2 => {
+                s.push_str("NONE").unwrap();
+                ("HEAP", 22 + ROW_HEIGHT * 2, cfg::COLOR_RESULT_VALUE)
+            }
+            n => {
+                let (label, bytes) = memory.buffers[n - 3];
+                write_kib(&mut s, bytes);
+                (
+                    label,
+                    BUFFERS_Y + ROW_HEIGHT * (n as i32 - 3),
+                    cfg::COLOR_RESULT_VALUE,
+                )
+            }
+        };
+
+        TINY_FONT
+            .render(
+                label,
+                Point::new(0, y),
+                VerticalPosition::Top,
+                FontColor::WithBackground {
+                    fg: cfg::COLOR_PEAK,
+                    bg: cfg::COLOR_BACKGROUND,
+                },
+                display,
+            )
+            .map_err(font_error)?;
+        let mut padded = String::<32>::default();
+        write!(padded, "{:>14}", &s[..]).unwrap();
+        TINY_FONT
+            .render_aligned(
+                &padded[..],
+                Point::new(width, y),
+                VerticalPosition::Top,
+                HorizontalAlignment::Right,
+                FontColor::WithBackground {
+                    fg: color,
+                    bg: cfg::COLOR_BACKGROUND,
+                },
+                display,
+            )
+            .map_err(font_error)?;
+        Ok(())
     }
 
-    fn draw_light_value(&mut self, display: &mut DT, origin: Point, avg_adc_values: u16) {
+    fn draw_light_value(
+        &mut self,
+        display: &mut DT,
+        origin: Point,
+        avg_adc_values: u16,
+    ) -> Result<(), E> {
         let mut s = String::<128>::default();
 
         TINY_FONT
@@ -171,7 +655,7 @@ impl<DT: AppDrawTarget<E>, E: Debug> DebugScreen<DT, E> {
                 },
                 display,
             )
-            .unwrap();
+            .map_err(font_error)?;
 
         let large_style = SevenSegmentStyleBuilder::new()
             .digit_size(Size::new(16, 28)) // digits are 10x20 pixels
@@ -194,8 +678,8 @@ impl<DT: AppDrawTarget<E>, E: Debug> DebugScreen<DT, E> {
             large_style,
             embedded_graphics::text::Alignment::Center,
         )
-        .draw(display)
-        .unwrap();
+        .draw(display)?;
+        Ok(())
     }
 
     fn draw_bar(
@@ -205,12 +689,15 @@ impl<DT: AppDrawTarget<E>, E: Debug> DebugScreen<DT, E> {
         avg_adc_value: u16,
         min_adc_value: u16,
         max_adc_value: u16,
-    ) {
+    ) -> Result<(), E> {
         const WIDTH: usize = 118;
         const HEIGHT: usize = 30;
 
-        let mut buffer_data = [cfg::COLOR_BACKGROUND; WIDTH * HEIGHT];
-        let mut buffer = FrameBuf::new(&mut buffer_data, WIDTH, HEIGHT);
+        let mut region = OffscreenRegion::<{ WIDTH * HEIGHT }>::new(
+            Rectangle::new(origin, Size::new(WIDTH as u32, HEIGHT as u32)),
+            cfg::COLOR_BACKGROUND,
+        );
+        let buffer = region.buffer();
 
         let scale = WIDTH as f32 / self.max_value as f32;
         let scale_value = |x: u16| (x as f32 * scale) as i32;
@@ -283,39 +770,59 @@ impl<DT: AppDrawTarget<E>, E: Debug> DebugScreen<DT, E> {
             }
         }
 
+        if !self.peak_hold.is_empty() {
+            for value in [self.peak_hold.min, self.peak_hold.max] {
+                buffer
+                    .fill_solid(
+                        &Rectangle::new(
+                            Point::new(scale_value(value).min(WIDTH as i32 - 1), 0),
+                            Size::new(1, 5),
+                        ),
+                        cfg::COLOR_PEAK,
+                    )
+                    .unwrap();
+            }
+        }
+
         Pointer::new(
             Point::new(scale_value(self.calibration.average), bar_h as i32 + 13),
             5,
             true,
             cfg::COLOR_CALIBRATION,
         )
-        .draw(&mut buffer)
+        .draw(buffer)
         .unwrap();
 
+        buffer
+            .fill_solid(
+                &Rectangle::with_corners(
+                    Point::new(scale_value(self.editor.low), bar_y + bar_h as i32 + 2),
+                    Point::new(scale_value(self.editor.high), bar_y + bar_h as i32 + 3),
+                ),
+                cfg::COLOR_HYSTERESIS,
+            )
+            .unwrap();
+
         Pointer::new(
-            Point::new(scale_value(self.threshold_low), bar_h as i32 + 13),
+            Point::new(scale_value(self.editor.low), bar_h as i32 + 13),
             5,
             true,
             cfg::COLOR_TRIGGER_LOW,
         )
-        .draw(&mut buffer)
+        .draw(buffer)
         .unwrap();
 
         Pointer::new(
-            Point::new(scale_value(self.threshold_high), bar_h as i32 + 13),
+            Point::new(scale_value(self.editor.high), bar_h as i32 + 13),
             5,
             true,
             cfg::COLOR_TRIGGER_HIGH,
         )
-        .draw(&mut buffer)
+        .draw(buffer)
         .unwrap();
 
-        display
-            .fill_contiguous(
-                &Rectangle::new(origin, Size::new(WIDTH as u32, HEIGHT as u32)),
-                buffer_data,
-            )
-            .unwrap();
+        region.blit(display)?;
+        Ok(())
     }
 
     fn draw_value(
@@ -325,7 +832,8 @@ impl<DT: AppDrawTarget<E>, E: Debug> DebugScreen<DT, E> {
         name: &str,
         value: u16,
         color: Rgb565,
-    ) {
+        highlighted: bool,
+    ) -> Result<(), E> {
         let mut s = String::<128>::default();
 
         TINY_FONT
@@ -339,21 +847,38 @@ impl<DT: AppDrawTarget<E>, E: Debug> DebugScreen<DT, E> {
                 },
                 display,
             )
-            .unwrap();
+            .map_err(font_error)?;
 
         s.clear();
-        uwrite!(s, "{}", value).unwrap();
+        uwrite!(s, "{} ", value).unwrap();
         SMALL_FONT
             .render(
                 &s[..],
                 origin + Point::new(1, 12),
                 VerticalPosition::Top,
-                FontColor::WithBackground {
-                    fg: color,
-                    bg: Rgb565::BLACK,
+                if highlighted {
+                    FontColor::WithBackground {
+                        fg: Rgb565::BLACK,
+                        bg: color,
+                    }
+                } else {
+                    FontColor::WithBackground {
+                        fg: color,
+                        bg: Rgb565::BLACK,
+                    }
                 },
                 display,
             )
-            .unwrap();
+            .map_err(font_error)?;
+        Ok(())
+    }
+}
+
+/// Tenths of a KiB, exact bytes below that
+fn write_kib<const N: usize>(s: &mut String<N>, bytes: u32) {
+    if bytes < 1024 {
+        uwrite!(s, "{}B", bytes).unwrap();
+    } else {
+        uwrite!(s, "{}.{}K", bytes / 1024, bytes % 1024 * 10 / 1024).unwrap();
     }
 }
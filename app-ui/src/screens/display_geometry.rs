@@ -0,0 +1,155 @@
+use core::fmt::Debug;
+
+use app_measurements::{DisplayGeometry, DisplayGeometryField};
+use embedded_graphics::geometry::{Point, Size};
+use embedded_graphics::pixelcolor::{Rgb565, RgbColor};
+use embedded_graphics::primitives::Rectangle;
+use heapless::String;
+use u8g2_fonts::types::{FontColor, HorizontalAlignment, VerticalPosition};
+use ufmt::uwrite;
+
+use super::{DrawFrameContext, Navigation, Screen};
+use crate::fonts::TINY_FONT;
+use crate::util::font_error;
+use crate::{config as cfg, draw_badge, AppDrawTarget};
+
+const ROWS_Y: i32 = 40;
+const ROW_HEIGHT: i32 = 16;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DisplayGeometryEditor {
+    pub geometry: DisplayGeometry,
+    pub field: DisplayGeometryField,
+}
+
+impl DisplayGeometryEditor {
+    pub fn new(geometry: DisplayGeometry) -> Self {
+        Self {
+            geometry,
+            field: DisplayGeometryField::OffsetX,
+        }
+    }
+
+    pub fn adjust(&mut self, delta: isize) {
+        self.geometry.adjust(self.field, delta);
+    }
+
+    pub fn select_next(&mut self) {
+        self.field = self.field.next();
+    }
+}
+
+/// Outlines the visible area, garbage columns show up outside of the outline
+/// until the offsets and size match the panel
+pub struct DisplayGeometryScreen<DT, E> {
+    pub editor: DisplayGeometryEditor,
+    /// Closed with a long press once the outline fits
+    pub closed: bool,
+    drawn: Option<DisplayGeometryEditor>,
+    _phantom: core::marker::PhantomData<(DT, E)>,
+}
+
+impl<DT: AppDrawTarget<E>, E: Debug> DisplayGeometryScreen<DT, E> {
+    pub fn new(geometry: DisplayGeometry) -> Self {
+        Self {
+            editor: DisplayGeometryEditor::new(geometry),
+            closed: false,
+            drawn: None,
+            _phantom: core::marker::PhantomData,
+        }
+    }
+}
+
+impl<DT: AppDrawTarget<E>, E: Debug> Screen<DT, E> for DisplayGeometryScreen<DT, E> {
+    async fn draw_init(&mut self, display: &mut DT) -> Result<(), E> {
+        display.clear(cfg::COLOR_BACKGROUND)?;
+        self.drawn = None;
+
+        let size = display.bounding_box().size;
+        for edge in [
+            Rectangle::new(Point::zero(), Size::new(size.width, 1)),
+            Rectangle::new(
+                Point::new(0, size.height as i32 - 1),
+                Size::new(size.width, 1),
+            ),
+            Rectangle::new(Point::zero(), Size::new(1, size.height)),
+            Rectangle::new(
+                Point::new(size.width as i32 - 1, 0),
+                Size::new(1, size.height),
+            ),
+        ] {
+            display.fill_solid(&edge, cfg::COLOR_MENU_ACTION)?;
+        }
+
+        draw_badge(
+            display,
+            Point::new(display.bounding_box().center().x, 14),
+            " DISPLAY ",
+            cfg::COLOR_BACKGROUND,
+            cfg::COLOR_MENU_ACTION,
+        )
+        .await?;
+
+        TINY_FONT
+            .render_aligned(
+                "PRESS FOR NEXT",
+                Point::new(display.bounding_box().center().x, size.height as i32 - 15),
+                VerticalPosition::Top,
+                HorizontalAlignment::Center,
+                FontColor::Transparent(cfg::COLOR_RESULT_VALUE_INACTIVE),
+                display,
+            )
+            .map_err(font_error)?;
+        Ok(())
+    }
+
+    async fn draw_frame(&mut self, display: &mut DT, _cx: DrawFrameContext) -> Result<(), E> {
+        if self.drawn == Some(self.editor) {
+            return Ok(());
+        }
+        self.drawn = Some(self.editor);
+
+        let width = display.bounding_box().size.width as i32;
+        for (index, field) in DisplayGeometryField::ALL.iter().enumerate() {
+            let y = ROWS_Y + index as i32 * ROW_HEIGHT;
+            let active = *field == self.editor.field;
+            TINY_FONT
+                .render(
+                    field.label(),
+                    Point::new(8, y),
+                    VerticalPosition::Top,
+                    FontColor::WithBackground {
+                        fg: Rgb565::BLACK,
+                        bg: if active {
+                            cfg::COLOR_MENU_ACTION
+                        } else {
+                            cfg::COLOR_RESULT_VALUE_INACTIVE
+                        },
+                    },
+                    display,
+                )
+                .map_err(font_error)?;
+
+            let mut s = String::<8>::default();
+            uwrite!(s, "  {}", self.editor.geometry.get(*field)).unwrap();
+            TINY_FONT
+                .render_aligned(
+                    &s[..],
+                    Point::new(width - 8, y),
+                    VerticalPosition::Top,
+                    HorizontalAlignment::Right,
+                    FontColor::WithBackground {
+                        fg: cfg::COLOR_RESULT_VALUE,
+                        bg: cfg::COLOR_BACKGROUND,
+                    },
+                    display,
+                )
+                .map_err(font_error)?;
+        }
+        Ok(())
+    }
+
+    fn take_navigation(&mut self) -> Option<Navigation<DT, E>> {
+        self.closed.then_some(Navigation::Pop)
+    }
+}
@@ -0,0 +1,311 @@
+use core::fmt::{Debug, Write};
+
+use app_measurements::{FocalPlaneResult, FOCAL_PLANE_SENSORS};
+use embedded_graphics::geometry::{Point, Size};
+use embedded_graphics::pixelcolor::{Rgb565, WebColors};
+use embedded_graphics::primitives::Rectangle;
+use heapless::String;
+use u8g2_fonts::types::{FontColor, HorizontalAlignment, VerticalPosition};
+
+use super::{DrawFrameContext, Screen};
+use crate::fonts::TINY_FONT;
+use crate::util::font_error;
+use crate::{config as cfg, draw_badge, AppDrawTarget, DrawProgress, ProgressBar};
+
+const COLOR: Rgb565 = Rgb565::CSS_MEDIUM_ORCHID;
+const MARGIN: i32 = 4;
+const CONTENT_Y: i32 = 20;
+const STATUS_Y: i32 = 24;
+const BARS_Y: i32 = 44;
+const BAR_PITCH: i32 = 20;
+const BAR_HEIGHT: u32 = 12;
+const LABEL_WIDTH: i32 = 12;
+const TABLE_Y: i32 = 28;
+const TABLE_LABEL_WIDTH: i32 = 32;
+const ROWS_Y: i32 = 90;
+const ROW_HEIGHT: i32 = 14;
+const SENSOR_LABELS: [&str; FOCAL_PLANE_SENSORS] = ["L", "C", "R"];
+/// A column per sensor, the row labels, both curtains and the error
+const RESULT_STEPS: usize = FOCAL_PLANE_SENSORS + 4;
+
+type DrawnState = ([u16; FOCAL_PLANE_SENSORS], bool, bool, bool);
+
+/// Three-point accessory: live levels of the left, center and right
+/// photodiodes, then exposure per sensor and the curtain travel times
+pub struct FocalPlaneScreen<DT, E> {
+    pub levels: [u16; FOCAL_PLANE_SENSORS],
+    pub calibrating: bool,
+    pub scanning: bool,
+    pub result: Option<FocalPlaneResult>,
+    max_value: u16,
+    drawn: Option<DrawnState>,
+    result_progress: DrawProgress,
+    _phantom: core::marker::PhantomData<(DT, E)>,
+}
+
+impl<DT: AppDrawTarget<E>, E: Debug> FocalPlaneScreen<DT, E> {
+    pub fn new(max_value: u16) -> Self {
+        Self {
+            levels: [0; FOCAL_PLANE_SENSORS],
+            calibrating: true,
+            scanning: false,
+            result: None,
+            max_value,
+            drawn: None,
+            result_progress: DrawProgress::default(),
+            _phantom: core::marker::PhantomData,
+        }
+    }
+
+    fn clear_content(display: &mut DT) -> Result<(), E> {
+        let size = display.bounding_box().size;
+        display.fill_solid(
+            &Rectangle::new(
+                Point::new(0, CONTENT_Y),
+                Size::new(size.width, size.height - CONTENT_Y as u32),
+            ),
+            cfg::COLOR_BACKGROUND,
+        )?;
+        Ok(())
+    }
+
+    fn draw_levels(&self, display: &mut DT) -> Result<(), E> {
+        let status = if self.calibrating {
+            "CALIBRATING"
+        } else if self.scanning {
+            "MEASURING"
+        } else {
+            "READY"
+        };
+        let mut s = String::<16>::default();
+        write!(s, "{:^13}", status).unwrap();
+        TINY_FONT
+            .render_aligned(
+                &s[..],
+                Point::new(display.bounding_box().center().x, STATUS_Y),
+                VerticalPosition::Top,
+                HorizontalAlignment::Center,
+                FontColor::WithBackground {
+                    fg: COLOR,
+                    bg: cfg::COLOR_BACKGROUND,
+                },
+                display,
+            )
+            .map_err(font_error)?;
+
+        let x = MARGIN + LABEL_WIDTH;
+        let width = display.bounding_box().size.width - (x + MARGIN) as u32;
+        for (index, &level) in self.levels.iter().enumerate() {
+            let y = BARS_Y + index as i32 * BAR_PITCH;
+            TINY_FONT
+                .render(
+                    SENSOR_LABELS[index],
+                    Point::new(MARGIN, y + BAR_HEIGHT as i32 / 2),
+                    VerticalPosition::Center,
+                    FontColor::Transparent(COLOR),
+                    display,
+                )
+                .map_err(font_error)?;
+            ProgressBar::new(Rectangle::new(
+                Point::new(x, y),
+                Size::new(width, BAR_HEIGHT),
+            ))
+            .draw(display, level as u32, self.max_value as u32)?;
+        }
+        Ok(())
+    }
+
+    fn draw_result(
+        &self,
+        display: &mut DT,
+        result: &FocalPlaneResult,
+        step: usize,
+    ) -> Result<(), E> {
+        let mut s = String::<16>::default();
+        if step < FOCAL_PLANE_SENSORS {
+            let index = step;
+            Self::draw_cell(display, 0, index, SENSOR_LABELS[index], COLOR)?;
+
+            s.clear();
+            match result.exposure_micros(index) {
+                Some(micros) => write_millis(&mut s, micros),
+                None => s.push_str("--").unwrap(),
+            }
+            Self::draw_cell(display, 1, index, &s[..], cfg::COLOR_RESULT_VALUE)?;
+
+            s.clear();
+            let color = match result.deviation_percent(index) {
+                Some(deviation) => {
+                    write!(s, "{:+}", deviation).unwrap();
+                    if deviation.abs() < 15 {
+                        cfg::COLOR_RESULT_GOOD
+                    } else if deviation.abs() < 30 {
+                        cfg::COLOR_RESULT_FAIR
+                    } else {
+                        cfg::COLOR_RESULT_BAD
+                    }
+                }
+                None => {
+                    s.push_str("--").unwrap();
+                    cfg::COLOR_RESULT_VALUE
+                }
+            };
+            Self::draw_cell(display, 2, index, &s[..], color)?;
+            return Ok(());
+        }
+
+        match step - FOCAL_PLANE_SENSORS {
+            0 => {
+                Self::draw_row_label(display, TABLE_Y + ROW_HEIGHT, " MS ")?;
+                Self::draw_row_label(display, TABLE_Y + ROW_HEIGHT * 2, " DEV% ")?;
+            }
+            row @ (1 | 2) => {
+                let (name, travel) = if row == 1 {
+                    (" OPEN ", result.first_curtain_micros())
+                } else {
+                    (" CLOSE ", result.second_curtain_micros())
+                };
+                match travel {
+                    Some(micros) => {
+                        write_millis(&mut s, micros.unsigned_abs());
+                        s.push_str(if micros >= 0 { " L>R" } else { " R>L" })
+                            .unwrap();
+                    }
+                    None => s.push_str("--").unwrap(),
+                }
+                Self::draw_row(display, row as i32 - 1, name, &s[..])?;
+            }
+            _ => {
+                write!(s, "+/- {} us", result.uncertainty_micros()).unwrap();
+                Self::draw_row(display, 2, " ERROR ", &s[..])?;
+            }
+        }
+        Ok(())
+    }
+
+    fn draw_cell(
+        display: &mut DT,
+        row: i32,
+        column: usize,
+        text: &str,
+        color: Rgb565,
+    ) -> Result<(), E> {
+        let width = display.bounding_box().size.width as i32 - MARGIN * 2 - TABLE_LABEL_WIDTH;
+        let column_width = width / FOCAL_PLANE_SENSORS as i32;
+        let x = MARGIN + TABLE_LABEL_WIDTH + column_width * column as i32 + column_width / 2;
+        TINY_FONT
+            .render_aligned(
+                text,
+                Point::new(x, TABLE_Y + row * ROW_HEIGHT),
+                VerticalPosition::Top,
+                HorizontalAlignment::Center,
+                FontColor::WithBackground {
+                    fg: color,
+                    bg: cfg::COLOR_BACKGROUND,
+                },
+                display,
+            )
+            .map_err(font_error)?;
+        Ok(())
+    }
+
+    fn draw_row_label(display: &mut DT, y: i32, name: &str) -> Result<(), E> {
+        TINY_FONT
+            .render(
+                name,
+                Point::new(MARGIN, y),
+                VerticalPosition::Top,
+                FontColor::WithBackground {
+                    fg: cfg::COLOR_BACKGROUND,
+                    bg: COLOR,
+                },
+                display,
+            )
+            .map_err(font_error)?;
+        Ok(())
+    }
+
+    fn draw_row(display: &mut DT, index: i32, name: &str, value: &str) -> Result<(), E> {
+        let y = ROWS_Y + index * ROW_HEIGHT;
+        Self::draw_row_label(display, y, name)?;
+        TINY_FONT
+            .render_aligned(
+                value,
+                Point::new(display.bounding_box().size.width as i32 - MARGIN, y),
+                VerticalPosition::Top,
+                HorizontalAlignment::Right,
+                FontColor::WithBackground {
+                    fg: cfg::COLOR_RESULT_VALUE,
+                    bg: cfg::COLOR_BACKGROUND,
+                },
+                display,
+            )
+            .map_err(font_error)?;
+        Ok(())
+    }
+}
+
+/// A decimal place below 100 ms, five characters at most either way
+fn write_millis(s: &mut String<16>, micros: u64) {
+    if micros < 100_000 {
+        write!(s, "{}.{}", micros / 1000, micros / 100 % 10).unwrap();
+    } else {
+        write!(s, "{}", micros / 1000).unwrap();
+    }
+}
+
+impl<DT: AppDrawTarget<E>, E: Debug> Screen<DT, E> for FocalPlaneScreen<DT, E> {
+    async fn draw_init(&mut self, display: &mut DT) -> Result<(), E> {
+        display.clear(cfg::COLOR_BACKGROUND)?;
+        self.drawn = None;
+
+        draw_badge(
+            display,
+            Point::new(display.bounding_box().center().x, 8),
+            " 3-POINT ",
+            cfg::COLOR_BACKGROUND,
+            COLOR,
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn draw_frame(&mut self, display: &mut DT, cx: DrawFrameContext) -> Result<(), E> {
+        let state = (
+            self.levels,
+            self.calibrating,
+            self.scanning,
+            self.result.is_some(),
+        );
+        let result_pending = state.3 && !self.result_progress.is_complete(RESULT_STEPS);
+        if self.drawn == Some(state) && !result_pending {
+            return Ok(());
+        }
+        let view_changed = self.drawn.map(|(.., has_result)| has_result) != Some(state.3);
+        self.drawn = Some(state);
+
+        match self.result {
+            // The result doesn't change once taken, only draw it once,
+            // spread over as many frames as the budget needs
+            Some(ref result) => {
+                if view_changed {
+                    Self::clear_content(display)?;
+                    self.result_progress.restart();
+                }
+                let mut budget = cx.budget;
+                let mut progress = self.result_progress;
+                for step in progress.steps(RESULT_STEPS, &mut budget) {
+                    self.draw_result(display, result, step)?;
+                }
+                self.result_progress = progress;
+            }
+            None => {
+                if view_changed {
+                    Self::clear_content(display)?;
+                }
+                self.draw_levels(display)?;
+            }
+        }
+        Ok(())
+    }
+}
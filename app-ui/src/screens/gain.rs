@@ -0,0 +1,142 @@
+use core::fmt::Debug;
+
+use app_measurements::{AccessoryGain, GainFault};
+use embedded_graphics::geometry::Point;
+use embedded_graphics::pixelcolor::{Rgb565, RgbColor};
+use embedded_graphics::prelude::Dimensions;
+use heapless::String;
+use u8g2_fonts::types::{FontColor, HorizontalAlignment, VerticalPosition};
+use ufmt::uwrite;
+
+use super::{DrawFrameContext, Screen};
+use crate::fonts::TINY_FONT;
+use crate::util::font_error;
+use crate::{config as cfg, draw_badge, AppDrawTarget};
+
+/// What the emitter loopback taught about the sensor module
+pub struct GainScreen<DT, E> {
+    pub outcome: Result<AccessoryGain, GainFault>,
+    /// Still in use after a fault, replaced otherwise
+    pub previous: Option<AccessoryGain>,
+    _phantom: core::marker::PhantomData<(DT, E)>,
+}
+
+impl<DT: AppDrawTarget<E>, E: Debug> GainScreen<DT, E> {
+    pub fn new(outcome: Result<AccessoryGain, GainFault>, previous: Option<AccessoryGain>) -> Self {
+        Self {
+            outcome,
+            previous,
+            _phantom: core::marker::PhantomData,
+        }
+    }
+}
+
+impl<DT: AppDrawTarget<E>, E: Debug> Screen<DT, E> for GainScreen<DT, E> {
+    async fn draw_init(&mut self, display: &mut DT) -> Result<(), E> {
+        display.clear(cfg::COLOR_BACKGROUND)?;
+        let center_x = display.bounding_box().center().x;
+
+        let (badge, color) = match self.outcome {
+            Ok(_) => (" GAIN LEARNED ", cfg::COLOR_RESULT_GOOD),
+            Err(_) => (" GAIN NOT LEARNED ", cfg::COLOR_RESULT_BAD),
+        };
+        draw_badge(
+            display,
+            Point::new(center_x, 15),
+            badge,
+            Rgb565::BLACK,
+            color,
+        )
+        .await?;
+
+        let mut s = String::<32>::default();
+        match self.outcome {
+            Ok(gain) => {
+                for (i, (label, value, unit)) in [
+                    (" GAIN ", gain.gain_percent as u32, "%"),
+                    (" RESPONSE ", gain.response_time_nanos / 1000, " US"),
+                ]
+                .into_iter()
+                .enumerate()
+                {
+                    s.clear();
+                    uwrite!(s, "{}{}{} ", label, value, unit).unwrap();
+                    draw_line(
+                        display,
+                        Point::new(center_x, 45 + i as i32 * 15),
+                        &s[..],
+                        cfg::COLOR_RESULT_VALUE,
+                    )?;
+                }
+            }
+            Err(fault) => {
+                s.clear();
+                uwrite!(s, " {} ", fault.label()).unwrap();
+                draw_line(
+                    display,
+                    Point::new(center_x, 45),
+                    &s[..],
+                    cfg::COLOR_RESULT_BAD,
+                )?;
+                let hint = match fault {
+                    GainFault::Clipped => " LOWER EMIT ",
+                    GainFault::TooDim => " RAISE EMIT OR CHECK SENSOR ",
+                };
+                draw_line(
+                    display,
+                    Point::new(center_x, 60),
+                    hint,
+                    cfg::COLOR_RESULT_VALUE,
+                )?;
+            }
+        }
+
+        s.clear();
+        match (self.outcome, self.previous) {
+            (Ok(_), None) => uwrite!(s, " WAS STOCK ").unwrap(),
+            (Err(_), None) => uwrite!(s, " KEEPING STOCK ").unwrap(),
+            (Ok(_), Some(previous)) => uwrite!(s, " WAS {}% ", previous.gain_percent).unwrap(),
+            (Err(_), Some(previous)) => uwrite!(s, " KEEPING {}% ", previous.gain_percent).unwrap(),
+        }
+        draw_line(
+            display,
+            Point::new(center_x, 90),
+            &s[..],
+            cfg::COLOR_RESULT_VALUE_INACTIVE,
+        )?;
+
+        draw_line(
+            display,
+            Point::new(center_x, display.bounding_box().size.height as i32 - 15),
+            " PRESS TO GO BACK ",
+            cfg::COLOR_RESULT_VALUE,
+        )?;
+        Ok(())
+    }
+
+    async fn draw_frame(&mut self, _display: &mut DT, _cx: DrawFrameContext) -> Result<(), E> {
+        Ok(())
+    }
+}
+
+fn draw_line<D: AppDrawTarget<E>, E: Debug>(
+    display: &mut D,
+    origin: Point,
+    text: &str,
+    color: Rgb565,
+) -> Result<(), E> {
+    TINY_FONT
+        .render_aligned(
+            text,
+            origin,
+            VerticalPosition::Top,
+            HorizontalAlignment::Center,
+            FontColor::WithBackground {
+                fg: color,
+                bg: cfg::COLOR_BACKGROUND,
+            },
+            display,
+        )
+        .map_err(font_error)?;
+    Ok(())
+}
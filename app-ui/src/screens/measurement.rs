@@ -1,13 +1,26 @@
-use core::fmt::Debug;
+use core::fmt::{Debug, Write};
 
+use app_measurements::MeasurementPhase;
 use embedded_graphics::geometry::{Dimensions, Point, Size};
 use embedded_graphics::pixelcolor::{Rgb565, RgbColor};
 use embedded_graphics::primitives::Rectangle;
+use heapless::String;
+use u8g2_fonts::types::{FontColor, HorizontalAlignment, VerticalPosition};
 
 use super::{DrawFrameContext, Screen};
+use crate::fonts::TINY_FONT;
+use crate::util::font_error;
 use crate::{draw_badge, AppDrawTarget};
 
 pub struct MeasurementScreen<DT, E> {
+    /// Release lag mode, the shutter is ignored until the sync input fires
+    pub waiting_for_sync: bool,
+    /// Fire lag mode, counting down to the release output
+    pub waiting_for_release: bool,
+    pub phase: MeasurementPhase,
+    /// Done, but watching whether the shutter fires again
+    pub watching_refire: bool,
+    drawn_status: Option<&'static str>,
     _phantom: core::marker::PhantomData<(DT, E)>,
 }
 
@@ -16,8 +29,8 @@ fn progress_origin<D: Dimensions>(d: &D) -> Point {
 }
 
 impl<DT: AppDrawTarget<E>, E: Debug> Screen<DT, E> for MeasurementScreen<DT, E> {
-    async fn draw_init(&mut self, display: &mut DT) {
-        display.clear(Rgb565::BLACK).unwrap();
+    async fn draw_init(&mut self, display: &mut DT) -> Result<(), E> {
+        display.clear(Rgb565::BLACK)?;
 
         draw_badge(
             display,
@@ -26,17 +39,39 @@ impl<DT: AppDrawTarget<E>, E: Debug> Screen<DT, E> for MeasurementScreen<DT, E>
             Rgb565::BLACK,
             Rgb565::RED,
         )
-        .await;
+        .await?;
 
-        display
-            .fill_solid(
-                &Rectangle::with_center(progress_origin(display), Size::new(40, 11)),
-                Rgb565::RED,
-            )
-            .unwrap();
+        display.fill_solid(
+            &Rectangle::with_center(progress_origin(display), Size::new(40, 11)),
+            Rgb565::RED,
+        )?;
+
+        self.drawn_status = None;
+        Ok(())
     }
 
-    async fn draw_frame(&mut self, display: &mut DT, cx: DrawFrameContext) {
+    async fn draw_frame(&mut self, display: &mut DT, cx: DrawFrameContext) -> Result<(), E> {
+        let status = self.status();
+        if self.drawn_status != Some(status) {
+            self.drawn_status = Some(status);
+            // Padded to overwrite a longer previous status
+            let mut s = String::<24>::default();
+            write!(s, "{:^19}", status).unwrap();
+            TINY_FONT
+                .render_aligned(
+                    &s[..],
+                    progress_origin(display) + Point::new(0, 20),
+                    VerticalPosition::Top,
+                    HorizontalAlignment::Center,
+                    FontColor::WithBackground {
+                        fg: Rgb565::RED,
+                        bg: Rgb565::BLACK,
+                    },
+                    display,
+                )
+                .map_err(font_error)?;
+        }
+
         let t = cx.animation_time_ms / 1000;
 
         let offsets = -1i32..2;
@@ -48,12 +83,27 @@ impl<DT: AppDrawTarget<E>, E: Debug> Screen<DT, E> for MeasurementScreen<DT, E>
             } else {
                 Rgb565::BLACK
             };
-            display
-                .fill_solid(
-                    &Rectangle::with_center(origin + Point::new(dx * 10, 0), Size::new(5, 5)),
-                    color,
-                )
-                .unwrap();
+            display.fill_solid(
+                &Rectangle::with_center(origin + Point::new(dx * 10, 0), Size::new(5, 5)),
+                color,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl<DT: AppDrawTarget<E>, E: Debug> MeasurementScreen<DT, E> {
+    /// A trigger that never comes stays on the first one
+    fn status(&self) -> &'static str {
+        if self.watching_refire {
+            return "TRY TO FIRE AGAIN";
+        }
+        match self.phase {
+            MeasurementPhase::Armed if self.waiting_for_release => "FIRING RELEASE",
+            MeasurementPhase::Armed if self.waiting_for_sync => "WAITING FOR SYNC",
+            MeasurementPhase::Armed => "WAITING FOR LIGHT",
+            MeasurementPhase::Exposing => "EXPOSING...",
+            MeasurementPhase::Finishing | MeasurementPhase::Done => "FINISHING...",
         }
     }
 }
@@ -61,6 +111,11 @@ impl<DT: AppDrawTarget<E>, E: Debug> Screen<DT, E> for MeasurementScreen<DT, E>
 impl<DT: AppDrawTarget<E>, E: Debug> Default for MeasurementScreen<DT, E> {
     fn default() -> Self {
         Self {
+            waiting_for_sync: false,
+            waiting_for_release: false,
+            phase: MeasurementPhase::Armed,
+            watching_refire: false,
+            drawn_status: None,
             _phantom: core::marker::PhantomData,
         }
     }
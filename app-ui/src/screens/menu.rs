@@ -1,30 +1,273 @@
-use core::fmt::Debug;
+use core::fmt::{Debug, Write};
 
-use embedded_graphics::geometry::Point;
+use embedded_graphics::geometry::{Point, Size};
 use embedded_graphics::pixelcolor::{Rgb565, RgbColor, WebColors};
+use embedded_graphics::primitives::Rectangle;
+use heapless::String;
 use u8g2_fonts::types::{FontColor, HorizontalAlignment, VerticalPosition};
 
-use super::{DrawFrameContext, Screen};
+use super::{
+    AboutScreen, AnnotationScreen, DisplayGeometryScreen, DrawFrameContext, Navigation, Screen,
+    Screens, UpdateScreen,
+};
 use crate::fonts::{SMALL_FONT, TINY_FONT};
-use crate::{config, AppDrawTarget};
+use crate::util::font_error;
+use crate::{config, AppDrawTarget, Spinner};
+
+const MENU_ITEMS: usize = 40;
+
+/// Everything the menu lists, top to bottom
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MenuItem {
+    Measure,
+    Debug,
+    Counter,
+    Digital,
+    Sequence,
+    Annotate,
+    Profile,
+    Emit,
+    Sensitivity,
+    ReleaseLag,
+    Sound,
+    Rolling,
+    Fx,
+    Dither,
+    AutoLow,
+    ThreePoint,
+    Hold,
+    Double,
+    Speeds,
+    Timing,
+    Fixture,
+    Tolerance,
+    FastTolerance,
+    Flip,
+    Refire,
+    QuickCal,
+    Outliers,
+    FireLag,
+    SelfCheck,
+    Soak,
+    AccIdle,
+    Instant,
+    Adc,
+    HighGain,
+    Burst,
+    Beeper,
+    Learn,
+    Display,
+    About,
+    UsbUpdate,
+}
+
+/// What follows an item's label, filled in from the settings
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MenuValue {
+    #[default]
+    None,
+    Label(&'static str),
+    Toggle(bool),
+    Number(u16),
+    /// Semitones, see the beeper tuning screen
+    Offset(i8),
+    /// Learned from the emitter loopback, `None` for the stock module
+    Gain(Option<u16>),
+}
+
+impl MenuItem {
+    pub const ALL: [MenuItem; MENU_ITEMS] = [
+        MenuItem::Measure,
+        MenuItem::Debug,
+        MenuItem::Counter,
+        MenuItem::Digital,
+        MenuItem::Sequence,
+        MenuItem::Annotate,
+        MenuItem::Profile,
+        MenuItem::Emit,
+        MenuItem::Sensitivity,
+        MenuItem::ReleaseLag,
+        MenuItem::Sound,
+        MenuItem::Rolling,
+        MenuItem::Fx,
+        MenuItem::Dither,
+        MenuItem::AutoLow,
+        MenuItem::ThreePoint,
+        MenuItem::Hold,
+        MenuItem::Double,
+        MenuItem::Speeds,
+        MenuItem::Timing,
+        MenuItem::Fixture,
+        MenuItem::Tolerance,
+        MenuItem::FastTolerance,
+        MenuItem::Flip,
+        MenuItem::Refire,
+        MenuItem::QuickCal,
+        MenuItem::Outliers,
+        MenuItem::FireLag,
+        MenuItem::SelfCheck,
+        MenuItem::Soak,
+        MenuItem::AccIdle,
+        MenuItem::Instant,
+        MenuItem::Adc,
+        MenuItem::HighGain,
+        MenuItem::Burst,
+        MenuItem::Beeper,
+        MenuItem::Learn,
+        MenuItem::Display,
+        MenuItem::About,
+        MenuItem::UsbUpdate,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            MenuItem::Measure => " MEASURE ",
+            MenuItem::Debug => " DEBUG ",
+            MenuItem::Counter => " COUNTER ",
+            MenuItem::Digital => " DIGITAL ",
+            MenuItem::Sequence => " SEQUENCE ",
+            MenuItem::Annotate => " ANNOTATE ",
+            MenuItem::Profile => " PROFILE ",
+            MenuItem::Emit => " EMIT ",
+            MenuItem::Sensitivity => " SENSITIVITY ",
+            MenuItem::ReleaseLag => " RELEASE LAG ",
+            MenuItem::Sound => " SOUND ",
+            MenuItem::Rolling => " ROLLING ",
+            MenuItem::Fx => " FX ",
+            MenuItem::Dither => " DITHER ",
+            MenuItem::AutoLow => " AUTO LOW ",
+            MenuItem::ThreePoint => " 3-POINT ",
+            MenuItem::Hold => " HOLD ",
+            MenuItem::Double => " DOUBLE ",
+            MenuItem::Speeds => " SPEEDS ",
+            MenuItem::Timing => " TIMING ",
+            MenuItem::Fixture => " FIXTURE ",
+            MenuItem::Tolerance => " TOLERANCE ",
+            MenuItem::FastTolerance => " FAST TOL ",
+            MenuItem::Flip => " FLIP ",
+            MenuItem::Refire => " REFIRE ",
+            MenuItem::QuickCal => " QUICK CAL ",
+            MenuItem::Outliers => " OUTLIERS ",
+            MenuItem::FireLag => " FIRE LAG ",
+            MenuItem::SelfCheck => " SELF CHECK ",
+            MenuItem::Soak => " SOAK ",
+            MenuItem::AccIdle => " ACC IDLE ",
+            MenuItem::Instant => " INSTANT ",
+            MenuItem::Adc => " ADC ",
+            MenuItem::HighGain => " HIGH GAIN ",
+            MenuItem::Burst => " BURST ",
+            MenuItem::Beeper => " BEEPER ",
+            MenuItem::Learn => " LEARN ",
+            MenuItem::Display => " DISPLAY ",
+            MenuItem::About => " ABOUT ",
+            MenuItem::UsbUpdate => " USB UPDATE ",
+        }
+    }
+
+    /// Starts a mode rather than changing a setting, a double press repeats these
+    pub fn is_mode(self) -> bool {
+        matches!(
+            self,
+            MenuItem::Measure
+                | MenuItem::Debug
+                | MenuItem::Counter
+                | MenuItem::Digital
+                | MenuItem::Sequence
+                | MenuItem::ReleaseLag
+                | MenuItem::Rolling
+                | MenuItem::ThreePoint
+                | MenuItem::FireLag
+                | MenuItem::SelfCheck
+                | MenuItem::Soak
+                | MenuItem::Learn
+        )
+    }
+
+    /// The items with a number for a value
+    fn spinner(self, value: u16) -> Option<Spinner> {
+        Some(match self {
+            MenuItem::Emit | MenuItem::Dither => Spinner::new(value, 0, 100)
+                .with_unit("% ")
+                .with_zero_label("OFF"),
+            MenuItem::Hold => Spinner::new(value, 0, 9999).with_unit("MS"),
+            MenuItem::Double => Spinner::new(value, 0, 999)
+                .with_unit("MS")
+                .with_zero_label("OFF"),
+            MenuItem::Fixture => Spinner::new(value, 0, 9999)
+                .with_unit("MS")
+                .with_zero_label("OFF"),
+            MenuItem::Tolerance | MenuItem::FastTolerance => {
+                Spinner::new(value, 0, 99).with_unit("% ")
+            }
+            MenuItem::Refire => Spinner::new(value, 0, 99)
+                .with_unit("S ")
+                .with_zero_label("OFF"),
+            MenuItem::Adc => Spinner::new(value, 0, 99).with_unit(" BIT"),
+            _ => return None,
+        })
+    }
+
+    fn write_row(self, value: MenuValue, s: &mut impl Write) {
+        s.write_str(self.label()).unwrap();
+        match value {
+            MenuValue::None => (),
+            // The sensitivity only shows in the indicator
+            MenuValue::Number(number) => {
+                if let Some(spinner) = self.spinner(number) {
+                    spinner.write(s);
+                }
+            }
+            MenuValue::Label(label) => match self {
+                MenuItem::Profile | MenuItem::Sound | MenuItem::Fx => {
+                    write!(s, "{:<5} ", label).unwrap()
+                }
+                _ => write!(s, "{:<4} ", label).unwrap(),
+            },
+            MenuValue::Toggle(on) => write!(s, "{:<3}", if on { "ON" } else { "OFF" }).unwrap(),
+            MenuValue::Offset(offset) => write!(s, "{:<+3}", offset).unwrap(),
+            MenuValue::Gain(Some(percent)) => write!(s, "{}%  ", percent).unwrap(),
+            MenuValue::Gain(None) => s.write_str("STOCK").unwrap(),
+        }
+    }
+}
+
+/// Opened over the menu, closing it comes back to the same item
+pub enum MenuPage<DT, E> {
+    About(AboutScreen<DT, E>),
+    Annotation(AnnotationScreen<DT, E>),
+    DisplayGeometry(DisplayGeometryScreen<DT, E>),
+    Update(UpdateScreen<DT, E>),
+}
 
 pub struct MenuScreen<DT, E> {
-    pub position: usize,
-    pub sensitivity: u8,
+    pub selected: MenuItem,
+    /// One per item of [`MenuItem::ALL`]
+    pub values: [MenuValue; MENU_ITEMS],
+    scroll: usize,
     last_position: usize,
-    _phantom: core::marker::PhantomData<(DT, E)>,
+    last_scroll: usize,
+    last_values: [MenuValue; MENU_ITEMS],
+    page: Option<MenuPage<DT, E>>,
 }
 
-const LABELS: [&str; 3] = [" MEASURE ", " DEBUG ", " USB UPDATE "];
+const VISIBLE_ITEMS: usize = 9;
+const MENU_Y: i32 = 8;
+const ITEM_HEIGHT: i32 = 15;
+const SCROLL_BAR_WIDTH: u32 = 2;
+/// Spans the full width on any display
+const TEXT_AREA: [Rectangle; 1] = [Rectangle::new(
+    Point::new(0, MENU_Y),
+    Size::new(u16::MAX as u32, (VISIBLE_ITEMS as i32 * ITEM_HEIGHT) as u32),
+)];
 
 impl<DT: AppDrawTarget<E>, E: Debug> Screen<DT, E> for MenuScreen<DT, E> {
-    async fn draw_init(&mut self, display: &mut DT) {
+    async fn draw_init(&mut self, display: &mut DT) -> Result<(), E> {
         let width = display.bounding_box().size.width;
         let height = display.bounding_box().size.height;
 
-        display
-            .fill_solid(&display.bounding_box(), config::COLOR_BACKGROUND)
-            .unwrap();
+        display.fill_solid(&display.bounding_box(), config::COLOR_BACKGROUND)?;
+        // Also after a page, the items go with the background
+        self.last_scroll = 999;
 
         TINY_FONT
             .render_aligned(
@@ -38,18 +281,50 @@ impl<DT: AppDrawTarget<E>, E: Debug> Screen<DT, E> for MenuScreen<DT, E> {
                 },
                 display,
             )
-            .unwrap();
+            .map_err(font_error)?;
+        Ok(())
     }
 
-    async fn draw_frame(&mut self, display: &mut DT, _cx: DrawFrameContext) {
+    async fn draw_frame(&mut self, display: &mut DT, _cx: DrawFrameContext) -> Result<(), E> {
         let bg = config::COLOR_BACKGROUND;
         let fg = config::COLOR_RESULT_VALUE;
+        let position = self.selected as usize;
+
+        // Keep the selection in view
+        if position < self.scroll {
+            self.scroll = position;
+        } else if position >= self.scroll + VISIBLE_ITEMS {
+            self.scroll = position + 1 - VISIBLE_ITEMS;
+        }
+
+        let scrolled = self.last_scroll != self.scroll;
+        if scrolled {
+            display.fill_solid(
+                &Rectangle::new(
+                    Point::new(0, MENU_Y),
+                    Size::new(
+                        display.bounding_box().size.width,
+                        (VISIBLE_ITEMS as i32 * ITEM_HEIGHT) as u32,
+                    ),
+                ),
+                bg,
+            )?;
+        }
 
-        let mut y_pos = 20;
-        let item_height = 20;
-        let should_draw = self.last_position != self.position;
+        let mut y_pos = MENU_Y;
+        let should_draw =
+            scrolled || self.last_position != position || self.last_values != self.values;
+
+        for (index, (item, value)) in MenuItem::ALL
+            .iter()
+            .zip(self.values)
+            .enumerate()
+            .skip(self.scroll)
+            .take(VISIBLE_ITEMS)
+        {
+            let mut s = String::<128>::default();
+            item.write_row(value, &mut s);
 
-        for (index, label) in LABELS.iter().enumerate() {
             if should_draw {
                 // display
                 //     .fill_solid(
@@ -60,51 +335,38 @@ impl<DT: AppDrawTarget<E>, E: Debug> Screen<DT, E> for MenuScreen<DT, E> {
 
                 SMALL_FONT
                     .render(
-                        *label,
+                        &s[..],
                         Point::new(16, y_pos),
                         VerticalPosition::Top,
-                        if index == self.position {
+                        if index == position {
                             FontColor::WithBackground { fg: bg, bg: fg }
                         } else {
                             FontColor::WithBackground { fg, bg }
                         },
                         display,
                     )
-                    .unwrap();
+                    .map_err(font_error)?;
             }
 
-            if index == self.position {
-                match index {
-                    0 | 1 | 2 => {
-                        SMALL_FONT
-                            .render(
-                                ">",
-                                Point::new(5, y_pos),
-                                VerticalPosition::Top,
-                                FontColor::WithBackground {
-                                    bg: config::COLOR_MENU_ACTION,
-                                    fg: config::COLOR_BACKGROUND,
-                                },
-                                display,
-                            )
-                            .unwrap();
+            if index == position {
+                let indicator = match (item, value) {
+                    (MenuItem::Sensitivity, MenuValue::Number(sensitivity)) => {
+                        ["1", "2", "3"][sensitivity as usize]
                     }
-                    // 2 => {
-                        // SMALL_FONT
-                        //     .render(
-                        //         ["1", "2", "3"][self.sensitivity as usize],
-                        //         Point::new(5, y_pos),
-                        //         VerticalPosition::Top,
-                        //         FontColor::WithBackground {
-                        //             bg: config::COLOR_MENU_ACTION,
-                        //             fg: config::COLOR_BACKGROUND,
-                        //         },
-                        //         display,
-                        //     )
-                        //     .unwrap();
-                    // }
-                    _ => (),
-                }
+                    _ => ">",
+                };
+                SMALL_FONT
+                    .render(
+                        indicator,
+                        Point::new(5, y_pos),
+                        VerticalPosition::Top,
+                        FontColor::WithBackground {
+                            bg: config::COLOR_MENU_ACTION,
+                            fg: config::COLOR_BACKGROUND,
+                        },
+                        display,
+                    )
+                    .map_err(font_error)?;
             } else {
                 SMALL_FONT
                     .render(
@@ -117,28 +379,79 @@ impl<DT: AppDrawTarget<E>, E: Debug> Screen<DT, E> for MenuScreen<DT, E> {
                         },
                         display,
                     )
-                    .unwrap();
+                    .map_err(font_error)?;
             }
 
-            y_pos += item_height;
+            y_pos += ITEM_HEIGHT;
+        }
+
+        // Over the ends of long labels, so it goes last
+        if should_draw && MENU_ITEMS > VISIBLE_ITEMS {
+            self.draw_scroll_bar(display)?;
         }
-        self.last_position = self.position;
+
+        self.last_position = position;
+        self.last_scroll = self.scroll;
+        self.last_values = self.values;
+        Ok(())
+    }
+
+    fn take_navigation(&mut self) -> Option<Navigation<DT, E>> {
+        let screen: Screens<DT, E> = match self.page.take()? {
+            MenuPage::About(screen) => screen.into(),
+            MenuPage::Annotation(screen) => screen.into(),
+            MenuPage::DisplayGeometry(screen) => screen.into(),
+            MenuPage::Update(screen) => screen.into(),
+        };
+        Some(Navigation::Push(screen))
+    }
+
+    fn fx_exclusions(&self) -> &[Rectangle] {
+        &TEXT_AREA
     }
 }
 
-impl MenuScreen<(), ()> {
-    pub fn options_len() -> usize {
-        LABELS.len()
+impl<DT: AppDrawTarget<E>, E: Debug> MenuScreen<DT, E> {
+    /// Pushed after the next frame
+    pub fn open(&mut self, page: MenuPage<DT, E>) {
+        self.page = Some(page);
+    }
+
+    /// Where the visible options sit in the whole list, along the right edge
+    fn draw_scroll_bar(&self, display: &mut DT) -> Result<(), E> {
+        let track_height = (VISIBLE_ITEMS as i32 * ITEM_HEIGHT) as u32;
+        let thumb_height = (track_height * VISIBLE_ITEMS as u32 / MENU_ITEMS as u32).max(4);
+        let thumb_y = (track_height - thumb_height) * self.scroll as u32
+            / (MENU_ITEMS - VISIBLE_ITEMS) as u32;
+        let x = (display.bounding_box().size.width - SCROLL_BAR_WIDTH) as i32;
+
+        display.fill_solid(
+            &Rectangle::new(
+                Point::new(x, MENU_Y),
+                Size::new(SCROLL_BAR_WIDTH, track_height),
+            ),
+            config::COLOR_BACKGROUND,
+        )?;
+        display.fill_solid(
+            &Rectangle::new(
+                Point::new(x, MENU_Y + thumb_y as i32),
+                Size::new(SCROLL_BAR_WIDTH, thumb_height),
+            ),
+            config::COLOR_MENU_ACTION,
+        )
     }
 }
 
 impl<DT: AppDrawTarget<E>, E: Debug> Default for MenuScreen<DT, E> {
     fn default() -> Self {
         Self {
-            position: 0,
-            sensitivity: 0,
+            selected: MenuItem::Measure,
+            values: [MenuValue::None; MENU_ITEMS],
+            scroll: 0,
             last_position: 999,
-            _phantom: core::marker::PhantomData,
+            last_scroll: 999,
+            last_values: [MenuValue::None; MENU_ITEMS],
+            page: None,
         }
     }
 }
@@ -1,37 +1,83 @@
+mod about;
+mod annotation;
+mod beeper_tuning;
 mod boot;
 mod calibration;
+mod clock_fault;
+mod counter;
 mod debug;
+mod display_geometry;
+mod focal_plane;
+mod gain;
 mod measurement;
 mod menu;
+mod navigation;
 mod no_accessory;
 mod results;
+mod resume;
+mod scan;
+mod self_check;
+mod sensor_fault;
+mod sequence;
+mod soak;
 mod start;
 mod update;
 
 use core::fmt::Debug;
 
+pub use about::{AboutScreen, BootDetails, BuildInfo};
+pub use annotation::{AnnotationEditor, AnnotationField, AnnotationScreen};
+pub use beeper_tuning::{BeeperTuner, BeeperTuningScreen, BEEPER_OFFSETS};
 pub use boot::BootScreen;
 pub use calibration::CalibrationScreen;
-pub use debug::DebugScreen;
+pub use clock_fault::ClockFaultScreen;
+pub use counter::CounterScreen;
+pub use debug::{
+    DebugPage, DebugScreen, MemoryUsage, ThresholdEditor, ThresholdSelection, MEMORY_BUFFERS,
+};
+pub use display_geometry::{DisplayGeometryEditor, DisplayGeometryScreen};
+use embedded_graphics::primitives::Rectangle;
 use enum_dispatch::enum_dispatch;
+pub use focal_plane::FocalPlaneScreen;
+pub use gain::GainScreen;
 pub use measurement::MeasurementScreen;
-pub use menu::MenuScreen;
+pub use menu::{MenuItem, MenuPage, MenuScreen, MenuValue};
+pub use navigation::{Navigation, ScreenStack, NAVIGATION_DEPTH};
 pub use no_accessory::NoAccessoryScreen;
 pub use results::ResultsScreen;
+pub use resume::ResumeScreen;
+pub use scan::ScanScreen;
+pub use self_check::SelfCheckScreen;
+pub use sensor_fault::SensorFaultScreen;
+pub use sequence::SequenceScreen;
+pub use soak::SoakScreen;
 pub use start::StartScreen;
 pub use update::UpdateScreen;
 
-use crate::AppDrawTarget;
+use crate::{AppDrawTarget, DrawBudget};
 
+#[derive(Clone, Copy, Debug)]
 pub struct DrawFrameContext {
     pub animation_time_ms: u32,
+    /// Shared by everything the screen draws this frame
+    pub budget: DrawBudget,
 }
 
 #[allow(async_fn_in_trait)]
 #[enum_dispatch(Screens<DT, E>)]
 pub trait Screen<DT: AppDrawTarget<E>, E: Debug> {
-    async fn draw_init(&mut self, display: &mut DT);
-    async fn draw_frame(&mut self, display: &mut DT, cx: DrawFrameContext);
+    async fn draw_init(&mut self, display: &mut DT) -> Result<(), E>;
+    async fn draw_frame(&mut self, display: &mut DT, cx: DrawFrameContext) -> Result<(), E>;
+
+    /// Polled by [`ScreenStack`] after every frame
+    fn take_navigation(&mut self) -> Option<Navigation<DT, E>> {
+        None
+    }
+
+    /// Text and other detail that FX would make harder to read
+    fn fx_exclusions(&self) -> &[Rectangle] {
+        &[]
+    }
 }
 
 #[allow(clippy::large_enum_variant)]
@@ -46,4 +92,18 @@ pub enum Screens<DT: AppDrawTarget<E>, E: Debug> {
     Update(UpdateScreen<DT, E>),
     NoAccessory(NoAccessoryScreen<DT, E>),
     Menu(MenuScreen<DT, E>),
+    Counter(CounterScreen<DT, E>),
+    Annotation(AnnotationScreen<DT, E>),
+    Sequence(SequenceScreen<DT, E>),
+    About(AboutScreen<DT, E>),
+    Scan(ScanScreen<DT, E>),
+    Resume(ResumeScreen<DT, E>),
+    FocalPlane(FocalPlaneScreen<DT, E>),
+    DisplayGeometry(DisplayGeometryScreen<DT, E>),
+    SensorFault(SensorFaultScreen<DT, E>),
+    ClockFault(ClockFaultScreen<DT, E>),
+    SelfCheck(SelfCheckScreen<DT, E>),
+    Soak(SoakScreen<DT, E>),
+    BeeperTuning(BeeperTuningScreen<DT, E>),
+    Gain(GainScreen<DT, E>),
 }
@@ -0,0 +1,126 @@
+use core::fmt::Debug;
+
+use heapless::Vec;
+
+use super::{DrawFrameContext, Screen, Screens};
+use crate::fx::{Transition, TransitionDirection};
+use crate::AppDrawTarget;
+
+pub const NAVIGATION_DEPTH: usize = 4;
+
+pub enum Navigation<DT: AppDrawTarget<E>, E: Debug> {
+    Push(Screens<DT, E>),
+    Replace(Screens<DT, E>),
+    Pop,
+}
+
+/// Stack of screens, only the topmost one is drawn.
+/// The root screen is owned by the app mode and can't be popped.
+pub struct ScreenStack<DT: AppDrawTarget<E>, E: Debug> {
+    stack: Vec<Screens<DT, E>, NAVIGATION_DEPTH>,
+    needs_init: bool,
+    transition: Transition,
+    pending_transition: Option<TransitionDirection>,
+}
+
+impl<DT: AppDrawTarget<E>, E: Debug> ScreenStack<DT, E> {
+    pub fn new(root: Screens<DT, E>) -> Self {
+        let mut stack = Vec::new();
+        let _ = stack.push(root);
+        Self {
+            stack,
+            needs_init: true,
+            transition: Transition::Off,
+            pending_transition: None,
+        }
+    }
+
+    pub fn set_transition(&mut self, transition: Transition) {
+        self.transition = transition;
+    }
+
+    pub fn current(&mut self) -> &mut Screens<DT, E> {
+        self.stack.last_mut().unwrap()
+    }
+
+    pub fn depth(&self) -> usize {
+        self.stack.len()
+    }
+
+    /// Drops the whole stack and starts over from a new root
+    pub fn reset(&mut self, root: Screens<DT, E>) {
+        self.stack.clear();
+        let _ = self.stack.push(root);
+        self.needs_init = true;
+        self.pending_transition = Some(TransitionDirection::Forward);
+    }
+
+    /// Makes the current screen draw from scratch on the next frame
+    pub fn redraw(&mut self) {
+        self.needs_init = true;
+    }
+
+    /// Replaces the topmost screen if the stack is full
+    pub fn push(&mut self, screen: Screens<DT, E>) {
+        if let Err(screen) = self.stack.push(screen) {
+            self.replace(screen);
+            return;
+        }
+        self.needs_init = true;
+        self.pending_transition = Some(TransitionDirection::Forward);
+    }
+
+    pub fn replace(&mut self, screen: Screens<DT, E>) {
+        *self.current() = screen;
+        self.needs_init = true;
+        self.pending_transition = Some(TransitionDirection::Forward);
+    }
+
+    pub fn pop(&mut self) -> Option<Screens<DT, E>> {
+        if self.stack.len() <= 1 {
+            return None;
+        }
+        self.needs_init = true;
+        self.pending_transition = Some(TransitionDirection::Back);
+        self.stack.pop()
+    }
+
+    pub fn navigate(&mut self, navigation: Navigation<DT, E>) {
+        match navigation {
+            Navigation::Push(screen) => self.push(screen),
+            Navigation::Replace(screen) => self.replace(screen),
+            Navigation::Pop => {
+                self.pop();
+            }
+        }
+    }
+
+    /// Errors leave the screen to be drawn from scratch, the caller decides
+    /// whether the display needs more than that
+    pub async fn draw_frame(&mut self, display: &mut DT, cx: DrawFrameContext) -> Result<(), E> {
+        display.hint_fx_exclusions(self.current().fx_exclusions());
+
+        let result = self.draw_current(display, cx).await;
+        if result.is_err() {
+            self.needs_init = true;
+        }
+
+        if let Some(navigation) = self.current().take_navigation() {
+            self.navigate(navigation);
+        }
+        result
+    }
+
+    async fn draw_current(&mut self, display: &mut DT, cx: DrawFrameContext) -> Result<(), E> {
+        if self.needs_init {
+            self.needs_init = false;
+            // Not for redraws, the old content is gone already
+            if let Some(direction) = self.pending_transition.take() {
+                self.transition.draw(display, direction).await?;
+            }
+            self.current().draw_init(display).await?;
+        }
+
+        self.current().draw_frame(display, cx).await
+    }
+}
@@ -12,6 +12,7 @@ use u8g2_fonts::types::{FontColor, HorizontalAlignment, VerticalPosition};
 
 use super::{DrawFrameContext, Screen};
 use crate::fonts::TINY_FONT;
+use crate::util::font_error;
 use crate::{draw_badge, AppDrawTarget};
 
 pub struct NoAccessoryScreen<DT, E> {
@@ -20,8 +21,8 @@ pub struct NoAccessoryScreen<DT, E> {
 }
 
 impl<DT: AppDrawTarget<E>, E: Debug> Screen<DT, E> for NoAccessoryScreen<DT, E> {
-    async fn draw_init(&mut self, display: &mut DT) {
-        display.clear(Rgb565::BLACK).unwrap();
+    async fn draw_init(&mut self, display: &mut DT) -> Result<(), E> {
+        display.clear(Rgb565::BLACK)?;
 
         draw_badge(
             display,
@@ -30,7 +31,7 @@ impl<DT: AppDrawTarget<E>, E: Debug> Screen<DT, E> for NoAccessoryScreen<DT, E>
             Rgb565::BLACK,
             Rgb565::RED,
         )
-        .await;
+        .await?;
 
         TINY_FONT
             .render_aligned(
@@ -44,17 +45,17 @@ impl<DT: AppDrawTarget<E>, E: Debug> Screen<DT, E> for NoAccessoryScreen<DT, E>
                 },
                 display,
             )
-            .unwrap();
+            .map_err(font_error)?;
 
         Image::new(
             &self.img,
             display.bounding_box().center() - self.img.bounding_box().size / 2 + Point::new(0, 50),
         )
-        .draw(display)
-        .unwrap();
+        .draw(display)?;
+        Ok(())
     }
 
-    async fn draw_frame(&mut self, display: &mut DT, cx: DrawFrameContext) {
+    async fn draw_frame(&mut self, display: &mut DT, cx: DrawFrameContext) -> Result<(), E> {
         let t = cx.animation_time_ms / 150;
 
         let offsets = [12, 12, 12, 9, 6, 4, 2, 2, 2, 2, 2];
@@ -83,10 +84,10 @@ impl<DT: AppDrawTarget<E>, E: Debug> Screen<DT, E> for NoAccessoryScreen<DT, E>
                         1,
                     ),
                     &mut display.translated(Point::new(0, dy)),
-                )
-                .unwrap();
+                )?;
             }
         }
+        Ok(())
     }
 }
 
@@ -1,55 +1,97 @@
-use core::fmt::Debug;
+use core::fmt::{Debug, Write};
 
-use app_measurements::util::get_closest_shutter_speed;
-use app_measurements::{CalibrationState, MeasurementResult};
+use app_measurements::util::SpeedTable;
+use app_measurements::{
+    Annotation, BurstTrace, CalibrationState, CameraSlot, DurationMethod, MeasurementResult,
+    RefireCheck, ShutterSpeed, Tolerances, Verdict,
+};
 use eg_seven_segment::SevenSegmentStyleBuilder;
+use embedded_graphics::draw_target::DrawTarget;
 use embedded_graphics::geometry::{Point, Size};
-use embedded_graphics::primitives::{Line, PrimitiveStyleBuilder, StyledDrawable};
+use embedded_graphics::pixelcolor::Rgb565;
+use embedded_graphics::primitives::{Line, PrimitiveStyleBuilder, Rectangle, StyledDrawable};
 use embedded_graphics::text::Text;
 use embedded_graphics::Drawable;
-use heapless::String;
-use u8g2_fonts::types::{FontColor, VerticalPosition};
+use heapless::{String, Vec};
+use micromath::F32Ext;
+use u8g2_fonts::types::{FontColor, HorizontalAlignment, VerticalPosition};
 use ufmt::uwrite;
 
 use super::{DrawFrameContext, Screen};
-use crate::chart::draw_chart;
-use crate::fonts::{ALT_FONT, TINY_FONT};
-use crate::format::write_fraction;
-use crate::ruler::draw_speed_ruler;
-use crate::{config as cfg, AppDrawTarget};
+use crate::chart::{draw_chart, ChartViewport};
+use crate::fonts::{ALT_FONT, SMALL_FONT, TINY_FONT};
+use crate::format::{micros_to_string, write_fraction};
+use crate::pager::draw_page_indicator;
+use crate::ruler::{RulerCursor, SpeedRuler};
+use crate::util::font_error;
+use crate::{config as cfg, AppDrawTarget, OffscreenRegion};
+
+const PAGE_SUMMARY: usize = 0;
+const PAGE_RULER: usize = 1;
+const PAGE_WAVEFORM: usize = 2;
+const PAGE_NUMBERS: usize = 3;
+const PAGE_TITLES: [&str; 4] = [" SUMMARY ", " RULER ", " WAVEFORM ", " NUMBERS "];
+const WARNING_WIDTH: usize = 24;
+const WARNING_CYCLE_MS: u64 = 1500;
+const SPEED_ORIGIN_Y: i32 = 35;
+/// The summary readouts are redrawn every frame offscreen, centered on the screen
+const READOUT_WIDTH: usize = 128;
+/// Under the title, the digits and the 1/ in front of them
+const SPEED_HEIGHT: usize = 32;
+/// The digits, FAST/SLOW and the verdict next to them
+const DEVIATION_HEIGHT: usize = 24;
+const SUMMARY_RULER_Y: i32 = 135;
+const CURSOR_RULER_Y: i32 = 110;
+/// Wide enough for the longest speed label, so that each covers the last
+const CURSOR_LABEL_WIDTH: usize = 14;
 
 pub struct ResultsScreen<DT, E> {
     pub calibration: CalibrationState,
     pub result: MeasurementResult,
+    pub annotation: Annotation,
+    pub page: usize,
+    pub viewport: ChartViewport,
+    /// Nearest speed and ruler, set before the first draw
+    pub speed_table: SpeedTable,
+    /// Which of the result's durations the summary shows, set before the first draw
+    pub duration_method: DurationMethod,
+    /// PASS/FAIL bands, set before the first draw
+    pub tolerances: Tolerances,
+    /// Whether the shutter fired again after the measurement, if it was watched
+    pub refire: Option<RefireCheck>,
+    /// The speed the ruler page compares against, see [`ResultsScreen::initial_cursor`]
+    pub cursor: RulerCursor,
+    /// Laid out by the ruler page for the cursor to be drawn over
+    ruler: Option<SpeedRuler>,
+    drawn_cursor: Option<RulerCursor>,
+    drawn_page: Option<usize>,
+    drawn_viewport: ChartViewport,
+    drawn_warning: Option<usize>,
     _phantom: core::marker::PhantomData<(DT, E)>,
 }
 
 impl<DT: AppDrawTarget<E>, E: Debug> Screen<DT, E> for ResultsScreen<DT, E> {
-    async fn draw_init(&mut self, display: &mut DT) {
-        display.clear(cfg::COLOR_BACKGROUND).unwrap();
-
-        draw_chart(
-            display,
-            &self.result.sample_buffer,
-            5,
-            Some(self.result.samples_since_start),
-            Some(self.result.samples_since_end),
-            self.result.duration_micros,
-            self.result.integrated_duration_micros,
-            false,
-        );
-
-        draw_speed_ruler(
-            display,
-            Point::new(0, 145),
-            self.result.integrated_duration_micros as f32 / 1_000_000.0,
-        );
+    async fn draw_init(&mut self, display: &mut DT) -> Result<(), E> {
+        self.draw_page(display)?;
+        Ok(())
     }
 
-    async fn draw_frame(&mut self, display: &mut DT, _cx: DrawFrameContext) {
-        let ss_origin = Point::new(display.bounding_box().center().x, 50);
-        self.draw_shutter_speed(display, ss_origin);
-        self.draw_deviation(display, ss_origin + Point::new(0, 60));
+    async fn draw_frame(&mut self, display: &mut DT, cx: DrawFrameContext) -> Result<(), E> {
+        if self.drawn_page != Some(self.page)
+            || (self.page == PAGE_WAVEFORM && self.drawn_viewport != self.viewport)
+        {
+            self.draw_page(display)?;
+        }
+
+        if self.page == PAGE_SUMMARY {
+            let ss_origin = Point::new(display.bounding_box().center().x, SPEED_ORIGIN_Y);
+            self.draw_readouts(display, ss_origin)?;
+            self.draw_warning(display, Point::new(ss_origin.x, 14), cx.animation_time_ms)?;
+        }
+        if self.page == PAGE_RULER && self.drawn_cursor != Some(self.cursor) {
+            self.draw_cursor_readouts(display)?;
+        }
+        Ok(())
     }
 }
 
@@ -63,17 +105,529 @@ fn micros_to_shutter_speed_str(micros: u64) -> String<128> {
     s
 }
 
+impl ResultsScreen<(), ()> {
+    pub fn pages_len() -> usize {
+        PAGE_TITLES.len()
+    }
+
+    pub fn is_zoomable_page(page: usize) -> bool {
+        page == PAGE_WAVEFORM
+    }
+
+    /// Where the button engages the [`RulerCursor`]
+    pub fn is_cursor_page(page: usize) -> bool {
+        page == PAGE_RULER
+    }
+}
+
 impl<DT: AppDrawTarget<E>, E: Debug> ResultsScreen<DT, E> {
-    pub fn new(calibration: CalibrationState, result: MeasurementResult) -> Self {
+    pub fn new(
+        calibration: CalibrationState,
+        result: MeasurementResult,
+        annotation: Annotation,
+    ) -> Self {
         Self {
             calibration,
             result,
+            annotation,
+            page: PAGE_SUMMARY,
+            viewport: ChartViewport::default(),
+            speed_table: SpeedTable::Standard,
+            duration_method: DurationMethod::Integral,
+            tolerances: Tolerances::default(),
+            refire: None,
+            cursor: RulerCursor::default(),
+            ruler: None,
+            drawn_cursor: None,
+            drawn_page: None,
+            drawn_viewport: ChartViewport::default(),
+            drawn_warning: None,
             _phantom: core::marker::PhantomData,
         }
     }
 
-    fn draw_shutter_speed(&mut self, display: &mut DT, origin: Point) {
-        let duration_micros = self.result.integrated_duration_micros.max(1);
+    fn duration_micros(&self) -> u64 {
+        self.result.duration_micros_by(self.duration_method)
+    }
+
+    fn speed_ruler(&self, origin: Point, width: u32) -> SpeedRuler {
+        SpeedRuler::new(
+            origin,
+            width,
+            self.duration_micros() as f32 / 1_000_000.0,
+            self.speed_table,
+            self.annotation.nominal_duration(),
+        )
+    }
+
+    /// On the speed the summary compares against, once the table and method are set
+    pub fn initial_cursor(&self) -> RulerCursor {
+        RulerCursor::new(
+            self.speed_ruler(Point::zero(), 0).best_match_index(),
+            self.speed_table.durations().len(),
+        )
+    }
+
+    fn draw_page(&mut self, display: &mut DT) -> Result<(), E> {
+        display.clear(cfg::COLOR_BACKGROUND)?;
+
+        let width = display.bounding_box().size.width as i32;
+        let height = display.bounding_box().size.height as i32;
+
+        match self.page {
+            PAGE_SUMMARY => {
+                self.draw_speed_title(display, Point::new(width / 2, SPEED_ORIGIN_Y))?;
+                self.speed_ruler(Point::new(0, SUMMARY_RULER_Y), width as u32)
+                    .draw(display)?;
+
+                let mut s = String::<128>::default();
+                match (
+                    self.result.fired_lag_micros,
+                    self.result.release_lag_micros,
+                    self.result.sync_offset_micros,
+                ) {
+                    // Fired by the release output, closed loop
+                    (Some(lag_micros), _, _) => {
+                        s.push_str(" FIRE").unwrap();
+                        s.push_str(&micros_to_string(lag_micros)).unwrap();
+                    }
+                    (None, Some(lag_micros), _) => {
+                        s.push_str(" LAG").unwrap();
+                        s.push_str(&micros_to_string(lag_micros)).unwrap();
+                    }
+                    // The sync contact closed once the shutter was already open
+                    (None, None, Some(offset_micros)) => {
+                        s.push_str(" SYNC +").unwrap();
+                        s.push_str(micros_to_string(offset_micros.unsigned_abs()).trim_start())
+                            .unwrap();
+                    }
+                    (None, None, None) => (),
+                }
+                if !s.is_empty() {
+                    TINY_FONT
+                        .render_aligned(
+                            &s[..],
+                            Point::new(width - 2, 2),
+                            VerticalPosition::Top,
+                            HorizontalAlignment::Right,
+                            FontColor::WithBackground {
+                                bg: cfg::COLOR_MENU_ACTION,
+                                fg: cfg::COLOR_BACKGROUND,
+                            },
+                            display,
+                        )
+                        .map_err(font_error)?;
+                }
+
+                if self.annotation.camera != CameraSlot::None {
+                    let mut s = String::<128>::default();
+                    uwrite!(s, " CAM {} ", self.annotation.camera.label()).unwrap();
+                    TINY_FONT
+                        .render(
+                            &s[..],
+                            Point::new(2, 2),
+                            VerticalPosition::Top,
+                            FontColor::WithBackground {
+                                bg: cfg::COLOR_RESULT_VALUE,
+                                fg: cfg::COLOR_BACKGROUND,
+                            },
+                            display,
+                        )
+                        .map_err(font_error)?;
+                }
+            }
+            PAGE_WAVEFORM => {
+                if let Some(burst) = self.shown_burst() {
+                    let len = burst.samples.len();
+                    // The burst starts right after the opening
+                    draw_chart(
+                        display,
+                        &burst.samples,
+                        self.viewport.window(len, 0),
+                        20,
+                        90,
+                        Some(len),
+                        burst.samples_since_end(),
+                        &[],
+                        self.result.duration_micros,
+                        self.result.integrated_duration_micros,
+                        false,
+                    )?;
+                } else {
+                    let len = self.result.sample_buffer.len();
+                    // Zoom in around the opening edge
+                    let focus = len.saturating_sub(self.result.samples_since_start);
+                    draw_chart(
+                        display,
+                        &self.result.sample_buffer,
+                        self.viewport.window(len, focus),
+                        20,
+                        90,
+                        Some(self.result.samples_since_start),
+                        Some(self.result.samples_since_end),
+                        &self.result.segments(),
+                        self.result.duration_micros,
+                        self.result.integrated_duration_micros,
+                        false,
+                    )?;
+                }
+
+                // Under the exposure marker, how fast the curtains or blades moved
+                if let Some(edges) = self.result.edge_times() {
+                    let mut s = String::<128>::default();
+                    uwrite!(
+                        s,
+                        " RISE{}FALL{}",
+                        &micros_to_string(edges.rise_micros)[..],
+                        &micros_to_string(edges.fall_micros)[..]
+                    )
+                    .unwrap();
+                    TINY_FONT
+                        .render_aligned(
+                            &s[..],
+                            Point::new(width / 2, 128),
+                            VerticalPosition::Top,
+                            HorizontalAlignment::Center,
+                            FontColor::WithBackground {
+                                fg: cfg::COLOR_RESULT_VALUE,
+                                bg: cfg::COLOR_BACKGROUND,
+                            },
+                            display,
+                        )
+                        .map_err(font_error)?;
+                }
+            }
+            PAGE_RULER => {
+                let ruler = self.speed_ruler(Point::new(0, CURSOR_RULER_Y), width as u32);
+                ruler.draw(display)?;
+                self.ruler = Some(ruler);
+            }
+            _ => self.draw_numbers(display, Point::new(5, 18))?,
+        }
+
+        if self.page != PAGE_SUMMARY {
+            let mut title = String::<128>::default();
+            title.push_str(PAGE_TITLES[self.page]).unwrap();
+            if self.page == PAGE_WAVEFORM && self.shown_burst().is_some() {
+                title.push_str("BURST ").unwrap();
+            }
+            if self.page == PAGE_WAVEFORM && self.viewport.is_zoomed() {
+                uwrite!(title, "x{} ", self.viewport.zoom_factor()).unwrap();
+            }
+            TINY_FONT
+                .render_aligned(
+                    &title[..],
+                    Point::new(width / 2, 2),
+                    VerticalPosition::Top,
+                    HorizontalAlignment::Center,
+                    FontColor::WithBackground {
+                        bg: cfg::COLOR_RESULT_VALUE,
+                        fg: cfg::COLOR_BACKGROUND,
+                    },
+                    display,
+                )
+                .map_err(font_error)?;
+        }
+
+        draw_page_indicator(
+            display,
+            Point::new(width / 2, height - 4),
+            PAGE_TITLES.len(),
+            self.page,
+        )?;
+
+        self.drawn_page = Some(self.page);
+        self.drawn_warning = None;
+        self.drawn_cursor = None;
+        self.drawn_viewport = self.viewport;
+        Ok(())
+    }
+
+    /// Takes turns showing each problem with the result in the same banner,
+    /// along with the refire check that can come out either way
+    fn draw_warning(&mut self, display: &mut DT, origin: Point, time_ms: u64) -> Result<(), E> {
+        let mut warnings = Vec::<(String<32>, Rgb565), 3>::new();
+        if self.result.clipped {
+            let mut s = String::<32>::default();
+            s.push_str("CLIPPED - REDUCE LIGHT").unwrap();
+            warnings.push((s, cfg::COLOR_RESULT_BAD)).unwrap();
+        }
+        if let Some(second_pulse) = self.result.second_pulse {
+            let mut s = String::<32>::default();
+            s.push_str("2ND PULSE").unwrap();
+            s.push_str(micros_to_string(second_pulse.duration_micros).trim_end())
+                .unwrap();
+            warnings.push((s, cfg::COLOR_RESULT_BAD)).unwrap();
+        }
+        if let Some(refire) = self.refire {
+            let mut s = String::<32>::default();
+            let color = match refire {
+                RefireCheck::Locked => {
+                    s.push_str("DOUBLE EXP. LOCKED").unwrap();
+                    cfg::COLOR_RESULT_GOOD
+                }
+                RefireCheck::Refired { after_millis } => {
+                    write!(
+                        s,
+                        "REFIRED AFTER {}.{}S",
+                        after_millis / 1000,
+                        after_millis / 100 % 10
+                    )
+                    .unwrap();
+                    cfg::COLOR_RESULT_BAD
+                }
+            };
+            warnings.push((s, color)).unwrap();
+        }
+        if warnings.is_empty() {
+            return Ok(());
+        }
+
+        let index = (time_ms / WARNING_CYCLE_MS) as usize % warnings.len();
+        if self.drawn_warning == Some(index) {
+            return Ok(());
+        }
+        self.drawn_warning = Some(index);
+
+        // Padded to a fixed width so that a shorter one covers the previous
+        let (warning, color) = &warnings[index];
+        let mut s = String::<128>::default();
+        write!(s, "{:^width$}", &warning[..], width = WARNING_WIDTH).unwrap();
+        TINY_FONT
+            .render_aligned(
+                &s[..],
+                origin,
+                VerticalPosition::Top,
+                HorizontalAlignment::Center,
+                FontColor::WithBackground {
+                    bg: *color,
+                    fg: cfg::COLOR_BACKGROUND,
+                },
+                display,
+            )
+            .map_err(font_error)?;
+        Ok(())
+    }
+
+    fn draw_numbers(&mut self, display: &mut DT, origin: Point) -> Result<(), E> {
+        let raw_micros = self.result.duration_micros.max(1);
+        let integrated_micros = self.result.integrated_duration_micros;
+
+        let mut efficiency = String::<128>::default();
+        uwrite!(efficiency, "{}%", integrated_micros * 100 / raw_micros).unwrap();
+
+        let mut sample_rate = String::<128>::default();
+        uwrite!(sample_rate, "1/{}", self.result.effective_divisor()).unwrap();
+        if let Some(burst) = self.shown_burst() {
+            uwrite!(sample_rate, " BURST {}NS", burst.sample_interval_nanos).unwrap();
+        }
+
+        let mut samples = String::<128>::default();
+        uwrite!(
+            samples,
+            "{}",
+            self.result
+                .samples_since_start
+                .saturating_sub(self.result.samples_since_end)
+        )
+        .unwrap();
+
+        let rows = [
+            (" EXPOSURE ", micros_to_string(integrated_micros)),
+            (" START-END ", micros_to_string(raw_micros)),
+            (
+                " HALF PEAK ",
+                micros_to_string(self.result.half_peak_duration_micros),
+            ),
+            (" EFFICIENCY ", efficiency),
+            (" SAMPLE RATE ", sample_rate),
+            (" SAMPLES ", samples),
+        ];
+
+        // Next to the exposure label, one stored sample either way
+        if self.result.sample_interval_nanos > 0 {
+            let mut uncertainty = String::<128>::default();
+            uncertainty.push_str("+-").unwrap();
+            uncertainty
+                .push_str(micros_to_string(self.result.uncertainty_micros()).trim_start())
+                .unwrap();
+            TINY_FONT
+                .render_aligned(
+                    &uncertainty[..],
+                    Point::new(
+                        display.bounding_box().size.width as i32 - origin.x,
+                        origin.y,
+                    ),
+                    VerticalPosition::Top,
+                    HorizontalAlignment::Right,
+                    FontColor::Transparent(cfg::COLOR_RESULT_VALUE),
+                    display,
+                )
+                .map_err(font_error)?;
+        }
+
+        for (index, (name, value)) in rows.iter().enumerate() {
+            let row_origin = origin + Point::new(0, index as i32 * 22);
+            TINY_FONT
+                .render(
+                    *name,
+                    row_origin,
+                    VerticalPosition::Top,
+                    FontColor::WithBackground {
+                        bg: cfg::COLOR_RESULT_VALUE,
+                        fg: cfg::COLOR_BACKGROUND,
+                    },
+                    display,
+                )
+                .map_err(font_error)?;
+            SMALL_FONT
+                .render(
+                    &value[..],
+                    row_origin + Point::new(1, 10),
+                    VerticalPosition::Top,
+                    FontColor::WithBackground {
+                        fg: cfg::COLOR_RESULT_VALUE,
+                        bg: cfg::COLOR_BACKGROUND,
+                    },
+                    display,
+                )
+                .map_err(font_error)?;
+        }
+        Ok(())
+    }
+
+    /// Both in one transfer each, so the digits never show half repainted
+    fn draw_readouts(&self, display: &mut DT, origin: Point) -> Result<(), E> {
+        let left = origin.x - READOUT_WIDTH as i32 / 2;
+        {
+            let mut region = OffscreenRegion::<{ READOUT_WIDTH * SPEED_HEIGHT }>::new(
+                Rectangle::new(
+                    Point::new(left, origin.y + 5),
+                    Size::new(READOUT_WIDTH as u32, SPEED_HEIGHT as u32),
+                ),
+                cfg::COLOR_BACKGROUND,
+            );
+            self.draw_shutter_speed(&mut region.target(), origin)
+                .unwrap();
+            region.blit(display)?;
+        }
+
+        self.draw_deviation_readout(display, origin + Point::new(0, 60), self.best_match())
+    }
+
+    fn draw_deviation_readout(
+        &self,
+        display: &mut DT,
+        origin: Point,
+        nominal_duration: f32,
+    ) -> Result<(), E> {
+        let mut region = OffscreenRegion::<{ READOUT_WIDTH * DEVIATION_HEIGHT }>::new(
+            Rectangle::new(
+                Point::new(origin.x - READOUT_WIDTH as i32 / 2, origin.y - 17),
+                Size::new(READOUT_WIDTH as u32, DEVIATION_HEIGHT as u32),
+            ),
+            cfg::COLOR_BACKGROUND,
+        );
+        self.draw_deviation(&mut region.target(), origin, nominal_duration)
+            .unwrap();
+        region.blit(display)
+    }
+
+    /// Compared with the speed under the cursor, or the summary's one while it's parked
+    fn draw_cursor_readouts(&mut self, display: &mut DT) -> Result<(), E> {
+        let Some(ruler) = self.ruler else {
+            return Ok(());
+        };
+        let center_x = display.bounding_box().center().x;
+        let nominal_duration = match self.speed_table.durations().get(self.cursor.index) {
+            Some(&duration) if self.cursor.active => duration,
+            _ => ruler.best_match(),
+        };
+        let nominal = ShutterSpeed::from_secs(nominal_duration);
+
+        let mut label = String::<32>::default();
+        write!(label, "VS {}", nominal).unwrap();
+        let mut s = String::<32>::default();
+        write!(s, "{:^width$}", &label[..], width = CURSOR_LABEL_WIDTH).unwrap();
+        SMALL_FONT
+            .render_aligned(
+                &s[..],
+                Point::new(center_x, 22),
+                VerticalPosition::Top,
+                HorizontalAlignment::Center,
+                FontColor::WithBackground {
+                    fg: if self.cursor.active {
+                        cfg::COLOR_NEAREST_SPEED
+                    } else {
+                        cfg::COLOR_RESULT_VALUE
+                    },
+                    bg: cfg::COLOR_BACKGROUND,
+                },
+                display,
+            )
+            .map_err(font_error)?;
+
+        self.draw_deviation_readout(display, Point::new(center_x, 65), nominal_duration)?;
+
+        // In tenths, the same sign as the percentage
+        label.clear();
+        if let Some(stops) = nominal.deviation_stops(self.duration_micros()) {
+            let tenths = (stops * 10.0).round() as i32;
+            uwrite!(
+                label,
+                "{}{}.{} STOPS",
+                if tenths < 0 { "-" } else { "+" },
+                tenths.unsigned_abs() / 10,
+                tenths.unsigned_abs() % 10
+            )
+            .unwrap();
+        }
+        s.clear();
+        write!(s, "{:^width$}", &label[..], width = CURSOR_LABEL_WIDTH).unwrap();
+        TINY_FONT
+            .render_aligned(
+                &s[..],
+                Point::new(center_x, 75),
+                VerticalPosition::Top,
+                HorizontalAlignment::Center,
+                FontColor::WithBackground {
+                    fg: cfg::COLOR_RESULT_VALUE,
+                    bg: cfg::COLOR_BACKGROUND,
+                },
+                display,
+            )
+            .map_err(font_error)?;
+
+        ruler.draw_cursor(display, self.cursor.active.then_some(nominal_duration))?;
+        self.drawn_cursor = Some(self.cursor);
+        Ok(())
+    }
+
+    /// Only one that saw the pulse close, the samples tell a longer one better
+    fn shown_burst(&self) -> Option<&BurstTrace> {
+        self.result
+            .burst
+            .as_ref()
+            .filter(|burst| burst.end_index.is_some())
+    }
+
+    /// Compare against the speed set on the camera if the user told us
+    fn best_match(&self) -> f32 {
+        self.annotation.nominal_duration().unwrap_or_else(|| {
+            self.speed_table
+                .closest(self.duration_micros() as f32 / 1_000_000.0)
+        })
+    }
+
+    fn draw_shutter_speed<D: DrawTarget<Color = Rgb565>>(
+        &self,
+        display: &mut D,
+        origin: Point,
+    ) -> Result<(), D::Error>
+    where
+        D::Error: Debug,
+    {
+        let duration_micros = self.duration_micros().max(1);
 
         let is_inverse = duration_micros < 500_000;
 
@@ -99,12 +653,9 @@ impl<DT: AppDrawTarget<E>, E: Debug> ResultsScreen<DT, E> {
             large_style,
             embedded_graphics::text::Alignment::Center,
         )
-        .draw(display)
-        .unwrap();
+        .draw(display)?;
 
-        Text::new("5", end_point + Point::new(5, 0), small_style)
-            .draw(display)
-            .unwrap();
+        Text::new("5", end_point + Point::new(5, 0), small_style).draw(display)?;
 
         if is_inverse {
             let one_ends = Text::new(
@@ -112,23 +663,30 @@ impl<DT: AppDrawTarget<E>, E: Debug> ResultsScreen<DT, E> {
                 number_origin * 2 - end_point + Point::new(-15, -15),
                 small_style,
             )
-            .draw(display)
-            .unwrap();
+            .draw(display)?;
 
-            Line::new(one_ends, one_ends + Point::new(5, -12))
-                .draw_styled(
-                    &PrimitiveStyleBuilder::new()
-                        .stroke_width(1)
-                        .stroke_color(cfg::COLOR_RESULT_VALUE)
-                        .build(),
-                    display,
-                )
-                .unwrap();
+            Line::new(one_ends, one_ends + Point::new(5, -12)).draw_styled(
+                &PrimitiveStyleBuilder::new()
+                    .stroke_width(1)
+                    .stroke_color(cfg::COLOR_RESULT_VALUE)
+                    .build(),
+                display,
+            )?;
         }
+        Ok(())
+    }
 
+    /// Stays put, only the digits under it are redrawn
+    fn draw_speed_title(&self, display: &mut DT, origin: Point) -> Result<(), E> {
+        let mut title = String::<32>::default();
+        title.push_str(" SHUTTER SPEED ").unwrap();
+        // The integral is what everything else uses, only call out the others
+        if self.duration_method != DurationMethod::Integral {
+            write!(title, "{} ", self.duration_method.label()).unwrap();
+        }
         TINY_FONT
             .render_aligned(
-                " SHUTTER SPEED ",
+                &title[..],
                 origin + Point::new(0, -6),
                 VerticalPosition::Top,
                 u8g2_fonts::types::HorizontalAlignment::Center,
@@ -138,24 +696,36 @@ impl<DT: AppDrawTarget<E>, E: Debug> ResultsScreen<DT, E> {
                 },
                 display,
             )
-            .unwrap();
+            .map_err(font_error)?;
+        Ok(())
     }
 
-    fn draw_deviation(&mut self, display: &mut DT, origin: Point) {
-        let best_match_duration =
-            get_closest_shutter_speed(self.result.integrated_duration_micros as f32 / 1_000_000.0);
-
-        let percent_offset = ((self.result.integrated_duration_micros as f32 / 1_000_000.0
-            - best_match_duration)
+    fn draw_deviation<D: DrawTarget<Color = Rgb565>>(
+        &self,
+        display: &mut D,
+        origin: Point,
+        best_match_duration: f32,
+    ) -> Result<(), D::Error>
+    where
+        D::Error: Debug,
+    {
+        let percent_offset = ((self.duration_micros() as f32 / 1_000_000.0 - best_match_duration)
             / best_match_duration
             * 100.0) as i16;
 
-        let (color, color_inactive) = if percent_offset.abs() < 15 {
+        let nominal_micros = (best_match_duration * 1_000_000.0 + 0.5) as u32;
+        let band = self.tolerances.percent_for(nominal_micros) as i16;
+        let verdict = self
+            .tolerances
+            .verdict(nominal_micros, self.duration_micros());
+
+        // Fair once past half of the band
+        let (color, color_inactive) = if verdict == Verdict::Fail {
+            (cfg::COLOR_RESULT_BAD, cfg::COLOR_RESULT_BAD_INACTIVE)
+        } else if percent_offset.abs() * 2 < band {
             (cfg::COLOR_RESULT_GOOD, cfg::COLOR_RESULT_GOOD_INACTIVE)
-        } else if percent_offset.abs() < 30 {
-            (cfg::COLOR_RESULT_FAIR, cfg::COLOR_RESULT_FAIR_INACTIVE)
         } else {
-            (cfg::COLOR_RESULT_BAD, cfg::COLOR_RESULT_BAD_INACTIVE)
+            (cfg::COLOR_RESULT_FAIR, cfg::COLOR_RESULT_FAIR_INACTIVE)
         };
 
         let small_style = SevenSegmentStyleBuilder::new()
@@ -175,8 +745,7 @@ impl<DT: AppDrawTarget<E>, E: Debug> ResultsScreen<DT, E> {
             small_style,
             embedded_graphics::text::Alignment::Center,
         )
-        .draw(display)
-        .unwrap();
+        .draw(display)?;
 
         ALT_FONT
             .render_aligned(
@@ -190,13 +759,13 @@ impl<DT: AppDrawTarget<E>, E: Debug> ResultsScreen<DT, E> {
                 },
                 display,
             )
-            .unwrap();
+            .map_err(font_error)?;
 
         for (sign, label, offset) in [
             (-1, " FAST ", Point::new(3, 8)),
             (1, " SLOW ", Point::new(3, -2)),
         ] {
-            let active = percent_offset.abs() >= 15 && percent_offset.signum() == sign;
+            let active = percent_offset.abs() * 2 >= band && percent_offset.signum() == sign;
             TINY_FONT
                 .render_aligned(
                     label,
@@ -209,7 +778,35 @@ impl<DT: AppDrawTarget<E>, E: Debug> ResultsScreen<DT, E> {
                     },
                     display,
                 )
-                .unwrap();
+                .map_err(font_error)?;
         }
+
+        s.clear();
+        uwrite!(s, " {} ", verdict.label()).unwrap();
+        TINY_FONT
+            .render(
+                &s[..],
+                end_point + Point::new(14, -8),
+                VerticalPosition::Baseline,
+                FontColor::WithBackground {
+                    bg: color,
+                    fg: cfg::COLOR_BACKGROUND,
+                },
+                display,
+            )
+            .map_err(font_error)?;
+
+        s.clear();
+        uwrite!(s, "+/-{}%", band).unwrap();
+        TINY_FONT
+            .render(
+                &s[..],
+                end_point + Point::new(15, 2),
+                VerticalPosition::Baseline,
+                FontColor::Transparent(color),
+                display,
+            )
+            .map_err(font_error)?;
+        Ok(())
     }
 }
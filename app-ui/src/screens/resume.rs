@@ -0,0 +1,78 @@
+use core::fmt::Debug;
+
+use embedded_graphics::geometry::Point;
+use embedded_graphics::pixelcolor::{Rgb565, RgbColor, WebColors};
+use heapless::String;
+use u8g2_fonts::types::{FontColor, HorizontalAlignment, VerticalPosition};
+
+use super::{DrawFrameContext, Screen};
+use crate::fonts::{SMALL_FONT, TINY_FONT};
+use crate::util::font_error;
+use crate::{config, draw_badge, AppDrawTarget};
+
+const COLOR: Rgb565 = Rgb565::CSS_ORANGE;
+
+/// Offered on boot when the previous session was cut short by a reset
+pub struct ResumeScreen<DT, E> {
+    description: String<24>,
+    _phantom: core::marker::PhantomData<(DT, E)>,
+}
+
+impl<DT: AppDrawTarget<E>, E: Debug> ResumeScreen<DT, E> {
+    pub fn new(description: &str) -> Self {
+        let mut s = String::new();
+        let _ = s.push_str(description);
+        Self {
+            description: s,
+            _phantom: core::marker::PhantomData,
+        }
+    }
+}
+
+impl<DT: AppDrawTarget<E>, E: Debug> Screen<DT, E> for ResumeScreen<DT, E> {
+    async fn draw_init(&mut self, display: &mut DT) -> Result<(), E> {
+        display.clear(config::COLOR_BACKGROUND)?;
+        let center = display.bounding_box().center();
+
+        draw_badge(
+            display,
+            center - Point::new(0, 45),
+            " RESUME? ",
+            Rgb565::BLACK,
+            COLOR,
+        )
+        .await?;
+
+        SMALL_FONT
+            .render_aligned(
+                &self.description[..],
+                center - Point::new(0, 10),
+                VerticalPosition::Center,
+                HorizontalAlignment::Center,
+                FontColor::Transparent(config::COLOR_RESULT_VALUE),
+                display,
+            )
+            .map_err(font_error)?;
+
+        for (offset, hint) in [(25, " PRESS TO RESUME "), (40, " TURN TO DISCARD ")] {
+            TINY_FONT
+                .render_aligned(
+                    hint,
+                    center + Point::new(0, offset),
+                    VerticalPosition::Top,
+                    HorizontalAlignment::Center,
+                    FontColor::WithBackground {
+                        fg: COLOR,
+                        bg: config::COLOR_BACKGROUND,
+                    },
+                    display,
+                )
+                .map_err(font_error)?;
+        }
+        Ok(())
+    }
+
+    async fn draw_frame(&mut self, _display: &mut DT, _cx: DrawFrameContext) -> Result<(), E> {
+        Ok(())
+    }
+}
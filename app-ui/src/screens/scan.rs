@@ -0,0 +1,262 @@
+use core::fmt::{Debug, Write};
+
+use app_measurements::{ScanResult, ShutterSpeed, SCAN_CHANNELS};
+use embedded_graphics::geometry::{Point, Size};
+use embedded_graphics::pixelcolor::{Rgb565, WebColors};
+use embedded_graphics::primitives::Rectangle;
+use heapless::String;
+use u8g2_fonts::types::{FontColor, HorizontalAlignment, VerticalPosition};
+
+use super::{DrawFrameContext, Screen};
+use crate::fonts::TINY_FONT;
+use crate::util::font_error;
+use crate::{config as cfg, draw_badge, AppDrawTarget};
+
+const COLOR: Rgb565 = Rgb565::CSS_DEEP_SKY_BLUE;
+const MARGIN: i32 = 4;
+const CONTENT_Y: i32 = 20;
+const STATUS_Y: i32 = 24;
+const BARS_Y: i32 = 40;
+const BAR_PITCH: i32 = 12;
+const BAR_HEIGHT: u32 = 8;
+const TIMELINE_Y: i32 = 26;
+const TIMELINE_PITCH: i32 = 8;
+const TIMELINE_HEIGHT: u32 = 5;
+const ROWS_Y: i32 = 100;
+const ROW_HEIGHT: i32 = 14;
+
+type DrawnState = ([u16; SCAN_CHANNELS], bool, bool, bool);
+
+/// Rolling shutter accessory: live photodiode levels, then the opening
+/// interval of each channel on a shared time axis, channel 0 at the top
+pub struct ScanScreen<DT, E> {
+    pub levels: [u16; SCAN_CHANNELS],
+    pub calibrating: bool,
+    pub scanning: bool,
+    pub result: Option<ScanResult<SCAN_CHANNELS>>,
+    max_value: u16,
+    drawn: Option<DrawnState>,
+    _phantom: core::marker::PhantomData<(DT, E)>,
+}
+
+impl<DT: AppDrawTarget<E>, E: Debug> ScanScreen<DT, E> {
+    pub fn new(max_value: u16) -> Self {
+        Self {
+            levels: [0; SCAN_CHANNELS],
+            calibrating: true,
+            scanning: false,
+            result: None,
+            max_value,
+            drawn: None,
+            _phantom: core::marker::PhantomData,
+        }
+    }
+
+    fn clear_content(display: &mut DT) -> Result<(), E> {
+        let size = display.bounding_box().size;
+        display.fill_solid(
+            &Rectangle::new(
+                Point::new(0, CONTENT_Y),
+                Size::new(size.width, size.height - CONTENT_Y as u32),
+            ),
+            cfg::COLOR_BACKGROUND,
+        )?;
+        Ok(())
+    }
+
+    fn draw_levels(&self, display: &mut DT) -> Result<(), E> {
+        let status = if self.calibrating {
+            "CALIBRATING"
+        } else if self.scanning {
+            "SCANNING"
+        } else {
+            "READY"
+        };
+        let mut s = String::<16>::default();
+        write!(s, "{:^13}", status).unwrap();
+        TINY_FONT
+            .render_aligned(
+                &s[..],
+                Point::new(display.bounding_box().center().x, STATUS_Y),
+                VerticalPosition::Top,
+                HorizontalAlignment::Center,
+                FontColor::WithBackground {
+                    fg: COLOR,
+                    bg: cfg::COLOR_BACKGROUND,
+                },
+                display,
+            )
+            .map_err(font_error)?;
+
+        let width = display.bounding_box().size.width - MARGIN as u32 * 2;
+        for (index, &level) in self.levels.iter().enumerate() {
+            let y = BARS_Y + index as i32 * BAR_PITCH;
+            let filled =
+                (level.min(self.max_value) as u32 * width / self.max_value.max(1) as u32).max(1);
+            display.fill_solid(
+                &Rectangle::new(Point::new(MARGIN, y), Size::new(filled, BAR_HEIGHT)),
+                cfg::COLOR_LEVEL,
+            )?;
+            display.fill_solid(
+                &Rectangle::new(
+                    Point::new(MARGIN + filled as i32, y),
+                    Size::new(width - filled, BAR_HEIGHT),
+                ),
+                cfg::COLOR_RESULT_VALUE_INACTIVE,
+            )?;
+        }
+        Ok(())
+    }
+
+    fn draw_result(&self, display: &mut DT, result: &ScanResult<SCAN_CHANNELS>) -> Result<(), E> {
+        let width = display.bounding_box().size.width - MARGIN as u32 * 2;
+        let start = result
+            .channels
+            .iter()
+            .filter_map(|c| c.opened_at_micros)
+            .min()
+            .unwrap_or(0);
+        let end = result
+            .channels
+            .iter()
+            .filter_map(|c| c.closed_at_micros.or(c.opened_at_micros))
+            .max()
+            .unwrap_or(start);
+        let span = (end - start).max(1);
+        let scale = |micros: u64| MARGIN + ((micros - start) * width as u64 / span) as i32;
+
+        for (index, channel) in result.channels.iter().enumerate() {
+            let y = TIMELINE_Y + index as i32 * TIMELINE_PITCH;
+            display.fill_solid(
+                &Rectangle::new(Point::new(MARGIN, y + 2), Size::new(width, 1)),
+                cfg::COLOR_RESULT_VALUE_INACTIVE,
+            )?;
+            let Some(opened_at) = channel.opened_at_micros else {
+                continue;
+            };
+            let closed_at = channel.closed_at_micros.unwrap_or(end);
+            display.fill_solid(
+                &Rectangle::with_corners(
+                    Point::new(scale(opened_at), y),
+                    Point::new(scale(closed_at), y + TIMELINE_HEIGHT as i32 - 1),
+                ),
+                match channel.closed_at_micros {
+                    Some(_) => cfg::COLOR_LEVEL,
+                    None => cfg::COLOR_RESULT_FAIR,
+                },
+            )?;
+        }
+
+        let mut s = String::<24>::default();
+        match result.scan_time_micros() {
+            Some(micros) => write!(
+                s,
+                "{}.{} ms {}",
+                micros / 1000,
+                micros / 100 % 10,
+                if result.is_forward() { "DOWN" } else { "UP" }
+            )
+            .unwrap(),
+            None => s.push_str("--").unwrap(),
+        }
+        Self::draw_row(display, 0, " SCAN ", &s[..])?;
+
+        s.clear();
+        match result.exposure_micros() {
+            Some(micros) => s
+                .push_str(&ShutterSpeed::from_micros(micros).label()[..])
+                .unwrap(),
+            None => s.push_str("--").unwrap(),
+        }
+        Self::draw_row(display, 1, " EXPOSURE ", &s[..])?;
+
+        s.clear();
+        match result.slit_width_um() {
+            Some(um) => write!(s, "{}.{} mm", um / 1000, um / 100 % 10).unwrap(),
+            None => s.push_str("--").unwrap(),
+        }
+        Self::draw_row(display, 2, " SLIT ", &s[..])?;
+
+        s.clear();
+        write!(s, "+/- {} us", result.uncertainty_micros()).unwrap();
+        Self::draw_row(display, 3, " ERROR ", &s[..])?;
+        Ok(())
+    }
+
+    fn draw_row(display: &mut DT, index: i32, name: &str, value: &str) -> Result<(), E> {
+        let y = ROWS_Y + index * ROW_HEIGHT;
+        TINY_FONT
+            .render(
+                name,
+                Point::new(MARGIN, y),
+                VerticalPosition::Top,
+                FontColor::WithBackground {
+                    fg: cfg::COLOR_BACKGROUND,
+                    bg: COLOR,
+                },
+                display,
+            )
+            .map_err(font_error)?;
+        TINY_FONT
+            .render_aligned(
+                value,
+                Point::new(display.bounding_box().size.width as i32 - MARGIN, y),
+                VerticalPosition::Top,
+                HorizontalAlignment::Right,
+                FontColor::WithBackground {
+                    fg: cfg::COLOR_RESULT_VALUE,
+                    bg: cfg::COLOR_BACKGROUND,
+                },
+                display,
+            )
+            .map_err(font_error)?;
+        Ok(())
+    }
+}
+
+impl<DT: AppDrawTarget<E>, E: Debug> Screen<DT, E> for ScanScreen<DT, E> {
+    async fn draw_init(&mut self, display: &mut DT) -> Result<(), E> {
+        display.clear(cfg::COLOR_BACKGROUND)?;
+        self.drawn = None;
+
+        draw_badge(
+            display,
+            Point::new(display.bounding_box().center().x, 8),
+            " ROLLING ",
+            cfg::COLOR_BACKGROUND,
+            COLOR,
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn draw_frame(&mut self, display: &mut DT, _cx: DrawFrameContext) -> Result<(), E> {
+        let state = (
+            self.levels,
+            self.calibrating,
+            self.scanning,
+            self.result.is_some(),
+        );
+        if self.drawn == Some(state) {
+            return Ok(());
+        }
+        let view_changed = self.drawn.map(|(.., has_result)| has_result) != Some(state.3);
+        self.drawn = Some(state);
+
+        match self.result {
+            // The result doesn't change once taken, only draw it once
+            Some(ref result) if view_changed => {
+                Self::clear_content(display)?;
+                self.draw_result(display, result)?;
+            }
+            Some(_) => (),
+            None => {
+                if view_changed {
+                    Self::clear_content(display)?;
+                }
+                self.draw_levels(display)?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,137 @@
+use core::fmt::Debug;
+
+use app_measurements::{LoopbackCheck, Verdict};
+use embedded_graphics::geometry::Point;
+use embedded_graphics::pixelcolor::{Rgb565, RgbColor};
+use embedded_graphics::prelude::Dimensions;
+use heapless::String;
+use u8g2_fonts::types::{FontColor, HorizontalAlignment, VerticalPosition};
+use ufmt::uwrite;
+
+use super::{DrawFrameContext, Screen};
+use crate::fonts::TINY_FONT;
+use crate::util::font_error;
+use crate::{config as cfg, draw_badge, AppDrawTarget};
+
+/// How far the emitter loopback pulse came out from what was commanded
+pub struct SelfCheckScreen<DT, E> {
+    pub check: LoopbackCheck,
+    /// The measured time passes within this much of the commanded one
+    pub tolerance_percent: u8,
+    _phantom: core::marker::PhantomData<(DT, E)>,
+}
+
+impl<DT: AppDrawTarget<E>, E: Debug> SelfCheckScreen<DT, E> {
+    pub fn new(check: LoopbackCheck, tolerance_percent: u8) -> Self {
+        Self {
+            check,
+            tolerance_percent,
+            _phantom: core::marker::PhantomData,
+        }
+    }
+}
+
+impl<DT: AppDrawTarget<E>, E: Debug> Screen<DT, E> for SelfCheckScreen<DT, E> {
+    async fn draw_init(&mut self, display: &mut DT) -> Result<(), E> {
+        display.clear(cfg::COLOR_BACKGROUND)?;
+        let center_x = display.bounding_box().center().x;
+
+        let verdict = self.check.verdict(self.tolerance_percent);
+        let color = match verdict {
+            Verdict::Pass => cfg::COLOR_RESULT_GOOD,
+            Verdict::Fail => cfg::COLOR_RESULT_BAD,
+        };
+
+        let mut s = String::<32>::default();
+        uwrite!(s, " SELF CHECK {} ", verdict.label()).unwrap();
+        draw_badge(
+            display,
+            Point::new(center_x, 15),
+            &s[..],
+            Rgb565::BLACK,
+            color,
+        )
+        .await?;
+
+        let error = self.check.error_micros();
+        let error_sign = if error < 0 { "-" } else { "+" };
+        for (i, (label, sign, micros)) in [
+            (" COMMANDED ", "", self.check.commanded_micros),
+            (" MEASURED ", "", self.check.measured_micros),
+            (" ERROR ", error_sign, error.unsigned_abs()),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            s.clear();
+            uwrite!(s, "{}{}{} US ", label, sign, micros).unwrap();
+            draw_line(
+                display,
+                Point::new(center_x, 45 + i as i32 * 15),
+                &s[..],
+                cfg::COLOR_RESULT_VALUE,
+            )?;
+        }
+
+        s.clear();
+        uwrite!(
+            s,
+            " +/-{} US, {}% ALLOWED ",
+            self.check.uncertainty_micros,
+            self.tolerance_percent
+        )
+        .unwrap();
+        draw_line(
+            display,
+            Point::new(center_x, 90),
+            &s[..],
+            cfg::COLOR_RESULT_VALUE_INACTIVE,
+        )?;
+
+        s.clear();
+        match self.check.latency_micros {
+            Some(latency) => uwrite!(s, " LATENCY {} US ", latency).unwrap(),
+            None => uwrite!(s, " LATENCY UNKNOWN ").unwrap(),
+        }
+        draw_line(
+            display,
+            Point::new(center_x, 115),
+            &s[..],
+            cfg::COLOR_RESULT_VALUE,
+        )?;
+
+        draw_line(
+            display,
+            Point::new(center_x, display.bounding_box().size.height as i32 - 15),
+            " PRESS TO GO BACK ",
+            cfg::COLOR_RESULT_VALUE,
+        )?;
+        Ok(())
+    }
+
+    async fn draw_frame(&mut self, _display: &mut DT, _cx: DrawFrameContext) -> Result<(), E> {
+        Ok(())
+    }
+}
+
+fn draw_line<D: AppDrawTarget<E>, E: Debug>(
+    display: &mut D,
+    origin: Point,
+    text: &str,
+    color: Rgb565,
+) -> Result<(), E> {
+    TINY_FONT
+        .render_aligned(
+            text,
+            origin,
+            VerticalPosition::Top,
+            HorizontalAlignment::Center,
+            FontColor::WithBackground {
+                fg: color,
+                bg: cfg::COLOR_BACKGROUND,
+            },
+            display,
+        )
+        .map_err(font_error)?;
+    Ok(())
+}
@@ -0,0 +1,77 @@
+use core::fmt::Debug;
+
+use app_measurements::SensorFault;
+use embedded_graphics::geometry::Point;
+use embedded_graphics::pixelcolor::{Rgb565, RgbColor};
+use embedded_graphics::prelude::Dimensions;
+use heapless::String;
+use u8g2_fonts::types::{FontColor, HorizontalAlignment, VerticalPosition};
+use ufmt::uwrite;
+
+use super::{DrawFrameContext, Screen};
+use crate::fonts::TINY_FONT;
+use crate::util::font_error;
+use crate::{draw_badge, AppDrawTarget};
+
+/// The accessory is plugged in but its sensor isn't answering, unlike [`super::NoAccessoryScreen`]
+pub struct SensorFaultScreen<DT, E> {
+    pub fault: SensorFault,
+    _phantom: core::marker::PhantomData<(DT, E)>,
+}
+
+impl<DT: AppDrawTarget<E>, E: Debug> SensorFaultScreen<DT, E> {
+    pub fn new(fault: SensorFault) -> Self {
+        Self {
+            fault,
+            _phantom: core::marker::PhantomData,
+        }
+    }
+}
+
+impl<DT: AppDrawTarget<E>, E: Debug> Screen<DT, E> for SensorFaultScreen<DT, E> {
+    async fn draw_init(&mut self, display: &mut DT) -> Result<(), E> {
+        display.clear(Rgb565::BLACK)?;
+        let center = display.bounding_box().center();
+
+        draw_badge(
+            display,
+            center - Point::new(0, 40),
+            " SENSOR FAULT ",
+            Rgb565::BLACK,
+            Rgb565::RED,
+        )
+        .await?;
+
+        let mut s = String::<32>::default();
+        let hint = match self.fault {
+            SensorFault::Stuck { value } => {
+                uwrite!(s, " READING STUCK AT {} ", value).unwrap();
+                " CHECK THE SENSOR CABLE "
+            }
+            SensorFault::Leaking { covered } => {
+                uwrite!(s, " READS {} COVERED ", covered).unwrap();
+                " COVER IT WHEN ASKED "
+            }
+        };
+        for (line, y) in [(&s[..], -15), (hint, 0), (" PRESS TO TRY AGAIN ", 30)] {
+            TINY_FONT
+                .render_aligned(
+                    line,
+                    center + Point::new(0, y),
+                    VerticalPosition::Top,
+                    HorizontalAlignment::Center,
+                    FontColor::WithBackground {
+                        fg: Rgb565::RED,
+                        bg: Rgb565::BLACK,
+                    },
+                    display,
+                )
+                .map_err(font_error)?;
+        }
+        Ok(())
+    }
+
+    async fn draw_frame(&mut self, _display: &mut DT, _cx: DrawFrameContext) -> Result<(), E> {
+        Ok(())
+    }
+}
@@ -0,0 +1,236 @@
+use core::fmt::{Debug, Write};
+
+use app_measurements::util::SpeedTable;
+use app_measurements::{ShutterSpeed, TestSequence};
+use embedded_graphics::geometry::Point;
+use embedded_graphics::pixelcolor::{Rgb565, RgbColor, WebColors};
+use heapless::String;
+use u8g2_fonts::types::{FontColor, HorizontalAlignment, VerticalPosition};
+
+use super::{DrawFrameContext, Screen};
+use crate::fonts::{SMALL_FONT, TINY_FONT};
+use crate::ruler::SpeedRuler;
+use crate::util::font_error;
+use crate::{config as cfg, draw_badge, AppDrawTarget};
+
+const ROW_HEIGHT: i32 = 12;
+
+pub struct SequenceScreen<DT, E> {
+    pub sequence: TestSequence,
+    /// Position and shots of the step
+    drawn_progress: Option<(usize, usize)>,
+    _phantom: core::marker::PhantomData<(DT, E)>,
+}
+
+impl<DT: AppDrawTarget<E>, E: Debug> Screen<DT, E> for SequenceScreen<DT, E> {
+    async fn draw_init(&mut self, _display: &mut DT) -> Result<(), E> {
+        self.drawn_progress = None;
+        Ok(())
+    }
+
+    async fn draw_frame(&mut self, display: &mut DT, _cx: DrawFrameContext) -> Result<(), E> {
+        let progress = (self.sequence.position(), self.sequence.shots().len());
+        if self.drawn_progress == Some(progress) {
+            return Ok(());
+        }
+        self.drawn_progress = Some(progress);
+
+        display.clear(cfg::COLOR_BACKGROUND)?;
+        if self.sequence.is_done() {
+            self.draw_summary(display)?;
+        } else {
+            self.draw_prompt(display).await?;
+        }
+        Ok(())
+    }
+}
+
+impl<DT: AppDrawTarget<E>, E: Debug> SequenceScreen<DT, E> {
+    async fn draw_prompt(&mut self, display: &mut DT) -> Result<(), E> {
+        let Some(step) = self.sequence.current() else {
+            return Ok(());
+        };
+        let center_x = display.bounding_box().center().x;
+        let height = display.bounding_box().size.height as i32;
+
+        let mut s = String::<128>::default();
+        write!(
+            s,
+            " STEP {}/{} ",
+            self.sequence.position() + 1,
+            self.sequence.steps().len()
+        )
+        .unwrap();
+        draw_badge(
+            display,
+            Point::new(center_x, 20),
+            &s[..],
+            Rgb565::BLACK,
+            Rgb565::CSS_TURQUOISE,
+        )
+        .await?;
+
+        TINY_FONT
+            .render_aligned(
+                "SET THE CAMERA TO",
+                Point::new(center_x, 40),
+                VerticalPosition::Top,
+                HorizontalAlignment::Center,
+                FontColor::Transparent(cfg::COLOR_RESULT_VALUE),
+                display,
+            )
+            .map_err(font_error)?;
+
+        let duration = step.nominal().secs();
+        SMALL_FONT
+            .render_aligned(
+                &step.nominal().label()[..],
+                Point::new(center_x, 52),
+                VerticalPosition::Top,
+                HorizontalAlignment::Center,
+                FontColor::Transparent(cfg::COLOR_RESULT_VALUE),
+                display,
+            )
+            .map_err(font_error)?;
+
+        let shots = self.sequence.rejection().shots();
+        if shots > 1 {
+            s.clear();
+            write!(s, " SHOT {}/{} ", self.sequence.shots().len() + 1, shots).unwrap();
+            TINY_FONT
+                .render_aligned(
+                    &s[..],
+                    Point::new(center_x, 74),
+                    VerticalPosition::Top,
+                    HorizontalAlignment::Center,
+                    FontColor::WithBackground {
+                        fg: cfg::COLOR_RESULT_VALUE,
+                        bg: cfg::COLOR_BACKGROUND,
+                    },
+                    display,
+                )
+                .map_err(font_error)?;
+        }
+
+        SpeedRuler::new(
+            Point::new(0, 100),
+            display.bounding_box().size.width,
+            duration,
+            SpeedTable::Standard,
+            None,
+        )
+        .draw(display)?;
+
+        for (label, y) in [(" PRESS TO MEASURE ", 28), (" > SKIP   < EXIT ", 15)] {
+            TINY_FONT
+                .render_aligned(
+                    label,
+                    Point::new(center_x, height - y),
+                    VerticalPosition::Top,
+                    HorizontalAlignment::Center,
+                    FontColor::WithBackground {
+                        fg: Rgb565::CSS_TURQUOISE,
+                        bg: cfg::COLOR_BACKGROUND,
+                    },
+                    display,
+                )
+                .map_err(font_error)?;
+        }
+        Ok(())
+    }
+
+    fn draw_summary(&mut self, display: &mut DT) -> Result<(), E> {
+        let width = display.bounding_box().size.width as i32;
+        let height = display.bounding_box().size.height as i32;
+
+        let mut title = String::<32>::default();
+        match self.sequence.rejection().shots() {
+            1 => title.push_str(" SUMMARY ").unwrap(),
+            // The median for three
+            shots => write!(title, " TRIMMED MEAN OF {} ", shots).unwrap(),
+        }
+        TINY_FONT
+            .render_aligned(
+                &title[..],
+                Point::new(width / 2, 2),
+                VerticalPosition::Top,
+                HorizontalAlignment::Center,
+                FontColor::WithBackground {
+                    bg: cfg::COLOR_RESULT_VALUE,
+                    fg: cfg::COLOR_BACKGROUND,
+                },
+                display,
+            )
+            .map_err(font_error)?;
+
+        // Leave room for the title and the hint
+        let max_rows = ((height - 32) / ROW_HEIGHT) as usize;
+        for (index, step) in self.sequence.steps().iter().take(max_rows).enumerate() {
+            let y = 16 + index as i32 * ROW_HEIGHT;
+
+            TINY_FONT
+                .render(
+                    &step.nominal().label()[..],
+                    Point::new(4, y),
+                    VerticalPosition::Top,
+                    FontColor::Transparent(cfg::COLOR_RESULT_VALUE),
+                    display,
+                )
+                .map_err(font_error)?;
+
+            let mut s = String::<128>::default();
+            let color = match (step.duration_micros, step.deviation_percent()) {
+                (Some(duration_micros), Some(deviation)) => {
+                    let actual = ShutterSpeed::from_micros(duration_micros);
+                    write!(s, "{:>7} {:>+4}%", actual, deviation).unwrap();
+                    if deviation.abs() < 15 {
+                        cfg::COLOR_RESULT_GOOD
+                    } else if deviation.abs() < 30 {
+                        cfg::COLOR_RESULT_FAIR
+                    } else {
+                        cfg::COLOR_RESULT_BAD
+                    }
+                }
+                _ => {
+                    write!(s, "{:>13}", "SKIPPED").unwrap();
+                    cfg::COLOR_RESULT_VALUE_INACTIVE
+                }
+            };
+            TINY_FONT
+                .render_aligned(
+                    &s[..],
+                    Point::new(width - 4, y),
+                    VerticalPosition::Top,
+                    HorizontalAlignment::Right,
+                    FontColor::Transparent(color),
+                    display,
+                )
+                .map_err(font_error)?;
+        }
+
+        TINY_FONT
+            .render_aligned(
+                " PRESS TO FINISH ",
+                Point::new(width / 2, height - 15),
+                VerticalPosition::Top,
+                HorizontalAlignment::Center,
+                FontColor::WithBackground {
+                    fg: Rgb565::CSS_TURQUOISE,
+                    bg: cfg::COLOR_BACKGROUND,
+                },
+                display,
+            )
+            .map_err(font_error)?;
+        Ok(())
+    }
+}
+
+impl<DT: AppDrawTarget<E>, E: Debug> Default for SequenceScreen<DT, E> {
+    fn default() -> Self {
+        Self {
+            sequence: TestSequence::default(),
+            drawn_progress: None,
+            _phantom: core::marker::PhantomData,
+        }
+    }
+}
@@ -0,0 +1,163 @@
+use core::fmt::{Debug, Write};
+
+use app_measurements::{SoakEvent, SoakLog};
+use embedded_graphics::geometry::Point;
+use embedded_graphics::pixelcolor::{Rgb565, RgbColor};
+use embedded_graphics::prelude::Dimensions;
+use heapless::String;
+use u8g2_fonts::types::{FontColor, HorizontalAlignment, VerticalPosition};
+use ufmt::uwrite;
+
+use super::{DrawFrameContext, Screen};
+use crate::fonts::{LARGE_DIGIT_FONT, TINY_FONT};
+use crate::util::font_error;
+use crate::{config as cfg, draw_badge, AppDrawTarget};
+
+/// Stays armed for hours, the extremes tell an intermittently sticky shutter apart
+pub struct SoakScreen<DT, E> {
+    pub log: SoakLog,
+    drawn: Option<u32>,
+    _phantom: core::marker::PhantomData<(DT, E)>,
+}
+
+impl<DT: AppDrawTarget<E>, E: Debug> SoakScreen<DT, E> {
+    pub fn new(log: SoakLog) -> Self {
+        Self {
+            log,
+            drawn: None,
+            _phantom: core::marker::PhantomData,
+        }
+    }
+}
+
+impl<DT: AppDrawTarget<E>, E: Debug> Screen<DT, E> for SoakScreen<DT, E> {
+    async fn draw_init(&mut self, display: &mut DT) -> Result<(), E> {
+        display.clear(cfg::COLOR_BACKGROUND)?;
+        self.drawn = None;
+
+        draw_badge(
+            display,
+            Point::new(display.bounding_box().center().x, 15),
+            " SOAK ",
+            Rgb565::BLACK,
+            cfg::COLOR_TRIGGER_HIGH,
+        )
+        .await?;
+
+        draw_line(
+            display,
+            Point::new(
+                display.bounding_box().center().x,
+                display.bounding_box().size.height as i32 - 15,
+            ),
+            " PRESS TO RESET ",
+            cfg::COLOR_TRIGGER_HIGH,
+        )?;
+        Ok(())
+    }
+
+    async fn draw_frame(&mut self, display: &mut DT, _cx: DrawFrameContext) -> Result<(), E> {
+        let count = self.log.count();
+        if self.drawn == Some(count) {
+            return Ok(());
+        }
+        self.drawn = Some(count);
+
+        let center_x = display.bounding_box().center().x;
+
+        let mut s = String::<48>::default();
+        uwrite!(s, " {} ", count).unwrap();
+        LARGE_DIGIT_FONT
+            .render_aligned(
+                &s[..],
+                Point::new(center_x, 35),
+                VerticalPosition::Top,
+                HorizontalAlignment::Center,
+                FontColor::WithBackground {
+                    fg: cfg::COLOR_RESULT_VALUE,
+                    bg: cfg::COLOR_BACKGROUND,
+                },
+                display,
+            )
+            .map_err(font_error)?;
+
+        for (i, (label, event)) in [
+            (" LAST ", self.log.last()),
+            (" FASTEST ", self.log.fastest()),
+            (" SLOWEST ", self.log.slowest()),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            s.clear();
+            match event {
+                Some(event) => write_event(&mut s, label, event),
+                None => uwrite!(s, "{}--- ", label).unwrap(),
+            }
+            draw_line(
+                display,
+                Point::new(center_x, 85 + i as i32 * 15),
+                &padded(&s),
+                cfg::COLOR_RESULT_VALUE,
+            )?;
+        }
+
+        s.clear();
+        if let (Some(mean), Some(spread)) = (self.log.mean_micros(), self.log.spread_percent()) {
+            uwrite!(s, " MEAN {} US, {}% SPREAD ", mean, spread).unwrap();
+        }
+        draw_line(
+            display,
+            Point::new(center_x, 135),
+            &padded(&s),
+            cfg::COLOR_RESULT_VALUE_INACTIVE,
+        )?;
+        Ok(())
+    }
+}
+
+/// Duration and when it happened as H:MM:SS into the soak
+fn write_event(s: &mut String<48>, label: &str, event: SoakEvent) {
+    let secs = event.at_millis / 1000;
+    uwrite!(
+        s,
+        "{}{} US @ {}:{}{}:{}{} ",
+        label,
+        event.duration_micros,
+        secs / 3600,
+        secs / 600 % 6,
+        secs / 60 % 10,
+        secs % 60 / 10,
+        secs % 10
+    )
+    .unwrap();
+}
+
+/// Wide enough to cover whatever was drawn there before
+fn padded(text: &str) -> String<48> {
+    let mut s = String::<48>::default();
+    write!(s, "{:^30}", text).unwrap();
+    s
+}
+
+fn draw_line<D: AppDrawTarget<E>, E: Debug>(
+    display: &mut D,
+    origin: Point,
+    text: &str,
+    color: Rgb565,
+) -> Result<(), E> {
+    TINY_FONT
+        .render_aligned(
+            text,
+            origin,
+            VerticalPosition::Top,
+            HorizontalAlignment::Center,
+            FontColor::WithBackground {
+                fg: color,
+                bg: cfg::COLOR_BACKGROUND,
+            },
+            display,
+        )
+        .map_err(font_error)?;
+    Ok(())
+}
@@ -3,17 +3,23 @@ use core::fmt::Debug;
 use embedded_graphics::geometry::{Point, Size};
 use embedded_graphics::pixelcolor::{Rgb565, RgbColor, WebColors};
 use embedded_graphics::primitives::Rectangle;
+use u8g2_fonts::types::{FontColor, HorizontalAlignment, VerticalPosition};
 
 use super::{DrawFrameContext, Screen};
+use crate::fonts::TINY_FONT;
+use crate::util::font_error;
 use crate::{draw_badge, AppDrawTarget};
 
 pub struct StartScreen<DT, E> {
+    /// A measurement is waiting for the shutter, no need to press anything
+    pub armed: bool,
+    drawn_armed: Option<bool>,
     _phantom: core::marker::PhantomData<(DT, E)>,
 }
 
 impl<DT: AppDrawTarget<E>, E: Debug> Screen<DT, E> for StartScreen<DT, E> {
-    async fn draw_init(&mut self, display: &mut DT) {
-        display.clear(Rgb565::BLACK).unwrap();
+    async fn draw_init(&mut self, display: &mut DT) -> Result<(), E> {
+        display.clear(Rgb565::BLACK)?;
 
         draw_badge(
             display,
@@ -22,10 +28,12 @@ impl<DT: AppDrawTarget<E>, E: Debug> Screen<DT, E> for StartScreen<DT, E> {
             Rgb565::CSS_PALE_GREEN,
             Rgb565::BLACK,
         )
-        .await;
+        .await?;
+        self.drawn_armed = None;
+        Ok(())
     }
 
-    async fn draw_frame(&mut self, display: &mut DT, cx: DrawFrameContext) {
+    async fn draw_frame(&mut self, display: &mut DT, cx: DrawFrameContext) -> Result<(), E> {
         let t = cx.animation_time_ms / 500;
 
         let color = if t % 2 == 0 {
@@ -34,18 +42,41 @@ impl<DT: AppDrawTarget<E>, E: Debug> Screen<DT, E> for StartScreen<DT, E> {
             Rgb565::BLACK
         };
         let center = display.bounding_box().center();
-        display
-            .fill_solid(
-                &Rectangle::with_center(center + Point::new(0, 10), Size::new(10, 10)),
-                color,
-            )
-            .unwrap();
+        display.fill_solid(
+            &Rectangle::with_center(center + Point::new(0, 10), Size::new(10, 10)),
+            color,
+        )?;
+
+        if self.drawn_armed != Some(self.armed) {
+            self.drawn_armed = Some(self.armed);
+            let text = if self.armed {
+                " FIRE TO MEASURE "
+            } else {
+                "                 "
+            };
+            TINY_FONT
+                .render_aligned(
+                    text,
+                    center + Point::new(0, 40),
+                    VerticalPosition::Top,
+                    HorizontalAlignment::Center,
+                    FontColor::WithBackground {
+                        fg: Rgb565::CSS_PALE_GREEN,
+                        bg: Rgb565::BLACK,
+                    },
+                    display,
+                )
+                .map_err(font_error)?;
+        }
+        Ok(())
     }
 }
 
 impl<DT: AppDrawTarget<E>, E: Debug> Default for StartScreen<DT, E> {
     fn default() -> Self {
         Self {
+            armed: false,
+            drawn_armed: None,
             _phantom: core::marker::PhantomData,
         }
     }
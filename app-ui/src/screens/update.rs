@@ -5,26 +5,31 @@ use embedded_graphics::pixelcolor::{Rgb565, RgbColor, WebColors};
 use embedded_graphics::Drawable;
 use u8g2_fonts::types::{FontColor, HorizontalAlignment, VerticalPosition};
 
-use super::{DrawFrameContext, Screen};
+use super::{DrawFrameContext, Navigation, Screen};
 use crate::fonts::{SMALL_FONT, TINY_FONT};
 use crate::primitives::Cross;
+use crate::util::font_error;
 use crate::AppDrawTarget;
 
+/// Asks to hold the button first, then shows the reboot going ahead
 pub struct UpdateScreen<DT, E> {
+    pub confirmed: bool,
+    /// Cancelled with a short press, back to the menu
+    pub closed: bool,
     _phantom: core::marker::PhantomData<(DT, E)>,
 }
 
 const COLOR: Rgb565 = Rgb565::CSS_GRAY;
 
 impl<DT: AppDrawTarget<E>, E: Debug> Screen<DT, E> for UpdateScreen<DT, E> {
-    async fn draw_init(&mut self, display: &mut DT) {
+    async fn draw_init(&mut self, display: &mut DT) -> Result<(), E> {
         let width = display.bounding_box().size.width;
 
-        display.fill_solid(&display.bounding_box(), COLOR).unwrap();
+        display.fill_solid(&display.bounding_box(), COLOR)?;
 
         for d in [-1, 0, 1] {
-            let _ = Cross::new(Point::new(width as i32 / 2 + d * 20, 25), 7, Rgb565::BLACK)
-                .draw(display);
+            Cross::new(Point::new(width as i32 / 2 + d * 20, 25), 7, Rgb565::BLACK)
+                .draw(display)?;
         }
 
         TINY_FONT
@@ -39,11 +44,16 @@ impl<DT: AppDrawTarget<E>, E: Debug> Screen<DT, E> for UpdateScreen<DT, E> {
                 },
                 display,
             )
-            .unwrap();
+            .map_err(font_error)?;
 
+        let label = if self.confirmed {
+            " REBOOTING "
+        } else {
+            " HOLD TO UPDATE "
+        };
         SMALL_FONT
             .render_aligned(
-                " REBOOTING ",
+                label,
                 Point::new(width as i32 / 2, 60),
                 VerticalPosition::Top,
                 HorizontalAlignment::Center,
@@ -53,15 +63,46 @@ impl<DT: AppDrawTarget<E>, E: Debug> Screen<DT, E> for UpdateScreen<DT, E> {
                 },
                 display,
             )
-            .unwrap();
+            .map_err(font_error)?;
+
+        if !self.confirmed {
+            TINY_FONT
+                .render_aligned(
+                    "PRESS TO CANCEL",
+                    Point::new(width as i32 / 2, 80),
+                    VerticalPosition::Top,
+                    HorizontalAlignment::Center,
+                    FontColor::Transparent(Rgb565::BLACK),
+                    display,
+                )
+                .map_err(font_error)?;
+        }
+        Ok(())
+    }
+
+    async fn draw_frame(&mut self, _display: &mut DT, _cx: DrawFrameContext) -> Result<(), E> {
+        Ok(())
+    }
+
+    fn take_navigation(&mut self) -> Option<Navigation<DT, E>> {
+        self.closed.then_some(Navigation::Pop)
     }
+}
 
-    async fn draw_frame(&mut self, _display: &mut DT, _cx: DrawFrameContext) {}
+impl<DT: AppDrawTarget<E>, E: Debug> UpdateScreen<DT, E> {
+    pub fn rebooting() -> Self {
+        Self {
+            confirmed: true,
+            ..Default::default()
+        }
+    }
 }
 
 impl<DT: AppDrawTarget<E>, E: Debug> Default for UpdateScreen<DT, E> {
     fn default() -> Self {
         Self {
+            confirmed: false,
+            closed: false,
             _phantom: core::marker::PhantomData,
         }
     }
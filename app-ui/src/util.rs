@@ -1,3 +1,5 @@
+use core::fmt::Debug;
+
 #[cfg(target_os = "none")]
 pub async fn delay_ms(ms: u32) {
     use fugit::ExtU32;
@@ -10,3 +12,11 @@ pub async fn delay_ms(ms: u32) {
     #[cfg(feature = "std")]
     tokio::time::sleep(tokio::time::Duration::from_millis(ms)).await;
 }
+
+/// Missing glyphs are bugs, only the display's own errors are worth propagating
+pub(crate) fn font_error<E: Debug>(error: u8g2_fonts::Error<E>) -> E {
+    match error {
+        u8g2_fonts::Error::DisplayError(error) => error,
+        error => panic!("{:?}", error),
+    }
+}
@@ -8,12 +8,14 @@
 //! updating `memory.x` ensures a rebuild of the application with the
 //! new memory settings.
 //!
-//! The build script also sets the linker flags to tell it which link script to use.
+//! The build script also sets the linker flags to tell it which link script to use,
+//! and passes build metadata for the About screen through `BUILD_*` env vars.
 
 use std::env;
-use std::fs::File;
+use std::fs::{self, File};
 use std::io::Write;
 use std::path::PathBuf;
+use std::process::Command;
 
 fn main() {
     // Put `memory.x` in our output directory and ensure it's
@@ -40,4 +42,49 @@ fn main() {
 
     // Set the linker script to the one provided by cortex-m-rt.
     println!("cargo:rustc-link-arg=-Tlink.x");
+
+    println!(
+        "cargo:rustc-env=BUILD_GIT_HASH={}",
+        command_output("git", &["rev-parse", "--short", "HEAD"])
+    );
+    println!(
+        "cargo:rustc-env=BUILD_DATE={}",
+        command_output("date", &["-u", "+%Y-%m-%d"])
+    );
+    println!(
+        "cargo:rustc-env=BUILD_HAL_VERSION={}",
+        locked_version("stm32f4xx-hal")
+    );
+    println!(
+        "cargo:rustc-env=BUILD_RTIC_VERSION={}",
+        locked_version("rtic")
+    );
+    println!("cargo:rerun-if-changed=../.git/HEAD");
+    println!("cargo:rerun-if-changed=../.git/refs/heads");
+    println!("cargo:rerun-if-changed=../Cargo.lock");
+}
+
+fn command_output(program: &str, args: &[&str]) -> String {
+    Command::new(program)
+        .args(args)
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .map(|s| s.trim().to_owned())
+        .filter(|s| !s.is_empty())
+        .unwrap_or_else(|| "unknown".to_owned())
+}
+
+/// Resolved version of a dependency from the workspace lockfile
+fn locked_version(package: &str) -> String {
+    let lock = fs::read_to_string("../Cargo.lock").unwrap_or_default();
+    let name_line = format!("name = \"{}\"", package);
+    lock.lines()
+        .skip_while(|line| *line != name_line)
+        .nth(1)
+        .and_then(|line| line.strip_prefix("version = \""))
+        .and_then(|version| version.strip_suffix('"'))
+        .unwrap_or("unknown")
+        .to_owned()
 }
@@ -11,8 +11,7 @@ MEMORY
 
   BOOTLOADER_RESERVED : ORIGIN = 0x00000000, LENGTH = 16K
   FLASH : ORIGIN = 0x00004000, LENGTH = 240K
-  RAM : ORIGIN = 0x20000000, LENGTH = 62K /* 2 KB left free for heap and bootloader flags */
-  HEAP : ORIGIN = 0x2000F800, LENGTH = 1K
+  RAM : ORIGIN = 0x20000000, LENGTH = 63K /* 1 KB left free for the bootloader flags */
 }
 
 SECTIONS {
@@ -22,11 +21,6 @@ SECTIONS {
       /* . = 0x0FFFC; */
       LONG(0xBEEFDEAD)
    } > BOOTLOADER_RESERVED
-
-   .heap ORIGIN(HEAP) :
-   {
-      HEAP = ORIGIN(HEAP);
-   } > HEAP
 }
 
 INCLUDE ../../../../../../bootloader-api/link.x
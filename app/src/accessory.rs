@@ -0,0 +1,83 @@
+use config as hw;
+use hw::hal::gpio::PinState;
+
+/// How the idle output tells the accessory that nothing is being measured
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IdleSignal {
+    /// High while idle, what the stock accessories expect
+    ActiveHigh,
+    ActiveLow,
+    /// Floating while idle and pulled low while sensing, for a line with its own pull-up
+    OpenDrain,
+    /// Always floating
+    Disabled,
+}
+
+impl IdleSignal {
+    pub const ALL: [IdleSignal; 4] = [
+        IdleSignal::ActiveHigh,
+        IdleSignal::ActiveLow,
+        IdleSignal::OpenDrain,
+        IdleSignal::Disabled,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            IdleSignal::ActiveHigh => "HIGH",
+            IdleSignal::ActiveLow => "LOW",
+            IdleSignal::OpenDrain => "OD",
+            IdleSignal::Disabled => "OFF",
+        }
+    }
+
+    pub fn next(&self) -> Self {
+        let index = Self::ALL.iter().position(|s| s == self).unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+}
+
+/// Drives the accessory idle output according to the [`IdleSignal`] setting
+pub struct AccessoryIo {
+    idle_pin: hw::AccessoryIdlePin,
+    signal: IdleSignal,
+    sensing: bool,
+}
+
+impl AccessoryIo {
+    /// Starts out idle
+    pub fn new(idle_pin: hw::AccessoryIdlePin, signal: IdleSignal) -> Self {
+        let mut io = Self {
+            idle_pin,
+            signal,
+            sensing: false,
+        };
+        io.update();
+        io
+    }
+
+    pub fn set_sensing(&mut self, sensing: bool) {
+        self.sensing = sensing;
+        self.update();
+    }
+
+    pub fn set_signal(&mut self, signal: IdleSignal) {
+        self.signal = signal;
+        self.update();
+    }
+
+    fn update(&mut self) {
+        let idle = !self.sensing;
+        match self.signal {
+            IdleSignal::ActiveHigh => self
+                .idle_pin
+                .make_push_pull_output_in_state(PinState::from(idle)),
+            IdleSignal::ActiveLow => self
+                .idle_pin
+                .make_push_pull_output_in_state(PinState::from(!idle)),
+            // Never driven high, so it can share the line with other open drain outputs
+            IdleSignal::OpenDrain if idle => self.idle_pin.make_floating_input(),
+            IdleSignal::OpenDrain => self.idle_pin.make_push_pull_output_in_state(PinState::Low),
+            IdleSignal::Disabled => self.idle_pin.make_floating_input(),
+        }
+    }
+}
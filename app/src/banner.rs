@@ -0,0 +1,30 @@
+use app_ui::Severity;
+use rtic_sync::channel::Sender;
+
+pub const BANNER_QUEUE_LEN: usize = 4;
+
+pub type BannerSender = Sender<'static, BannerMessage, BANNER_QUEUE_LEN>;
+
+/// Shown by `banner_task` at the top of whatever screen is up
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BannerMessage {
+    pub text: &'static str,
+    pub severity: Severity,
+    pub timeout_ms: u32,
+}
+
+/// Dropped if the queue is full, the banners are only a heads-up
+pub fn show_banner(
+    banner_sender: &mut impl rtic::Mutex<T = BannerSender>,
+    text: &'static str,
+    severity: Severity,
+    timeout_ms: u32,
+) {
+    banner_sender.lock(|sender| {
+        let _ = sender.try_send(BannerMessage {
+            text,
+            severity,
+            timeout_ms,
+        });
+    });
+}
@@ -0,0 +1,44 @@
+use app_measurements::CalibrationResult;
+use rtic_sync::arbiter::Arbiter;
+use rtic_sync::channel::{Receiver, Sender};
+use rtic_sync::make_channel;
+
+/// What `calibration_task` serves [`calibrate`] on, one request at a time
+pub struct CalibrationService {
+    pub requests: Receiver<'static, (), 1>,
+    pub replies: Sender<'static, Option<CalibrationResult>, 1>,
+}
+
+struct CalibrationClient {
+    requests: Sender<'static, (), 1>,
+    replies: Receiver<'static, Option<CalibrationResult>, 1>,
+}
+
+/// Held by whichever task is calibrating, the others wait their turn.
+/// Set from init, before any task can ask.
+static CALIBRATION: Arbiter<Option<CalibrationClient>> = Arbiter::new(None);
+
+/// Connects [`calibrate`] to the service handed to `calibration_task`, only once from init
+pub fn init() -> CalibrationService {
+    let (request_sender, request_receiver) = make_channel!((), 1);
+    let (reply_sender, reply_receiver) = make_channel!(Option<CalibrationResult>, 1);
+    // Nothing is running yet to hold it
+    *CALIBRATION.try_access().unwrap() = Some(CalibrationClient {
+        requests: request_sender,
+        replies: reply_receiver,
+    });
+    CalibrationService {
+        requests: request_receiver,
+        replies: reply_sender,
+    }
+}
+
+/// Calibrates for the mode that asks, from any task. `None` when the mode changed
+/// meanwhile or the sensor is faulty.
+pub async fn calibrate() -> Option<CalibrationResult> {
+    let mut client = CALIBRATION.access().await;
+    // Only missing if init never set it up
+    let client = client.as_mut().unwrap();
+    client.requests.send(()).await.ok()?;
+    client.replies.recv().await.ok().flatten()
+}
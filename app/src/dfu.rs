@@ -0,0 +1,106 @@
+#![cfg_attr(not(feature = "usb"), allow(dead_code))]
+
+use core::marker::PhantomData;
+
+use usb_device::class_prelude::*;
+use usb_device::control::{Recipient, RequestType};
+use usb_device::Result;
+
+// DFU 1.1 runtime interface, the download itself is done by the ST system bootloader
+const USB_CLASS_APPLICATION_SPECIFIC: u8 = 0xfe;
+const DFU_SUBCLASS: u8 = 0x01;
+const DFU_PROTOCOL_RUNTIME: u8 = 0x01;
+const DFU_FUNCTIONAL_DESCRIPTOR: u8 = 0x21;
+
+const DFU_DETACH: u8 = 0;
+const DFU_GETSTATUS: u8 = 3;
+const DFU_GETSTATE: u8 = 5;
+
+const DFU_STATUS_OK: u8 = 0;
+const DFU_STATE_APP_IDLE: u8 = 0;
+
+/// bitWillDetach, the firmware resets by itself instead of waiting for a bus reset
+const DFU_ATTRIBUTES: u8 = 0x08;
+const DETACH_TIMEOUT_MS: u16 = 1000;
+const TRANSFER_SIZE: u16 = 1024;
+const DFU_VERSION: u16 = 0x0110;
+
+/// Lets `dfu-util -e` reboot the device into the bootloader
+pub struct DfuRuntimeClass<'a, B: UsbBus> {
+    interface: InterfaceNumber,
+    detach_requested: bool,
+    _bus: PhantomData<&'a B>,
+}
+
+impl<'a, B: UsbBus> DfuRuntimeClass<'a, B> {
+    pub fn new(alloc: &'a UsbBusAllocator<B>) -> Self {
+        Self {
+            interface: alloc.interface(),
+            detach_requested: false,
+            _bus: PhantomData,
+        }
+    }
+
+    /// Once per request, a refused one doesn't come back
+    pub fn take_detach_request(&mut self) -> bool {
+        core::mem::take(&mut self.detach_requested)
+    }
+
+    fn is_own_request(&self, request: &usb_device::control::Request) -> bool {
+        request.request_type == RequestType::Class
+            && request.recipient == Recipient::Interface
+            && request.index == u8::from(self.interface) as u16
+    }
+}
+
+impl<B: UsbBus> UsbClass<B> for DfuRuntimeClass<'_, B> {
+    fn get_configuration_descriptors(&self, writer: &mut DescriptorWriter) -> Result<()> {
+        writer.interface(
+            self.interface,
+            USB_CLASS_APPLICATION_SPECIFIC,
+            DFU_SUBCLASS,
+            DFU_PROTOCOL_RUNTIME,
+        )?;
+
+        let [timeout_lo, timeout_hi] = DETACH_TIMEOUT_MS.to_le_bytes();
+        let [transfer_lo, transfer_hi] = TRANSFER_SIZE.to_le_bytes();
+        let [version_lo, version_hi] = DFU_VERSION.to_le_bytes();
+        writer.write(
+            DFU_FUNCTIONAL_DESCRIPTOR,
+            &[
+                DFU_ATTRIBUTES,
+                timeout_lo,
+                timeout_hi,
+                transfer_lo,
+                transfer_hi,
+                version_lo,
+                version_hi,
+            ],
+        )
+    }
+
+    fn control_in(&mut self, xfer: ControlIn<B>) {
+        if !self.is_own_request(xfer.request()) {
+            return;
+        }
+        let _ = match xfer.request().request {
+            // bStatus, bwPollTimeout (3 bytes), bState, iString
+            DFU_GETSTATUS => xfer.accept_with(&[DFU_STATUS_OK, 0, 0, 0, DFU_STATE_APP_IDLE, 0]),
+            DFU_GETSTATE => xfer.accept_with(&[DFU_STATE_APP_IDLE]),
+            _ => xfer.reject(),
+        };
+    }
+
+    fn control_out(&mut self, xfer: ControlOut<B>) {
+        if !self.is_own_request(xfer.request()) {
+            return;
+        }
+        let _ = match xfer.request().request {
+            DFU_DETACH => {
+                self.detach_requested = true;
+                xfer.accept()
+            }
+            _ => xfer.reject(),
+        };
+    }
+}
@@ -1,6 +1,7 @@
+use app_measurements::DisplayGeometry;
 #[cfg(feature = "effects")]
 use app_ui::FX;
-use app_ui::{FXParams, HintRefresh};
+use app_ui::{DrawFrameContext, FXParams, HintRefresh};
 use config as hw;
 use embedded_graphics::draw_target::DrawTarget;
 use embedded_graphics::geometry::Dimensions;
@@ -14,30 +15,103 @@ use mipidsi::models::ST7735s;
 pub trait DisplayInterface: embedded_hal::spi::SpiDevice<u8> {}
 impl<W: embedded_hal::spi::SpiDevice<u8>> DisplayInterface for W {}
 
+type InnerDisplay<DI> =
+    mipidsi::Display<SPIInterface<DI, ErasedPin<Output>>, ST7735s, ErasedPin<Output>>;
+
+/// Draw errors are counted and passed on, the display task decides
+/// when a redraw isn't enough and calls [`Display::recover`]
 pub struct Display<DI: DisplayInterface> {
-    inner: mipidsi::Display<SPIInterface<DI, ErasedPin<Output>>, ST7735s, ErasedPin<Output>>,
+    // Only `None` if a re-init has failed
+    inner: Option<InnerDisplay<DI>>,
+    bounding_box: Rectangle,
     backlight_pin: ErasedPin<Output>,
+    delay: hw::DisplayDelayType,
+    geometry: DisplayGeometry,
+    flipped: bool,
     fx_params: FXParams,
+    failures: u32,
 }
 
 impl<DI: DisplayInterface> Display<DI> {
     pub fn new(
-        inner: mipidsi::Display<
-            SPIInterface<DI, ErasedPin<Output>>,
-            ST7735s,
-            ErasedPin<Output>,
-        >,
+        inner: InnerDisplay<DI>,
         backlight_pin: ErasedPin<Output>,
+        delay: hw::DisplayDelayType,
+        geometry: DisplayGeometry,
+        flipped: bool,
     ) -> Self {
         Display {
-            inner,
+            bounding_box: inner.bounding_box(),
+            inner: Some(inner),
             backlight_pin,
+            delay,
+            geometry,
+            flipped,
             fx_params: FXParams::default(),
+            failures: 0,
+        }
+    }
+
+    /// Number of failed draw calls since boot
+    pub fn failures(&self) -> u32 {
+        self.failures
+    }
+
+    /// Resets the controller and sends the init sequence again.
+    /// The screen content is lost and has to be redrawn.
+    pub fn recover(&mut self) {
+        let Some(inner) = self.inner.take() else {
+            return;
+        };
+        let (di, _, rst_pin) = inner.release();
+        let Some(rst_pin) = rst_pin else {
+            return;
+        };
+        self.inner =
+            hw::init_display!(di, rst_pin, &mut self.delay, self.geometry, self.flipped).ok();
+        match self.inner.as_ref() {
+            Some(inner) => self.bounding_box = inner.bounding_box(),
+            None => self.failures += 1,
         }
     }
 
-    pub fn step_fx(&mut self) {
-        self.fx_params.step();
+    pub fn geometry(&self) -> DisplayGeometry {
+        self.geometry
+    }
+
+    /// Takes a re-init, the screens need a redraw afterwards
+    pub fn set_geometry(&mut self, geometry: DisplayGeometry) {
+        self.geometry = geometry;
+        self.recover();
+    }
+
+    pub fn flipped(&self) -> bool {
+        self.flipped
+    }
+
+    /// Turns the picture around, the screens draw the same either way.
+    /// Takes a re-init like [`Self::set_geometry`].
+    pub fn set_flipped(&mut self, flipped: bool) {
+        self.flipped = flipped;
+        self.recover();
+    }
+
+    fn check(
+        &mut self,
+        result: Result<(), mipidsi::error::Error>,
+    ) -> Result<(), mipidsi::error::Error> {
+        if result.is_err() {
+            self.failures += 1;
+        }
+        result
+    }
+
+    pub fn step_fx(&mut self, cx: &DrawFrameContext) {
+        self.fx_params.set_time(cx.animation_time_ms);
+    }
+
+    pub fn set_fx_intensity(&mut self, percent: u8) {
+        self.fx_params.set_intensity(percent);
     }
 
     pub fn backlight_on(&mut self) {
@@ -50,7 +124,7 @@ impl<DI: DisplayInterface> Display<DI> {
 
     pub fn sneaky_clear(&mut self, color: Rgb565) {
         self.backlight_off();
-        self.inner.clear(color).unwrap();
+        let _ = self.clear(color);
         self.backlight_on();
     }
 
@@ -65,12 +139,16 @@ impl<DI: DisplayInterface> Display<DI> {
 
 impl<DI: DisplayInterface> Dimensions for Display<DI> {
     fn bounding_box(&self) -> Rectangle {
-        self.inner.bounding_box()
+        self.bounding_box
     }
 }
 
 impl<DI: DisplayInterface> HintRefresh for Display<DI> {
     fn hint_refresh(&mut self) {}
+
+    fn hint_fx_exclusions(&mut self, areas: &[Rectangle]) {
+        self.fx_params.set_exclusions(areas);
+    }
 }
 
 impl<DI: DisplayInterface> DrawTarget for Display<DI> {
@@ -81,25 +159,37 @@ impl<DI: DisplayInterface> DrawTarget for Display<DI> {
     where
         I: IntoIterator<Item = Pixel<Self::Color>>,
     {
+        let Some(inner) = self.inner.as_mut() else {
+            return Ok(());
+        };
         #[cfg(feature = "effects")]
-        let mut d = FX::new(&mut self.inner, self.fx_params);
+        let mut d = FX::new(inner, &self.fx_params);
         #[cfg(not(feature = "effects"))]
-        let d = &mut self.inner;
-        d.draw_iter(pixels)
+        let d = inner;
+        let result = d.draw_iter(pixels);
+        self.check(result)
     }
 
     fn fill_contiguous<I>(&mut self, area: &Rectangle, colors: I) -> Result<(), Self::Error>
     where
         I: IntoIterator<Item = Self::Color>,
     {
+        let Some(inner) = self.inner.as_mut() else {
+            return Ok(());
+        };
         #[cfg(feature = "effects")]
-        let mut d = FX::new(&mut self.inner, self.fx_params);
+        let mut d = FX::new(inner, &self.fx_params);
         #[cfg(not(feature = "effects"))]
-        let d = &mut self.inner;
-        d.fill_contiguous(area, colors)
+        let d = inner;
+        let result = d.fill_contiguous(area, colors);
+        self.check(result)
     }
 
     fn clear(&mut self, color: Self::Color) -> Result<(), Self::Error> {
-        self.inner.fill_solid(&self.bounding_box(), color)
+        let Some(inner) = self.inner.as_mut() else {
+            return Ok(());
+        };
+        let result = inner.fill_solid(&self.bounding_box, color);
+        self.check(result)
     }
 }
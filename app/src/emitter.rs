@@ -0,0 +1,4 @@
+pub trait EmitterExt {
+    /// `0` switches the emitter off
+    fn set_intensity_percent(&mut self, intensity_percent: u8);
+}
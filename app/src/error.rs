@@ -0,0 +1,43 @@
+use rtic_sync::channel::Sender;
+
+pub const ERROR_QUEUE_LEN: usize = 4;
+
+pub type ErrorSender = Sender<'static, AppError, ERROR_QUEUE_LEN>;
+
+/// Recoverable failures, reported to `error_task` instead of panicking
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AppError {
+    /// The ADC DMA stream wasn't ready for the next buffer, a sample got lost
+    Dma,
+    /// Nothing was waiting for the calibration result anymore
+    Calibration,
+    /// A task was started again while still running
+    TaskBusy,
+    /// Draw calls failed and the display got reset
+    Display,
+    /// A screen was opened without the result it shows
+    NoResult,
+    /// The bootloader was asked for while a measurement was running
+    UpdateRefused,
+}
+
+impl AppError {
+    /// Short enough for the toast
+    pub fn label(&self) -> &'static str {
+        match self {
+            AppError::Dma => "SAMPLE LOST",
+            AppError::Calibration => "CALIBRATION LOST",
+            AppError::TaskBusy => "BUSY",
+            AppError::Display => "DISPLAY RESET",
+            AppError::NoResult => "NO RESULT",
+            AppError::UpdateRefused => "MEASURING, NO UPDATE",
+        }
+    }
+}
+
+/// Dropped if the queue is full, the first errors are the interesting ones
+pub fn report_error(error_sender: &mut impl rtic::Mutex<T = ErrorSender>, error: AppError) {
+    error_sender.lock(|sender| {
+        let _ = sender.try_send(error);
+    });
+}
@@ -0,0 +1,99 @@
+#![cfg_attr(not(feature = "usb"), allow(dead_code))]
+
+use core::ops::RangeInclusive;
+
+use embedded_hal::i2c::I2c;
+use heapless::Vec;
+
+/// 7-bit addresses, the rest are reserved
+const ADDRESSES: RangeInclusive<u8> = 0x08..=0x77;
+
+pub const MAX_EXPANSION_DEVICES: usize = 8;
+
+/// What answered on the expansion bus, by address
+pub type ExpansionDevices = Vec<(u8, ExpansionDevice), MAX_EXPANSION_DEVICES>;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExpansionDevice {
+    /// BH1750
+    AmbientLight,
+    /// SSD1306
+    Viewfinder,
+    /// 24Cxx
+    Eeprom,
+    /// Answered at an address without a driver
+    Unknown,
+}
+
+impl ExpansionDevice {
+    pub fn label(&self) -> &'static str {
+        match self {
+            ExpansionDevice::AmbientLight => "AMBIENT_LIGHT",
+            ExpansionDevice::Viewfinder => "VIEWFINDER",
+            ExpansionDevice::Eeprom => "EEPROM",
+            ExpansionDevice::Unknown => "UNKNOWN",
+        }
+    }
+}
+
+/// The addresses there is a driver for. A new accessory needs an entry here and its
+/// driver run from `expansion_task`, which owns the bus.
+const DRIVERS: [(u8, ExpansionDevice); 5] = [
+    (0x23, ExpansionDevice::AmbientLight),
+    (0x5C, ExpansionDevice::AmbientLight),
+    (0x3C, ExpansionDevice::Viewfinder),
+    (0x3D, ExpansionDevice::Viewfinder),
+    (0x50, ExpansionDevice::Eeprom),
+];
+
+/// The I2C expansion header, shared by whatever accessories are plugged into it
+pub struct ExpansionBus<I2C: I2c> {
+    i2c: I2C,
+}
+
+impl<I2C: I2c> ExpansionBus<I2C> {
+    pub fn new(i2c: I2C) -> Self {
+        Self { i2c }
+    }
+
+    /// Every address, a few milliseconds with nothing on the bus. Beyond
+    /// [`MAX_EXPANSION_DEVICES`] the rest are left out.
+    pub fn scan(&mut self) -> ExpansionDevices {
+        let mut devices = ExpansionDevices::new();
+        for address in ADDRESSES {
+            if self.probe(address) {
+                let _ = devices.push((address, driver_for(address)));
+            }
+        }
+        devices
+    }
+
+    /// Only the addresses with a driver, quick enough to repeat for hot plugging.
+    /// Devices without one stay as the last full scan found them.
+    pub fn poll(&mut self, devices: &ExpansionDevices) -> ExpansionDevices {
+        let mut found: ExpansionDevices = devices
+            .iter()
+            .filter(|(_, device)| *device == ExpansionDevice::Unknown)
+            .copied()
+            .collect();
+        for (address, device) in DRIVERS {
+            if self.probe(address) {
+                let _ = found.push((address, device));
+            }
+        }
+        found.sort_unstable_by_key(|(address, _)| *address);
+        found
+    }
+
+    /// Reading a byte is harmless for everything on the list, unlike a write
+    fn probe(&mut self, address: u8) -> bool {
+        self.i2c.read(address, &mut [0]).is_ok()
+    }
+}
+
+fn driver_for(address: u8) -> ExpansionDevice {
+    DRIVERS
+        .iter()
+        .find(|(a, _)| *a == address)
+        .map_or(ExpansionDevice::Unknown, |(_, device)| *device)
+}
@@ -0,0 +1,44 @@
+use app_measurements::MeasurementPhase;
+use config as hw;
+use fugit::ExtU32;
+use hw::hal::gpio::{ErasedPin, Output};
+use rtic_monotonics::systick::Systick;
+use rtic_monotonics::Monotonic;
+
+/// Blinks the board LED as the shutter opens and closes, straight from the DMA
+/// handler. Exposures shorter than the blink run both into one.
+pub struct LedFlash {
+    /// The Blackpill LED lights with the pin low
+    pin: ErasedPin<Output>,
+    last_phase: MeasurementPhase,
+    off_at: Option<<Systick as Monotonic>::Instant>,
+}
+
+impl LedFlash {
+    /// Starts out dark
+    pub fn new(mut pin: ErasedPin<Output>) -> Self {
+        pin.set_high();
+        Self {
+            pin,
+            last_phase: MeasurementPhase::Armed,
+            off_at: None,
+        }
+    }
+
+    /// After every sample, with the phase the measurement ended up in
+    pub fn step(&mut self, phase: MeasurementPhase) {
+        let exposing = phase == MeasurementPhase::Exposing;
+        let was_exposing = self.last_phase == MeasurementPhase::Exposing;
+        self.last_phase = phase;
+
+        if exposing != was_exposing {
+            self.pin.set_low();
+            self.off_at = Some(Systick::now() + hw::LED_FLASH_MS.millis());
+        } else if let Some(off_at) = self.off_at {
+            if Systick::now() >= off_at {
+                self.pin.set_high();
+                self.off_at = None;
+            }
+        }
+    }
+}
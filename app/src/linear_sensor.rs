@@ -0,0 +1,28 @@
+use embedded_hal::spi::SpiDevice;
+
+/// Row of photodiodes on an MCP3208, one per ADC channel
+pub struct LinearSensor<SPI: SpiDevice<u8>, const N: usize> {
+    spi: SPI,
+}
+
+impl<SPI: SpiDevice<u8>, const N: usize> LinearSensor<SPI, N> {
+    pub fn new(spi: SPI) -> Self {
+        Self { spi }
+    }
+
+    pub fn read_channel(&mut self, channel: u8) -> Result<u16, SPI::Error> {
+        // Start bit, single ended, channel number; the 12 bit result comes back in the last bits
+        let mut buf = [0x06 | (channel >> 2), (channel & 0x03) << 6, 0];
+        self.spi.transfer_in_place(&mut buf)?;
+        Ok((((buf[1] & 0x0f) as u16) << 8) | buf[2] as u16)
+    }
+
+    /// Reads all channels back to back, a sweep takes about 15us per channel
+    pub fn sweep(&mut self) -> Result<[u16; N], SPI::Error> {
+        let mut values = [0; N];
+        for (channel, value) in values.iter_mut().enumerate() {
+            *value = self.read_channel(channel as u8)?;
+        }
+        Ok(values)
+    }
+}
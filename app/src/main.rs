@@ -3,37 +3,72 @@
 #![feature(type_alias_impl_trait)]
 #![feature(iter_array_chunks)]
 #![feature(sync_unsafe_cell)]
+// No global allocator on purpose: anything sized at runtime goes into heapless
+// containers, so RAM use is fixed at link time
 
+mod accessory;
+mod banner;
+mod calibration;
+mod dfu;
 mod display;
+mod emitter;
+mod error;
+mod expansion;
+mod led;
+mod linear_sensor;
+mod memory;
 mod panic;
+mod settings;
+mod snapshot;
 mod sound;
-
-extern "C" {
-    static mut HEAP: u32;
-}
+mod stream;
+mod usb;
 
 // HWCONFIG
 #[rtic::app(device = hal::pac, dispatchers = [SPI2, SPI3, SPI4, I2C1_EV])]
 mod app {
-    use core::cell::UnsafeCell;
     use core::num::Wrapping;
     use core::panic;
     #[cfg(feature = "usb")]
     use core::ptr::addr_of_mut;
 
-    use app_measurements::{CalibrationResult, CalibrationState, CycleCounterClock, Measurement};
+    use app_measurements::util::Deadline;
+    #[cfg(feature = "usb")]
+    use app_measurements::util::LaxMonotonic;
+    #[cfg(any(feature = "usb", feature = "profiling"))]
+    use app_measurements::ProfiledSection;
+    use app_measurements::{
+        check_clock_rate, compress_trace, AccessoryEvent, AccessoryGain, AccessoryInput, AdcFaults,
+        Annotation, ButtonGesture, ButtonInput, CalibrationResult, CalibrationState,
+        CaptureMeasurement, ClockFault, CycleCounterClock, EventCounter, FixtureInput,
+        FocalPlaneResult, GainFault, History, HistoryEntry, LoopbackCheck, Measurement,
+        MeasurementPhase, MeasurementSetup, Oversampler, PeakHold, Profile, RefireCheck,
+        ScanMeasurement, SensorFault, Session, SoakEvent, SoakLog, StuckDetector, TestSequence,
+        TraceHistory, FOCAL_PLANE_CENTER, FOCAL_PLANE_SENSORS, SCAN_CHANNELS, SESSION_WORDS,
+    };
+    #[cfg(feature = "usb")]
+    use app_measurements::{
+        write_report_row, Capabilities, LevelMonitor, ReportSummary, TraceDecoder, CAPABILITY_KEYS,
+        HISTORY_LEN, MONITOR_INTERVAL_MS, OBSERVER_BLOCK_LEN, REPORT_COLUMNS,
+        SAMPLING_BUFFER_LEN_WITH_MARGINS, TRACE_HISTORY_LEN,
+    };
     use app_ui::{
-        BootScreen, CalibrationScreen, DebugScreen, DrawFrameContext, MeasurementScreen,
-        MenuScreen, NoAccessoryScreen, ResultsScreen, Screen, Screens, StartScreen, UpdateScreen,
+        AboutScreen, AnnotationEditor, AnnotationScreen, Banner, BeeperTuner, BeeperTuningScreen,
+        BootDetails, BootScreen, BuildInfo, CalibrationScreen, ChartViewport, ClockFaultScreen,
+        CounterScreen, DebugPage, DebugScreen, DisplayGeometryEditor, DisplayGeometryScreen,
+        DrawBudget, DrawFrameContext, FocalPlaneScreen, GainScreen, MeasurementScreen, MemoryUsage,
+        MenuItem, MenuPage, MenuScreen, NoAccessoryScreen, ResultsScreen, ResumeScreen,
+        RulerCursor, ScanScreen, Screen, ScreenStack, Screens, SelfCheckScreen, SensorFaultScreen,
+        SequenceScreen, Severity, SoakScreen, StartScreen, ThresholdEditor, Toast, UpdateScreen,
     };
-    use config::{self as hw, hal, AllGpio};
+    use config::{self as hw, hal, AllGpio, BackupRegisters, HardwareRevision, InputCapture};
+    use cortex_m::peripheral::DWT;
     #[cfg(feature = "usb")]
     use cortex_m::peripheral::NVIC;
     use cortex_m_microclock::CYCCNTClock;
-    use embedded_alloc::Heap;
     use embedded_graphics::pixelcolor::{Rgb565, RgbColor};
     use fugit::ExtU32;
-    use hal::adc::config::Resolution;
+    use hal::adc::config::{Resolution, SampleTime};
     use hal::gpio::{Edge, ErasedPin, Input, Output};
     #[cfg(feature = "usb")]
     use hal::otg_fs::UsbBusType;
@@ -51,6 +86,7 @@ mod app {
     use rtic_monotonics::{create_systick_token, Monotonic};
     use rtic_sync::channel::{Receiver, Sender};
     use rtic_sync::make_channel;
+    use sampler::{SamplerBackend, SamplerError, BURST_LEN};
     #[cfg(feature = "usb")]
     use stm32f4xx_hal::pac::Interrupt;
     #[cfg(feature = "usb")]
@@ -58,16 +94,70 @@ mod app {
     use usb_device::class_prelude::UsbBusAllocator;
     use usb_device::device::UsbDevice;
     #[cfg(feature = "usb")]
-    use usb_device::device::{StringDescriptors, UsbDeviceBuilder, UsbVidPid};
+    use usb_device::device::{StringDescriptors, UsbDeviceBuilder, UsbDeviceState, UsbVidPid};
     use usbd_serial::SerialPort;
 
+    use crate::accessory::{AccessoryIo, IdleSignal};
+    use crate::banner::{show_banner, BannerMessage, BannerSender, BANNER_QUEUE_LEN};
+    use crate::calibration::{self, calibrate, CalibrationService};
+    use crate::dfu::DfuRuntimeClass;
     use crate::display::Display;
-    use crate::panic::set_panic_display_ref;
-    use crate::sound::{BeeperExt, Chirp};
+    use crate::emitter::EmitterExt;
+    use crate::error::{report_error, AppError, ErrorSender, ERROR_QUEUE_LEN};
+    use crate::expansion::{ExpansionBus, ExpansionDevices};
+    use crate::led::LedFlash;
+    use crate::linear_sensor::LinearSensor;
+    use crate::memory;
+    use crate::panic::PANIC_DISPLAY;
+    use crate::settings::Settings;
+    #[cfg(feature = "usb")]
+    use crate::settings::{CONFIG_KEYS, TOLERANCE_OVERRIDE_KEY};
+    use crate::snapshot::{
+        SamplingView, SnapshotPublisher, StatusView, SAMPLING_VIEW, STATUS_INTERVAL_SAMPLES,
+        STATUS_VIEW,
+    };
+    use crate::sound::{self, BeeperExt, Chirp};
+    use crate::stream::{StreamEvent, StreamObserver, StreamReceiver, STREAM_QUEUE_LEN};
+    #[cfg(feature = "usb")]
+    use crate::usb::{
+        console_mode, is_bootloader_touch, CommandParser, ConsoleMode, DeviceEvent, UsbRequest,
+    };
+    use crate::usb::{wake_usb, UsbEvents, UsbExport, UsbLog, UsbWakeReceiver, UsbWakeSender};
 
     pub type DisplayType = Display<config::DisplaySpiType>;
 
     config::beeper_type!();
+    config::emitter_type!();
+
+    const TOAST_DURATION_MS: u32 = 2000;
+    const BANNER_DURATION_MS: u32 = 3000;
+    /// Detents decoded but not handled yet
+    const ROTARY_QUEUE_LEN: usize = 8;
+    /// Failed frames in a row that get redrawn before the display is re-initialized
+    const DISPLAY_RETRIES: u32 = 2;
+    /// Font renders per frame while the ADC runs, the rest waits for the next frame
+    const SAMPLING_DRAW_BUDGET: DrawBudget = DrawBudget::new(3);
+    /// Spun out on the cycle counter before the release output fires, Systick isn't that precise
+    const RELEASE_SPIN_MS: u32 = 2;
+    #[cfg(feature = "usb")]
+    const USB_WRITE_RETRIES: u32 = 100;
+    /// Between `usb_task` runs nothing woke it for, the trigger stream is timestamped anyway
+    #[cfg(feature = "usb")]
+    const USB_IDLE_TICK_MS: u32 = 100;
+    /// Level samples per monitor frame come from this tick
+    #[cfg(feature = "usb")]
+    const USB_MONITOR_TICK_MS: u32 = 10;
+    /// A log writer gives up waiting for room after the queue stopped draining this long
+    #[cfg(feature = "usb")]
+    const USB_LOG_STALL_MS: u32 = 10;
+
+    const BUILD_INFO: BuildInfo = BuildInfo {
+        version: env!("CARGO_PKG_VERSION"),
+        git_hash: env!("BUILD_GIT_HASH"),
+        build_date: env!("BUILD_DATE"),
+        hal_version: env!("BUILD_HAL_VERSION"),
+        rtic_version: env!("BUILD_RTIC_VERSION"),
+    };
 
     #[derive(Debug, Clone, Copy, PartialEq, Eq)]
     pub enum AppModeInner {
@@ -77,21 +167,127 @@ mod app {
         Measure,
         Results,
         Debug,
-        Update,
+        /// display_task reboots into the bootloader once the screen is up
+        Rebooting,
         NoAccessory,
         Menu,
+        Counter,
+        Sequence,
+        Scan,
+        FocalPlane,
+        /// Asks whether to pick up the session interrupted by a reset
+        Resume,
+        /// The accessory is there but its sensor isn't, see `sensor_fault_task`
+        SensorFault,
+        /// The core clock isn't what durations are counted in, see `clock_check_task`
+        ClockFault,
+        /// Outcome of the emitter loopback, see `self_check_task`
+        SelfCheck,
+        /// Rearms after every exposure for as long as it's left alone, see `soak_task`
+        Soak,
+        /// Sweeps the beeper for the loudest pitch, see `beeper_tuning_task`
+        BeeperTuning,
+        /// What the emitter loopback taught about the sensor module, see `self_check_task`
+        Gain,
+    }
+
+    /// A mode's position here is stored across resets, only ever append
+    const RESUMABLE_MODES: [AppModeInner; 5] = [
+        AppModeInner::Sequence,
+        AppModeInner::Counter,
+        AppModeInner::Debug,
+        AppModeInner::Scan,
+        AppModeInner::Soak,
+    ];
+
+    impl AppModeInner {
+        fn session_id(self) -> Option<u8> {
+            RESUMABLE_MODES
+                .iter()
+                .position(|&mode| mode == self)
+                .map(|index| index as u8 + 1)
+        }
+
+        fn from_session_id(id: u8) -> Option<Self> {
+            RESUMABLE_MODES.get((id as usize).checked_sub(1)?).copied()
+        }
+
+        /// As `EVT:MODE` tells a subscribed host
+        #[cfg(feature = "usb")]
+        fn label(self) -> &'static str {
+            match self {
+                AppModeInner::None => "NONE",
+                AppModeInner::Start => "START",
+                AppModeInner::Calibrating => "CALIBRATING",
+                AppModeInner::Measure => "MEASURE",
+                AppModeInner::Results => "RESULTS",
+                AppModeInner::Debug => "DEBUG",
+                AppModeInner::Rebooting => "REBOOTING",
+                AppModeInner::NoAccessory => "NO_ACCESSORY",
+                AppModeInner::Menu => "MENU",
+                AppModeInner::Counter => "COUNTER",
+                AppModeInner::Sequence => "SEQUENCE",
+                AppModeInner::Scan => "SCAN",
+                AppModeInner::FocalPlane => "FOCAL_PLANE",
+                AppModeInner::Resume => "RESUME",
+                AppModeInner::SensorFault => "SENSOR_FAULT",
+                AppModeInner::ClockFault => "CLOCK_FAULT",
+                AppModeInner::SelfCheck => "SELF_CHECK",
+                AppModeInner::Soak => "SOAK",
+                AppModeInner::BeeperTuning => "BEEPER_TUNING",
+                AppModeInner::Gain => "GAIN",
+            }
+        }
     }
 
+    /// Every mode a host may see in `EVT:MODE`, for `capabilities?`
+    #[cfg(feature = "usb")]
+    const ALL_MODES: [AppModeInner; 20] = [
+        AppModeInner::None,
+        AppModeInner::Start,
+        AppModeInner::Calibrating,
+        AppModeInner::Measure,
+        AppModeInner::Results,
+        AppModeInner::Debug,
+        AppModeInner::Rebooting,
+        AppModeInner::NoAccessory,
+        AppModeInner::Menu,
+        AppModeInner::Counter,
+        AppModeInner::Sequence,
+        AppModeInner::Scan,
+        AppModeInner::FocalPlane,
+        AppModeInner::Resume,
+        AppModeInner::SensorFault,
+        AppModeInner::ClockFault,
+        AppModeInner::SelfCheck,
+        AppModeInner::Soak,
+        AppModeInner::BeeperTuning,
+        AppModeInner::Gain,
+    ];
+
     pub struct AppMode {
         inner: AppModeInner,
-        acc_idle_pin: ErasedPin<Output>,
+        accessory_io: AccessoryIo,
+        emitter: Emitter,
+        emitter_intensity: u8,
+        /// Takes over the emitter until the mode leaves the sensing ones
+        emitter_override: Option<u8>,
+        /// Sampling on the start screen, see `instant_task`
+        instant_armed: bool,
+        /// Shown over the menu, see [`MenuPage`]. Changing the mode closes it
+        page: Option<MenuItem>,
     }
 
     impl AppMode {
-        pub fn new(acc_idle_pin: ErasedPin<Output>) -> Self {
+        pub fn new(accessory_io: AccessoryIo, emitter: Emitter) -> Self {
             AppMode {
                 inner: AppModeInner::Start,
-                acc_idle_pin,
+                accessory_io,
+                emitter,
+                emitter_intensity: 0,
+                emitter_override: None,
+                instant_armed: false,
+                page: None,
             }
         }
 
@@ -100,24 +296,141 @@ mod app {
         }
 
         pub fn set(&mut self, mode: AppModeInner) {
+            let was_sampling = self.is_sampling();
             self.inner = mode;
-            match mode {
-                AppModeInner::Calibrating | AppModeInner::Measure | AppModeInner::Debug => {
-                    self.acc_idle_pin.set_low()
+            self.page = None;
+            if mode != AppModeInner::Start {
+                self.instant_armed = false;
+            }
+            self.update_outputs(was_sampling);
+        }
+
+        /// Only sticks on the start screen, leaving it disarms
+        pub fn arm_instant(&mut self, armed: bool) {
+            let was_sampling = self.is_sampling();
+            self.instant_armed = armed && self.inner == AppModeInner::Start;
+            self.update_outputs(was_sampling);
+        }
+
+        pub fn is_instant_armed(&self) -> bool {
+            self.instant_armed
+        }
+
+        /// The button and the encoder go to the page while it's open
+        pub fn open_page(&mut self, item: MenuItem) {
+            if self.inner == AppModeInner::Menu {
+                self.page = Some(item);
+            }
+        }
+
+        pub fn close_page(&mut self) {
+            self.page = None;
+        }
+
+        pub fn page(&self) -> Option<MenuItem> {
+            self.page
+        }
+
+        fn update_outputs(&mut self, was_sampling: bool) {
+            if !self.is_sensing() {
+                self.emitter_override = None;
+            }
+            self.accessory_io.set_sensing(self.is_sensing());
+            self.update_emitter();
+            if self.is_sampling() != was_sampling {
+                // Outranks every task that sets the mode, so it's done before the next change
+                let _ = sampling_task::spawn(self.is_sampling());
+            }
+        }
+
+        /// Unplugging leaves whatever mode for the accessory prompt,
+        /// plugging back in only leaves the prompt
+        pub fn on_accessory(&mut self, event: AccessoryEvent) {
+            match (event, self.inner) {
+                // The bootloader takes over regardless
+                (_, AppModeInner::Rebooting) => (),
+                (AccessoryEvent::Disconnected, _) => self.set(AppModeInner::NoAccessory),
+                (AccessoryEvent::Connected, AppModeInner::NoAccessory) => {
+                    self.set(AppModeInner::Start)
                 }
-                _ => self.acc_idle_pin.set_high(),
+                (AccessoryEvent::Connected, _) => (),
+                // Only a banner from acc_sense_task
+                (AccessoryEvent::Glitched, _) => (),
             }
         }
+
+        pub fn set_idle_signal(&mut self, signal: IdleSignal) {
+            self.accessory_io.set_signal(signal);
+        }
+
+        pub fn emitter_intensity(&self) -> u8 {
+            self.emitter_intensity
+        }
+
+        pub fn set_emitter_intensity(&mut self, intensity_percent: u8) {
+            self.emitter_intensity = intensity_percent;
+            self.update_emitter();
+        }
+
+        /// For the self check, which needs the emitter dark while calibrating
+        pub fn override_emitter(&mut self, intensity_percent: Option<u8>) {
+            self.emitter_override = intensity_percent;
+            self.update_emitter();
+        }
+
+        fn is_sensing(&self) -> bool {
+            matches!(
+                self.inner,
+                AppModeInner::Calibrating
+                    | AppModeInner::Measure
+                    | AppModeInner::Debug
+                    | AppModeInner::Counter
+                    | AppModeInner::Scan
+                    | AppModeInner::FocalPlane
+                    | AppModeInner::Soak
+            ) || self.instant_armed
+        }
+
+        /// The ADC pipeline only runs for these, the linear sensor has its own timer
+        fn is_sampling(&self) -> bool {
+            matches!(
+                self.inner,
+                AppModeInner::Calibrating
+                    | AppModeInner::Measure
+                    | AppModeInner::Debug
+                    | AppModeInner::Counter
+                    | AppModeInner::FocalPlane
+                    | AppModeInner::Soak
+            ) || self.instant_armed
+        }
+
+        // The emitter stays on from calibration through the end of the
+        // measurement so that the calibrated baseline includes its light
+        fn update_emitter(&mut self) {
+            let intensity = match self.emitter_override {
+                Some(intensity) => intensity,
+                None if self.is_sensing() => self.emitter_intensity,
+                None => 0,
+            };
+            self.emitter.set_intensity_percent(intensity);
+        }
     }
 
     #[self_referencing]
     pub struct UsbDevices {
         bus: UsbBusAllocator<UsbBus<USB>>,
+        command_parser: CommandParser,
+        /// The port still at 1200 baud, see [`is_bootloader_touch`]
+        bootloader_touched: bool,
 
         #[borrows(bus)]
         #[covariant]
         pub serial: SerialPort<'this, UsbBus<USB>>,
 
+        #[borrows(bus)]
+        #[covariant]
+        pub dfu: DfuRuntimeClass<'this, UsbBus<USB>>,
+
         #[borrows(bus)]
         #[covariant]
         pub device: UsbDevice<'this, UsbBus<USB>>,
@@ -128,6 +441,8 @@ mod app {
         pub fn make(bus: UsbBusAllocator<UsbBus<USB>>) -> Self {
             let usb = UsbDevicesBuilder {
                 bus,
+                command_parser: CommandParser::default(),
+                bootloader_touched: false,
                 device_builder: |bus| {
                     cortex_m::interrupt::free(|_cs| {
                         UsbDeviceBuilder::new(&bus, UsbVidPid(0x16c0, 0x27dd))
@@ -135,11 +450,13 @@ mod app {
                                 .product("Shutter Speed Tester")
                                 .manufacturer("inbox@null.page")])
                             .unwrap()
-                            .device_class(usbd_serial::USB_CLASS_CDC)
+                            // CDC and DFU runtime interfaces
+                            .composite_with_iads()
                             .build()
                     })
                 },
                 serial_builder: |bus| cortex_m::interrupt::free(|_cs| SerialPort::new(bus)),
+                dfu_builder: |bus| cortex_m::interrupt::free(|_cs| DfuRuntimeClass::new(bus)),
             }
             .build();
 
@@ -149,7 +466,16 @@ mod app {
         }
 
         pub fn poll_serial(&mut self) -> bool {
-            self.with_mut(|s| s.device.poll(&mut [s.serial]))
+            self.with_mut(|s| s.device.poll(&mut [s.serial, s.dfu]))
+        }
+
+        pub fn console_mode(&self) -> ConsoleMode {
+            self.with_serial(|serial| console_mode(serial.line_coding().data_rate()))
+        }
+
+        /// A host has the port open, otherwise logs have nowhere to go
+        pub fn is_listening(&self) -> bool {
+            self.with(|s| s.device.state() == UsbDeviceState::Configured && s.serial.dtr())
         }
     }
 
@@ -161,62 +487,159 @@ mod app {
     #[cfg(not(feature = "usb"))]
     type UsbDevicesImpl = UsbDevicesStub;
 
+    type Rotary = RotaryEncoder<StandardMode, ErasedPin<Input>, ErasedPin<Input>>;
+    type RotarySender = Sender<'static, isize, ROTARY_QUEUE_LEN>;
+
     macro_rules! serial_log {
         ($usb_devices: expr, $slice: expr) => {
             #[cfg(feature = "usb")]
             $usb_devices.lock(|usb| {
-                usb.with_serial_mut(|serial| {
-                    let _ = serial.write($slice);
-                })
+                if usb.console_mode() == ConsoleMode::Text {
+                    usb.with_serial_mut(|serial| {
+                        let _ = serial.write($slice);
+                    })
+                }
             });
         };
     }
 
+    /// Records the cycles spent in `$body`, only runs the body without the `profiling` feature
+    macro_rules! profiled {
+        ($profile: expr, $section: expr, $body: block) => {{
+            #[cfg(feature = "profiling")]
+            let started_at = DWT::cycle_count();
+            $body
+            #[cfg(feature = "profiling")]
+            {
+                let cycles = DWT::cycle_count().wrapping_sub(started_at);
+                $profile.lock(|profile| profile.record($section, cycles));
+            }
+        }};
+    }
+
     #[shared]
     struct Shared {
-        transfer: config::DmaTransfer,
+        /// See `sampling_task`
+        sampler: hw::SamplerType,
         adc_value: u16,
+        adc_peak_hold: PeakHold,
+        oversampler: Oversampler,
         sample_counter: Wrapping<u32>,
         app_mode: AppMode,
         calibration_state: CalibrationState,
         calibration_result: Option<CalibrationResult>,
         measurement: Measurement<CycleCounterClock<{ hw::SYSCLK }>>,
-        display: UnsafeCell<DisplayType>,
+        event_counter: Option<EventCounter<CycleCounterClock<{ hw::SYSCLK }>>>,
+        /// From the last measurement, if the refire watch was on
+        refire_check: Option<RefireCheck>,
+        input_capture: InputCapture,
+        capture_measurement: Option<CaptureMeasurement>,
         beep_sender: Sender<'static, Chirp, 1>,
-        selected_menu_option: usize,
+        error_sender: ErrorSender,
+        /// Shown over the current screen until `error_task` clears it
+        error_toast: Option<AppError>,
+        banner_sender: BannerSender,
+        usb_wake_sender: UsbWakeSender,
+        usb_log: UsbLog,
+        usb_events: UsbEvents,
+        /// Shown at the top of the current screen until `banner_task` clears it
+        banner: Option<BannerMessage>,
+        selected_menu_option: MenuItem,
+        results_page: usize,
         usb_devices: UsbDevicesImpl,
+        settings: Settings,
+        threshold_editor: ThresholdEditor,
+        annotation_editor: AnnotationEditor,
+        history: History,
+        trace_history: TraceHistory,
+        sequence: Option<TestSequence>,
+        usb_export: Option<UsbExport>,
+        wait_for_sync: bool,
+        /// Fires the release output instead of waiting for the shutter, stays on like
+        /// `wait_for_sync`
+        fire_release: bool,
+        /// Makes the next measurement an emitter loopback, taken by `measure_task`
+        self_check: bool,
+        /// Shown by the self check screen
+        loopback_check: Option<LoopbackCheck>,
+        /// Makes the self check learn the accessory gain from its pulse, taken by `measure_task`
+        learn_gain: bool,
+        /// Shown by the gain screen, with the gain it replaced or kept
+        learned_gain: Option<(Result<AccessoryGain, GainFault>, Option<AccessoryGain>)>,
+        /// Exposures caught since the soak started or was reset
+        soak_log: SoakLog,
+        /// From the last measurement calibrated with the emitter as set, see `instant_task`
+        instant_calibration: Option<CalibrationResult>,
+        /// Hands the measurement armed by `instant_task` over to `measure_task`
+        instant_trigger: bool,
+        chart_viewport: ChartViewport,
+        ruler_cursor: RulerCursor,
+        hardware_revision: HardwareRevision,
+        /// Since boot, unlike the history this never drops old entries
+        measurement_count: u32,
+        profile: Profile,
+        debug_page: DebugPage,
+        scan_measurement: Option<ScanMeasurement<SCAN_CHANNELS>>,
+        focal_plane_measurement: Option<ScanMeasurement<FOCAL_PLANE_SENSORS>>,
+        resume_session: Option<Session>,
+        button_input: ButtonInput,
+        adc_faults: AdcFaults,
+        /// Fed every center channel conversion, reset whenever sampling starts
+        stuck_detector: StuckDetector,
+        /// Shown by the sensor fault screen
+        sensor_fault: Option<SensorFault>,
+        clock_fault: Option<ClockFault>,
+        /// Decoded on pin edges rather than polled
+        rotary: Rotary,
+        /// Applied by display_task, stored by session_task
+        display_geometry_editor: DisplayGeometryEditor,
+        /// Saved to the settings when the button is pressed on the tuning screen
+        beeper_tuner: BeeperTuner,
+        /// Kept up to date by `expansion_task`
+        expansion_devices: ExpansionDevices,
     }
 
     #[local]
     struct Local {
-        adc_dma_buffer: Option<&'static mut u16>,
-        timer: config::AdcTimerType,
         measure_button_pin: ErasedPin<Input>,
-        led_pin: ErasedPin<Output>,
+        sync_pin: ErasedPin<Input>,
+        fixture_pin: ErasedPin<Input>,
+        led_flash: LedFlash,
+        release_pin: ErasedPin<Output>,
         beeper: Beeper,
-        rotary: RotaryEncoder<StandardMode, ErasedPin<Input>, ErasedPin<Input>>,
-        measurement_button_last_pressed: <Systick as Monotonic>::Instant,
+        rotary_sender: RotarySender,
+        rotary_settle_sender: RotarySender,
+        last_mode_option: Option<MenuItem>,
         acc_sense_pin: ErasedPin<Input>,
-        debug_calibration_channel_sender: Sender<'static, CalibrationResult, 1>,
-        debug_calibration_channel_receiver: Receiver<'static, CalibrationResult, 1>,
-        measurement_calibration_channel_sender: Sender<'static, CalibrationResult, 1>,
-        measurement_calibration_channel_receiver: Receiver<'static, CalibrationResult, 1>,
+        linear_sensor: LinearSensor<config::LinearSensorSpiType, SCAN_CHANNELS>,
+        scan_timer: config::LinearSensorTimerType,
+        expansion_bus: ExpansionBus<config::ExpansionI2cType>,
+        backup_registers: BackupRegisters,
+        stream_observer: StreamObserver,
+        sampling_view: SnapshotPublisher<SamplingView>,
+        status_view: SnapshotPublisher<StatusView>,
     }
 
+    const USB_EP_MEMORY_WORDS: usize = 1024;
     #[cfg(feature = "usb")]
-    static mut USB_EP_MEMORY: [u32; 1024] = [0; 1024];
-
-    #[global_allocator]
-    static HEAP: Heap = Heap::empty();
+    static mut USB_EP_MEMORY: [u32; USB_EP_MEMORY_WORDS] = [0; USB_EP_MEMORY_WORDS];
 
-    #[init(local = [first_buffer: u16 = 0, _adc_dma_buffer: u16 = 0])]
+    #[init(local = [
+        first_buffer: [u16; hw::ADC_CHANNELS] = [0; hw::ADC_CHANNELS],
+        adc_dma_buffer: [u16; hw::ADC_CHANNELS] = [0; hw::ADC_CHANNELS],
+        burst_buffer: [u16; BURST_LEN] = [0; BURST_LEN],
+    ])]
     fn init(mut cx: init::Context) -> (Shared, Local) {
-        {
-            const HEAP_SIZE: usize = 1024;
-            // unsafe { HEAP.init(super::HEAP as usize, HEAP_SIZE) }
-        }
-
+        // Before anything goes deep, so that the debug memory page sees every frame since
+        memory::paint_stack();
+        // The task attributes need literals, a board that retunes config has to follow suit
+        hw::check_priorities();
         let mut dp: pac::Peripherals = cx.device;
+        let hardware_revision = HardwareRevision::read(&dp.DBGMCU);
+        // Before the clock setup takes RCC
+        let backup_registers = BackupRegisters::new(dp.RTC, &dp.RCC, &dp.PWR);
+        let resume_session = Session::decode(&backup_registers.read())
+            .filter(|session| AppModeInner::from_session_id(session.mode).is_some());
 
         let gpio = AllGpio {
             a: dp.GPIOA.split(),
@@ -253,21 +676,36 @@ mod app {
         let mut syscfg = dp.SYSCFG.constrain();
 
         let clocks = config::setup_clocks!(dp);
+        let clock_fault = hw::clock_fault(&clocks);
 
         CYCCNTClock::<{ hw::SYSCLK }>::init(&mut cx.core.DCB, cx.core.DWT);
 
         let systick_token = create_systick_token!();
         Systick::start(cx.core.SYST, hw::SYSCLK, systick_token);
 
-        let adc = config::setup_adc!(dp, gpio);
-        let transfer = config::setup_adc_dma_transfer!(cx.core, dp, adc, cx.local.first_buffer);
-        let timer = config::setup_adc_timer!(dp, &clocks);
+        let sampler = config::setup_sampler!(
+            cx.core,
+            dp,
+            gpio,
+            &clocks,
+            cx.local.first_buffer,
+            cx.local.adc_dma_buffer,
+            cx.local.burst_buffer
+        );
         let mut delay = config::delay_timer!(dp).delay_us(&clocks);
 
+        let display_geometry = backup_registers
+            .read_display_geometry()
+            .unwrap_or(hw::DISPLAY_GEOMETRY);
+        let flipped = Settings::default().flipped;
         let mut display = {
             Display::new(
-                hw::setup_display!(dp, gpio, &clocks, &mut delay).unwrap(),
+                hw::setup_display!(dp, gpio, &clocks, &mut delay, display_geometry, flipped)
+                    .unwrap(),
                 backlight_pin.erase(),
+                delay,
+                display_geometry,
+                flipped,
             )
         };
 
@@ -276,16 +714,37 @@ mod app {
 
         let mut measure_button_pin = hw::measure_button_pin!(gpio).into_pull_down_input();
         measure_button_pin.make_interrupt_source(&mut syscfg);
-        measure_button_pin.trigger_on_edge(&mut dp.EXTI, Edge::Rising);
+        measure_button_pin.trigger_on_edge(&mut dp.EXTI, Edge::RisingFalling);
         measure_button_pin.enable_interrupt(&mut dp.EXTI);
 
+        let mut sync_pin = hw::sync_pin!(gpio).into_pull_down_input();
+        sync_pin.make_interrupt_source(&mut syscfg);
+        sync_pin.trigger_on_edge(&mut dp.EXTI, Edge::Rising);
+        sync_pin.enable_interrupt(&mut dp.EXTI);
+
+        // Shares the sync input's interrupt
+        let mut rotary_dt_pin = hw::rotary_dt_pin!(gpio).into_pull_up_input().erase();
+        let mut rotary_clk_pin = hw::rotary_clk_pin!(gpio).into_pull_up_input().erase();
+        for pin in [&mut rotary_dt_pin, &mut rotary_clk_pin] {
+            pin.make_interrupt_source(&mut syscfg);
+            pin.trigger_on_edge(&mut dp.EXTI, Edge::RisingFalling);
+            pin.enable_interrupt(&mut dp.EXTI);
+        }
+        let rotary = RotaryEncoder::new(rotary_dt_pin, rotary_clk_pin).into_standard_mode();
+
+        let fixture_pin = hw::fixture_pin!(gpio).into_pull_up_input();
+        let mut release_pin = hw::release_pin!(gpio).into_push_pull_output();
+        release_pin.set_low();
+
         let acc_sense_pin = hw::accessory_sense_pin!(gpio).into_pull_down_input();
-        let mut acc_idle_pin = hw::accessory_idle_signal!(gpio).into_push_pull_output();
-        acc_idle_pin.set_high();
+        let accessory_io = AccessoryIo::new(
+            hw::accessory_idle_signal!(gpio).into_dynamic(),
+            Settings::default().idle_signal,
+        );
 
         led_pin.set_low();
 
-        let display = UnsafeCell::new(display);
+        PANIC_DISPLAY.store(display);
 
         #[cfg(feature = "usb")]
         let usb_bus = UsbBusType::new(
@@ -301,464 +760,3368 @@ mod app {
         );
 
         let beeper = config::setup_sound_pwm!(dp, gpio, &clocks);
+        let emitter = config::setup_emitter_pwm!(dp, gpio, &clocks);
+        let input_capture = config::setup_input_capture!(dp, gpio, &clocks);
+        let linear_sensor = LinearSensor::new(config::setup_linear_sensor_spi!(dp, gpio, &clocks));
+        let scan_timer = config::setup_linear_sensor_timer!(dp, &clocks);
+        let expansion_bus = ExpansionBus::new(config::setup_expansion_i2c!(dp, gpio, &clocks));
         let (beep_tx, beep_rx) = make_channel!(Chirp, 1);
         beeper_task::spawn(beep_rx).unwrap();
+        let (error_tx, error_rx) = make_channel!(AppError, ERROR_QUEUE_LEN);
+        error_task::spawn(error_rx).unwrap();
+        let (banner_tx, banner_rx) = make_channel!(BannerMessage, BANNER_QUEUE_LEN);
+        banner_task::spawn(banner_rx).unwrap();
 
+        let (stream_tx, stream_rx) = make_channel!(StreamEvent, STREAM_QUEUE_LEN);
+        let (usb_wake_tx, usb_wake_rx) = make_channel!((), 1);
         #[cfg(feature = "usb")]
-        usb_task::spawn().unwrap();
+        usb_task::spawn(stream_rx, usb_wake_rx).unwrap();
+        #[cfg(not(feature = "usb"))]
+        let _ = (stream_rx, usb_wake_rx);
 
-        let rotary = RotaryEncoder::new(
-            hw::rotary_dt_pin!(gpio).into_pull_up_input().erase(),
-            hw::rotary_clk_pin!(gpio).into_pull_up_input().erase(),
-        )
-        .into_standard_mode();
-        rotary_encoder_task::spawn().unwrap();
+        let (rotary_tx, rotary_rx) = make_channel!(isize, ROTARY_QUEUE_LEN);
+        rotary_encoder_task::spawn(rotary_rx).unwrap();
+        button_task::spawn().unwrap();
 
         display_task::spawn().unwrap();
         acc_sense_task::spawn().unwrap();
+        fixture_task::spawn().unwrap();
+        instant_task::spawn().unwrap();
+        session_task::spawn().unwrap();
+        clock_check_task::spawn().unwrap();
+        calibration_task::spawn(calibration::init()).unwrap();
+        expansion_task::spawn().unwrap();
 
-        let (debug_calibration_channel_sender, debug_calibration_channel_receiver) =
-            make_channel!(CalibrationResult, 1);
-        let (measurement_calibration_channel_sender, measurement_calibration_channel_receiver) =
-            make_channel!(CalibrationResult, 1);
+        let mut app_mode = AppMode::new(accessory_io, emitter);
+        if resume_session.is_some() {
+            app_mode.set(AppModeInner::Resume);
+        }
 
         (
             Shared {
-                transfer,
+                sampler,
                 adc_value: 0,
+                adc_peak_hold: PeakHold::default(),
+                oversampler: Oversampler::default(),
                 sample_counter: Wrapping(0),
-                app_mode: AppMode::new(acc_idle_pin.erase()),
+                app_mode,
                 calibration_state: CalibrationState::default(),
                 calibration_result: None,
                 measurement: Measurement::new(CalibrationResult::default(), hw::TRIGGER_THRESHOLDS),
-                display,
+                event_counter: None,
+                refire_check: None,
+                input_capture,
+                capture_measurement: None,
                 #[cfg(feature = "usb")]
                 usb_devices: UsbDevices::make(usb_bus),
                 #[cfg(not(feature = "usb"))]
                 usb_devices: UsbDevicesStub,
                 beep_sender: beep_tx,
-                selected_menu_option: 0,
+                error_sender: error_tx,
+                error_toast: None,
+                banner_sender: banner_tx,
+                usb_wake_sender: usb_wake_tx,
+                usb_log: UsbLog::new(),
+                usb_events: UsbEvents::new(),
+                banner: None,
+                selected_menu_option: MenuItem::Measure,
+                results_page: 0,
+                settings: Settings::default(),
+                threshold_editor: ThresholdEditor::default(),
+                annotation_editor: AnnotationEditor::default(),
+                history: History::new(),
+                trace_history: TraceHistory::new(),
+                sequence: None,
+                usb_export: None,
+                wait_for_sync: false,
+                fire_release: false,
+                self_check: false,
+                loopback_check: None,
+                learn_gain: false,
+                learned_gain: None,
+                soak_log: SoakLog::default(),
+                instant_calibration: None,
+                instant_trigger: false,
+                chart_viewport: ChartViewport::default(),
+                ruler_cursor: RulerCursor::default(),
+                hardware_revision,
+                measurement_count: 0,
+                profile: Profile::new(hw::SYSCLK, hw::SAMPLE_RATE_HZ),
+                debug_page: DebugPage::Live,
+                scan_measurement: None,
+                focal_plane_measurement: None,
+                resume_session,
+                button_input: ButtonInput::new(Settings::default().button_timings()),
+                adc_faults: AdcFaults::default(),
+                stuck_detector: StuckDetector::new(hw::SENSOR_STUCK_SAMPLES)
+                    .with_ignored(hw::ADC_RANGE - 1),
+                sensor_fault: None,
+                clock_fault,
+                rotary,
+                display_geometry_editor: DisplayGeometryEditor::new(display_geometry),
+                beeper_tuner: BeeperTuner::new(),
+                expansion_devices: ExpansionDevices::new(),
             },
             Local {
-                adc_dma_buffer: Some(cx.local._adc_dma_buffer),
-                timer,
                 measure_button_pin: measure_button_pin.erase(),
-                led_pin: led_pin.erase(),
+                sync_pin: sync_pin.erase(),
+                fixture_pin: fixture_pin.erase(),
+                led_flash: LedFlash::new(led_pin.erase()),
+                release_pin: release_pin.erase(),
                 beeper,
-                rotary,
-                measurement_button_last_pressed: Systick::now(),
+                rotary_sender: rotary_tx.clone(),
+                rotary_settle_sender: rotary_tx,
+                last_mode_option: None,
                 acc_sense_pin: acc_sense_pin.erase(),
-                debug_calibration_channel_sender,
-                debug_calibration_channel_receiver,
-                measurement_calibration_channel_sender,
-                measurement_calibration_channel_receiver,
+                linear_sensor,
+                scan_timer,
+                expansion_bus,
+                backup_registers,
+                stream_observer: StreamObserver::new(stream_tx),
+                sampling_view: SAMPLING_VIEW.publisher().unwrap(),
+                status_view: STATUS_VIEW.publisher().unwrap(),
             },
         )
     }
 
-    #[task(local=[rotary], shared=[app_mode, selected_menu_option, usb_devices], priority=2)]
-    async fn rotary_encoder_task(mut cx: rotary_encoder_task::Context) {
-        let encoder = cx.local.rotary;
-        loop {
-            encoder.update();
-            match encoder.direction() {
-                Direction::None => (),
-                x => {
-                    serial_log!(cx.shared.usb_devices, b"turned\r\n");
-
-                    let d: isize = match x {
-                        Direction::Clockwise => 1,
-                        Direction::Anticlockwise => -1,
-                        _ => 0,
-                    };
+    #[task(shared=[app_mode, selected_menu_option, results_page, chart_viewport, ruler_cursor, threshold_editor, annotation_editor, sequence, usb_devices, scan_measurement, focal_plane_measurement, resume_session, display_geometry_editor, beeper_tuner, settings], priority=2)]
+    async fn rotary_encoder_task(
+        mut cx: rotary_encoder_task::Context,
+        mut rotary_rx: Receiver<'static, isize, ROTARY_QUEUE_LEN>,
+    ) {
+        hw::check_task_priority(hw::PRIORITY_TASKS);
+        while let Ok(mut d) = rotary_rx.recv().await {
+            serial_log!(cx.shared.usb_devices, b"turned\r\n");
+            if cx.shared.settings.lock(|s| s.flipped) {
+                d = -d;
+            }
 
-                    (&mut cx.shared.app_mode, &mut cx.shared.selected_menu_option).lock(
-                        |app_mode, selected_menu_option| match app_mode.get() {
-                            AppModeInner::Start
-                            | AppModeInner::Calibrating
-                            | AppModeInner::Measure
-                            | AppModeInner::Results
-                            | AppModeInner::Debug => {
-                                app_mode.set(AppModeInner::Menu);
-                            }
-                            AppModeInner::Menu => {
-                                *selected_menu_option = (*selected_menu_option as isize
-                                    + MenuScreen::options_len() as isize
-                                    + d)
-                                    as usize
-                                    % MenuScreen::options_len();
+            match cx.shared.app_mode.lock(|app_mode| app_mode.get()) {
+                AppModeInner::Start
+                | AppModeInner::Calibrating
+                | AppModeInner::Measure
+                | AppModeInner::Counter
+                | AppModeInner::Soak
+                | AppModeInner::Scan
+                | AppModeInner::FocalPlane => {
+                    cx.shared.sequence.lock(|sequence| *sequence = None);
+                    cx.shared.scan_measurement.lock(|m| *m = None);
+                    cx.shared.focal_plane_measurement.lock(|m| *m = None);
+                    cx.shared.app_mode.lock(|app_mode| {
+                        app_mode.set(AppModeInner::Menu);
+                    });
+                }
+                AppModeInner::Sequence => {
+                    if d > 0 {
+                        cx.shared.sequence.lock(|sequence| {
+                            if let Some(sequence) = sequence {
+                                sequence.skip();
                             }
-                            _ => (),
-                        },
-                    );
+                        });
+                    } else {
+                        cx.shared.sequence.lock(|sequence| *sequence = None);
+                        cx.shared.app_mode.lock(|app_mode| {
+                            app_mode.set(AppModeInner::Menu);
+                        });
+                    }
                 }
-            }
-            Systick::delay(1.millis()).await;
-        }
-    }
-
-    #[task(local=[beeper], priority=5)]
-    async fn beeper_task(cx: beeper_task::Context, mut beep_rx: Receiver<'static, Chirp, 1>) {
-        let beeper = cx.local.beeper;
-        while let Ok(chirp) = beep_rx.recv().await {
-            match chirp {
-                Chirp::Startup => {
-                    // Remember
-                    beeper.play(12 + -2, 250).await;
-                    beeper.play(12 + 5, 250).await;
-                    beeper.play(12 + 9, 250).await;
-                    Systick::delay(2000.millis()).await;
+                AppModeInner::Resume => {
+                    cx.shared.resume_session.lock(|session| *session = None);
+                    cx.shared.app_mode.lock(|app_mode| {
+                        app_mode.set(AppModeInner::Start);
+                    });
                 }
-                Chirp::Button => {
-                    beeper.note(9);
-                    Systick::delay(50.millis()).await;
-                    beeper.disable();
+                AppModeInner::Results => {
+                    let page = cx.shared.results_page.lock(|page| *page);
+                    let zoomed = cx.shared.chart_viewport.lock(|v| v.is_zoomed());
+                    let cursor_active = cx.shared.ruler_cursor.lock(|c| c.active);
+                    if zoomed && ResultsScreen::is_zoomable_page(page) {
+                        cx.shared.chart_viewport.lock(|v| v.pan(d));
+                    } else if cursor_active && ResultsScreen::is_cursor_page(page) {
+                        cx.shared.ruler_cursor.lock(|c| c.step(d));
+                    } else {
+                        cx.shared.results_page.lock(|page| {
+                            *page = wrap_index(*page, d, ResultsScreen::pages_len());
+                        });
+                    }
                 }
-                Chirp::Measuring => {
-                    beeper.play(24 - 2, 100).await;
-                    beeper.play(20, 100).await;
+                AppModeInner::Debug => {
+                    let adc_range = cx.shared.settings.lock(|s| s.adc_range());
+                    cx.shared.threshold_editor.lock(|editor| {
+                        editor.adjust(
+                            d as i32 * hw::trigger_adjust_step(adc_range) as i32,
+                            adc_range - 1,
+                        );
+                    });
                 }
-                Chirp::Done => {
-                    beeper.play(12 - 2, 100).await;
-                    beeper.play(24 - 2, 100).await;
+                AppModeInner::BeeperTuning => {
+                    cx.shared.beeper_tuner.lock(|tuner| tuner.adjust(d));
                 }
+                AppModeInner::Menu => match cx.shared.app_mode.lock(|app_mode| app_mode.page()) {
+                    Some(MenuItem::Annotate) => {
+                        cx.shared.annotation_editor.lock(|editor| editor.adjust(d));
+                    }
+                    Some(MenuItem::Display) => {
+                        cx.shared
+                            .display_geometry_editor
+                            .lock(|editor| editor.adjust(d));
+                    }
+                    Some(MenuItem::About) => {
+                        cx.shared.app_mode.lock(AppMode::close_page);
+                    }
+                    Some(_) => (),
+                    None => {
+                        cx.shared.selected_menu_option.lock(|option| {
+                            *option =
+                                MenuItem::ALL[wrap_index(*option as usize, d, MenuItem::ALL.len())];
+                        });
+                    }
+                },
+                _ => (),
             }
         }
     }
 
-    // HWCONFIG
-    #[task(binds = TIM2, shared = [transfer], local = [timer], priority = 3)]
-    fn adcstart(mut cx: adcstart::Context) {
-        cx.shared.transfer.lock(|transfer| {
-            transfer.start(|adc| {
-                adc.start_conversion();
-            });
-        });
-        cx.local.timer.clear_flags(Flag::Update);
+    #[task(shared=[button_input], priority=2)]
+    async fn button_task(mut cx: button_task::Context) {
+        hw::check_task_priority(hw::PRIORITY_TASKS);
+        loop {
+            // Long and double presses are decided by timeouts as well as edges
+            if !cx.shared.button_input.lock(|input| input.is_idle()) {
+                rtic::pend(hal::pac::Interrupt::EXTI2);
+            }
+            Systick::delay(10.millis()).await;
+        }
     }
 
-    // HWCONFIG
-    #[task(binds = EXTI2, shared = [app_mode, beep_sender, selected_menu_option], local=[measure_button_pin, measurement_button_last_pressed, led_pin], priority = 4)]
-    fn measure_button_press(mut cx: measure_button_press::Context) {
-        if (Systick::now() - *cx.local.measurement_button_last_pressed).to_millis() < 100 {
-            cx.local.measure_button_pin.clear_interrupt_pending_bit();
-            return;
-        }
-        *cx.local.measurement_button_last_pressed = Systick::now();
+    fn wrap_index(index: usize, delta: isize, len: usize) -> usize {
+        (index as isize + len as isize + delta) as usize % len
+    }
 
-        cx.shared.beep_sender.lock(|beep_sender| {
-            let _ = beep_sender.try_send(Chirp::Button);
-        });
-        let selected_option = cx
-            .shared
-            .selected_menu_option
-            .lock(|selected_menu_option| *selected_menu_option);
-        cx.shared.app_mode.lock(|app_mode| match app_mode.get() {
-            AppModeInner::Calibrating | AppModeInner::Measure | AppModeInner::Debug => {
-                app_mode.set(AppModeInner::Start);
-            }
-            AppModeInner::Menu => match selected_option {
-                0 => {
-                    let _ = measure_task::spawn();
-                }
-                1 => {
-                    let _ = debug_task::spawn();
-                }
-                2 => {
-                    app_mode.set(AppModeInner::Update);
-                }
-                _ => (),
-            },
-            AppModeInner::Update | AppModeInner::None | AppModeInner::NoAccessory => (),
-            AppModeInner::Start | AppModeInner::Results => {
-                let _ = measure_task::spawn();
+    #[task(local=[beeper], shared=[settings], priority=5)]
+    async fn beeper_task(mut cx: beeper_task::Context, mut beep_rx: Receiver<'static, Chirp, 1>) {
+        hw::check_task_priority(hw::PRIORITY_BEEPER);
+        while let Ok(chirp) = beep_rx.recv().await {
+            let (profile, offset) = cx
+                .shared
+                .settings
+                .lock(|s| (s.sound_profile, s.beeper_offset));
+            if let Chirp::Tuning(offset) = chirp {
+                cx.local
+                    .beeper
+                    .play_tune(sound::TUNE_TUNING, offset as isize)
+                    .await;
+            } else {
+                cx.local
+                    .beeper
+                    .play_tune(profile.tune(chirp), offset as isize)
+                    .await;
             }
-        });
-        cx.local.measure_button_pin.clear_interrupt_pending_bit();
+        }
     }
 
-    // HWCONFIG
-    #[task(binds = DMA2_STREAM0, shared = [transfer, adc_value, sample_counter, calibration_state, measurement], local = [adc_dma_buffer], priority = 5)]
-    fn dma(cx: dma::Context) {
-        let mut shared = cx.shared;
-        let local = cx.local;
+    #[task(shared=[beep_sender, usb_devices, error_toast], priority=1)]
+    async fn error_task(
+        mut cx: error_task::Context,
+        mut error_rx: Receiver<'static, AppError, ERROR_QUEUE_LEN>,
+    ) {
+        hw::check_task_priority(hw::PRIORITY_UI);
+        #[cfg(feature = "usb")]
+        let mut usb_devices = cx.shared.usb_devices;
+        while let Ok(error) = error_rx.recv().await {
+            #[cfg(feature = "usb")]
+            {
+                let mut s = String::<64>::default();
+                uwrite!(s, "Error: {}\r\n", error.label()).unwrap();
+                serial_log!(usb_devices, s.as_bytes());
+            }
+            cx.shared.beep_sender.lock(|beep_sender| {
+                let _ = beep_sender.try_send(Chirp::Error);
+            });
 
-        let last_adc_dma_buffer = shared.transfer.lock(|transfer| {
-            let (last_adc_dma_buffer, _) = transfer
-                .next_transfer(local.adc_dma_buffer.take().unwrap())
-                .unwrap();
-            last_adc_dma_buffer
-        });
+            // Errors queued meanwhile get their turn afterwards
+            cx.shared.error_toast.lock(|toast| *toast = Some(error));
+            Systick::delay(TOAST_DURATION_MS.millis()).await;
+            cx.shared.error_toast.lock(|toast| *toast = None);
+        }
+    }
+
+    #[task(shared=[banner], priority=1)]
+    async fn banner_task(
+        mut cx: banner_task::Context,
+        mut banner_rx: Receiver<'static, BannerMessage, BANNER_QUEUE_LEN>,
+    ) {
+        hw::check_task_priority(hw::PRIORITY_UI);
+        // Queued ones get their turn afterwards
+        while let Ok(message) = banner_rx.recv().await {
+            cx.shared.banner.lock(|banner| *banner = Some(message));
+            Systick::delay(message.timeout_ms.millis()).await;
+            cx.shared.banner.lock(|banner| *banner = None);
+        }
+    }
 
-        let value = *last_adc_dma_buffer;
-        // Return adc_dma_buffer to resources pool for next transfer
-        *local.adc_dma_buffer = Some(last_adc_dma_buffer);
+    // HWCONFIG
+    #[task(binds = TIM2, shared = [sampler], priority = 3)]
+    fn adcstart(mut cx: adcstart::Context) {
+        cx.shared.sampler.lock(|sampler| sampler.trigger());
+    }
 
+    /// Spawned by [`AppMode::set`], the pipeline is only noise and current draw
+    /// on the screens that don't measure
+    #[task(shared = [sampler, oversampler, stuck_detector], priority = 5)]
+    async fn sampling_task(cx: sampling_task::Context, running: bool) {
+        hw::check_task_priority(hw::PRIORITY_ADC_DMA);
         (
-            shared.adc_value,
-            shared.calibration_state,
-            shared.measurement,
-            shared.sample_counter,
+            cx.shared.sampler,
+            cx.shared.oversampler,
+            cx.shared.stuck_detector,
         )
-            .lock(
-                |adc_value, calibration_state, measurement, sample_counter| {
-                    if let CalibrationState::InProgress { .. } = calibration_state {
-                        calibration_state.step(value)
-                    } else {
-                        measurement.step(value);
-                    }
-                    *adc_value = value;
-                    *sample_counter += Wrapping(1);
-                },
-            );
+            .lock(|sampler, oversampler, stuck_detector| {
+                if sampler.is_running() == running {
+                    return;
+                }
+                if running {
+                    // A partial sum from before the pause would skew the first value
+                    *oversampler = Oversampler::new(oversampler.factor());
+                    stuck_detector.reset();
+                    sampler.start();
+                } else {
+                    sampler.stop();
+                }
+            });
     }
 
-    #[task(shared=[app_mode], local=[acc_sense_pin], priority=2)]
-    async fn acc_sense_task(mut cx: acc_sense_task::Context) {
-        let mut last_state = cx.local.acc_sense_pin.is_high();
-        // TODO use adc
-        loop {
-            let state = cx.local.acc_sense_pin.is_high();
-            Systick::delay(250.millis()).await;
-
-            if !state {
-                cx.shared.app_mode.lock(|app_mode| {
-                    app_mode.set(AppModeInner::NoAccessory);
-                });
-            }
-
-            if state != last_state {
-                last_state = state;
-
-                if state {
-                    cx.shared.app_mode.lock(|app_mode| {
-                        app_mode.set(AppModeInner::Start);
-                    });
-                }
-            }
-        }
+    /// After the resolution changed in the menu or over USB. The converter takes it up
+    /// the next time sampling starts, calibrations from before are in the wrong counts
+    #[task(shared = [sampler, stuck_detector, instant_calibration], priority = 2)]
+    async fn adc_resolution_task(mut cx: adc_resolution_task::Context, resolution: Resolution) {
+        hw::check_task_priority(hw::PRIORITY_TASKS);
+        cx.shared
+            .sampler
+            .lock(|sampler| sampler.set_resolution(hw::adc_bits(resolution)));
+        cx.shared.stuck_detector.lock(|stuck_detector| {
+            *stuck_detector = StuckDetector::new(hw::SENSOR_STUCK_SAMPLES)
+                .with_ignored(hw::adc_range(resolution) - 1);
+        });
+        cx.shared.instant_calibration.lock(|c| *c = None);
     }
 
-    #[task(shared = [app_mode, calibration_result, calibration_state], priority = 3)]
-    async fn calibration_task(
-        mut cx: calibration_task::Context,
-        mut sender: Sender<'static, CalibrationResult, 1>,
-    ) {
-        cx.shared.calibration_result.lock(|r| *r = None);
+    /// After high gain was switched, by hand or with a trigger profile. Taken up like
+    /// the resolution, and calibrations from before saw another sensor response.
+    #[task(shared = [sampler, instant_calibration], priority = 2)]
+    async fn adc_sample_time_task(mut cx: adc_sample_time_task::Context, sample_time: SampleTime) {
+        hw::check_task_priority(hw::PRIORITY_TASKS);
+        cx.shared
+            .sampler
+            .lock(|sampler| sampler.set_sample_cycles(hw::sample_cycles(sample_time)));
+        cx.shared.instant_calibration.lock(|c| *c = None);
+    }
 
+    /// Leaves whatever was sampling for the fault screen, a measurement in
+    /// progress cancels itself on the mode change
+    #[task(shared = [app_mode, sensor_fault], priority = 2)]
+    async fn sensor_fault_task(mut cx: sensor_fault_task::Context, fault: SensorFault) {
+        hw::check_task_priority(hw::PRIORITY_TASKS);
+        cx.shared.sensor_fault.lock(|f| *f = Some(fault));
         cx.shared.app_mode.lock(|app_mode| {
-            app_mode.set(AppModeInner::Calibrating);
+            // Unplugged in the meantime, or already on the way out
+            if app_mode.is_sampling() {
+                app_mode.set(AppModeInner::SensorFault);
+            }
         });
+    }
 
-        // Let the system settle a bit
-        Systick::delay(250.millis()).await;
+    /// Times the cycle counter against SysTick once at startup. Both count the core
+    /// clock, so they disagree when either is set up for a rate it doesn't run at.
+    #[task(shared = [app_mode, clock_fault], priority = 2)]
+    async fn clock_check_task(mut cx: clock_check_task::Context) {
+        hw::check_task_priority(hw::PRIORITY_TASKS);
+        // Both ends right after a tick, the wakeup delays cancel out
+        let started_at = Systick::now() + 1.millis();
+        Systick::delay_until(started_at).await;
+        let start_cycles = DWT::cycle_count();
+        Systick::delay_until(started_at + hw::CLOCK_CHECK_INTERVAL_MS.millis()).await;
+        let cycles = DWT::cycle_count().wrapping_sub(start_cycles);
 
-        cx.shared.calibration_state.lock(|calibration_state| {
-            calibration_state.begin();
+        let measured = check_clock_rate(
+            cycles,
+            hw::CLOCK_CHECK_INTERVAL_MS * 1000,
+            hw::SYSCLK,
+            hw::CLOCK_TOLERANCE_PPM,
+        );
+        // What init found in the clock setup comes first
+        let fault = cx.shared.clock_fault.lock(|fault| {
+            *fault = fault.or(measured);
+            *fault
         });
-
-        let calibration_result = loop {
-            Systick::delay(100.millis()).await;
-            let result = cx.shared.calibration_state.lock(|state| match state {
-                CalibrationState::InProgress { .. } => None,
-                CalibrationState::Done(result) => Some(result.clone()),
+        if fault.is_some() {
+            cx.shared.app_mode.lock(|app_mode| {
+                app_mode.set(AppModeInner::ClockFault);
             });
-            if let Some(result) = result {
-                break result;
-            }
-        };
-
-        sender.send(calibration_result).await.unwrap();
+        }
     }
 
-    #[task(
-        shared=[app_mode, adc_value, measurement, beep_sender, usb_devices],
-        local=[measurement_calibration_channel_receiver, measurement_calibration_channel_sender],
-        priority=2,
-    )]
-    async fn measure_task(mut cx: measure_task::Context) {
+    /// Scans the expansion bus once, then polls the addresses with a driver for
+    /// accessories coming and going, logging `I2C:FOUND` and `I2C:LOST <address> <device>`
+    #[task(shared = [expansion_devices, usb_devices], local = [expansion_bus], priority = 1)]
+    async fn expansion_task(mut cx: expansion_task::Context) {
+        hw::check_task_priority(hw::PRIORITY_UI);
         #[cfg(feature = "usb")]
         let mut usb_devices = cx.shared.usb_devices;
+        let mut devices = cx.local.expansion_bus.scan();
+        loop {
+            #[cfg(feature = "usb")]
+            {
+                use core::fmt::Write;
 
-        calibration_task::spawn(cx.local.measurement_calibration_channel_sender.clone()).unwrap();
-        let result = cx
-            .local
-            .measurement_calibration_channel_receiver
-            .recv()
-            .await
-            .unwrap();
+                // Only this task changes them
+                let previous = cx.shared.expansion_devices.lock(|d| d.clone());
+                let lost = previous.iter().filter(|d| !devices.contains(d));
+                let found = devices.iter().filter(|d| !previous.contains(d));
+                for (event, (address, device)) in
+                    lost.map(|d| ("LOST", d)).chain(found.map(|d| ("FOUND", d)))
+                {
+                    let mut s = String::<48>::default();
+                    write!(s, "I2C:{} {:#04X} {}\r\n", event, address, device.label()).unwrap();
+                    serial_log!(usb_devices, s.as_bytes());
+                }
+            }
+            cx.shared.expansion_devices.lock(|d| *d = devices.clone());
 
-        cx.shared.beep_sender.lock(|beep_sender| {
-            let _ = beep_sender.try_send(Chirp::Measuring);
-        });
+            Systick::delay(hw::EXPANSION_POLL_MS.millis()).await;
+            devices = cx.local.expansion_bus.poll(&devices);
+        }
+    }
 
-        #[cfg(feature = "usb")]
+    // HWCONFIG
+    #[task(binds = TIM5, shared = [scan_measurement], local = [linear_sensor, scan_timer], priority = 3)]
+    fn linear_sensor_sweep(mut cx: linear_sensor_sweep::Context) {
+        // The sweep runs outside the lock, it takes a while with all channels
+        if cx
+            .shared
+            .scan_measurement
+            .lock(|m| m.as_ref().is_some_and(|m| !m.is_done()))
         {
-            let mut s = String::<128>::default();
-            uwrite!(s, "Calibrated to: {}\r\n", result).unwrap();
-            serial_log!(usb_devices, s.as_bytes());
+            if let Ok(values) = cx.local.linear_sensor.sweep() {
+                cx.shared.scan_measurement.lock(|m| {
+                    if let Some(m) = m {
+                        m.step(&values);
+                    }
+                });
+            }
         }
+        cx.local.scan_timer.clear_flags(Flag::Update);
+    }
 
-        cx.shared.measurement.lock(|measurement| {
-            *measurement = Measurement::new(result, hw::TRIGGER_THRESHOLDS);
-        });
+    // HWCONFIG
+    #[task(binds = EXTI2, shared = [app_mode, adc_peak_hold, oversampler, beep_sender, selected_menu_option, settings, threshold_editor, annotation_editor, sequence, event_counter, soak_log, wait_for_sync, fire_release, results_page, chart_viewport, ruler_cursor, profile, debug_page, scan_measurement, focal_plane_measurement, resume_session, button_input, display_geometry_editor, beeper_tuner], local=[measure_button_pin, last_mode_option], priority = 4)]
+    fn measure_button_press(mut cx: measure_button_press::Context) {
+        // Also pended by button_task to time out gestures, without an edge
+        cx.local.measure_button_pin.clear_interrupt_pending_bit();
+        let pressed = cx.local.measure_button_pin.is_high();
+        let now_ms = (Systick::now() - <Systick as Monotonic>::ZERO).to_millis();
+        let Some(gesture) = cx
+            .shared
+            .button_input
+            .lock(|input| input.update(pressed, now_ms))
+        else {
+            return;
+        };
 
-        cx.shared.app_mode.lock(|app_mode| {
-            app_mode.set(AppModeInner::Measure);
+        cx.shared.beep_sender.lock(|beep_sender| {
+            let _ = beep_sender.try_send(Chirp::Button);
         });
-
-        loop {
-            if cx.shared.app_mode.lock(|app_mode| app_mode.get()) != AppModeInner::Measure {
+        match gesture {
+            ButtonGesture::Short => button_short_press(&mut cx),
+            ButtonGesture::Long => button_long_press(&mut cx),
+            ButtonGesture::Double => {
+                if let Some(option) = *cx.local.last_mode_option {
+                    activate_menu_option(&mut cx, option);
+                }
+            }
+        }
+    }
+
+    fn button_short_press(cx: &mut measure_button_press::Context) {
+        let selected_option = cx
+            .shared
+            .selected_menu_option
+            .lock(|selected_menu_option| *selected_menu_option);
+        // Cancelling a measurement during a sequence goes back to its prompt
+        let idle_mode = if cx.shared.sequence.lock(|sequence| sequence.is_some()) {
+            AppModeInner::Sequence
+        } else {
+            AppModeInner::Start
+        };
+        match cx.shared.app_mode.lock(|app_mode| app_mode.get()) {
+            AppModeInner::Calibrating => {
+                // calibration_task picks up the mode change and resets its state
+                cx.shared.app_mode.lock(|app_mode| {
+                    app_mode.set(idle_mode);
+                });
+                cx.shared.beep_sender.lock(|beep_sender| {
+                    let _ = beep_sender.try_send(Chirp::Cancel);
+                });
+            }
+            AppModeInner::Measure => {
+                cx.shared.app_mode.lock(|app_mode| {
+                    app_mode.set(idle_mode);
+                });
+            }
+            AppModeInner::Debug => debug_button_short_press(cx),
+            AppModeInner::Counter => {
+                cx.shared.event_counter.lock(|event_counter| {
+                    if let Some(event_counter) = event_counter {
+                        event_counter.reset();
+                    }
+                });
+            }
+            AppModeInner::Soak => {
+                cx.shared.soak_log.lock(|log| *log = SoakLog::default());
+            }
+            AppModeInner::Menu => match cx.shared.app_mode.lock(|app_mode| app_mode.page()) {
+                Some(MenuItem::Annotate) => {
+                    if cx
+                        .shared
+                        .annotation_editor
+                        .lock(|editor| editor.select_next())
+                    {
+                        cx.shared.app_mode.lock(|app_mode| {
+                            app_mode.set(AppModeInner::Start);
+                        });
+                    }
+                }
+                Some(MenuItem::Display) => {
+                    cx.shared
+                        .display_geometry_editor
+                        .lock(DisplayGeometryEditor::select_next);
+                }
+                Some(_) => {
+                    cx.shared.app_mode.lock(AppMode::close_page);
+                }
+                None => {
+                    activate_menu_option(cx, selected_option);
+                    if selected_option.is_mode() {
+                        *cx.local.last_mode_option = Some(selected_option);
+                    }
+                }
+            },
+            AppModeInner::Sequence => {
+                if cx
+                    .shared
+                    .sequence
+                    .lock(|sequence| sequence.as_ref().is_some_and(TestSequence::is_done))
+                {
+                    cx.shared.sequence.lock(|sequence| *sequence = None);
+                    cx.shared.app_mode.lock(|app_mode| {
+                        app_mode.set(AppModeInner::Start);
+                    });
+                } else {
+                    let _ = measure_task::spawn();
+                }
+            }
+            AppModeInner::Scan => {
+                if cx
+                    .shared
+                    .scan_measurement
+                    .lock(|m| m.as_ref().is_some_and(ScanMeasurement::is_done))
+                {
+                    let _ = scan_measure_task::spawn();
+                } else {
+                    // scan_measure_task picks up the mode change and drops the measurement
+                    cx.shared.app_mode.lock(|app_mode| {
+                        app_mode.set(AppModeInner::Start);
+                    });
+                }
+            }
+            AppModeInner::FocalPlane => {
+                if cx
+                    .shared
+                    .focal_plane_measurement
+                    .lock(|m| m.as_ref().is_some_and(ScanMeasurement::is_done))
+                {
+                    let _ = focal_plane_task::spawn();
+                } else {
+                    // focal_plane_task picks up the mode change and drops the measurement
+                    cx.shared.app_mode.lock(|app_mode| {
+                        app_mode.set(AppModeInner::Start);
+                    });
+                }
+            }
+            AppModeInner::Resume => resume_previous_session(cx),
+            AppModeInner::SensorFault
+            | AppModeInner::ClockFault
+            | AppModeInner::SelfCheck
+            | AppModeInner::Gain => {
+                cx.shared.app_mode.lock(|app_mode| {
+                    app_mode.set(AppModeInner::Start);
+                });
+            }
+            AppModeInner::BeeperTuning => {
+                // beeper_tuning_task stops with the mode change
+                let offset = cx.shared.beeper_tuner.lock(|tuner| tuner.offset);
+                cx.shared.settings.lock(|s| s.beeper_offset = offset);
+                cx.shared.app_mode.lock(|app_mode| {
+                    app_mode.set(AppModeInner::Menu);
+                });
+            }
+            AppModeInner::Rebooting | AppModeInner::None | AppModeInner::NoAccessory => (),
+            // The chart page uses the button for zoom and the ruler page for its cursor,
+            // measuring again works from the other pages
+            AppModeInner::Results
+                if ResultsScreen::is_zoomable_page(cx.shared.results_page.lock(|page| *page)) =>
+            {
+                cx.shared.chart_viewport.lock(ChartViewport::cycle_zoom);
+            }
+            AppModeInner::Results
+                if ResultsScreen::is_cursor_page(cx.shared.results_page.lock(|page| *page)) =>
+            {
+                cx.shared.ruler_cursor.lock(RulerCursor::toggle);
+            }
+            AppModeInner::Results if idle_mode == AppModeInner::Sequence => {
+                cx.shared.app_mode.lock(|app_mode| {
+                    app_mode.set(AppModeInner::Sequence);
+                });
+            }
+            AppModeInner::Start | AppModeInner::Results => {
+                let _ = measure_task::spawn();
+            }
+        }
+    }
+
+    /// Leaves for the menu from anywhere, the debug mode resets its peaks instead
+    fn button_long_press(cx: &mut measure_button_press::Context) {
+        match cx.shared.app_mode.lock(|app_mode| app_mode.get()) {
+            AppModeInner::Debug => {
+                cx.shared.adc_peak_hold.lock(PeakHold::reset);
+                cx.shared.profile.lock(Profile::reset);
+            }
+            AppModeInner::Menu => cx.shared.app_mode.lock(|app_mode| match app_mode.page() {
+                // Holding is the confirmation
+                Some(MenuItem::UsbUpdate) => app_mode.set(AppModeInner::Rebooting),
+                Some(_) => app_mode.close_page(),
+                None => (),
+            }),
+            AppModeInner::Resume
+            | AppModeInner::Rebooting
+            | AppModeInner::None
+            | AppModeInner::NoAccessory => (),
+            _ => {
+                // Running tasks pick up the mode change and cancel themselves
+                cx.shared.sequence.lock(|sequence| *sequence = None);
+                cx.shared.scan_measurement.lock(|m| *m = None);
+                cx.shared.focal_plane_measurement.lock(|m| *m = None);
+                cx.shared.app_mode.lock(|app_mode| {
+                    app_mode.set(AppModeInner::Menu);
+                });
+            }
+        }
+    }
+
+    fn activate_menu_option(cx: &mut measure_button_press::Context, item: MenuItem) {
+        match item {
+            MenuItem::Measure => {
+                cx.shared.wait_for_sync.lock(|w| *w = false);
+                cx.shared.fire_release.lock(|f| *f = false);
+                let _ = measure_task::spawn();
+            }
+            MenuItem::Debug => {
+                let _ = debug_task::spawn();
+            }
+            MenuItem::Counter => {
+                let _ = counter_task::spawn();
+            }
+            MenuItem::Digital => {
+                let _ = digital_measure_task::spawn();
+            }
+            MenuItem::Sequence => {
+                cx.shared.wait_for_sync.lock(|w| *w = false);
+                cx.shared.fire_release.lock(|f| *f = false);
+                let rejection = cx.shared.settings.lock(|s| s.outlier_rejection);
+                cx.shared.sequence.lock(|sequence| {
+                    *sequence = Some(
+                        TestSequence::new(&hw::TEST_SEQUENCE_SPEEDS).with_rejection(rejection),
+                    );
+                });
+                cx.shared.app_mode.lock(|app_mode| {
+                    app_mode.set(AppModeInner::Sequence);
+                });
+            }
+            MenuItem::Annotate | MenuItem::Display | MenuItem::About | MenuItem::UsbUpdate => {
+                cx.shared.app_mode.lock(|app_mode| app_mode.open_page(item));
+            }
+            MenuItem::Profile => {
+                let (oversampling, sample_time) = cx.shared.settings.lock(|s| {
+                    s.cycle_trigger_profile();
+                    (s.oversampling(), s.sample_time())
+                });
+                cx.shared.oversampler.lock(|oversampler| {
+                    *oversampler = Oversampler::new(oversampling);
+                });
+                let _ = adc_sample_time_task::spawn(sample_time);
+            }
+            MenuItem::Emit => {
+                let intensity = cx.shared.settings.lock(|s| s.cycle_emitter_intensity());
+                cx.shared.app_mode.lock(|app_mode| {
+                    app_mode.set_emitter_intensity(intensity);
+                });
+            }
+            MenuItem::Sensitivity => {
+                let oversampling = cx.shared.settings.lock(|s| {
+                    s.cycle_sensitivity();
+                    s.oversampling()
+                });
+                cx.shared.oversampler.lock(|oversampler| {
+                    *oversampler = Oversampler::new(oversampling);
+                });
+            }
+            MenuItem::ReleaseLag => {
+                // Stays on for repeated measurements from the results screen
+                cx.shared.wait_for_sync.lock(|w| *w = true);
+                cx.shared.fire_release.lock(|f| *f = false);
+                let _ = measure_task::spawn();
+            }
+            MenuItem::Sound => {
+                cx.shared.settings.lock(|s| s.cycle_sound_profile());
+            }
+            MenuItem::Rolling => {
+                let _ = scan_measure_task::spawn();
+            }
+            MenuItem::Fx => {
+                cx.shared.settings.lock(|s| s.cycle_transition());
+            }
+            MenuItem::Dither => {
+                cx.shared.settings.lock(|s| s.cycle_fx_intensity());
+            }
+            MenuItem::AutoLow => {
+                cx.shared.settings.lock(|s| s.toggle_auto_trigger_low());
+            }
+            MenuItem::ThreePoint => {
+                let _ = focal_plane_task::spawn();
+            }
+            MenuItem::Hold | MenuItem::Double => {
+                let timings = cx.shared.settings.lock(|s| {
+                    if item == MenuItem::Hold {
+                        s.cycle_long_press();
+                    } else {
+                        s.cycle_double_press();
+                    }
+                    s.button_timings()
+                });
+                cx.shared
+                    .button_input
+                    .lock(|input| input.set_timings(timings));
+            }
+            MenuItem::Speeds => {
+                cx.shared.settings.lock(|s| s.cycle_speed_table());
+            }
+            MenuItem::Timing => {
+                cx.shared.settings.lock(|s| s.cycle_duration_method());
+            }
+            MenuItem::Fixture => {
+                cx.shared.settings.lock(|s| s.cycle_fixture_settle());
+            }
+            MenuItem::Tolerance => {
+                cx.shared.settings.lock(|s| s.cycle_tolerance());
+            }
+            MenuItem::FastTolerance => {
+                cx.shared.settings.lock(|s| s.cycle_fast_tolerance());
+            }
+            MenuItem::Flip => {
+                cx.shared.settings.lock(|s| s.toggle_flipped());
+            }
+            MenuItem::Refire => {
+                cx.shared.settings.lock(|s| s.cycle_refire_watch());
+            }
+            MenuItem::QuickCal => {
+                cx.shared.settings.lock(|s| s.toggle_quick_recal());
+            }
+            MenuItem::Outliers => {
+                cx.shared.settings.lock(|s| s.cycle_outlier_rejection());
+            }
+            MenuItem::FireLag => {
+                // Stays on for repeated measurements from the results screen
+                cx.shared.wait_for_sync.lock(|w| *w = false);
+                cx.shared.fire_release.lock(|f| *f = true);
+                let _ = measure_task::spawn();
+            }
+            MenuItem::SelfCheck => {
+                let _ = self_check_task::spawn(false);
+            }
+            MenuItem::Soak => {
+                let _ = soak_task::spawn();
+            }
+            MenuItem::AccIdle => {
+                let signal = cx.shared.settings.lock(|s| s.cycle_idle_signal());
+                cx.shared.app_mode.lock(|app_mode| {
+                    app_mode.set_idle_signal(signal);
+                });
+            }
+            MenuItem::Instant => {
+                cx.shared.settings.lock(|s| s.toggle_instant());
+            }
+            MenuItem::Adc => {
+                let resolution = cx.shared.settings.lock(|s| s.cycle_adc_resolution());
+                let _ = adc_resolution_task::spawn(resolution);
+            }
+            MenuItem::HighGain => {
+                let sample_time = cx.shared.settings.lock(|s| {
+                    s.toggle_high_gain();
+                    s.sample_time()
+                });
+                let _ = adc_sample_time_task::spawn(sample_time);
+            }
+            MenuItem::Burst => {
+                cx.shared.settings.lock(|s| s.toggle_burst_capture());
+            }
+            MenuItem::Beeper => {
+                let _ = beeper_tuning_task::spawn();
+            }
+            MenuItem::Learn => {
+                let _ = self_check_task::spawn(true);
+            }
+        }
+    }
+
+    fn resume_previous_session(cx: &mut measure_button_press::Context) {
+        let Some(session) = cx.shared.resume_session.lock(Option::take) else {
+            return;
+        };
+        match (
+            AppModeInner::from_session_id(session.mode),
+            session.sequence,
+        ) {
+            (Some(AppModeInner::Sequence), Some(sequence)) => {
+                cx.shared.wait_for_sync.lock(|w| *w = false);
+                cx.shared.fire_release.lock(|f| *f = false);
+                // Shots of an unfinished step are lost either way
+                let rejection = cx.shared.settings.lock(|s| s.outlier_rejection);
+                cx.shared
+                    .sequence
+                    .lock(|s| *s = Some(sequence.with_rejection(rejection)));
+                cx.shared.app_mode.lock(|app_mode| {
+                    app_mode.set(AppModeInner::Sequence);
+                });
+            }
+            (Some(AppModeInner::Counter), _) => {
+                let _ = counter_task::spawn();
+            }
+            (Some(AppModeInner::Debug), _) => {
+                let _ = debug_task::spawn();
+            }
+            (Some(AppModeInner::Scan), _) => {
+                let _ = scan_measure_task::spawn();
+            }
+            (Some(AppModeInner::Soak), _) => {
+                let _ = soak_task::spawn();
+            }
+            _ => {
+                cx.shared.app_mode.lock(|app_mode| {
+                    app_mode.set(AppModeInner::Start);
+                });
+            }
+        }
+    }
+
+    fn debug_button_short_press(cx: &mut measure_button_press::Context) {
+        let next_page = match cx.shared.debug_page.lock(|p| *p) {
+            DebugPage::Live => None,
+            DebugPage::Timing => Some(DebugPage::Memory),
+            DebugPage::Memory => {
+                cx.shared.debug_page.lock(|p| *p = DebugPage::Live);
+                cx.shared.app_mode.lock(|app_mode| {
+                    app_mode.set(AppModeInner::Start);
+                });
+                return;
+            }
+        };
+        if let Some(page) = next_page {
+            cx.shared.debug_page.lock(|p| *p = page);
+            return;
+        }
+
+        let done = (&mut cx.shared.settings, &mut cx.shared.threshold_editor).lock(
+            |settings, threshold_editor| {
+                if !threshold_editor.select_next() {
+                    return false;
+                }
+                if threshold_editor.changed {
+                    settings.trigger_thresholds = threshold_editor.thresholds();
+                }
+                true
+            },
+        );
+        if done {
+            // The timing page comes after the thresholds, then the memory one
+            let page = if cfg!(feature = "profiling") {
+                DebugPage::Timing
+            } else {
+                DebugPage::Memory
+            };
+            cx.shared.debug_page.lock(|p| *p = page);
+        }
+    }
+
+    // HWCONFIG
+    #[task(binds = DMA2_STREAM0, shared = [sampler, adc_value, adc_peak_hold, oversampler, sample_counter, calibration_state, measurement, event_counter, profile, error_sender, focal_plane_measurement, adc_faults, stuck_detector], local = [stream_observer, sampling_view, status_view, led_flash, burst_started_at: Option<fugit::TimerInstantU64<{ hw::SYSCLK }>> = None], priority = 5)]
+    fn dma(cx: dma::Context) {
+        let mut shared = cx.shared;
+        let stream_observer = cx.local.stream_observer;
+        let sampling_view = cx.local.sampling_view;
+        let status_view = cx.local.status_view;
+        let led_flash = cx.local.led_flash;
+        let burst_started_at = cx.local.burst_started_at;
+
+        profiled!(shared.profile, ProfiledSection::Dma, {
+            let values = match shared.sampler.lock(|sampler| sampler.next_scan()) {
+                Ok(Some(values)) => values,
+                // Cut short by sampling_task, nothing went missing, or the burst is in
+                Ok(None) => {
+                    if let Some(started_at) = burst_started_at.take() {
+                        (&mut shared.sampler, &mut shared.measurement).lock(
+                            |sampler, measurement| {
+                                sampler.take_burst(|samples, interval_nanos| {
+                                    measurement.merge_burst(
+                                        samples,
+                                        started_at,
+                                        interval_nanos,
+                                        stream_observer,
+                                    )
+                                })
+                            },
+                        );
+                    }
+                    return;
+                }
+                Err(SamplerError::NoBuffer) => {
+                    report_error(&mut shared.error_sender, AppError::Dma);
+                    return;
+                }
+                Err(error) => {
+                    discard_adc_buffer(&mut shared, error == SamplerError::Transfer);
+                    return;
+                }
+            };
+
+            // The side sensors only matter to the focal plane accessory, it doesn't oversample
+            shared.focal_plane_measurement.lock(|m| {
+                if let Some(m) = m {
+                    m.step(&values);
+                }
+            });
+            let value = values[FOCAL_PLANE_CENTER];
+
+            if let Some(fault) = shared.stuck_detector.lock(|d| d.step(value)) {
+                shared.adc_faults.lock(|faults| faults.stuck += 1);
+                let _ = sensor_fault_task::spawn(fault);
+            }
+
+            // Partial oversampling sums still count towards the handler time
+            if let Some(value) = shared
+                .oversampler
+                .lock(|oversampler| oversampler.push(value))
+            {
+                let (opened, publish_status) = (
+                    shared.adc_value,
+                    shared.calibration_state,
+                    shared.measurement,
+                    &mut shared.event_counter,
+                    shared.sample_counter,
+                )
+                    .lock(
+                        |adc_value,
+                         calibration_state,
+                         measurement,
+                         event_counter,
+                         sample_counter| {
+                            let was_armed = measurement.phase() == MeasurementPhase::Armed;
+                            if calibration_state.is_in_progress() {
+                                calibration_state.step(value)
+                            } else if let Some(event_counter) = event_counter {
+                                event_counter.step(value);
+                            } else {
+                                measurement.step_observed(value, stream_observer);
+                            }
+                            *adc_value = value;
+                            *sample_counter += Wrapping(1);
+                            let phase = measurement.phase();
+                            led_flash.step(phase);
+                            // The screens read this instead of locking out the handler
+                            sampling_view.publish(SamplingView {
+                                adc_value: value,
+                                phase,
+                                waiting_for_sync: measurement.is_waiting_for_sync(),
+                                waiting_for_release: measurement.is_waiting_for_release(),
+                                calibration_stage: calibration_state.stage(),
+                                calibration_progress: calibration_state.progress(),
+                                calibration_remaining_samples: calibration_state
+                                    .remaining_samples(),
+                            });
+                            (
+                                was_armed
+                                    && phase == MeasurementPhase::Exposing
+                                    && measurement.setup().burst_capture,
+                                sample_counter.0 % STATUS_INTERVAL_SAMPLES == 0,
+                            )
+                        },
+                    );
+                let peak_hold = shared.adc_peak_hold.lock(|peak_hold| {
+                    peak_hold.step(value);
+                    *peak_hold
+                });
+                if publish_status {
+                    let (adc_faults, profile, event_counter) = (
+                        &mut shared.adc_faults,
+                        &mut shared.profile,
+                        &mut shared.event_counter,
+                    )
+                        .lock(|adc_faults, profile, event_counter| {
+                            (
+                                *adc_faults,
+                                *profile,
+                                event_counter
+                                    .as_ref()
+                                    .map(|c| (c.count(), c.events_per_minute())),
+                            )
+                        });
+                    status_view.publish(StatusView {
+                        peak_hold,
+                        adc_faults,
+                        profile,
+                        event_count: event_counter.map(|(count, _)| count),
+                        events_per_minute: event_counter.and_then(|(_, rate)| rate),
+                    });
+                }
+
+                // The side sensors would go without samples for the burst
+                if opened && shared.focal_plane_measurement.lock(|m| m.is_none()) {
+                    shared.sampler.lock(|sampler| {
+                        if sampler.start_burst(FOCAL_PLANE_CENTER) {
+                            *burst_started_at = Some(CycleCounterClock::<{ hw::SYSCLK }>::now());
+                        }
+                    });
+                }
+            }
+        });
+    }
+
+    /// Samples went missing, so a pulse in progress can't be trusted anymore
+    fn discard_adc_buffer(shared: &mut dma::SharedResources, transfer_error: bool) {
+        let invalidated = (&mut shared.measurement, &mut shared.focal_plane_measurement).lock(
+            |measurement, focal_plane_measurement| {
+                let focal_plane_invalidated = focal_plane_measurement
+                    .as_mut()
+                    .is_some_and(ScanMeasurement::invalidate);
+                measurement.invalidate() || focal_plane_invalidated
+            },
+        );
+        shared.adc_faults.lock(|faults| {
+            if transfer_error {
+                faults.transfer_errors += 1;
+            }
+            if invalidated {
+                faults.invalidated += 1;
+            }
+        });
+        report_error(&mut shared.error_sender, AppError::Dma);
+    }
+
+    // HWCONFIG
+    #[task(binds = ADC, shared = [sampler, adc_faults], priority = 5)]
+    fn adc_overrun(cx: adc_overrun::Context) {
+        (cx.shared.sampler, cx.shared.adc_faults).lock(|sampler, adc_faults| {
+            if sampler.check_overrun() {
+                adc_faults.overruns += 1;
+            }
+        });
+    }
+
+    // HWCONFIG
+    #[task(binds = EXTI15_10, shared = [measurement, rotary], local = [sync_pin, rotary_sender], priority = 5)]
+    fn sync_or_rotary_edge(mut cx: sync_or_rotary_edge::Context) {
+        if cx.local.sync_pin.check_interrupt() {
+            cx.shared.measurement.lock(Measurement::mark_sync);
+            cx.local.sync_pin.clear_interrupt_pending_bit();
+        }
+
+        let rotary_edge = cx.shared.rotary.lock(|encoder| {
+            let (dt_pin, clk_pin) = encoder.borrow_pins();
+            let edge = dt_pin.check_interrupt() || clk_pin.check_interrupt();
+            dt_pin.clear_interrupt_pending_bit();
+            clk_pin.clear_interrupt_pending_bit();
+            edge
+        });
+        if !rotary_edge {
+            return;
+        }
+        if hw::ROTARY_DEBOUNCE_MS == 0 {
+            decode_rotary(&mut cx.shared.rotary, cx.local.rotary_sender);
+        } else {
+            // Already waiting, the edges get read together once the contacts settle
+            let _ = rotary_settle_task::spawn();
+        }
+    }
+
+    #[task(shared=[rotary], local=[rotary_settle_sender], priority=2)]
+    async fn rotary_settle_task(mut cx: rotary_settle_task::Context) {
+        hw::check_task_priority(hw::PRIORITY_TASKS);
+        Systick::delay(hw::ROTARY_DEBOUNCE_MS.millis()).await;
+        decode_rotary(&mut cx.shared.rotary, cx.local.rotary_settle_sender);
+    }
+
+    /// Queues a detent for rotary_encoder_task, if the pins completed one
+    fn decode_rotary(rotary: &mut impl rtic::Mutex<T = Rotary>, rotary_sender: &mut RotarySender) {
+        let direction = rotary.lock(|encoder| {
+            encoder.update();
+            encoder.direction()
+        });
+        let d = match direction {
+            Direction::Clockwise => 1,
+            Direction::Anticlockwise => -1,
+            Direction::None => return,
+        };
+        let _ = rotary_sender.try_send(d);
+    }
+
+    // HWCONFIG
+    #[task(binds = TIM1_CC, shared = [input_capture, capture_measurement], priority = 5)]
+    fn capture_edge(cx: capture_edge::Context) {
+        (cx.shared.input_capture, cx.shared.capture_measurement).lock(
+            |input_capture, capture_measurement| {
+                if let Some((edge, ticks)) = input_capture.on_capture() {
+                    if let Some(capture_measurement) = capture_measurement {
+                        capture_measurement.on_edge(edge, ticks);
+                    }
+                }
+            },
+        );
+    }
+
+    // HWCONFIG
+    #[task(binds = TIM1_UP_TIM10, shared = [input_capture], priority = 5)]
+    fn capture_overflow(mut cx: capture_overflow::Context) {
+        cx.shared.input_capture.lock(InputCapture::on_update);
+    }
+
+    #[task(shared=[app_mode, measurement, banner_sender], local=[acc_sense_pin], priority=2)]
+    async fn acc_sense_task(mut cx: acc_sense_task::Context) {
+        hw::check_task_priority(hw::PRIORITY_TASKS);
+        let mut input = AccessoryInput::new(hw::ACCESSORY_DEBOUNCE_MS, hw::ACCESSORY_GRACE_MS);
+        // TODO use adc
+        loop {
+            let present = cx.local.acc_sense_pin.is_high();
+            let busy = cx.shared.measurement.lock(|m| m.is_running());
+            let now_ms = (Systick::now() - <Systick as Monotonic>::ZERO).to_millis();
+
+            if let Some(event) = input.update(present, busy, now_ms) {
+                if event == AccessoryEvent::Glitched {
+                    show_banner(
+                        &mut cx.shared.banner_sender,
+                        "ACCESSORY GLITCH",
+                        Severity::Warning,
+                        BANNER_DURATION_MS,
+                    );
+                }
+                cx.shared
+                    .app_mode
+                    .lock(|app_mode| app_mode.on_accessory(event));
+            }
+            Systick::delay(hw::ACCESSORY_POLL_MS.millis()).await;
+        }
+    }
+
+    /// Measures when a test stand lid closes, like a short press would from the start screen
+    #[task(shared=[app_mode, settings, sequence], local=[fixture_pin], priority=2)]
+    async fn fixture_task(mut cx: fixture_task::Context) {
+        hw::check_task_priority(hw::PRIORITY_TASKS);
+        let mut input = FixtureInput::new(0);
+        loop {
+            let settle_ms = cx.shared.settings.lock(|s| s.fixture_settle_ms);
+            input.set_settle_ms(settle_ms as u32);
+            let now_ms = (Systick::now() - <Systick as Monotonic>::ZERO).to_millis();
+            // Still tracks the lid while off, turning it on doesn't measure right away
+            if input.update(cx.local.fixture_pin.is_low(), now_ms) && settle_ms > 0 {
+                let ready = match cx.shared.app_mode.lock(|app_mode| app_mode.get()) {
+                    AppModeInner::Start | AppModeInner::Results => true,
+                    AppModeInner::Sequence => cx
+                        .shared
+                        .sequence
+                        .lock(|sequence| sequence.as_ref().is_some_and(|s| !s.is_done())),
+                    _ => false,
+                };
+                if ready {
+                    let _ = measure_task::spawn();
+                }
+            }
+            Systick::delay(10.millis()).await;
+        }
+    }
+
+    /// Keeps a measurement armed with the last calibration while the start screen is up,
+    /// so firing the shutter is enough. `measure_task` takes over once it triggers
+    #[task(shared=[app_mode, measurement, settings, instant_calibration, instant_trigger], priority=2)]
+    async fn instant_task(mut cx: instant_task::Context) {
+        hw::check_task_priority(hw::PRIORITY_TASKS);
+        let mut installed = false;
+        loop {
+            Systick::delay(50.millis()).await;
+
+            let (mode, armed) = cx
+                .shared
+                .app_mode
+                .lock(|app_mode| (app_mode.get(), app_mode.is_instant_armed()));
+            let enabled = mode == AppModeInner::Start
+                && cx.shared.settings.lock(|s| s.instant)
+                && cx.shared.instant_calibration.lock(|c| c.is_some());
+            if !enabled {
+                if armed {
+                    cx.shared
+                        .app_mode
+                        .lock(|app_mode| app_mode.arm_instant(false));
+                }
+                installed = false;
+                continue;
+            }
+
+            if !armed {
+                let emitter_intensity = cx.shared.app_mode.lock(|app_mode| {
+                    app_mode.arm_instant(true);
+                    app_mode.emitter_intensity()
+                });
+                installed = false;
+                // Same settling as before a calibration, the emitter coming on isn't a shutter
+                Systick::delay(250.millis()).await;
+                if emitter_intensity > 0 {
+                    Systick::delay(hw::EMITTER_SETTLE_MS.millis()).await;
+                }
+                continue;
+            }
+
+            if !installed {
+                let Some(calibration) = cx.shared.instant_calibration.lock(|c| c.clone()) else {
+                    continue;
+                };
+                arm_measurement(
+                    &mut cx.shared.settings,
+                    &mut cx.shared.measurement,
+                    &calibration,
+                    false,
+                    false,
+                );
+                installed = true;
+                continue;
+            }
+
+            if cx.shared.measurement.lock(|m| m.phase()) != MeasurementPhase::Armed {
+                installed = false;
+                cx.shared.instant_trigger.lock(|t| *t = true);
+                if measure_task::spawn().is_err() {
+                    cx.shared.instant_trigger.lock(|t| *t = false);
+                }
+            }
+        }
+    }
+
+    #[task(shared=[app_mode, sequence, display_geometry_editor], local=[backup_registers], priority=1)]
+    async fn session_task(mut cx: session_task::Context) {
+        hw::check_task_priority(hw::PRIORITY_UI);
+        let mut stored = None;
+        // Unset until changed, a stock panel keeps following the config
+        let mut stored_geometry = cx
+            .local
+            .backup_registers
+            .read_display_geometry()
+            .unwrap_or(hw::DISPLAY_GEOMETRY);
+        loop {
+            let geometry = cx
+                .shared
+                .display_geometry_editor
+                .lock(|editor| editor.geometry);
+            if geometry != stored_geometry {
+                cx.local.backup_registers.write_display_geometry(&geometry);
+                stored_geometry = geometry;
+            }
+
+            let mode = cx.shared.app_mode.lock(|app_mode| app_mode.get());
+            // The interrupted session stays stored until the prompt is answered
+            if mode != AppModeInner::Resume {
+                let sequence = cx.shared.sequence.lock(|sequence| sequence.clone());
+                // Measurements taken during a sequence resume into the sequence itself
+                let mode = if sequence.is_some() {
+                    AppModeInner::Sequence
+                } else {
+                    mode
+                };
+                let words = match mode.session_id() {
+                    Some(id) => Session { mode: id, sequence }.encode(),
+                    None => [0; SESSION_WORDS],
+                };
+                if stored != Some(words) {
+                    cx.local.backup_registers.write(&words);
+                    stored = Some(words);
+                }
+            }
+            Systick::delay(250.millis()).await;
+        }
+    }
+
+    /// Calibrates for whichever task asks through `calibration::calibrate`, forever
+    #[task(
+        shared = [app_mode, calibration_result, calibration_state, settings, error_sender],
+        priority = 2,
+    )]
+    async fn calibration_task(mut cx: calibration_task::Context, mut service: CalibrationService) {
+        hw::check_task_priority(hw::PRIORITY_TASKS);
+        let mut last_calibration: Option<(CalibrationResult, u16)> = None;
+        while service.requests.recv().await.is_ok() {
+            cx.shared.calibration_result.lock(|r| *r = None);
+
+            let emitter_intensity = cx.shared.app_mode.lock(|app_mode| {
+                app_mode.set(AppModeInner::Calibrating);
+                app_mode.emitter_intensity()
+            });
+
+            // Let the system settle a bit
+            Systick::delay(250.millis()).await;
+            if emitter_intensity > 0 {
+                Systick::delay(hw::EMITTER_SETTLE_MS.millis()).await;
+            }
+
+            // Spot checked against the last one instead, only redone if the baseline moved.
+            // One taken at another resolution is in different counts
+            let (quick_recal, adc_range) =
+                cx.shared.settings.lock(|s| (s.quick_recal, s.adc_range()));
+            let previous = last_calibration
+                .take()
+                .filter(|&(_, range)| range == adc_range)
+                .map(|(previous, _)| previous);
+            cx.shared
+                .calibration_state
+                .lock(|calibration_state| match previous {
+                    Some(previous) if quick_recal => calibration_state
+                        .begin_spot_check(previous, hw::calibration_max_drift(adc_range)),
+                    // The covered level belongs to the sensor, no need to ask for it again
+                    Some(previous) => calibration_state.begin_ambient(previous.covered),
+                    None => calibration_state.begin(),
+                });
+
+            let mut prompt_until = None;
+            let calibration_result = loop {
+                Systick::delay(100.millis()).await;
+
+                if cx.shared.app_mode.lock(|app_mode| app_mode.get()) != AppModeInner::Calibrating {
+                    // Cancelled
+                    cx.shared.calibration_state.lock(CalibrationState::cancel);
+                    break None;
+                }
+
+                let (waiting, result) = cx.shared.calibration_state.lock(|state| match state {
+                    CalibrationState::Done(result) => (false, Some(result.clone())),
+                    _ => (state.is_waiting(), None),
+                });
+                if let Some(result) = result {
+                    if let Some(fault) = result.sensor_fault(hw::calibration_max_covered(adc_range))
+                    {
+                        let _ = sensor_fault_task::spawn(fault);
+                        break None;
+                    }
+                    break Some(result);
+                }
+                if waiting {
+                    let until = *prompt_until
+                        .get_or_insert(Systick::now() + hw::CALIBRATION_PROMPT_MS.millis());
+                    if Systick::now() >= until {
+                        cx.shared.calibration_state.lock(CalibrationState::resume);
+                        prompt_until = None;
+                    }
+                }
+            };
+
+            last_calibration = calibration_result.clone().map(|result| (result, adc_range));
+            if service.replies.send(calibration_result).await.is_err() {
+                report_error(&mut cx.shared.error_sender, AppError::Calibration);
+            }
+        }
+    }
+
+    #[task(
+        shared=[app_mode, adc_value, measurement, event_counter, refire_check, beep_sender, error_sender, banner_sender, settings, annotation_editor, history, trace_history, sequence, usb_export, usb_wake_sender, usb_log, usb_events, wait_for_sync, fire_release, self_check, loopback_check, learn_gain, learned_gain, instant_calibration, instant_trigger, measurement_count, profile],
+        priority=2,
+    )]
+    async fn measure_task(mut cx: measure_task::Context) {
+        hw::check_task_priority(hw::PRIORITY_TASKS);
+        cx.shared.refire_check.lock(|check| *check = None);
+        // Armed on the start screen already and triggered, see `instant_task`
+        let instant = cx.shared.instant_trigger.lock(core::mem::take);
+        // One measurement only, whatever ends it
+        let self_check = !instant && cx.shared.self_check.lock(core::mem::take);
+        let learn_gain = cx.shared.learn_gain.lock(core::mem::take) && self_check;
+        if self_check {
+            // The baseline has to be dark for the pulse to stand out
+            cx.shared
+                .app_mode
+                .lock(|app_mode| app_mode.override_emitter(Some(0)));
+        }
+
+        let result = if instant {
+            let Some(result) = cx.shared.instant_calibration.lock(|c| c.clone()) else {
+                return;
+            };
+            // Sampling carries on through the mode change, nothing is lost
+            cx.shared.app_mode.lock(|app_mode| {
+                app_mode.set(AppModeInner::Measure);
+            });
+            result
+        } else {
+            let Some(result) = calibrate().await else {
+                // Cancelled
+                return;
+            };
+            if !self_check {
+                cx.shared
+                    .instant_calibration
+                    .lock(|c| *c = Some(result.clone()));
+            }
+
+            cx.shared.beep_sender.lock(|beep_sender| {
+                let _ = beep_sender.try_send(Chirp::Measuring);
+            });
+
+            #[cfg(feature = "usb")]
+            {
+                let mut s = String::<128>::default();
+                uwrite!(s, "Calibrated to: {}\r\n", result).unwrap();
+                UsbLogWriter::new(&mut cx.shared.usb_log, &mut cx.shared.usb_wake_sender)
+                    .line(s.as_bytes())
+                    .await;
+                cx.shared
+                    .usb_events
+                    .lock(|events| events.push(DeviceEvent::calibrated(&result)));
+                wake_usb(&mut cx.shared.usb_wake_sender);
+            }
+            result
+        };
+
+        let (trigger_thresholds, refire_watch_secs) = cx
+            .shared
+            .settings
+            .lock(|s| (s.measurement_thresholds(), s.refire_watch_secs));
+        // Same thresholds as the measurement, so whatever triggered it counts as a refire
+        let refire_counter = EventCounter::new(&result, &trigger_thresholds);
+
+        if !instant {
+            let wait_for_sync = cx.shared.wait_for_sync.lock(|w| *w);
+            let fire_release = cx.shared.fire_release.lock(|f| *f);
+            arm_measurement(
+                &mut cx.shared.settings,
+                &mut cx.shared.measurement,
+                &result,
+                wait_for_sync,
+                fire_release || self_check,
+            );
+            if learn_gain {
+                let thresholds = cx
+                    .shared
+                    .settings
+                    .lock(|s| AccessoryGain::learning_thresholds(s.trigger_thresholds));
+                cx.shared.measurement.lock(|m| {
+                    let setup = *m.setup();
+                    m.rearm(&result, thresholds, setup);
+                });
+            }
+
+            cx.shared.app_mode.lock(|app_mode| {
+                app_mode.set(AppModeInner::Measure);
+            });
+
+            // Cycle counter timestamps for host-synchronized experiments
+            #[cfg(feature = "usb")]
+            {
+                let mut s = String::<128>::default();
+                let now = CycleCounterClock::<{ hw::SYSCLK }>::now();
+                uwrite!(s, "MEAS:ARMED {}\r\n", now.ticks()).unwrap();
+                UsbLogWriter::new(&mut cx.shared.usb_log, &mut cx.shared.usb_wake_sender)
+                    .line(s.as_bytes())
+                    .await;
+            }
+            if self_check {
+                if loopback_task::spawn().is_err() {
+                    report_error(&mut cx.shared.error_sender, AppError::TaskBusy);
+                }
+            } else if fire_release && release_task::spawn().is_err() {
+                report_error(&mut cx.shared.error_sender, AppError::TaskBusy);
+            }
+        }
+
+        let mut phase = MeasurementPhase::Armed;
+
+        loop {
+            if cx.shared.app_mode.lock(|app_mode| app_mode.get()) != AppModeInner::Measure {
+                // Cancelled, nothing half measured is left to trigger on later
+                cx.shared.measurement.lock(Measurement::reset);
+                return;
+            }
+
+            let done;
+            profiled!(cx.shared.profile, ProfiledSection::MeasureLoop, {
+                done = cx
+                    .shared
+                    .measurement
+                    .lock(|measurement| measurement.is_done());
+            });
+            if done {
+                break;
+            }
+
+            // Silence while armed makes a missed trigger obvious
+            let new_phase = cx.shared.measurement.lock(|m| m.phase());
+            if new_phase != phase {
+                phase = new_phase;
+                let chirp = match phase {
+                    MeasurementPhase::Exposing => Some(Chirp::Exposing),
+                    MeasurementPhase::Finishing => Some(Chirp::Finishing),
+                    MeasurementPhase::Armed | MeasurementPhase::Done => None,
+                };
+                if let Some(chirp) = chirp {
+                    cx.shared.beep_sender.lock(|beep_sender| {
+                        let _ = beep_sender.try_send(chirp);
+                    });
+                }
+            }
+
+            Systick::delay(100.millis()).await;
+        }
+
+        // Nothing worth keeping, better than a wrapped number in the history
+        if cx
+            .shared
+            .measurement
+            .lock(|m| m.too_long_micros().is_some())
+        {
+            #[cfg(feature = "usb")]
+            {
+                let duration_micros = cx.shared.measurement.lock(|m| m.too_long_micros());
+                let mut s = String::<128>::default();
+                uwrite!(
+                    s,
+                    "Exposure too long: gave up after {} us\r\n",
+                    duration_micros.unwrap_or(0)
+                )
+                .unwrap();
+                UsbLogWriter::new(&mut cx.shared.usb_log, &mut cx.shared.usb_wake_sender)
+                    .line(s.as_bytes())
+                    .await;
+            }
+            show_banner(
+                &mut cx.shared.banner_sender,
+                "EXPOSURE TOO LONG",
+                Severity::Error,
+                BANNER_DURATION_MS,
+            );
+            cx.shared.app_mode.lock(|app_mode| {
+                app_mode.set(AppModeInner::Start);
+            });
+            return;
+        }
+
+        if self_check {
+            // Kept out of the history, it's not a shutter
+            let method = cx.shared.settings.lock(|s| s.duration_method);
+            let check = cx.shared.measurement.lock(|measurement| {
+                measurement.result().map(|result| {
+                    LoopbackCheck::new(result, hw::SELF_CHECK_PULSE_MICROS as u64, method)
+                })
+            });
+            #[cfg(feature = "usb")]
+            if let Some(check) = check {
+                let mut s = String::<128>::default();
+                uwrite!(
+                    s,
+                    "SELF:CHECK {} {} {} {}",
+                    check.verdict(hw::SELF_CHECK_TOLERANCE_PERCENT).label(),
+                    check.commanded_micros,
+                    check.measured_micros,
+                    check.error_micros()
+                )
+                .unwrap();
+                if let Some(latency_micros) = check.latency_micros {
+                    uwrite!(s, " {}", latency_micros).unwrap();
+                }
+                s.push_str("\r\n").unwrap();
+                UsbLogWriter::new(&mut cx.shared.usb_log, &mut cx.shared.usb_wake_sender)
+                    .line(s.as_bytes())
+                    .await;
+            }
+            cx.shared.loopback_check.lock(|c| *c = check);
+
+            let learned = if learn_gain {
+                let (previous, adc_range) = cx
+                    .shared
+                    .settings
+                    .lock(|s| (s.accessory_gain, s.adc_range()));
+                let emitter_percent = cx
+                    .shared
+                    .app_mode
+                    .lock(|app_mode| loopback_intensity(app_mode.emitter_intensity()));
+                let reference_amplitude = (hw::LOOPBACK_REFERENCE_AMPLITUDE as u32
+                    * adc_range as u32
+                    / hw::ADC_RANGE as u32) as u16;
+                cx.shared.measurement.lock(|measurement| {
+                    measurement.result().map(|measured| {
+                        let outcome = AccessoryGain::learn(
+                            measured,
+                            result.average,
+                            emitter_percent,
+                            reference_amplitude,
+                        );
+                        (outcome, previous)
+                    })
+                })
+            } else {
+                None
+            };
+            if let Some((outcome, _)) = learned {
+                if let Ok(gain) = outcome {
+                    cx.shared.settings.lock(|s| s.accessory_gain = Some(gain));
+                }
+                #[cfg(feature = "usb")]
+                {
+                    let mut s = String::<64>::default();
+                    match outcome {
+                        Ok(gain) => uwrite!(
+                            s,
+                            "GAIN:LEARNED {} {}\r\n",
+                            gain.gain_percent,
+                            gain.response_time_nanos
+                        )
+                        .unwrap(),
+                        Err(fault) => uwrite!(s, "GAIN:FAULT {}\r\n", fault.label()).unwrap(),
+                    }
+                    UsbLogWriter::new(&mut cx.shared.usb_log, &mut cx.shared.usb_wake_sender)
+                        .line(s.as_bytes())
+                        .await;
+                }
+                cx.shared.learned_gain.lock(|l| *l = learned);
+                cx.shared.beep_sender.lock(|beep_sender| {
+                    let _ = beep_sender.try_send(if outcome.is_ok() {
+                        Chirp::Done
+                    } else {
+                        Chirp::Error
+                    });
+                });
+                cx.shared.app_mode.lock(|app_mode| {
+                    app_mode.set(AppModeInner::Gain);
+                });
+                return;
+            }
+
+            cx.shared.beep_sender.lock(|beep_sender| {
+                let _ = beep_sender.try_send(Chirp::Done);
+            });
+            cx.shared.app_mode.lock(|app_mode| {
+                app_mode.set(AppModeInner::SelfCheck);
+            });
+            return;
+        }
+
+        let mut annotation = cx.shared.annotation_editor.lock(|editor| editor.annotation);
+        if let Some(step) = cx
+            .shared
+            .sequence
+            .lock(|sequence| sequence.as_ref().and_then(TestSequence::current))
+        {
+            annotation = Annotation {
+                nominal_speed: Some(step.nominal_speed),
+                custom_speed: None,
+                ..annotation
+            };
+        }
+
+        if let Some((entry, trace)) = cx.shared.measurement.lock(|measurement| {
+            measurement.result().map(|result| {
+                (
+                    HistoryEntry::new(result, annotation),
+                    compress_trace(result.sample_buffer.oldest_ordered().copied()),
+                )
+            })
+        }) {
+            cx.shared.history.lock(|history| history.write(entry));
+            if let Some(trace) = trace {
+                cx.shared
+                    .trace_history
+                    .lock(|trace_history| trace_history.write(trace));
+            }
+            cx.shared.measurement_count.lock(|count| *count += 1);
+
+            if cx
+                .shared
+                .measurement
+                .lock(|m| m.result().is_some_and(|result| result.clipped))
+            {
+                show_banner(
+                    &mut cx.shared.banner_sender,
+                    "CLIPPED, REDUCE LIGHT",
+                    Severity::Warning,
+                    BANNER_DURATION_MS,
+                );
+            }
+
+            let sequence_done = cx.shared.sequence.lock(|sequence| match sequence {
+                Some(sequence) => {
+                    sequence.record(entry.integrated_duration_micros);
+                    sequence.is_done()
+                }
+                None => false,
+            });
+            if sequence_done {
+                cx.shared
+                    .usb_export
+                    .lock(|usb_export| *usb_export = Some(UsbExport::Sequence));
+                wake_usb(&mut cx.shared.usb_wake_sender);
+            }
+        }
+
+        // Off the measurement lock, the dump waits on the host
+        #[cfg(feature = "usb")]
+        if let Some(result) = cx.shared.measurement.lock(|m| m.result().cloned()) {
+            // Ahead of the dump, which can take a while to drain
+            cx.shared
+                .usb_events
+                .lock(|events| events.push(DeviceEvent::measured(&result)));
+            let mut log = UsbLogWriter::new(&mut cx.shared.usb_log, &mut cx.shared.usb_wake_sender);
+            log.line(b"Result: \r\n").await;
+
+            let mut s = String::<128>::default();
+            uwrite!(s, "Camera: {}\r\n", annotation.camera.label()).unwrap();
+            log.line(s.as_bytes()).await;
+
+            if let Some(nominal_micros) = annotation.nominal_duration_micros() {
+                let mut s = String::<128>::default();
+                uwrite!(s, "Nominal time: {} us\r\n", nominal_micros).unwrap();
+                log.line(s.as_bytes()).await;
+            }
+
+            let mut s = String::<128>::default();
+            uwrite!(s, "Raw start-end time: {} us\r\n", result.duration_micros).unwrap();
+            log.line(s.as_bytes()).await;
+
+            let mut s = String::<128>::default();
+            uwrite!(
+                s,
+                "Integrated time: {} us\r\n",
+                result.integrated_duration_micros
+            )
+            .unwrap();
+            log.line(s.as_bytes()).await;
+
+            let mut s = String::<128>::default();
+            uwrite!(
+                s,
+                "Half peak time: {} us\r\n",
+                result.half_peak_duration_micros
+            )
+            .unwrap();
+            log.line(s.as_bytes()).await;
+
+            if let Some(edges) = result.edge_times() {
+                let mut s = String::<128>::default();
+                uwrite!(
+                    s,
+                    "Rise time: {} us, fall time: {} us\r\n",
+                    edges.rise_micros,
+                    edges.fall_micros
+                )
+                .unwrap();
+                log.line(s.as_bytes()).await;
+            }
+
+            let mut s = String::<128>::default();
+            uwrite!(s, "Uncertainty: +-{} us\r\n", result.uncertainty_micros()).unwrap();
+            log.line(s.as_bytes()).await;
+
+            if result.clipped {
+                log.line(b"Clipped: ADC saturated, reduce light\r\n").await;
+            }
+
+            let mut s = String::<128>::default();
+            uwrite!(
+                s,
+                "Sample rate at the end: 1/{}\r\n",
+                result.effective_divisor()
+            )
+            .unwrap();
+            log.line(s.as_bytes()).await;
+
+            if let Some(second_pulse) = result.second_pulse {
+                let mut s = String::<128>::default();
+                uwrite!(
+                    s,
+                    "Second pulse detected: {} us, {} us after the first\r\n",
+                    second_pulse.duration_micros,
+                    second_pulse.gap_micros
+                )
+                .unwrap();
+                log.line(s.as_bytes()).await;
+            }
+
+            if let Some(lag_micros) = result.release_lag_micros {
+                let mut s = String::<128>::default();
+                uwrite!(s, "Release lag: {} us\r\n", lag_micros).unwrap();
+                log.line(s.as_bytes()).await;
+            }
+
+            if let Some(offset_micros) = result.sync_offset_micros {
+                let mut s = String::<128>::default();
+                uwrite!(s, "Sync offset: {} us\r\n", offset_micros).unwrap();
+                log.line(s.as_bytes()).await;
+            }
+
+            if let Some(lag_micros) = result.fired_lag_micros {
+                let mut s = String::<128>::default();
+                uwrite!(s, "Fired lag: {} us\r\n", lag_micros).unwrap();
+                log.line(s.as_bytes()).await;
+            }
+
+            let mut s = String::<128>::default();
+            uwrite!(s, "Samples since start: {}\r\n", result.samples_since_start).unwrap();
+            log.line(s.as_bytes()).await;
+
+            let mut s = String::<128>::default();
+            uwrite!(s, "Samples since end: {}\r\n", result.samples_since_end).unwrap();
+            log.line(s.as_bytes()).await;
+
+            let l = result.sample_buffer.len();
+            for (index, item) in result.sample_buffer.oldest_ordered().enumerate() {
+                if index == l - result.samples_since_end {
+                    log.line(b"** end **\r\n").await;
+                }
+
+                let mut s = String::<128>::default();
+                uwrite!(s, "- {}\r\n", item).unwrap();
+                log.line(s.as_bytes()).await;
+
+                if index == l - result.samples_since_start {
+                    log.line(b"** start **\r\n").await;
+                }
+            }
+
+            log.line(b"\r\n").await;
+        }
+
+        cx.shared.beep_sender.lock(|beep_sender| {
+            let _ = beep_sender.try_send(Chirp::Done);
+        });
+
+        if refire_watch_secs > 0 {
+            let Some(check) = watch_for_refire(
+                &mut cx.shared.app_mode,
+                &mut cx.shared.event_counter,
+                refire_counter,
+                refire_watch_secs,
+            )
+            .await
+            else {
+                // Cancelled
+                return;
+            };
+            #[cfg(feature = "usb")]
+            {
+                let mut log =
+                    UsbLogWriter::new(&mut cx.shared.usb_log, &mut cx.shared.usb_wake_sender);
+                let mut s = String::<128>::default();
+                match check {
+                    RefireCheck::Locked => uwrite!(s, "Refire check: locked\r\n").unwrap(),
+                    RefireCheck::Refired { after_millis } => {
+                        uwrite!(s, "Refire check: fired again after {} ms\r\n", after_millis)
+                            .unwrap()
+                    }
+                }
+                log.line(s.as_bytes()).await;
+            }
+            cx.shared.refire_check.lock(|c| *c = Some(check));
+        }
+
+        cx.shared.app_mode.lock(|app_mode| {
+            app_mode.set(AppModeInner::Results);
+        });
+    }
+
+    /// Fires the release output a while after arming. Systick sleeps through most of the
+    /// wait, the cycle counter times the edge the measurement's lag is taken from.
+    #[task(shared = [app_mode, measurement], local = [release_pin], priority = 2)]
+    async fn release_task(mut cx: release_task::Context) {
+        hw::check_task_priority(hw::PRIORITY_TASKS);
+        let deadline = Deadline::<CycleCounterClock<{ hw::SYSCLK }>>::after(
+            fugit::TimerDurationU64::millis(hw::RELEASE_DELAY_MS as u64),
+        );
+        if let Some(remaining) = deadline.remaining() {
+            let sleep_ms = (remaining.to_millis() as u32).saturating_sub(RELEASE_SPIN_MS);
+            Systick::delay(sleep_ms.millis()).await;
+        }
+        if cx.shared.app_mode.lock(|app_mode| app_mode.get()) != AppModeInner::Measure {
+            // Cancelled
+            return;
+        }
+
+        deadline.spin();
+        // Nothing may come between the edge and its timestamp
+        let fired_at = cortex_m::interrupt::free(|_| {
+            cx.local.release_pin.set_high();
+            <CycleCounterClock<{ hw::SYSCLK }> as app_measurements::util::LaxMonotonic>::now()
+        });
+        cx.shared.measurement.lock(|m| m.mark_release(fired_at));
+
+        Systick::delay(hw::RELEASE_PULSE_MS.millis()).await;
+        cx.local.release_pin.set_low();
+    }
+
+    /// Arms a measurement of an emitter pulse instead of the shutter, the accessory
+    /// has to be empty so that the light reaches the sensor. With `learn_gain` the
+    /// sensor module's gain and response are taken from the pulse too.
+    #[task(shared = [wait_for_sync, fire_release, self_check, learn_gain], priority = 2)]
+    async fn self_check_task(mut cx: self_check_task::Context, learn_gain: bool) {
+        hw::check_task_priority(hw::PRIORITY_TASKS);
+        cx.shared.wait_for_sync.lock(|w| *w = false);
+        cx.shared.fire_release.lock(|f| *f = false);
+        cx.shared.self_check.lock(|s| *s = true);
+        cx.shared.learn_gain.lock(|l| *l = learn_gain);
+        if measure_task::spawn().is_err() {
+            // Would turn the next measurement into one otherwise
+            cx.shared.self_check.lock(|s| *s = false);
+            cx.shared.learn_gain.lock(|l| *l = false);
+        }
+    }
+
+    /// Switches the emitter on for [`hw::SELF_CHECK_PULSE_MICROS`], both edges timed
+    /// on the cycle counter like the release output
+    #[task(shared = [app_mode, measurement], priority = 2)]
+    async fn loopback_task(mut cx: loopback_task::Context) {
+        hw::check_task_priority(hw::PRIORITY_TASKS);
+        let on = Deadline::<CycleCounterClock<{ hw::SYSCLK }>>::after(
+            fugit::TimerDurationU64::millis(hw::RELEASE_DELAY_MS as u64),
+        );
+        if let Some(remaining) = on.remaining() {
+            let sleep_ms = (remaining.to_millis() as u32).saturating_sub(RELEASE_SPIN_MS);
+            Systick::delay(sleep_ms.millis()).await;
+        }
+        let intensity = cx.shared.app_mode.lock(|app_mode| {
+            (app_mode.get() == AppModeInner::Measure)
+                .then(|| loopback_intensity(app_mode.emitter_intensity()))
+        });
+        let Some(intensity) = intensity else {
+            // Cancelled
+            return;
+        };
+
+        on.spin();
+        let on_at = cx.shared.app_mode.lock(|app_mode| {
+            cortex_m::interrupt::free(|_| {
+                app_mode.override_emitter(Some(intensity));
+                <CycleCounterClock<{ hw::SYSCLK }> as app_measurements::util::LaxMonotonic>::now()
+            })
+        });
+        cx.shared.measurement.lock(|m| m.mark_release(on_at));
+
+        let off = Deadline::<CycleCounterClock<{ hw::SYSCLK }>>::until(
+            on_at
+                + fugit::TimerDurationU64::<{ hw::SYSCLK }>::micros(
+                    hw::SELF_CHECK_PULSE_MICROS as u64,
+                ),
+        );
+        if let Some(remaining) = off.remaining() {
+            let sleep_ms = (remaining.to_millis() as u32).saturating_sub(RELEASE_SPIN_MS);
+            Systick::delay(sleep_ms.millis()).await;
+        }
+        off.spin();
+        cx.shared.app_mode.lock(|app_mode| {
+            // Leaving the mode already put the emitter back
+            if app_mode.get() == AppModeInner::Measure {
+                app_mode.override_emitter(Some(0));
+            }
+        });
+    }
+
+    /// Of the self check pulse, full when the emitter is off in the settings, which is
+    /// the default
+    fn loopback_intensity(emitter_intensity: u8) -> u8 {
+        match emitter_intensity {
+            0 => 100,
+            intensity => intensity,
+        }
+    }
+
+    /// Rearms the measurement in place with what every ADC measurement is set up with,
+    /// a fresh one would take its buffers' worth of stack on the way in
+    fn arm_measurement(
+        settings: &mut impl rtic::Mutex<T = Settings>,
+        measurement: &mut impl rtic::Mutex<T = Measurement<CycleCounterClock<{ hw::SYSCLK }>>>,
+        calibration: &CalibrationResult,
+        wait_for_sync: bool,
+        wait_for_release: bool,
+    ) {
+        let (trigger_thresholds, setup) = settings.lock(|s| {
+            let setup = MeasurementSetup {
+                oversampling: s.oversampling(),
+                sample_interval_nanos: 1_000_000_000 / hw::SAMPLE_RATE_HZ,
+                wait_for_sync,
+                wait_for_release,
+                saturation_level: s.adc_max_value(),
+                auto_trigger_low_from: s.auto_trigger_low.then_some(calibration.max),
+                response_time_nanos: s.response_time_nanos(),
+                latency: Some(fugit::TimerDurationU64::nanos(hw::adc_latency_nanos(
+                    s.adc_resolution,
+                    s.sample_time(),
+                ) as u64)),
+                burst_capture: s.burst_capture,
+                ..MeasurementSetup::default()
+            };
+            (s.measurement_thresholds(), setup)
+        });
+        measurement.lock(|m| m.rearm(calibration, trigger_thresholds, setup));
+    }
+
+    /// Keeps sampling after a measurement and counts anything over the trigger,
+    /// `None` if the measure mode was left in the meantime
+    async fn watch_for_refire(
+        app_mode: &mut impl rtic::Mutex<T = AppMode>,
+        event_counter: &mut impl rtic::Mutex<
+            T = Option<EventCounter<CycleCounterClock<{ hw::SYSCLK }>>>,
+        >,
+        counter: EventCounter<CycleCounterClock<{ hw::SYSCLK }>>,
+        watch_secs: u8,
+    ) -> Option<RefireCheck> {
+        let started =
+            <CycleCounterClock<{ hw::SYSCLK }> as app_measurements::util::LaxMonotonic>::now();
+        let deadline = Systick::now() + (watch_secs as u32).secs();
+        event_counter.lock(|c| *c = Some(counter));
+
+        let mut refired_at = None;
+        let mut cancelled = false;
+        while Systick::now() < deadline {
+            if app_mode.lock(|app_mode| app_mode.get()) != AppModeInner::Measure {
+                cancelled = true;
+                break;
+            }
+            refired_at = event_counter.lock(|c| c.as_ref().and_then(EventCounter::first_event));
+            if refired_at.is_some() {
+                break;
+            }
+            Systick::delay(100.millis()).await;
+        }
+        event_counter.lock(|c| *c = None);
+
+        if cancelled {
+            return None;
+        }
+        Some(match refired_at {
+            Some(at) => RefireCheck::Refired {
+                after_millis: (at - started).to_millis() as u32,
+            },
+            None => RefireCheck::Locked,
+        })
+    }
+
+    #[task(shared=[app_mode, adc_peak_hold, calibration_result, settings, threshold_editor], priority=2)]
+    async fn debug_task(mut cx: debug_task::Context) {
+        hw::check_task_priority(hw::PRIORITY_TASKS);
+        let Some(result) = calibrate().await else {
+            // Cancelled
+            return;
+        };
+
+        let trigger_thresholds = cx.shared.settings.lock(|s| s.trigger_thresholds);
+        cx.shared.threshold_editor.lock(|threshold_editor| {
+            *threshold_editor = ThresholdEditor::new(&result, &trigger_thresholds);
+        });
+        cx.shared.adc_peak_hold.lock(PeakHold::reset);
+
+        cx.shared
+            .calibration_result
+            .lock(|calibration_result| *calibration_result = Some(result));
+
+        cx.shared.app_mode.lock(|app_mode| {
+            app_mode.set(AppModeInner::Debug);
+        });
+    }
+
+    #[task(shared=[app_mode, event_counter, settings], priority=2)]
+    async fn counter_task(mut cx: counter_task::Context) {
+        hw::check_task_priority(hw::PRIORITY_TASKS);
+        let Some(result) = calibrate().await else {
+            // Cancelled
+            return;
+        };
+
+        let trigger_thresholds = cx.shared.settings.lock(|s| s.measurement_thresholds());
+        cx.shared.event_counter.lock(|event_counter| {
+            *event_counter = Some(EventCounter::new(&result, &trigger_thresholds));
+        });
+
+        cx.shared.app_mode.lock(|app_mode| {
+            app_mode.set(AppModeInner::Counter);
+        });
+
+        while cx.shared.app_mode.lock(|app_mode| app_mode.get()) == AppModeInner::Counter {
+            Systick::delay(100.millis()).await;
+        }
+
+        cx.shared
+            .event_counter
+            .lock(|event_counter| *event_counter = None);
+    }
+
+    /// Measures every exposure until the mode is left, for shutters that only
+    /// stick once in a while. The summary stays on screen, each event goes out over USB
+    #[task(
+        shared=[app_mode, measurement, settings, soak_log, usb_devices],
+        priority=2
+    )]
+    async fn soak_task(mut cx: soak_task::Context) {
+        hw::check_task_priority(hw::PRIORITY_TASKS);
+        #[cfg(feature = "usb")]
+        let mut usb_devices = cx.shared.usb_devices;
+
+        let Some(calibration) = calibrate().await else {
+            // Cancelled
+            return;
+        };
+
+        // Calibrated once, hours of drift in the ambient light are part of what's watched
+        arm_measurement(
+            &mut cx.shared.settings,
+            &mut cx.shared.measurement,
+            &calibration,
+            false,
+            false,
+        );
+        cx.shared.soak_log.lock(|log| *log = SoakLog::default());
+        cx.shared.app_mode.lock(|app_mode| {
+            app_mode.set(AppModeInner::Soak);
+        });
+        serial_log!(usb_devices, b"SOAK:START\r\n");
+        let started = Systick::now();
+
+        while cx.shared.app_mode.lock(|app_mode| app_mode.get()) == AppModeInner::Soak {
+            let method = cx.shared.settings.lock(|s| s.duration_method);
+            let (duration_micros, too_long) = cx.shared.measurement.lock(|m| {
+                (
+                    m.result().map(|result| result.duration_micros_by(method)),
+                    m.too_long_micros().is_some(),
+                )
+            });
+            if too_long {
+                // Only the shutter's extremes are logged, an open bulb isn't one
+                arm_measurement(
+                    &mut cx.shared.settings,
+                    &mut cx.shared.measurement,
+                    &calibration,
+                    false,
+                    false,
+                );
+                serial_log!(usb_devices, b"SOAK:TOO LONG\r\n");
+                continue;
+            }
+            let Some(duration_micros) = duration_micros else {
+                Systick::delay(100.millis()).await;
+                continue;
+            };
+            arm_measurement(
+                &mut cx.shared.settings,
+                &mut cx.shared.measurement,
+                &calibration,
+                false,
+                false,
+            );
+
+            let event = SoakEvent {
+                at_millis: (Systick::now() - started).to_millis(),
+                duration_micros,
+            };
+            cx.shared.soak_log.lock(|log| log.record(event));
+            #[cfg(feature = "usb")]
+            {
+                let mut s = String::<64>::default();
+                uwrite!(
+                    s,
+                    "SOAK:EVENT {} {}\r\n",
+                    event.at_millis,
+                    event.duration_micros
+                )
+                .unwrap();
+                serial_log!(usb_devices, s.as_bytes());
+            }
+        }
+
+        #[cfg(feature = "usb")]
+        {
+            let log = cx.shared.soak_log.lock(|log| *log);
+            let mut s = String::<128>::default();
+            uwrite!(s, "SOAK:SUMMARY {}", log.count()).unwrap();
+            if let (Some(fastest), Some(slowest)) = (log.fastest(), log.slowest()) {
+                uwrite!(
+                    s,
+                    " {} {} {} {}",
+                    fastest.at_millis,
+                    fastest.duration_micros,
+                    slowest.at_millis,
+                    slowest.duration_micros
+                )
+                .unwrap();
+            }
+            s.push_str("\r\n").unwrap();
+            serial_log!(usb_devices, s.as_bytes());
+        }
+    }
+
+    #[task(shared=[app_mode, beeper_tuner, beep_sender], priority=2)]
+    async fn beeper_tuning_task(mut cx: beeper_tuning_task::Context) {
+        hw::check_task_priority(hw::PRIORITY_TASKS);
+        cx.shared
+            .beeper_tuner
+            .lock(|tuner| *tuner = BeeperTuner::new());
+        cx.shared.app_mode.lock(|app_mode| {
+            app_mode.set(AppModeInner::BeeperTuning);
+        });
+
+        while cx.shared.app_mode.lock(|app_mode| app_mode.get()) == AppModeInner::BeeperTuning {
+            let offset = cx.shared.beeper_tuner.lock(|tuner| tuner.offset);
+            cx.shared.beep_sender.lock(|beep_sender| {
+                let _ = beep_sender.try_send(Chirp::Tuning(offset));
+            });
+            // Long enough to tell one step from the next
+            Systick::delay(600.millis()).await;
+            cx.shared.beeper_tuner.lock(BeeperTuner::sweep);
+        }
+    }
+
+    #[task(
+        shared=[app_mode, beep_sender, error_sender, capture_measurement, input_capture, measurement, annotation_editor, history, trace_history, measurement_count],
+        priority=2
+    )]
+    async fn digital_measure_task(mut cx: digital_measure_task::Context) {
+        hw::check_task_priority(hw::PRIORITY_TASKS);
+        let clock_hz = cx.shared.input_capture.lock(|c| c.clock_hz());
+        cx.shared.capture_measurement.lock(|capture_measurement| {
+            *capture_measurement = Some(CaptureMeasurement::new(clock_hz, hw::ADC_RANGE - 1));
+        });
+        cx.shared.input_capture.lock(InputCapture::listen);
+
+        cx.shared.beep_sender.lock(|beep_sender| {
+            let _ = beep_sender.try_send(Chirp::Measuring);
+        });
+        cx.shared.app_mode.lock(|app_mode| {
+            app_mode.set(AppModeInner::Measure);
+        });
+
+        let done = loop {
+            if cx.shared.app_mode.lock(|app_mode| app_mode.get()) != AppModeInner::Measure {
                 // Cancelled
-                return;
+                break false;
             }
 
             if cx
                 .shared
-                .measurement
-                .lock(|measurement| measurement.is_done())
+                .capture_measurement
+                .lock(|m| m.as_ref().is_some_and(CaptureMeasurement::is_done))
             {
-                break;
+                break true;
+            }
+
+            Systick::delay(10.millis()).await;
+        };
+
+        cx.shared.input_capture.lock(InputCapture::unlisten);
+        let capture_measurement = cx.shared.capture_measurement.lock(Option::take);
+        if !done {
+            return;
+        }
+
+        let Some(result) = capture_measurement.and_then(CaptureMeasurement::take_result) else {
+            report_error(&mut cx.shared.error_sender, AppError::NoResult);
+            cx.shared.app_mode.lock(|app_mode| {
+                app_mode.set(AppModeInner::Start);
+            });
+            return;
+        };
+        let annotation = cx.shared.annotation_editor.lock(|editor| editor.annotation);
+        cx.shared
+            .history
+            .lock(|history| history.write(HistoryEntry::new(&result, annotation)));
+        if let Some(trace) = compress_trace(result.sample_buffer.oldest_ordered().copied()) {
+            cx.shared
+                .trace_history
+                .lock(|trace_history| trace_history.write(trace));
+        }
+        cx.shared.measurement_count.lock(|count| *count += 1);
+        cx.shared.measurement.lock(|measurement| {
+            *measurement = Measurement::from_result(result);
+        });
+
+        cx.shared.beep_sender.lock(|beep_sender| {
+            let _ = beep_sender.try_send(Chirp::Done);
+        });
+        cx.shared.app_mode.lock(|app_mode| {
+            app_mode.set(AppModeInner::Results);
+        });
+    }
+
+    #[task(
+        shared=[app_mode, beep_sender, usb_log, usb_wake_sender, settings, scan_measurement, measurement_count],
+        priority=2
+    )]
+    async fn scan_measure_task(mut cx: scan_measure_task::Context) {
+        hw::check_task_priority(hw::PRIORITY_TASKS);
+        let trigger_thresholds = cx.shared.settings.lock(|s| s.trigger_thresholds);
+        cx.shared.scan_measurement.lock(|scan_measurement| {
+            *scan_measurement = Some(ScanMeasurement::new(
+                trigger_thresholds,
+                hw::LINEAR_SENSOR_SWEEP_RATE_HZ,
+                hw::LINEAR_SENSOR_PITCH_UM,
+                hw::FRAME_HEIGHT_UM,
+            ));
+        });
+
+        cx.shared.beep_sender.lock(|beep_sender| {
+            let _ = beep_sender.try_send(Chirp::Measuring);
+        });
+        cx.shared.app_mode.lock(|app_mode| {
+            app_mode.set(AppModeInner::Scan);
+        });
+
+        let done = loop {
+            if cx.shared.app_mode.lock(|app_mode| app_mode.get()) != AppModeInner::Scan {
+                // Cancelled
+                break false;
+            }
+
+            if cx
+                .shared
+                .scan_measurement
+                .lock(|m| m.as_ref().is_some_and(ScanMeasurement::is_done))
+            {
+                break true;
+            }
+
+            Systick::delay(25.millis()).await;
+        };
+
+        if !done {
+            cx.shared.scan_measurement.lock(|m| *m = None);
+            return;
+        }
+
+        // The result stays on the scan screen until the next scan or leaving the mode
+        cx.shared.measurement_count.lock(|count| *count += 1);
+
+        // Off the lock, the sweep interrupt shares it and the dump waits on the host
+        #[cfg(feature = "usb")]
+        if let Some(result) = cx
+            .shared
+            .scan_measurement
+            .lock(|m| m.as_ref().and_then(ScanMeasurement::result).cloned())
+        {
+            let mut log = UsbLogWriter::new(&mut cx.shared.usb_log, &mut cx.shared.usb_wake_sender);
+            log.line(b"Rolling shutter result: \r\n").await;
+
+            if let Some(scan_micros) = result.scan_time_micros() {
+                let mut s = String::<128>::default();
+                uwrite!(
+                    s,
+                    "Scan time: {} us, {}\r\n",
+                    scan_micros,
+                    if result.is_forward() { "down" } else { "up" }
+                )
+                .unwrap();
+                log.line(s.as_bytes()).await;
+            }
+
+            if let Some(exposure_micros) = result.exposure_micros() {
+                let mut s = String::<128>::default();
+                uwrite!(s, "Exposure: {} us\r\n", exposure_micros).unwrap();
+                log.line(s.as_bytes()).await;
+            }
+
+            if let Some(slit_um) = result.slit_width_um() {
+                let mut s = String::<128>::default();
+                uwrite!(s, "Slit width: {} um\r\n", slit_um).unwrap();
+                log.line(s.as_bytes()).await;
+            }
+
+            let mut s = String::<128>::default();
+            uwrite!(s, "Uncertainty: +-{} us\r\n", result.uncertainty_micros()).unwrap();
+            log.line(s.as_bytes()).await;
+
+            for (index, channel) in result.channels.iter().enumerate() {
+                let mut s = String::<128>::default();
+                match (channel.opened_at_micros, channel.closed_at_micros) {
+                    (Some(opened_at), Some(closed_at)) => {
+                        uwrite!(s, "- {}: {} us - {} us\r\n", index, opened_at, closed_at)
+                    }
+                    (Some(opened_at), None) => {
+                        uwrite!(s, "- {}: {} us - not closed\r\n", index, opened_at)
+                    }
+                    _ => uwrite!(s, "- {}: not opened\r\n", index),
+                }
+                .unwrap();
+                log.line(s.as_bytes()).await;
+            }
+
+            log.line(b"\r\n").await;
+        }
+
+        cx.shared.beep_sender.lock(|beep_sender| {
+            let _ = beep_sender.try_send(Chirp::Done);
+        });
+    }
+
+    #[task(
+        shared=[app_mode, beep_sender, usb_log, usb_wake_sender, settings, focal_plane_measurement, measurement_count],
+        priority=2
+    )]
+    async fn focal_plane_task(mut cx: focal_plane_task::Context) {
+        hw::check_task_priority(hw::PRIORITY_TASKS);
+        let trigger_thresholds = cx.shared.settings.lock(|s| s.measurement_thresholds());
+        cx.shared
+            .focal_plane_measurement
+            .lock(|focal_plane_measurement| {
+                *focal_plane_measurement = Some(ScanMeasurement::new(
+                    trigger_thresholds,
+                    hw::SAMPLE_RATE_HZ,
+                    hw::FOCAL_PLANE_SENSOR_PITCH_UM,
+                    hw::FRAME_WIDTH_UM,
+                ));
+            });
+
+        cx.shared.beep_sender.lock(|beep_sender| {
+            let _ = beep_sender.try_send(Chirp::Measuring);
+        });
+        cx.shared.app_mode.lock(|app_mode| {
+            app_mode.set(AppModeInner::FocalPlane);
+        });
+
+        let done = loop {
+            if cx.shared.app_mode.lock(|app_mode| app_mode.get()) != AppModeInner::FocalPlane {
+                // Cancelled
+                break false;
+            }
+
+            if cx
+                .shared
+                .focal_plane_measurement
+                .lock(|m| m.as_ref().is_some_and(ScanMeasurement::is_done))
+            {
+                break true;
+            }
+
+            Systick::delay(25.millis()).await;
+        };
+
+        if !done {
+            cx.shared.focal_plane_measurement.lock(|m| *m = None);
+            return;
+        }
+
+        // The result stays on screen and for export until the next measurement
+        cx.shared.measurement_count.lock(|count| *count += 1);
+
+        #[cfg(feature = "usb")]
+        if let Some(result) = cx.shared.focal_plane_measurement.lock(|m| {
+            m.as_ref()
+                .and_then(ScanMeasurement::result)
+                .map(FocalPlaneResult::from)
+        }) {
+            let mut log = UsbLogWriter::new(&mut cx.shared.usb_log, &mut cx.shared.usb_wake_sender);
+            log.line(b"Focal plane result: \r\n").await;
+
+            for (index, label) in ["Left", "Center", "Right"].iter().enumerate() {
+                let mut s = String::<128>::default();
+                match (
+                    result.exposure_micros(index),
+                    result.deviation_percent(index),
+                ) {
+                    (Some(micros), Some(deviation)) => {
+                        uwrite!(s, "- {}: {} us, {}%\r\n", label, micros, deviation)
+                    }
+                    _ => uwrite!(s, "- {}: not measured\r\n", label),
+                }
+                .unwrap();
+                log.line(s.as_bytes()).await;
+            }
+
+            for (label, travel) in [
+                ("First curtain", result.first_curtain_micros()),
+                ("Second curtain", result.second_curtain_micros()),
+            ] {
+                if let Some(micros) = travel {
+                    let mut s = String::<128>::default();
+                    uwrite!(s, "{}: {} us\r\n", label, micros).unwrap();
+                    log.line(s.as_bytes()).await;
+                }
+            }
+
+            let mut s = String::<128>::default();
+            uwrite!(
+                s,
+                "Uncertainty: +-{} us\r\n\r\n",
+                result.uncertainty_micros()
+            )
+            .unwrap();
+            log.line(s.as_bytes()).await;
+        }
+
+        cx.shared.beep_sender.lock(|beep_sender| {
+            let _ = beep_sender.try_send(Chirp::Done);
+        });
+    }
+
+    #[cfg(feature = "usb")]
+    fn handle_usb_activity(_usb: &mut UsbDevicesImpl) -> Option<UsbRequest> {
+        let mode = _usb.console_mode();
+        _usb.with_mut(|fields| {
+            if fields.dfu.take_detach_request() {
+                return Some(UsbRequest::Bootloader);
+            }
+            let serial = fields.serial;
+            // Only as the port closes, a refused touch stays at 1200 baud until reopened
+            let touched = is_bootloader_touch(serial.line_coding().data_rate(), serial.dtr());
+            let was_touched = core::mem::replace(fields.bootloader_touched, touched);
+            if touched && !was_touched {
+                return Some(UsbRequest::Bootloader);
+            }
+            if let Some(request) = fields.command_parser.next_request() {
+                return Some(request);
+            }
+            let mut buf = [0; 64];
+            match serial.read(&mut buf) {
+                // The binary stream is output only, drop whatever the host sends
+                Ok(count) if count > 0 && mode == ConsoleMode::Text => {
+                    // Best effort echo, the host might not be reading
+                    let _ = serial.write(b"\r\n");
+                    let _ = serial.write(&buf[..count]);
+                    fields.command_parser.feed(&buf[..count])
+                }
+                _ => None,
+            }
+        })
+    }
+
+    #[cfg(feature = "usb")]
+    #[allow(clippy::too_many_arguments)]
+    fn apply_usb_request(
+        request: UsbRequest,
+        app_mode: &mut impl rtic::Mutex<T = AppMode>,
+        usb_export: &mut impl rtic::Mutex<T = Option<UsbExport>>,
+        usb_events: &mut impl rtic::Mutex<T = UsbEvents>,
+        settings: &mut impl rtic::Mutex<T = Settings>,
+        oversampler: &mut impl rtic::Mutex<T = Oversampler>,
+        button_input: &mut impl rtic::Mutex<T = ButtonInput>,
+        banner_sender: &mut impl rtic::Mutex<T = BannerSender>,
+        measurement: &mut impl rtic::Mutex<T = Measurement<CycleCounterClock<{ hw::SYSCLK }>>>,
+        error_sender: &mut impl rtic::Mutex<T = ErrorSender>,
+    ) {
+        match request {
+            // display_task reboots once the update screen is up
+            // The host asking is confirmation enough, unless a measurement would be cut off
+            UsbRequest::Bootloader => {
+                let refused = app_mode.lock(|app_mode| {
+                    let busy = matches!(
+                        app_mode.get(),
+                        AppModeInner::Measure | AppModeInner::Calibrating
+                    ) || measurement.lock(|m| m.is_running());
+                    if !busy {
+                        app_mode.set(AppModeInner::Rebooting);
+                    }
+                    busy
+                });
+                if refused {
+                    report_error(error_sender, AppError::UpdateRefused);
+                }
+            }
+            UsbRequest::Export(export) => {
+                usb_export.lock(|usb_export| *usb_export = Some(export));
+            }
+            UsbRequest::Arm => {
+                if matches!(
+                    app_mode.lock(|app_mode| app_mode.get()),
+                    AppModeInner::Start | AppModeInner::Results
+                ) {
+                    let _ = measure_task::spawn();
+                }
+            }
+            UsbRequest::SelfCheck => {
+                if matches!(
+                    app_mode.lock(|app_mode| app_mode.get()),
+                    AppModeInner::Start | AppModeInner::Results | AppModeInner::SelfCheck
+                ) {
+                    let _ = self_check_task::spawn(false);
+                }
+            }
+            UsbRequest::SetTolerance {
+                nominal_micros,
+                percent,
+            } => {
+                let set = settings.lock(|s| match percent {
+                    // Without a percent the speed goes back to the general band
+                    Some(percent) => s.tolerances.set_override(nominal_micros, percent),
+                    None => {
+                        s.tolerances.clear_override(nominal_micros);
+                        true
+                    }
+                });
+                let (text, severity) = match (set, percent) {
+                    (true, Some(_)) => ("TOLERANCE SET OVER USB", Severity::Info),
+                    (true, None) => ("TOLERANCE RESET OVER USB", Severity::Info),
+                    (false, _) => ("TOO MANY TOLERANCES", Severity::Error),
+                };
+                show_banner(banner_sender, text, severity, BANNER_DURATION_MS);
+            }
+            // The dump at the end shows what was taken
+            UsbRequest::LoadConfig(line) => {
+                settings.lock(|s| s.load_config(line.key(), line.value()));
+            }
+            // Same as changing these in the menu
+            UsbRequest::ConfigLoaded => {
+                let (
+                    emitter_intensity,
+                    idle_signal,
+                    oversampling,
+                    timings,
+                    adc_resolution,
+                    sample_time,
+                ) = settings.lock(|s| {
+                    (
+                        s.emitter_intensity,
+                        s.idle_signal,
+                        s.oversampling(),
+                        s.button_timings(),
+                        s.adc_resolution,
+                        s.sample_time(),
+                    )
+                });
+                app_mode.lock(|app_mode| {
+                    app_mode.set_emitter_intensity(emitter_intensity);
+                    app_mode.set_idle_signal(idle_signal);
+                });
+                oversampler.lock(|oversampler| *oversampler = Oversampler::new(oversampling));
+                button_input.lock(|input| input.set_timings(timings));
+                let _ = adc_resolution_task::spawn(adc_resolution);
+                let _ = adc_sample_time_task::spawn(sample_time);
+                usb_export.lock(|usb_export| *usb_export = Some(UsbExport::Config));
+                show_banner(
+                    banner_sender,
+                    "CONFIG LOADED OVER USB",
+                    Severity::Info,
+                    BANNER_DURATION_MS,
+                );
+            }
+            // `usb_task` starts with the current mode
+            UsbRequest::Subscribe(subscribed) => {
+                usb_events.lock(|events| events.set_subscribed(subscribed));
+            }
+        }
+    }
+
+    /// Only services the device, `usb_task` reads and answers the host once woken
+    #[task(binds=OTG_FS, shared=[usb_devices, usb_wake_sender], priority=1)]
+    fn usb_interrupt(_cx: usb_interrupt::Context) {
+        #[cfg(feature = "usb")]
+        {
+            let mut usb = _cx.shared.usb_devices;
+            let mut usb_wake_sender = _cx.shared.usb_wake_sender;
+            // Line coding and DFU requests don't count as data, wake up for those too
+            usb.lock(|usb| usb.poll_serial());
+            wake_usb(&mut usb_wake_sender);
+        }
+    }
+
+    #[task(shared=[usb_devices, usb_log, usb_events, history, trace_history, sequence, usb_export, app_mode, adc_value, profile, focal_plane_measurement, adc_faults, threshold_editor, settings, oversampler, button_input, banner_sender, hardware_revision, instant_calibration, expansion_devices, measurement, error_sender], priority=1)]
+    async fn usb_task(_cx: usb_task::Context, _stream: StreamReceiver, _wake: UsbWakeReceiver) {
+        hw::check_task_priority(hw::PRIORITY_UI);
+        #[cfg(feature = "usb")]
+        {
+            let mut usb = _cx.shared.usb_devices;
+            let mut usb_log = _cx.shared.usb_log;
+            let mut usb_events = _cx.shared.usb_events;
+            let mut history = _cx.shared.history;
+            let mut trace_history = _cx.shared.trace_history;
+            let mut sequence = _cx.shared.sequence;
+            let mut usb_export = _cx.shared.usb_export;
+            let mut app_mode = _cx.shared.app_mode;
+            let mut adc_value = _cx.shared.adc_value;
+            let mut profile = _cx.shared.profile;
+            let mut focal_plane_measurement = _cx.shared.focal_plane_measurement;
+            let mut adc_faults = _cx.shared.adc_faults;
+            let mut threshold_editor = _cx.shared.threshold_editor;
+            let mut settings = _cx.shared.settings;
+            let mut oversampler = _cx.shared.oversampler;
+            let mut button_input = _cx.shared.button_input;
+            let mut banner_sender = _cx.shared.banner_sender;
+            let mut hardware_revision = _cx.shared.hardware_revision;
+            let mut instant_calibration = _cx.shared.instant_calibration;
+            let mut expansion_devices = _cx.shared.expansion_devices;
+            let mut measurement = _cx.shared.measurement;
+            let mut error_sender = _cx.shared.error_sender;
+            let mut stream = _stream;
+            let mut wake = _wake;
+            // Live view stream, toggled by the `monitor` command. The level
+            // only moves in the modes that sample.
+            let mut monitor: Option<LevelMonitor> = None;
+            let mut monitor_sent_at = Systick::now();
+            // Last `EVT:MODE` sent, `None` while nobody is subscribed
+            let mut reported_mode = None;
+            loop {
+                let tick_ms = match usb.lock(|usb| usb.console_mode()) {
+                    ConsoleMode::Binary => 1,
+                    ConsoleMode::Text if monitor.is_some() => USB_MONITOR_TICK_MS,
+                    ConsoleMode::Text => USB_IDLE_TICK_MS,
+                };
+                let _ = Systick::timeout_after(tick_ms.millis(), wake.recv()).await;
+
+                while let Some(request) = usb.lock(handle_usb_activity) {
+                    apply_usb_request(
+                        request,
+                        &mut app_mode,
+                        &mut usb_export,
+                        &mut usb_events,
+                        &mut settings,
+                        &mut oversampler,
+                        &mut button_input,
+                        &mut banner_sender,
+                        &mut measurement,
+                        &mut error_sender,
+                    );
+                }
+                match usb.lock(|usb| usb.console_mode()) {
+                    ConsoleMode::Text => {
+                        match usb_export.lock(Option::take) {
+                            Some(UsbExport::History) => {
+                                export_history(&mut usb, &mut history).await
+                            }
+                            Some(UsbExport::Sequence) => {
+                                export_sequence(&mut usb, &mut sequence).await
+                            }
+                            Some(UsbExport::Profile) => {
+                                export_profile(&mut usb, &mut profile).await
+                            }
+                            Some(UsbExport::Traces) => {
+                                export_traces(&mut usb, &mut trace_history).await
+                            }
+                            Some(UsbExport::FocalPlane) => {
+                                export_focal_plane(&mut usb, &mut focal_plane_measurement).await
+                            }
+                            Some(UsbExport::Status) => {
+                                export_status(&mut usb, &mut adc_faults, &mut expansion_devices)
+                                    .await
+                            }
+                            Some(UsbExport::Config) => export_config(&mut usb, &mut settings).await,
+                            Some(UsbExport::Report) => {
+                                export_report(
+                                    &mut usb,
+                                    &mut sequence,
+                                    &mut settings,
+                                    &mut hardware_revision,
+                                    &mut instant_calibration,
+                                )
+                                .await
+                            }
+                            Some(UsbExport::Capabilities) => {
+                                export_capabilities(&mut usb, &mut hardware_revision).await
+                            }
+                            Some(UsbExport::Monitor) => {
+                                monitor = match monitor {
+                                    Some(_) => None,
+                                    None => Some(LevelMonitor::default()),
+                                };
+                            }
+                            None => (),
+                        }
+
+                        flush_usb_log(&mut usb, &mut usb_log).await;
+                        send_usb_events(
+                            &mut usb,
+                            &mut usb_events,
+                            &mut app_mode,
+                            &mut reported_mode,
+                        )
+                        .await;
+
+                        // Drained here too so that the binary stream doesn't start stale
+                        while let Ok(event) = stream.try_recv() {
+                            if let StreamEvent::Trigger(ticks) = event {
+                                let mut s = String::<128>::default();
+                                uwrite!(s, "MEAS:TRIG {}\r\n", ticks).unwrap();
+                                serial_write_all(&mut usb, s.as_bytes()).await;
+                            }
+                        }
+
+                        if let Some(monitor) = monitor.as_mut() {
+                            use core::fmt::Write;
+
+                            let value = adc_value.lock(|adc_value| *adc_value);
+                            let (low, high) = threshold_editor.lock(|e| (e.low, e.high));
+                            monitor.step(value, low, high);
+                            if Systick::now() - monitor_sent_at >= MONITOR_INTERVAL_MS.millis() {
+                                monitor_sent_at = Systick::now();
+                                if let Some(frame) = monitor.take_frame() {
+                                    let mut s = String::<64>::default();
+                                    write!(s, "{}", frame).unwrap();
+                                    serial_write_all(&mut usb, s.as_bytes()).await;
+                                }
+                            }
+                        }
+                    }
+                    ConsoleMode::Binary => {
+                        usb_log.lock(UsbLog::clear);
+                        usb_events.lock(|events| events.set_subscribed(false));
+
+                        // Every sample while a measurement steps, otherwise the level
+                        // once a millisecond
+                        let mut streamed = false;
+                        while let Ok(event) = stream.try_recv() {
+                            let StreamEvent::Samples(samples) = event else {
+                                continue;
+                            };
+                            let mut bytes = [0u8; 2 * OBSERVER_BLOCK_LEN];
+                            for (chunk, sample) in bytes.chunks_exact_mut(2).zip(&samples) {
+                                chunk.copy_from_slice(&sample.to_le_bytes());
+                            }
+                            usb.lock(|usb| {
+                                usb.with_serial_mut(|serial| {
+                                    // Dropped if the host falls behind, the stream is best effort
+                                    let _ = serial.write(&bytes[..2 * samples.len()]);
+                                })
+                            });
+                            streamed = true;
+                        }
+                        if !streamed {
+                            let value = adc_value.lock(|adc_value| *adc_value);
+                            usb.lock(|usb| {
+                                usb.with_serial_mut(|serial| {
+                                    let _ = serial.write(&value.to_le_bytes());
+                                })
+                            });
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Writes the result history as CSV, oldest first
+    #[cfg(feature = "usb")]
+    async fn export_history(
+        usb: &mut impl rtic::Mutex<T = UsbDevicesImpl>,
+        history: &mut impl rtic::Mutex<T = History>,
+    ) {
+        serial_write_all(
+            usb,
+            b"index,camera,nominal_us,duration_us,integrated_us,uncertainty_us\r\n",
+        )
+        .await;
+
+        let mut index = 0;
+        while let Some(entry) = history.lock(|h| h.oldest_ordered().nth(index).copied()) {
+            let mut s = String::<128>::default();
+            uwrite!(s, "{},{},", index, entry.annotation.camera.label()).unwrap();
+            if let Some(nominal_micros) = entry.annotation.nominal_duration_micros() {
+                uwrite!(s, "{}", nominal_micros).unwrap();
             }
+            uwrite!(
+                s,
+                ",{},{},{}\r\n",
+                entry.duration_micros,
+                entry.integrated_duration_micros,
+                entry.uncertainty_micros
+            )
+            .unwrap();
+            serial_write_all(usb, s.as_bytes()).await;
+            index += 1;
+        }
+    }
 
-            Systick::delay(100.millis()).await;
+    /// Writes the fault counters and what's on the expansion bus, one `KEY value` line each
+    #[cfg(feature = "usb")]
+    async fn export_status(
+        usb: &mut impl rtic::Mutex<T = UsbDevicesImpl>,
+        adc_faults: &mut impl rtic::Mutex<T = AdcFaults>,
+        expansion_devices: &mut impl rtic::Mutex<T = ExpansionDevices>,
+    ) {
+        use core::fmt::Write;
+
+        let faults = adc_faults.lock(|f| *f);
+        let mut s = String::<128>::default();
+        uwrite!(
+            s,
+            "ADC:TRANSFER_ERRORS {}\r\nADC:OVERRUNS {}\r\nMEAS:INVALIDATED {}\r\nADC:STUCK {}\r\n",
+            faults.transfer_errors,
+            faults.overruns,
+            faults.invalidated,
+            faults.stuck
+        )
+        .unwrap();
+        serial_write_all(usb, s.as_bytes()).await;
+        for (address, device) in expansion_devices.lock(|d| d.clone()) {
+            s.clear();
+            write!(s, "I2C:DEVICE {:#04X} {}\r\n", address, device.label()).unwrap();
+            serial_write_all(usb, s.as_bytes()).await;
         }
+    }
 
-        #[cfg(feature = "usb")]
-        cx.shared.measurement.lock(|measurement| {
-            if let Some(result) = measurement.result() {
-                serial_log!(usb_devices, b"Result: \r\n");
+    /// Writes the settings as a `config load` block that restores them when sent back
+    #[cfg(feature = "usb")]
+    async fn export_config(
+        usb: &mut impl rtic::Mutex<T = UsbDevicesImpl>,
+        settings: &mut impl rtic::Mutex<T = Settings>,
+    ) {
+        use core::fmt::Write;
 
-                let mut s = String::<128>::default();
-                uwrite!(s, "Raw start-end time: {} us\r\n", result.duration_micros).unwrap();
-                serial_log!(usb_devices, s.as_bytes());
+        let settings = settings.lock(|s| s.clone());
+        serial_write_all(usb, b"config load\r\n").await;
+        for key in CONFIG_KEYS {
+            let mut s = String::<64>::default();
+            write!(s, "{}=", key).unwrap();
+            settings.write_config_value(key, &mut s).unwrap();
+            s.push_str("\r\n").unwrap();
+            serial_write_all(usb, s.as_bytes()).await;
+        }
+        let mut s = String::<64>::default();
+        uwrite!(s, "{}=clear\r\n", TOLERANCE_OVERRIDE_KEY).unwrap();
+        serial_write_all(usb, s.as_bytes()).await;
+        for &(nominal_micros, percent) in settings.tolerances.overrides() {
+            s.clear();
+            uwrite!(
+                s,
+                "{}={}:{}\r\n",
+                TOLERANCE_OVERRIDE_KEY,
+                nominal_micros,
+                percent
+            )
+            .unwrap();
+            serial_write_all(usb, s.as_bytes()).await;
+        }
+        serial_write_all(usb, b"end\r\n").await;
+    }
 
-                let mut s = String::<128>::default();
-                uwrite!(
-                    s,
-                    "Integrated time: {} us\r\n",
-                    result.integrated_duration_micros
-                )
-                .unwrap();
-                serial_log!(usb_devices, s.as_bytes());
+    /// Writes what this build supports as a `capabilities` block, one `key=value` line each
+    #[cfg(feature = "usb")]
+    async fn export_capabilities(
+        usb: &mut impl rtic::Mutex<T = UsbDevicesImpl>,
+        hardware_revision: &mut impl rtic::Mutex<T = HardwareRevision>,
+    ) {
+        use core::fmt::Write;
 
-                let mut s = String::<128>::default();
-                uwrite!(
-                    s,
-                    "Sample rate at the end: 1/{}\r\n",
-                    result.sample_rate.divisor()
-                )
-                .unwrap();
-                serial_log!(usb_devices, s.as_bytes());
+        let revision = hardware_revision.lock(|r| *r);
+        let mut firmware = String::<64>::default();
+        write!(
+            firmware,
+            "{} {} {}",
+            BUILD_INFO.version, BUILD_INFO.git_hash, BUILD_INFO.build_date
+        )
+        .unwrap();
+        let mut hardware = String::<32>::default();
+        write!(
+            hardware,
+            "{:03X} REV {} {}K",
+            revision.dev_id,
+            revision.rev_name(),
+            revision.flash_kb
+        )
+        .unwrap();
+        let modes = ALL_MODES.map(AppModeInner::label);
+        let adc_bits = hw::ADC_RESOLUTIONS.map(hw::adc_bits);
 
-                let mut s = String::<128>::default();
-                uwrite!(s, "Samples since start: {}\r\n", result.samples_since_start).unwrap();
-                serial_log!(usb_devices, s.as_bytes());
+        let capabilities = Capabilities {
+            firmware: &firmware,
+            hardware: &hardware,
+            modes: &modes,
+            sample_rate_hz: hw::SAMPLE_RATE_HZ,
+            oversampling: &hw::OVERSAMPLING_FACTORS,
+            adc_bits: &adc_bits,
+            buffer_samples: SAMPLING_BUFFER_LEN_WITH_MARGINS,
+            burst_samples: BURST_LEN,
+            history_len: HISTORY_LEN,
+            trace_history_len: TRACE_HISTORY_LEN,
+            accessories: &["SENSOR", "FOCAL_PLANE", "LINEAR", "DIGITAL", "FIXTURE"],
+            focal_plane_sensors: FOCAL_PLANE_SENSORS,
+            scan_channels: SCAN_CHANNELS,
+            features: &[
+                "usb",
+                #[cfg(feature = "effects")]
+                "effects",
+                #[cfg(feature = "profiling")]
+                "profiling",
+            ],
+        };
+        serial_write_all(usb, b"capabilities\r\n").await;
+        for key in CAPABILITY_KEYS {
+            // The mode list is the long one
+            let mut s = String::<256>::default();
+            write!(s, "{}=", key).unwrap();
+            capabilities.write_value(key, &mut s).unwrap();
+            s.push_str("\r\n").unwrap();
+            serial_write_all(usb, s.as_bytes()).await;
+        }
+        serial_write_all(usb, b"end\r\n").await;
+    }
 
-                let mut s = String::<128>::default();
-                uwrite!(s, "Samples since end: {}\r\n", result.samples_since_end).unwrap();
-                serial_log!(usb_devices, s.as_bytes());
+    /// Writes the test sequence as plain text for a repair ticket: the tester, the
+    /// calibration and settings it ran with, then a row per speed and the totals
+    #[cfg(feature = "usb")]
+    async fn export_report(
+        usb: &mut impl rtic::Mutex<T = UsbDevicesImpl>,
+        sequence: &mut impl rtic::Mutex<T = Option<TestSequence>>,
+        settings: &mut impl rtic::Mutex<T = Settings>,
+        hardware_revision: &mut impl rtic::Mutex<T = HardwareRevision>,
+        instant_calibration: &mut impl rtic::Mutex<T = Option<CalibrationResult>>,
+    ) {
+        use core::fmt::Write;
 
-                let l = result.sample_buffer.len();
-                for (index, item) in result.sample_buffer.oldest_ordered().enumerate() {
-                    if index == l - result.samples_since_end {
-                        serial_log!(usb_devices, b"** end **\r\n");
-                    }
+        let sequence = sequence.lock(|s| s.clone());
+        let settings = settings.lock(|s| s.clone());
+        let revision = hardware_revision.lock(|r| *r);
+        // The last one a measurement ran with
+        let calibration = instant_calibration.lock(|c| c.clone());
 
-                    let mut s = String::<128>::default();
-                    uwrite!(s, "- {}\r\n", item).unwrap();
-                    serial_log!(usb_devices, s.as_bytes());
+        let mut s = String::<160>::default();
+        write!(
+            s,
+            "SHUTTER SPEED TEST REPORT\r\nFIRMWARE     {} ({}, {})\r\n",
+            BUILD_INFO.version, BUILD_INFO.git_hash, BUILD_INFO.build_date
+        )
+        .unwrap();
+        write!(
+            s,
+            "HARDWARE     {:03X} REV {} {}K\r\n",
+            revision.dev_id,
+            revision.rev_name(),
+            revision.flash_kb
+        )
+        .unwrap();
+        serial_write_all(usb, s.as_bytes()).await;
 
-                    if index == l - result.samples_since_start {
-                        serial_log!(usb_devices, b"** start **\r\n");
-                    }
-                }
+        s.clear();
+        match calibration {
+            Some(calibration) => write!(
+                s,
+                "CALIBRATION  DARK {} AVG, {}..{}, COVERED {} OF {}\r\n",
+                calibration.average,
+                calibration.min,
+                calibration.max,
+                calibration.covered,
+                settings.adc_max_value()
+            )
+            .unwrap(),
+            None => s.push_str("CALIBRATION  NONE\r\n").unwrap(),
+        }
+        write!(
+            s,
+            "SETTINGS     {} BIT, {}X OVERSAMPLING, EMITTER {}%, {} TIMING\r\n",
+            settings.adc_bits(),
+            settings.oversampling(),
+            settings.emitter_intensity,
+            settings.duration_method.label()
+        )
+        .unwrap();
+        serial_write_all(usb, s.as_bytes()).await;
 
-                serial_log!(usb_devices, b"\r\n");
-            }
-        });
+        let tolerances = &settings.tolerances;
+        s.clear();
+        write!(
+            s,
+            "TOLERANCE    {}%, {}% FROM {} US, {} OVERRIDES\r\n\r\n",
+            tolerances.percent,
+            tolerances.fast_percent,
+            tolerances.fast_from_micros,
+            tolerances.overrides().len()
+        )
+        .unwrap();
+        serial_write_all(usb, s.as_bytes()).await;
 
-        cx.shared.beep_sender.lock(|beep_sender| {
-            let _ = beep_sender.try_send(Chirp::Done);
-        });
-        cx.shared.app_mode.lock(|app_mode| {
-            app_mode.set(AppModeInner::Results);
-        });
-    }
+        let Some(sequence) = sequence else {
+            serial_write_all(usb, b"NO TEST SEQUENCE\r\n").await;
+            return;
+        };
+        s.clear();
+        write!(s, "{}\r\n", REPORT_COLUMNS).unwrap();
+        serial_write_all(usb, s.as_bytes()).await;
+        for step in sequence.steps() {
+            s.clear();
+            write_report_row(&mut s, step, tolerances).unwrap();
+            s.push_str("\r\n").unwrap();
+            serial_write_all(usb, s.as_bytes()).await;
+        }
 
-    #[task(
-        shared=[app_mode, calibration_result],
-        local=[debug_calibration_channel_sender, debug_calibration_channel_receiver],
-        priority=2
-    )]
-    async fn debug_task(mut cx: debug_task::Context) {
-        calibration_task::spawn(cx.local.debug_calibration_channel_sender.clone()).unwrap();
-        let result = cx
-            .local
-            .debug_calibration_channel_receiver
-            .recv()
-            .await
+        s.clear();
+        s.push_str("\r\n").unwrap();
+        ReportSummary::new(sequence.steps(), tolerances)
+            .write(&mut s)
             .unwrap();
+        s.push_str("\r\n").unwrap();
+        serial_write_all(usb, s.as_bytes()).await;
+    }
 
-        cx.shared
-            .calibration_result
-            .lock(|calibration_result| *calibration_result = Some(result));
+    /// Writes the recent sample buffers as CSV, one row per sample, oldest first
+    #[cfg(feature = "usb")]
+    async fn export_traces(
+        usb: &mut impl rtic::Mutex<T = UsbDevicesImpl>,
+        trace_history: &mut impl rtic::Mutex<T = TraceHistory>,
+    ) {
+        serial_write_all(usb, b"trace,sample,value\r\n").await;
 
-        cx.shared.app_mode.lock(|app_mode| {
-            app_mode.set(AppModeInner::Debug);
-        });
+        let mut index = 0;
+        while let Some(trace) = trace_history.lock(|h| h.oldest_ordered().nth(index).cloned()) {
+            for (sample, value) in TraceDecoder::new(&trace).enumerate() {
+                let mut s = String::<32>::default();
+                uwrite!(s, "{},{},{}\r\n", index, sample, value).unwrap();
+                serial_write_all(usb, s.as_bytes()).await;
+            }
+            index += 1;
+        }
     }
 
+    /// Writes the last focal plane result as CSV, a row per sensor then the curtain travel,
+    /// empty cells for anything not measured
     #[cfg(feature = "usb")]
-    fn handle_usb_activity(_usb: &mut UsbDevicesImpl) {
-        _usb.with_serial_mut(|serial| {
-            let mut buf = [0; 64];
-            match serial.read(&mut buf) {
-                Ok(count) if count > 0 => {
-                    serial.write(b"\r\n").unwrap();
-                    serial.write(&buf[..count]).unwrap();
-                }
-                _ => {}
+    async fn export_focal_plane(
+        usb: &mut impl rtic::Mutex<T = UsbDevicesImpl>,
+        focal_plane_measurement: &mut impl rtic::Mutex<T = Option<ScanMeasurement<FOCAL_PLANE_SENSORS>>>,
+    ) {
+        serial_write_all(
+            usb,
+            b"row,exposure_us,deviation_pct,travel_us,uncertainty_us\r\n",
+        )
+        .await;
+
+        let Some(result) = focal_plane_measurement.lock(|m| {
+            m.as_ref()
+                .and_then(ScanMeasurement::result)
+                .map(FocalPlaneResult::from)
+        }) else {
+            return;
+        };
+
+        for (index, label) in ["left", "center", "right"].iter().enumerate() {
+            let mut s = String::<128>::default();
+            uwrite!(s, "{},", label).unwrap();
+            if let Some(micros) = result.exposure_micros(index) {
+                uwrite!(s, "{}", micros).unwrap();
             }
-        })
+            s.push(',').unwrap();
+            if let Some(deviation) = result.deviation_percent(index) {
+                uwrite!(s, "{}", deviation).unwrap();
+            }
+            uwrite!(s, ",,{}\r\n", result.uncertainty_micros()).unwrap();
+            serial_write_all(usb, s.as_bytes()).await;
+        }
+
+        // Signed, negative when the curtain runs right to left
+        for (label, travel) in [
+            ("first_curtain", result.first_curtain_micros()),
+            ("second_curtain", result.second_curtain_micros()),
+        ] {
+            let mut s = String::<128>::default();
+            uwrite!(s, "{},,,", label).unwrap();
+            if let Some(micros) = travel {
+                uwrite!(s, "{}", micros).unwrap();
+            }
+            uwrite!(s, ",{}\r\n", result.uncertainty_micros()).unwrap();
+            serial_write_all(usb, s.as_bytes()).await;
+        }
     }
 
-    #[task(binds=OTG_FS, shared=[usb_devices])]
-    fn usb_interrupt(_cx: usb_interrupt::Context) {
-        #[cfg(feature = "usb")]
+    /// Writes the current test sequence as CSV, skipped steps have no duration and
+    /// repeated ones their trimmed mean
+    #[cfg(feature = "usb")]
+    async fn export_sequence(
+        usb: &mut impl rtic::Mutex<T = UsbDevicesImpl>,
+        sequence: &mut impl rtic::Mutex<T = Option<TestSequence>>,
+    ) {
+        serial_write_all(usb, b"step,nominal_us,duration_us,deviation_pct\r\n").await;
+
+        let mut index = 0;
+        while let Some(step) =
+            sequence.lock(|s| s.as_ref().and_then(|s| s.steps().get(index).copied()))
         {
-            let mut usb = _cx.shared.usb_devices;
-            usb.lock(handle_usb_activity);
+            let mut s = String::<128>::default();
+            uwrite!(s, "{},{},", index, step.nominal_duration_micros()).unwrap();
+            if let (Some(duration_micros), Some(deviation)) =
+                (step.duration_micros, step.deviation_percent())
+            {
+                uwrite!(s, "{},{}", duration_micros, deviation).unwrap();
+            } else {
+                s.push(',').unwrap();
+            }
+            s.push_str("\r\n").unwrap();
+            serial_write_all(usb, s.as_bytes()).await;
+            index += 1;
         }
     }
 
-    #[task(shared=[usb_devices], priority=1)]
-    async fn usb_task(_cx: usb_task::Context) {
-        #[cfg(feature = "usb")]
-        {
-            let mut usb = _cx.shared.usb_devices;
-            loop {
-                if !usb.lock(|usb| usb.poll_serial()) {
-                    Systick::delay(10.millis()).await;
-                }
-                usb.lock(handle_usb_activity);
+    /// Writes the execution time stats as CSV, empty unless built with `profiling`
+    #[cfg(feature = "usb")]
+    async fn export_profile(
+        usb: &mut impl rtic::Mutex<T = UsbDevicesImpl>,
+        profile: &mut impl rtic::Mutex<T = Profile>,
+    ) {
+        serial_write_all(
+            usb,
+            b"section,count,min_cycles,mean_cycles,max_cycles,max_ns\r\n",
+        )
+        .await;
+
+        let profile = profile.lock(|p| *p);
+        for section in ProfiledSection::ALL {
+            let stats = profile.get(section);
+            let mut s = String::<128>::default();
+            uwrite!(
+                s,
+                "{},{},{},{},{},{}\r\n",
+                section.label(),
+                stats.count,
+                stats.min,
+                stats.mean(),
+                stats.max,
+                profile.cycles_to_nanos(stats.max)
+            )
+            .unwrap();
+            serial_write_all(usb, s.as_bytes()).await;
+        }
+    }
+
+    /// The serial buffer is small, keep polling until the host drains it
+    #[cfg(feature = "usb")]
+    async fn serial_write_all(usb: &mut impl rtic::Mutex<T = UsbDevicesImpl>, mut data: &[u8]) {
+        let mut retries = 0;
+        while !data.is_empty() && retries < USB_WRITE_RETRIES {
+            let written = usb.lock(|usb| {
+                usb.poll_serial();
+                usb.with_serial_mut(|serial| serial.write(data).unwrap_or(0))
+            });
+            if written == 0 {
+                retries += 1;
+                Systick::delay(1.millis()).await;
+            }
+            data = &data[written..];
+        }
+    }
+
+    /// Sends what got queued with [`UsbLogWriter`], with nobody listening it's dropped
+    #[cfg(feature = "usb")]
+    async fn flush_usb_log(
+        usb: &mut impl rtic::Mutex<T = UsbDevicesImpl>,
+        log: &mut impl rtic::Mutex<T = UsbLog>,
+    ) {
+        if !usb.lock(|usb| usb.is_listening()) {
+            log.lock(UsbLog::clear);
+            return;
+        }
+
+        let dropped_lines = log.lock(UsbLog::take_dropped_lines);
+        if dropped_lines > 0 {
+            let mut s = String::<64>::default();
+            uwrite!(s, "({} log lines dropped)\r\n", dropped_lines).unwrap();
+            serial_write_all(usb, s.as_bytes()).await;
+        }
+
+        let mut chunk = [0u8; 64];
+        loop {
+            let len = log.lock(|log| log.pop_into(&mut chunk));
+            if len == 0 {
+                break;
+            }
+            serial_write_all(usb, &chunk[..len]).await;
+        }
+    }
+
+    /// Pushes what happened to a subscribed host, the mode as it's seen changing here
+    #[cfg(feature = "usb")]
+    async fn send_usb_events(
+        usb: &mut impl rtic::Mutex<T = UsbDevicesImpl>,
+        usb_events: &mut impl rtic::Mutex<T = UsbEvents>,
+        app_mode: &mut impl rtic::Mutex<T = AppMode>,
+        reported_mode: &mut Option<AppModeInner>,
+    ) {
+        use core::fmt::Write;
+
+        // Closing the port ends the subscription
+        if !usb.lock(|usb| usb.is_listening()) {
+            usb_events.lock(|events| events.set_subscribed(false));
+        }
+        if !usb_events.lock(|events| events.is_subscribed()) {
+            *reported_mode = None;
+            return;
+        }
+
+        let mode = app_mode.lock(|app_mode| app_mode.get());
+        if *reported_mode != Some(mode) {
+            *reported_mode = Some(mode);
+            usb_events.lock(|events| events.push(DeviceEvent::Mode(mode.label())));
+        }
+        while let Some(event) = usb_events.lock(UsbEvents::pop) {
+            let mut s = String::<96>::default();
+            write!(s, "{}", event).unwrap();
+            serial_write_all(usb, s.as_bytes()).await;
+        }
+    }
+
+    /// Queues lines for `usb_task` to send. While the host keeps reading, a full queue
+    /// is waited on so that long dumps arrive whole. Once it stops draining the writer
+    /// stops waiting and the oldest lines give way instead.
+    #[cfg(feature = "usb")]
+    struct UsbLogWriter<'a, L, W> {
+        log: &'a mut L,
+        usb_wake_sender: &'a mut W,
+        stalled: bool,
+    }
+
+    #[cfg(feature = "usb")]
+    impl<'a, L: rtic::Mutex<T = UsbLog>, W: rtic::Mutex<T = UsbWakeSender>> UsbLogWriter<'a, L, W> {
+        fn new(log: &'a mut L, usb_wake_sender: &'a mut W) -> Self {
+            Self {
+                log,
+                usb_wake_sender,
+                stalled: false,
+            }
+        }
+
+        async fn line(&mut self, line: &[u8]) {
+            let mut free = self.log.lock(|log| log.free());
+            let mut stalled_ms = 0;
+            while !self.stalled && free < line.len() {
+                wake_usb(&mut *self.usb_wake_sender);
+                Systick::delay(1.millis()).await;
+                let now_free = self.log.lock(|log| log.free());
+                stalled_ms = if now_free > free { 0 } else { stalled_ms + 1 };
+                self.stalled = stalled_ms >= USB_LOG_STALL_MS;
+                free = now_free;
             }
+            self.log.lock(|log| log.push(line));
+            wake_usb(&mut *self.usb_wake_sender);
         }
     }
 
-    #[task(shared=[adc_value, sample_counter, app_mode, calibration_state, calibration_result, measurement, display, beep_sender, selected_menu_option], priority=1)]
+    #[task(shared=[app_mode, calibration_state, calibration_result, measurement, refire_check, beep_sender, selected_menu_option, results_page, chart_viewport, ruler_cursor, settings, threshold_editor, annotation_editor, history, sequence, hardware_revision, measurement_count, profile, debug_page, scan_measurement, focal_plane_measurement, resume_session, error_sender, error_toast, banner, display_geometry_editor, sensor_fault, clock_fault, loopback_check, learned_gain, soak_log, beeper_tuner], priority=1)]
     async fn display_task(mut cx: display_task::Context) {
-        // Only shared with the panic handler, which never returns
-        let display = unsafe { cx.shared.display.lock(|d| &mut *d.get()) };
+        hw::check_task_priority(hw::PRIORITY_UI);
+        // Spawned once from init
+        let Some(display) = PANIC_DISPLAY.claim() else {
+            return;
+        };
 
-        BootScreen::default().draw_init(display).await;
+        if BootScreen::default().draw_init(display).await.is_err() {
+            display.recover();
+        }
 
         cx.shared.beep_sender.lock(|beep_sender| {
             let _ = beep_sender.try_send(Chirp::Startup);
         });
 
         let mut mode = AppModeInner::None;
-        let mut screen: Screens<DisplayType, MipidsiError> = StartScreen::default().into();
+        let mut shown_toast = None;
+        let mut shown_banner = None;
+        let mut failed_frames = 0;
+        let mut screens: ScreenStack<DisplayType, MipidsiError> =
+            ScreenStack::new(StartScreen::default().into());
 
         loop {
             if let Some(changed_mode) = cx.shared.app_mode.lock(|app_mode| {
@@ -768,93 +4131,202 @@ mod app {
                 }
                 None
             }) {
-                match changed_mode {
-                    AppModeInner::Start => {
-                        screen = Screens::Start(StartScreen::default());
-                    }
-                    AppModeInner::Calibrating => {
-                        screen = Screens::Calibration(CalibrationScreen::default());
-                    }
-                    AppModeInner::Measure => {
-                        screen = Screens::Measurement(MeasurementScreen::default());
-                    }
-                    AppModeInner::Debug => {
-                        screen = Screens::Debug(DebugScreen::new(
-                            cx.shared.calibration_result.lock(Option::take).unwrap(),
-                            hw::TRIGGER_THRESHOLDS,
-                            match hw::ADC_RESOLUTION {
-                                Resolution::Six => 63,
-                                Resolution::Eight => 255,
-                                Resolution::Ten => 1023,
-                                Resolution::Twelve => 4095,
-                            },
-                        ));
-                    }
-                    AppModeInner::Results => {
-                        let calibration = cx.shared.calibration_state.lock(core::mem::take);
-                        let result = cx
-                            .shared
-                            .measurement
-                            .lock(|m| {
-                                core::mem::replace(
-                                    m,
-                                    Measurement::new(CalibrationResult::default(), hw::TRIGGER_THRESHOLDS),
-                                )
-                            })
-                            .take_result()
-                            .unwrap();
-                        screen = Screens::Results(ResultsScreen::new(calibration, result));
-                    }
-                    AppModeInner::Update => {
-                        screen = Screens::Update(UpdateScreen::default());
-                    }
-                    AppModeInner::Menu => {
-                        screen = Screens::Menu(MenuScreen::default());
-                    }
-                    AppModeInner::NoAccessory => {
-                        screen = Screens::NoAccessory(NoAccessoryScreen::default());
-                    }
-                    AppModeInner::None => (),
-                };
-                screen.draw_init(display).await;
+                if let Some(root) = screen_for_mode(&mut cx, changed_mode) {
+                    screens.reset(root);
+                }
+            }
+
+            let geometry = cx
+                .shared
+                .display_geometry_editor
+                .lock(|editor| editor.geometry);
+            if geometry != display.geometry() {
+                display.set_geometry(geometry);
+                screens.redraw();
+            }
+            let flipped = cx.shared.settings.lock(|s| s.flipped);
+            if flipped != display.flipped() {
+                display.set_flipped(flipped);
+                screens.redraw();
             }
 
-            match screen {
-                Screens::Debug(ref mut screen) => {
-                    let adc_value = cx.shared.adc_value.lock(|adc_value| *adc_value);
+            // Pages over the menu push and pop themselves, see `menu_page`
+            let page = cx.shared.app_mode.lock(|app_mode| app_mode.page());
+            match screens.current() {
+                Screens::Debug(screen) => {
+                    let adc_value = SAMPLING_VIEW.read().adc_value;
+                    let status = STATUS_VIEW.read();
+                    screen.editor = cx.shared.threshold_editor.lock(|e| e.clone());
+                    screen.peak_hold = status.peak_hold;
+                    screen.display_failures = display.failures();
+                    screen.adc_faults = status.adc_faults;
+                    screen.page = cx.shared.debug_page.lock(|p| *p);
+                    screen.profile = status.profile;
+                    if screen.page == DebugPage::Memory {
+                        screen.memory = memory_usage();
+                    }
                     screen.step(adc_value);
                 }
-                Screens::Calibration(ref mut screen) => {
-                    let progress = cx.shared.calibration_state.lock(|c| c.progress());
-                    screen.step(progress);
+                Screens::Calibration(screen) => {
+                    let view = SAMPLING_VIEW.read();
+                    let oversampling = cx.shared.settings.lock(|s| s.oversampling());
+                    screen.step(
+                        view.calibration_stage,
+                        view.calibration_progress,
+                        view.calibration_remaining_samples
+                            .map(|samples| samples * oversampling * 1000 / hw::SAMPLE_RATE_HZ),
+                    );
                 }
-                Screens::Menu(ref mut screen) => {
-                    let selected_menu_option = cx
+                Screens::Menu(screen) => {
+                    screen.selected = cx
                         .shared
                         .selected_menu_option
                         .lock(|selected_menu_option| *selected_menu_option);
-                    screen.position = selected_menu_option;
+                    screen.values = cx
+                        .shared
+                        .settings
+                        .lock(|s| MenuItem::ALL.map(|item| s.menu_value(item)));
+                    if let Some(page) = page.and_then(|item| menu_page(&mut cx, item)) {
+                        screen.open(page);
+                    }
+                }
+                Screens::Measurement(screen) => {
+                    let view = SAMPLING_VIEW.read();
+                    screen.waiting_for_sync = view.waiting_for_sync;
+                    screen.waiting_for_release = view.waiting_for_release;
+                    screen.phase = view.phase;
+                    // Only ever set during a measurement for the refire watch
+                    screen.watching_refire = STATUS_VIEW.read().event_count.is_some();
+                }
+                Screens::Results(screen) => {
+                    screen.page = cx.shared.results_page.lock(|page| *page);
+                    screen.viewport = cx.shared.chart_viewport.lock(|v| *v);
+                    screen.cursor = cx.shared.ruler_cursor.lock(|c| *c);
+                }
+                Screens::Annotation(screen) => {
+                    screen.editor = cx.shared.annotation_editor.lock(|editor| *editor);
+                    screen.closed = page != Some(MenuItem::Annotate);
+                }
+                Screens::DisplayGeometry(screen) => {
+                    screen.editor = cx.shared.display_geometry_editor.lock(|editor| *editor);
+                    screen.closed = page != Some(MenuItem::Display);
+                }
+                Screens::Sequence(screen) => {
+                    cx.shared.sequence.lock(|sequence| {
+                        if let Some(sequence) = sequence {
+                            screen.sequence.clone_from(sequence);
+                        }
+                    });
+                }
+                Screens::About(screen) => {
+                    screen.uptime_secs =
+                        (Systick::now() - <Systick as rtic_monotonics::Monotonic>::ZERO).to_secs();
+                    screen.measurement_count = cx.shared.measurement_count.lock(|count| *count);
+                    screen.closed = page != Some(MenuItem::About);
+                }
+                Screens::Scan(screen) => {
+                    cx.shared.scan_measurement.lock(|scan_measurement| {
+                        if let Some(scan_measurement) = scan_measurement {
+                            screen.levels = *scan_measurement.last_values();
+                            screen.calibrating = scan_measurement.is_calibrating();
+                            screen.scanning = scan_measurement.is_scanning();
+                            screen.result = scan_measurement.result().cloned();
+                        }
+                    });
+                }
+                Screens::FocalPlane(screen) => {
+                    cx.shared
+                        .focal_plane_measurement
+                        .lock(|focal_plane_measurement| {
+                            if let Some(focal_plane_measurement) = focal_plane_measurement {
+                                screen.levels = *focal_plane_measurement.last_values();
+                                screen.calibrating = focal_plane_measurement.is_calibrating();
+                                screen.scanning = focal_plane_measurement.is_scanning();
+                                screen.result =
+                                    focal_plane_measurement.result().map(FocalPlaneResult::from);
+                            }
+                        });
+                }
+                Screens::Counter(screen) => {
+                    let status = STATUS_VIEW.read();
+                    if let Some(count) = status.event_count {
+                        screen.count = count;
+                        screen.events_per_minute = status.events_per_minute;
+                    }
+                }
+                Screens::Soak(screen) => {
+                    screen.log = cx.shared.soak_log.lock(|log| *log);
+                }
+                Screens::BeeperTuning(screen) => {
+                    screen.tuner = cx.shared.beeper_tuner.lock(|tuner| *tuner);
+                    screen.frequency_hz = sound::tuning_frequency(screen.tuner.offset);
+                }
+                // The rebooting one is the root of its own mode
+                Screens::Update(screen) if !screen.confirmed => {
+                    screen.closed = page != Some(MenuItem::UsbUpdate);
+                }
+                Screens::Start(screen) => {
+                    screen.armed = cx.shared.app_mode.lock(|m| m.is_instant_armed());
                 }
                 _ => (),
             }
 
-            screen
-                .draw_frame(
-                    display,
-                    DrawFrameContext {
-                        animation_time_ms: (Systick::now()
-                            - <Systick as rtic_monotonics::Monotonic>::ZERO)
-                            .to_millis(),
-                    },
-                )
-                .await;
-            display.step_fx();
+            let (transition, fx_intensity) =
+                cx.shared.settings.lock(|s| (s.transition, s.fx_intensity));
+            screens.set_transition(transition);
+            display.set_fx_intensity(fx_intensity);
+
+            let frame_cx = DrawFrameContext {
+                animation_time_ms: (Systick::now() - <Systick as rtic_monotonics::Monotonic>::ZERO)
+                    .to_millis(),
+                budget: if cx.shared.app_mode.lock(|app_mode| app_mode.is_sampling()) {
+                    SAMPLING_DRAW_BUDGET
+                } else {
+                    DrawBudget::UNLIMITED
+                },
+            };
+            display.step_fx(&frame_cx);
+            let mut drawn = Ok(());
+            profiled!(cx.shared.profile, ProfiledSection::DisplayFrame, {
+                drawn = screens.draw_frame(display, frame_cx).await;
+            });
+
+            // Screens only draw what changed, keep the toast on top
+            let toast = cx.shared.error_toast.lock(|toast| *toast);
+            match toast {
+                Some(error) if drawn.is_ok() => drawn = Toast::new(error.label()).draw(display),
+                None if shown_toast.is_some() => screens.redraw(),
+                _ => (),
+            }
+            shown_toast = toast;
 
-            #[allow(clippy::single_match)]
-            match screen {
-                Screens::Update(_) => bootloader_api::reboot_into_bootloader(),
+            let banner = cx.shared.banner.lock(|banner| *banner);
+            match banner {
+                Some(banner) if drawn.is_ok() => {
+                    drawn = Banner::new(banner.text, banner.severity).draw(display)
+                }
+                None if shown_banner.is_some() => screens.redraw(),
                 _ => (),
             }
+            shown_banner = banner;
+
+            if drawn.is_ok() {
+                failed_frames = 0;
+            } else {
+                // A glitched transfer only needs the frame drawn again,
+                // a controller that keeps failing has likely lost its state
+                failed_frames += 1;
+                if failed_frames > DISPLAY_RETRIES {
+                    failed_frames = 0;
+                    report_error(&mut cx.shared.error_sender, AppError::Display);
+                    display.recover();
+                }
+                screens.redraw();
+            }
+
+            if mode == AppModeInner::Rebooting && matches!(screens.current(), Screens::Update(_)) {
+                bootloader_api::reboot_into_bootloader();
+            }
 
             let delay = match mode {
                 AppModeInner::Debug => 10.millis(),
@@ -866,11 +4338,217 @@ mod app {
         }
     }
 
-    #[idle(shared=[display])]
-    fn idle(mut cx: idle::Context) -> ! {
-        cx.shared.display.lock(|display| {
-            set_panic_display_ref(display);
-        });
+    /// For the debug memory page, scanning for the stack peak takes a while
+    fn memory_usage() -> MemoryUsage {
+        use core::mem::size_of;
+
+        let usb_ep_bytes = if cfg!(feature = "usb") {
+            size_of::<[u32; USB_EP_MEMORY_WORDS]>()
+        } else {
+            0
+        };
+        let buffers = [
+            (
+                "MEASURE",
+                size_of::<Measurement<CycleCounterClock<{ hw::SYSCLK }>>>(),
+            ),
+            ("CALIBRATE", size_of::<CalibrationState>()),
+            ("HISTORY", size_of::<History>()),
+            ("TRACES", size_of::<TraceHistory>()),
+            ("SCAN", size_of::<Option<ScanMeasurement<SCAN_CHANNELS>>>()),
+            ("USB EP", usb_ep_bytes),
+            ("USB LOG", size_of::<UsbLog>()),
+        ];
+        MemoryUsage {
+            ram_bytes: memory::ram_bytes(),
+            static_bytes: memory::static_bytes(),
+            stack_bytes: memory::stack_bytes(),
+            stack_peak_bytes: memory::stack_peak_bytes(),
+            buffers: buffers.map(|(label, bytes)| (label, bytes as u32)),
+        }
+    }
+
+    fn screen_for_mode(
+        cx: &mut display_task::Context,
+        mode: AppModeInner,
+    ) -> Option<Screens<DisplayType, MipidsiError>> {
+        let screen: Screens<DisplayType, MipidsiError> = match mode {
+            AppModeInner::Start => StartScreen::default().into(),
+            AppModeInner::Calibrating => CalibrationScreen::default().into(),
+            AppModeInner::Measure => MeasurementScreen::default().into(),
+            AppModeInner::Debug => {
+                cx.shared.debug_page.lock(|p| *p = DebugPage::Live);
+                let calibration = cx
+                    .shared
+                    .calibration_result
+                    .lock(Option::take)
+                    .unwrap_or_else(|| {
+                        report_error(&mut cx.shared.error_sender, AppError::NoResult);
+                        CalibrationResult::default()
+                    });
+                let (trigger_thresholds, adc_max_value) = cx
+                    .shared
+                    .settings
+                    .lock(|s| (s.trigger_thresholds, s.adc_max_value()));
+                DebugScreen::new(calibration, trigger_thresholds, adc_max_value).into()
+            }
+            AppModeInner::Results => {
+                let calibration = cx.shared.calibration_state.lock(core::mem::take);
+                let Some(result) = cx
+                    .shared
+                    .measurement
+                    .lock(|m| {
+                        core::mem::replace(
+                            m,
+                            Measurement::new(CalibrationResult::default(), hw::TRIGGER_THRESHOLDS),
+                        )
+                    })
+                    .take_result()
+                else {
+                    report_error(&mut cx.shared.error_sender, AppError::NoResult);
+                    return None;
+                };
+                // The entry recorded along with this result
+                let annotation = cx
+                    .shared
+                    .history
+                    .lock(|history| history.recent().map(|entry| entry.annotation))
+                    .unwrap_or_default();
+                cx.shared.results_page.lock(|page| *page = 0);
+                cx.shared
+                    .chart_viewport
+                    .lock(|v| *v = ChartViewport::default());
+                let mut screen = ResultsScreen::new(calibration, result, annotation);
+                (
+                    screen.speed_table,
+                    screen.duration_method,
+                    screen.tolerances,
+                ) = cx
+                    .shared
+                    .settings
+                    .lock(|s| (s.speed_table, s.duration_method, s.tolerances.clone()));
+                // Belongs to this result only
+                screen.refire = cx.shared.refire_check.lock(Option::take);
+                screen.cursor = screen.initial_cursor();
+                cx.shared.ruler_cursor.lock(|c| *c = screen.cursor);
+                screen.into()
+            }
+            AppModeInner::Rebooting => UpdateScreen::rebooting().into(),
+            AppModeInner::Menu => MenuScreen::default().into(),
+            AppModeInner::NoAccessory => NoAccessoryScreen::default().into(),
+            AppModeInner::SensorFault => {
+                let Some(fault) = cx.shared.sensor_fault.lock(|f| *f) else {
+                    report_error(&mut cx.shared.error_sender, AppError::NoResult);
+                    return None;
+                };
+                SensorFaultScreen::new(fault).into()
+            }
+            AppModeInner::ClockFault => {
+                let Some(fault) = cx.shared.clock_fault.lock(|f| *f) else {
+                    report_error(&mut cx.shared.error_sender, AppError::NoResult);
+                    return None;
+                };
+                ClockFaultScreen::new(fault).into()
+            }
+            AppModeInner::SelfCheck => {
+                let Some(check) = cx.shared.loopback_check.lock(|c| *c) else {
+                    report_error(&mut cx.shared.error_sender, AppError::NoResult);
+                    return None;
+                };
+                SelfCheckScreen::new(check, hw::SELF_CHECK_TOLERANCE_PERCENT).into()
+            }
+            AppModeInner::Gain => {
+                let Some((outcome, previous)) = cx.shared.learned_gain.lock(|l| *l) else {
+                    report_error(&mut cx.shared.error_sender, AppError::NoResult);
+                    return None;
+                };
+                GainScreen::new(outcome, previous).into()
+            }
+            AppModeInner::Counter => CounterScreen::default().into(),
+            AppModeInner::Soak => SoakScreen::new(cx.shared.soak_log.lock(|log| *log)).into(),
+            AppModeInner::BeeperTuning => {
+                BeeperTuningScreen::new(cx.shared.settings.lock(|s| s.beeper_offset)).into()
+            }
+            AppModeInner::Sequence => SequenceScreen::default().into(),
+            // MCP3208, 12 bits regardless of the internal ADC resolution
+            AppModeInner::Scan => ScanScreen::new(4095).into(),
+            AppModeInner::FocalPlane => {
+                FocalPlaneScreen::new(cx.shared.settings.lock(|s| s.adc_max_value())).into()
+            }
+            AppModeInner::Resume => {
+                use core::fmt::Write;
+
+                let mut description = heapless::String::<24>::default();
+                cx.shared.resume_session.lock(|session| {
+                    let Some(session) = session else {
+                        return;
+                    };
+                    let label = match AppModeInner::from_session_id(session.mode) {
+                        Some(AppModeInner::Counter) => "COUNTER",
+                        Some(AppModeInner::Debug) => "DEBUG",
+                        Some(AppModeInner::Scan) => "ROLLING",
+                        Some(AppModeInner::Soak) => "SOAK",
+                        _ => "SEQUENCE",
+                    };
+                    let _ = match &session.sequence {
+                        Some(sequence) if !sequence.is_done() => write!(
+                            description,
+                            "{} {}/{}",
+                            label,
+                            sequence.position() + 1,
+                            sequence.steps().len()
+                        ),
+                        _ => write!(description, "{}", label),
+                    };
+                });
+                ResumeScreen::new(&description).into()
+            }
+            AppModeInner::None => return None,
+        };
+        Some(screen)
+    }
+
+    /// The menu items that open a page over it rather than change the mode
+    fn menu_page(
+        cx: &mut display_task::Context,
+        item: MenuItem,
+    ) -> Option<MenuPage<DisplayType, MipidsiError>> {
+        Some(match item {
+            MenuItem::Annotate => MenuPage::Annotation(AnnotationScreen::default()),
+            MenuItem::Display => MenuPage::DisplayGeometry(DisplayGeometryScreen::new(
+                cx.shared
+                    .display_geometry_editor
+                    .lock(|editor| editor.geometry),
+            )),
+            MenuItem::About => {
+                use core::fmt::Write;
+
+                let revision = cx.shared.hardware_revision.lock(|r| *r);
+                let mut hardware = heapless::String::<24>::default();
+                let _ = write!(
+                    hardware,
+                    "{:03X} REV {} {}K",
+                    revision.dev_id,
+                    revision.rev_name(),
+                    revision.flash_kb
+                );
+                let mut screen = AboutScreen::new(BUILD_INFO, &hardware);
+                screen.boot = bootloader_api::boot_info().map(|info| BootDetails {
+                    boot_count: info.boot_count,
+                    reset_cause: info.reset_cause.label(),
+                    app_crc_valid: info.app_crc_valid,
+                });
+                MenuPage::About(screen)
+            }
+            MenuItem::UsbUpdate => MenuPage::Update(UpdateScreen::default()),
+            _ => return None,
+        })
+    }
+
+    #[idle]
+    fn idle(_cx: idle::Context) -> ! {
+        // Everything is up, the display task has claimed the display by now
+        PANIC_DISPLAY.arm();
 
         loop {
             rtic::export::wfi()
@@ -0,0 +1,61 @@
+use core::ptr::{addr_of, read_volatile, write_volatile};
+
+/// Unlikely to be left behind by a real stack frame
+const STACK_PAINT: u32 = 0xC5AC_5AC5;
+/// Below the frame painting from, for what `paint_stack` itself pushes
+const PAINT_MARGIN: usize = 256;
+
+// From the cortex-m-rt linker script
+extern "C" {
+    static __sdata: u32;
+    /// End of `.bss` and `.uninit`, where a heap would start. The stack grows down to it.
+    static __sheap: u32;
+    static _stack_start: u32;
+}
+
+fn ram_start() -> usize {
+    unsafe { addr_of!(__sdata) as usize }
+}
+
+fn statics_end() -> usize {
+    unsafe { addr_of!(__sheap) as usize }
+}
+
+fn stack_top() -> usize {
+    unsafe { addr_of!(_stack_start) as usize }
+}
+
+/// Fills the stack that hasn't been used yet, first thing at boot so that
+/// [`stack_peak_bytes`] can tell later how deep it went
+#[inline(never)]
+pub fn paint_stack() {
+    let limit = cortex_m::register::msp::read() as usize - PAINT_MARGIN;
+    let mut address = statics_end();
+    while address < limit {
+        unsafe { write_volatile(address as *mut u32, STACK_PAINT) };
+        address += 4;
+    }
+}
+
+/// Deepest the stack has been since [`paint_stack`], from the first overwritten word
+pub fn stack_peak_bytes() -> u32 {
+    let mut address = statics_end();
+    while address < stack_top() && unsafe { read_volatile(address as *const u32) } == STACK_PAINT {
+        address += 4;
+    }
+    (stack_top() - address) as u32
+}
+
+/// `.data`, `.bss` and the RTIC resources, fixed at link time
+pub fn static_bytes() -> u32 {
+    (statics_end() - ram_start()) as u32
+}
+
+/// Everything between the statics and the top of RAM
+pub fn stack_bytes() -> u32 {
+    (stack_top() - statics_end()) as u32
+}
+
+pub fn ram_bytes() -> u32 {
+    (stack_top() - ram_start()) as u32
+}
@@ -1,5 +1,6 @@
-use core::cell::{RefCell, UnsafeCell};
+use core::cell::{Cell, UnsafeCell};
 use core::fmt::Write;
+use core::mem::MaybeUninit;
 use core::panic::PanicInfo;
 use core::sync::atomic::{self, Ordering};
 
@@ -8,12 +9,90 @@ use cortex_m::interrupt::{CriticalSection, Mutex};
 
 use super::app::DisplayType;
 
-static PANIC_DISPLAY_REF: Mutex<RefCell<Option<&mut DisplayType>>> = Mutex::new(RefCell::new(None));
+pub static PANIC_DISPLAY: PanicDisplay = PanicDisplay::new();
 
-pub fn set_panic_display_ref(display: &UnsafeCell<DisplayType>) {
-    cortex_m::interrupt::free(|cs| {
-        *PANIC_DISPLAY_REF.borrow(cs).borrow_mut() = Some(unsafe { &mut *display.get() });
-    });
+/// The display, shared by exactly two parties: the task that draws and the panic handler.
+///
+/// `init` stores it, `display_task` [`PanicDisplay::claim`]s the one `&mut` there is for
+/// drawing, and idle [`PanicDisplay::arm`]s the handoff once everything is up. A panic
+/// after that takes the display over from wherever the drawing was. That aliases the
+/// claimed reference, which only holds up because the panic handler is the single
+/// consumer of the handoff and never returns to what it interrupted. The handoff is
+/// private to this module so nothing else can take the display a second time.
+pub struct PanicDisplay {
+    display: UnsafeCell<MaybeUninit<DisplayType>>,
+    state: Mutex<Cell<PanicDisplayState>>,
+}
+
+#[derive(Clone, Copy)]
+struct PanicDisplayState {
+    stored: bool,
+    claimed: bool,
+    armed: bool,
+    taken: bool,
+}
+
+// The state only changes in critical sections and it guards every access to the display
+unsafe impl Sync for PanicDisplay {}
+
+impl PanicDisplay {
+    const fn new() -> Self {
+        Self {
+            display: UnsafeCell::new(MaybeUninit::uninit()),
+            state: Mutex::new(Cell::new(PanicDisplayState {
+                stored: false,
+                claimed: false,
+                armed: false,
+                taken: false,
+            })),
+        }
+    }
+
+    /// Only the first display is kept, later ones are dropped
+    pub fn store(&'static self, display: DisplayType) {
+        cortex_m::interrupt::free(|cs| {
+            let mut state = self.state.borrow(cs).get();
+            if state.stored {
+                return;
+            }
+            unsafe { (*self.display.get()).write(display) };
+            state.stored = true;
+            self.state.borrow(cs).set(state);
+        });
+    }
+
+    /// The display for drawing, `None` if there is none or it was claimed already
+    pub fn claim(&'static self) -> Option<&'static mut DisplayType> {
+        cortex_m::interrupt::free(|cs| {
+            let mut state = self.state.borrow(cs).get();
+            if !state.stored || state.claimed {
+                return None;
+            }
+            state.claimed = true;
+            self.state.borrow(cs).set(state);
+            Some(unsafe { (*self.display.get()).assume_init_mut() })
+        })
+    }
+
+    /// Lets the panic handler draw, panics before this leave the screen as it is
+    pub fn arm(&'static self) {
+        cortex_m::interrupt::free(|cs| {
+            let mut state = self.state.borrow(cs).get();
+            state.armed = state.stored;
+            self.state.borrow(cs).set(state);
+        });
+    }
+
+    /// For the panic handler alone, see the contract above
+    fn take(&'static self, cs: &CriticalSection) -> Option<&'static mut DisplayType> {
+        let mut state = self.state.borrow(cs).get();
+        if !state.armed || state.taken {
+            return None;
+        }
+        state.taken = true;
+        self.state.borrow(cs).set(state);
+        Some(unsafe { (*self.display.get()).assume_init_mut() })
+    }
 }
 
 #[inline(never)]
@@ -21,7 +100,12 @@ pub fn set_panic_display_ref(display: &UnsafeCell<DisplayType>) {
 fn panic(info: &PanicInfo) -> ! {
     // We're dying, go all out just this once
     let cs = unsafe { CriticalSection::new() };
-    let display = PANIC_DISPLAY_REF.borrow(&cs).borrow_mut().take().unwrap();
+    let Some(display) = PANIC_DISPLAY.take(&cs) else {
+        // Too early, or panicking again while drawing the last one
+        loop {
+            atomic::compiler_fence(Ordering::SeqCst);
+        }
+    };
 
     unsafe {
         cortex_m::interrupt::enable();
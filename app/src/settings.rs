@@ -0,0 +1,621 @@
+// `config dump` and `config load` need the USB console
+#![cfg_attr(not(feature = "usb"), allow(dead_code))]
+
+use core::fmt::Write;
+use core::str::FromStr;
+
+use app_measurements::util::SpeedTable;
+use app_measurements::{
+    AccessoryGain, ButtonTimings, DurationMethod, OutlierRejection, Tolerances, TriggerProfile,
+    TriggerThresholds, TRIGGER_PROFILES, USER_PROFILE_LABEL,
+};
+use app_ui::{MenuItem, MenuValue, Transition, BEEPER_OFFSETS};
+use config as hw;
+use hw::hal::adc::config::{Resolution, SampleTime};
+
+use crate::accessory::IdleSignal;
+use crate::sound::SoundProfile;
+
+const FX_INTENSITY_STEP: u8 = 25;
+const LONG_PRESS_OPTIONS_MS: [u16; 4] = [500, 800, 1200, 2000];
+/// 0 turns double presses off, short presses don't wait for a second one then
+const DOUBLE_PRESS_OPTIONS_MS: [u16; 4] = [0, 250, 400, 600];
+const TOLERANCE_OPTIONS_PERCENT: [u8; 7] = [10, 15, 20, 25, 30, 40, 50];
+/// 0 goes straight to the results without watching for a second release
+const REFIRE_WATCH_OPTIONS_SECS: [u8; 4] = [0, 3, 5, 10];
+
+/// What `config dump` writes as `key=value` lines, in order. Tolerance overrides follow
+/// as `tolerance_override=clear` and then one `tolerance_override=<nominal us>:<percent>`
+/// line each.
+pub const CONFIG_KEYS: [&str; 29] = [
+    "trigger_low_ratio",
+    "trigger_high_ratio",
+    "trigger_low_delta",
+    "trigger_high_delta",
+    "emitter_intensity",
+    "sensitivity",
+    "sound_profile",
+    "transition",
+    "fx_intensity",
+    "auto_trigger_low",
+    "long_press_ms",
+    "double_press_ms",
+    "speed_table",
+    "duration_method",
+    "fixture_settle_ms",
+    "tolerance_percent",
+    "fast_tolerance_percent",
+    "fast_from_us",
+    "flipped",
+    "refire_watch_secs",
+    "quick_recal",
+    "outlier_rejection",
+    "idle_signal",
+    "instant",
+    "adc_bits",
+    "high_gain",
+    "burst",
+    "beeper_offset",
+    "accessory_gain",
+];
+pub const TOLERANCE_OVERRIDE_KEY: &str = "tolerance_override";
+
+#[derive(Clone, Debug)]
+pub struct Settings {
+    pub trigger_thresholds: TriggerThresholds,
+    pub emitter_intensity: u8,
+    pub sensitivity: u8,
+    pub sound_profile: SoundProfile,
+    pub transition: Transition,
+    pub fx_intensity: u8,
+    /// Ends pulses relative to their peak instead of at the fixed trigger low
+    pub auto_trigger_low: bool,
+    pub long_press_ms: u16,
+    pub double_press_ms: u16,
+    /// Nominal speeds results are compared against
+    pub speed_table: SpeedTable,
+    /// Which of the result's durations is shown
+    pub duration_method: DurationMethod,
+    /// See [`hw::FIXTURE_SETTLE_OPTIONS_MS`]
+    pub fixture_settle_ms: u16,
+    /// PASS/FAIL bands of the results, overrides come in over USB
+    pub tolerances: Tolerances,
+    /// Upside down in a fixture: the screen turns around and the knob reverses
+    pub flipped: bool,
+    /// How long to watch for the shutter firing again after a measurement
+    pub refire_watch_secs: u8,
+    /// Reuses the last calibration after a short check of the baseline
+    pub quick_recal: bool,
+    /// Shots per sequence step, applies to sequences started afterwards
+    pub outlier_rejection: OutlierRejection,
+    /// Polarity of the accessory idle output, or none for accessories that don't use it
+    pub idle_signal: IdleSignal,
+    /// Stays armed on the start screen once there's a calibration to arm with
+    pub instant: bool,
+    /// One of [`hw::ADC_RESOLUTIONS`], the trigger deltas are counted in it
+    pub adc_resolution: Resolution,
+    /// Samples the sensor longer for dim light, see [`hw::HIGH_GAIN_SAMPLE_TIME`]
+    pub high_gain: bool,
+    /// Converts back to back for a moment as the shutter opens, to time the fastest speeds
+    pub burst_capture: bool,
+    /// Semitones every tune is moved by, picked on the beeper tuning screen
+    pub beeper_offset: i8,
+    /// Learned from the emitter loopback for a module other than the stock one, see
+    /// [`Self::measurement_thresholds`]
+    pub accessory_gain: Option<AccessoryGain>,
+    /// What was set by hand before a preset replaced it, back after the last preset
+    pub user_profile: Option<TriggerProfile>,
+}
+
+impl Settings {
+    pub fn cycle_emitter_intensity(&mut self) -> u8 {
+        self.emitter_intensity = if self.emitter_intensity >= 100 {
+            0
+        } else {
+            (self.emitter_intensity + hw::EMITTER_INTENSITY_STEP).min(100)
+        };
+        self.emitter_intensity
+    }
+
+    pub fn cycle_sensitivity(&mut self) -> u8 {
+        self.sensitivity = (self.sensitivity + 1) % hw::OVERSAMPLING_FACTORS.len() as u8;
+        self.sensitivity
+    }
+
+    pub fn cycle_sound_profile(&mut self) -> SoundProfile {
+        self.sound_profile = self.sound_profile.next();
+        self.sound_profile
+    }
+
+    /// Stays off without the `effects` feature
+    pub fn cycle_transition(&mut self) -> Transition {
+        if cfg!(feature = "effects") {
+            self.transition = self.transition.next();
+        }
+        self.transition
+    }
+
+    /// Stays put without the `effects` feature
+    pub fn cycle_fx_intensity(&mut self) -> u8 {
+        if cfg!(feature = "effects") {
+            self.fx_intensity = if self.fx_intensity >= 100 {
+                0
+            } else {
+                (self.fx_intensity + FX_INTENSITY_STEP).min(100)
+            };
+        }
+        self.fx_intensity
+    }
+
+    pub fn toggle_auto_trigger_low(&mut self) -> bool {
+        self.auto_trigger_low = !self.auto_trigger_low;
+        self.auto_trigger_low
+    }
+
+    pub fn cycle_long_press(&mut self) -> u16 {
+        self.long_press_ms = next_option(&LONG_PRESS_OPTIONS_MS, self.long_press_ms);
+        self.long_press_ms
+    }
+
+    pub fn cycle_double_press(&mut self) -> u16 {
+        self.double_press_ms = next_option(&DOUBLE_PRESS_OPTIONS_MS, self.double_press_ms);
+        self.double_press_ms
+    }
+
+    pub fn cycle_speed_table(&mut self) -> SpeedTable {
+        self.speed_table = self.speed_table.next();
+        self.speed_table
+    }
+
+    pub fn cycle_duration_method(&mut self) -> DurationMethod {
+        self.duration_method = self.duration_method.next();
+        self.duration_method
+    }
+
+    pub fn cycle_fixture_settle(&mut self) -> u16 {
+        self.fixture_settle_ms =
+            next_option(&hw::FIXTURE_SETTLE_OPTIONS_MS, self.fixture_settle_ms);
+        self.fixture_settle_ms
+    }
+
+    pub fn cycle_tolerance(&mut self) -> u8 {
+        self.tolerances.percent = next_option(&TOLERANCE_OPTIONS_PERCENT, self.tolerances.percent);
+        self.tolerances.percent
+    }
+
+    /// Band from 1/500 on
+    pub fn cycle_fast_tolerance(&mut self) -> u8 {
+        self.tolerances.fast_percent =
+            next_option(&TOLERANCE_OPTIONS_PERCENT, self.tolerances.fast_percent);
+        self.tolerances.fast_percent
+    }
+
+    pub fn toggle_flipped(&mut self) -> bool {
+        self.flipped = !self.flipped;
+        self.flipped
+    }
+
+    pub fn cycle_refire_watch(&mut self) -> u8 {
+        self.refire_watch_secs = next_option(&REFIRE_WATCH_OPTIONS_SECS, self.refire_watch_secs);
+        self.refire_watch_secs
+    }
+
+    pub fn toggle_quick_recal(&mut self) -> bool {
+        self.quick_recal = !self.quick_recal;
+        self.quick_recal
+    }
+
+    pub fn cycle_outlier_rejection(&mut self) -> OutlierRejection {
+        self.outlier_rejection = self.outlier_rejection.next();
+        self.outlier_rejection
+    }
+
+    pub fn cycle_idle_signal(&mut self) -> IdleSignal {
+        self.idle_signal = self.idle_signal.next();
+        self.idle_signal
+    }
+
+    pub fn toggle_instant(&mut self) -> bool {
+        self.instant = !self.instant;
+        self.instant
+    }
+
+    pub fn toggle_high_gain(&mut self) -> bool {
+        self.high_gain = !self.high_gain;
+        self.high_gain
+    }
+
+    pub fn toggle_burst_capture(&mut self) -> bool {
+        self.burst_capture = !self.burst_capture;
+        self.burst_capture
+    }
+
+    /// Carries the trigger deltas over, so they stay the same share of the range
+    pub fn cycle_adc_resolution(&mut self) -> Resolution {
+        let from_range = self.adc_range();
+        self.adc_resolution = next_option(&hw::ADC_RESOLUTIONS, self.adc_resolution);
+        self.trigger_thresholds = self
+            .trigger_thresholds
+            .rescaled(from_range, self.adc_range());
+        self.adc_resolution
+    }
+
+    /// The preset the trigger settings still match, if any
+    pub fn trigger_profile(&self) -> Option<&'static TriggerProfile> {
+        TRIGGER_PROFILES.iter().find(|profile| {
+            profile.thresholds_for(self.adc_range()) == self.trigger_thresholds
+                && profile.oversampling == self.oversampling()
+                && profile.auto_trigger_low == self.auto_trigger_low
+                && profile.duration_method == self.duration_method
+                && profile.high_gain == self.high_gain
+        })
+    }
+
+    pub fn trigger_profile_label(&self) -> &'static str {
+        self.trigger_profile()
+            .map_or(USER_PROFILE_LABEL, |profile| profile.label)
+    }
+
+    /// What the menu shows next to the item, the modes have nothing to show
+    pub fn menu_value(&self, item: MenuItem) -> MenuValue {
+        match item {
+            MenuItem::Profile => MenuValue::Label(self.trigger_profile_label()),
+            MenuItem::Emit => MenuValue::Number(self.emitter_intensity as u16),
+            MenuItem::Sensitivity => MenuValue::Number(self.sensitivity as u16),
+            MenuItem::Sound => MenuValue::Label(self.sound_profile.label()),
+            MenuItem::Fx => MenuValue::Label(self.transition.label()),
+            MenuItem::Dither => MenuValue::Number(self.fx_intensity as u16),
+            MenuItem::AutoLow => MenuValue::Toggle(self.auto_trigger_low),
+            MenuItem::Hold => MenuValue::Number(self.long_press_ms),
+            MenuItem::Double => MenuValue::Number(self.double_press_ms),
+            MenuItem::Speeds => MenuValue::Label(self.speed_table.label()),
+            MenuItem::Timing => MenuValue::Label(self.duration_method.label()),
+            MenuItem::Fixture => MenuValue::Number(self.fixture_settle_ms),
+            MenuItem::Tolerance => MenuValue::Number(self.tolerances.percent as u16),
+            MenuItem::FastTolerance => MenuValue::Number(self.tolerances.fast_percent as u16),
+            MenuItem::Flip => MenuValue::Toggle(self.flipped),
+            MenuItem::Refire => MenuValue::Number(self.refire_watch_secs as u16),
+            MenuItem::QuickCal => MenuValue::Toggle(self.quick_recal),
+            MenuItem::Outliers => MenuValue::Label(self.outlier_rejection.label()),
+            MenuItem::AccIdle => MenuValue::Label(self.idle_signal.label()),
+            MenuItem::Instant => MenuValue::Toggle(self.instant),
+            MenuItem::Adc => MenuValue::Number(self.adc_bits() as u16),
+            MenuItem::HighGain => MenuValue::Toggle(self.high_gain),
+            MenuItem::Burst => MenuValue::Toggle(self.burst_capture),
+            MenuItem::Beeper => MenuValue::Offset(self.beeper_offset),
+            MenuItem::Learn => MenuValue::Gain(self.accessory_gain.map(|gain| gain.gain_percent)),
+            _ => MenuValue::None,
+        }
+    }
+
+    /// Through the presets and back to the user's own settings, changing any of them
+    /// afterwards makes them the user's again
+    pub fn cycle_trigger_profile(&mut self) -> &'static str {
+        let next = match self.trigger_profile() {
+            Some(profile) => TRIGGER_PROFILES
+                .iter()
+                .position(|p| p.label == profile.label)
+                .map(|index| index + 1),
+            None => {
+                self.user_profile = Some(TriggerProfile {
+                    label: USER_PROFILE_LABEL,
+                    thresholds: self.trigger_thresholds,
+                    adc_range: self.adc_range(),
+                    oversampling: self.oversampling(),
+                    auto_trigger_low: self.auto_trigger_low,
+                    duration_method: self.duration_method,
+                    high_gain: self.high_gain,
+                });
+                Some(0)
+            }
+        };
+        let profile = match next.and_then(|index| TRIGGER_PROFILES.get(index)) {
+            Some(profile) => *profile,
+            // Nothing set by hand yet, start over
+            None => self.user_profile.take().unwrap_or(TRIGGER_PROFILES[0]),
+        };
+        self.trigger_thresholds = profile.thresholds_for(self.adc_range());
+        self.sensitivity = hw::OVERSAMPLING_FACTORS
+            .iter()
+            .position(|&factor| factor == profile.oversampling)
+            .unwrap_or(0) as u8;
+        self.auto_trigger_low = profile.auto_trigger_low;
+        self.duration_method = profile.duration_method;
+        self.high_gain = profile.high_gain;
+        self.trigger_profile_label()
+    }
+
+    /// The value of one of [`CONFIG_KEYS`] the way [`Self::load_config`] reads it
+    pub fn write_config_value(&self, key: &str, out: &mut impl Write) -> core::fmt::Result {
+        match key {
+            "trigger_low_ratio" => write!(out, "{}", self.trigger_thresholds.low_ratio),
+            "trigger_high_ratio" => write!(out, "{}", self.trigger_thresholds.high_ratio),
+            "trigger_low_delta" => write!(out, "{}", self.trigger_thresholds.low_delta),
+            "trigger_high_delta" => write!(out, "{}", self.trigger_thresholds.high_delta),
+            "emitter_intensity" => write!(out, "{}", self.emitter_intensity),
+            "sensitivity" => write!(out, "{}", self.sensitivity),
+            "sound_profile" => out.write_str(self.sound_profile.label()),
+            "transition" => out.write_str(self.transition.label()),
+            "fx_intensity" => write!(out, "{}", self.fx_intensity),
+            "auto_trigger_low" => out.write_str(on_off(self.auto_trigger_low)),
+            "long_press_ms" => write!(out, "{}", self.long_press_ms),
+            "double_press_ms" => write!(out, "{}", self.double_press_ms),
+            "speed_table" => out.write_str(self.speed_table.label()),
+            "duration_method" => out.write_str(self.duration_method.label()),
+            "fixture_settle_ms" => write!(out, "{}", self.fixture_settle_ms),
+            "tolerance_percent" => write!(out, "{}", self.tolerances.percent),
+            "fast_tolerance_percent" => write!(out, "{}", self.tolerances.fast_percent),
+            "fast_from_us" => write!(out, "{}", self.tolerances.fast_from_micros),
+            "flipped" => out.write_str(on_off(self.flipped)),
+            "refire_watch_secs" => write!(out, "{}", self.refire_watch_secs),
+            "quick_recal" => out.write_str(on_off(self.quick_recal)),
+            "outlier_rejection" => out.write_str(self.outlier_rejection.label()),
+            "idle_signal" => out.write_str(self.idle_signal.label()),
+            "instant" => out.write_str(on_off(self.instant)),
+            "adc_bits" => write!(out, "{}", self.adc_bits()),
+            "high_gain" => out.write_str(on_off(self.high_gain)),
+            "burst" => out.write_str(on_off(self.burst_capture)),
+            "beeper_offset" => write!(out, "{}", self.beeper_offset),
+            "accessory_gain" => match self.accessory_gain {
+                Some(gain) => write!(out, "{}:{}", gain.gain_percent, gain.response_time_nanos),
+                None => out.write_str("off"),
+            },
+            _ => Ok(()),
+        }
+    }
+
+    /// One line of `config load`, `false` leaves the setting alone for an unknown key or a
+    /// value the menu couldn't have picked either
+    pub fn load_config(&mut self, key: &str, value: &str) -> bool {
+        let thresholds = &mut self.trigger_thresholds;
+        let tolerances = &mut self.tolerances;
+        match key {
+            "trigger_low_ratio" => {
+                set(&mut thresholds.low_ratio, parse(value).filter(|&r| r > 0.0))
+            }
+            "trigger_high_ratio" => set(
+                &mut thresholds.high_ratio,
+                parse(value).filter(|&r| r > 0.0),
+            ),
+            "trigger_low_delta" => set(&mut thresholds.low_delta, parse(value)),
+            "trigger_high_delta" => set(&mut thresholds.high_delta, parse(value)),
+            "emitter_intensity" => set(
+                &mut self.emitter_intensity,
+                parse(value).filter(|&i| i <= 100),
+            ),
+            "sensitivity" => set(
+                &mut self.sensitivity,
+                parse(value).filter(|&s: &u8| (s as usize) < hw::OVERSAMPLING_FACTORS.len()),
+            ),
+            "sound_profile" => set(
+                &mut self.sound_profile,
+                labeled(&SoundProfile::ALL, SoundProfile::label, value),
+            ),
+            // Like the menu, only `OFF` without the `effects` feature
+            "transition" => set(
+                &mut self.transition,
+                labeled(&Transition::ALL, Transition::label, value)
+                    .filter(|&t| cfg!(feature = "effects") || t == Transition::Off),
+            ),
+            "fx_intensity" => set(
+                &mut self.fx_intensity,
+                parse(value).filter(|&i| cfg!(feature = "effects") && i <= 100),
+            ),
+            "auto_trigger_low" => set(&mut self.auto_trigger_low, parse_on_off(value)),
+            "long_press_ms" => set(
+                &mut self.long_press_ms,
+                parse(value).filter(|ms| LONG_PRESS_OPTIONS_MS.contains(ms)),
+            ),
+            "double_press_ms" => set(
+                &mut self.double_press_ms,
+                parse(value).filter(|ms| DOUBLE_PRESS_OPTIONS_MS.contains(ms)),
+            ),
+            "speed_table" => set(
+                &mut self.speed_table,
+                labeled(&SpeedTable::ALL, SpeedTable::label, value),
+            ),
+            "duration_method" => set(
+                &mut self.duration_method,
+                labeled(&DurationMethod::ALL, DurationMethod::label, value),
+            ),
+            "fixture_settle_ms" => set(
+                &mut self.fixture_settle_ms,
+                parse(value).filter(|ms| hw::FIXTURE_SETTLE_OPTIONS_MS.contains(ms)),
+            ),
+            "tolerance_percent" => set(
+                &mut tolerances.percent,
+                parse(value).filter(|p| TOLERANCE_OPTIONS_PERCENT.contains(p)),
+            ),
+            "fast_tolerance_percent" => set(
+                &mut tolerances.fast_percent,
+                parse(value).filter(|p| TOLERANCE_OPTIONS_PERCENT.contains(p)),
+            ),
+            "fast_from_us" => set(&mut tolerances.fast_from_micros, parse(value)),
+            "flipped" => set(&mut self.flipped, parse_on_off(value)),
+            "refire_watch_secs" => set(
+                &mut self.refire_watch_secs,
+                parse(value).filter(|secs| REFIRE_WATCH_OPTIONS_SECS.contains(secs)),
+            ),
+            "quick_recal" => set(&mut self.quick_recal, parse_on_off(value)),
+            "outlier_rejection" => set(
+                &mut self.outlier_rejection,
+                labeled(&OutlierRejection::ALL, OutlierRejection::label, value),
+            ),
+            "idle_signal" => set(
+                &mut self.idle_signal,
+                labeled(&IdleSignal::ALL, IdleSignal::label, value),
+            ),
+            "instant" => set(&mut self.instant, parse_on_off(value)),
+            // The dump has the deltas in the same resolution, nothing to rescale
+            "adc_bits" => set(
+                &mut self.adc_resolution,
+                parse(value).and_then(hw::adc_resolution_from_bits),
+            ),
+            "high_gain" => set(&mut self.high_gain, parse_on_off(value)),
+            "burst" => set(&mut self.burst_capture, parse_on_off(value)),
+            "beeper_offset" => set(
+                &mut self.beeper_offset,
+                parse(value).filter(|o| BEEPER_OFFSETS.contains(o)),
+            ),
+            "accessory_gain" if value == "off" => {
+                self.accessory_gain = None;
+                true
+            }
+            "accessory_gain" => match value.split_once(':') {
+                Some((percent, nanos)) => match (parse(percent), parse(nanos)) {
+                    (Some(gain_percent), Some(response_time_nanos)) if gain_percent > 0 => {
+                        self.accessory_gain = Some(AccessoryGain {
+                            gain_percent,
+                            response_time_nanos,
+                        });
+                        true
+                    }
+                    _ => false,
+                },
+                None => false,
+            },
+            TOLERANCE_OVERRIDE_KEY if value == "clear" => {
+                tolerances.clear_overrides();
+                true
+            }
+            TOLERANCE_OVERRIDE_KEY => match value.split_once(':') {
+                Some((micros, percent)) => match (parse(micros), parse(percent)) {
+                    (Some(micros), Some(percent)) => tolerances.set_override(micros, percent),
+                    _ => false,
+                },
+                None => false,
+            },
+            _ => false,
+        }
+    }
+
+    pub fn button_timings(&self) -> ButtonTimings {
+        ButtonTimings {
+            long_press_ms: self.long_press_ms as u32,
+            double_press_ms: self.double_press_ms as u32,
+        }
+    }
+
+    pub fn oversampling(&self) -> u32 {
+        hw::OVERSAMPLING_FACTORS[self.sensitivity as usize]
+    }
+
+    /// The trigger levels measurements are armed with, scaled to the learned gain. The
+    /// debug screen edits the stored ones.
+    pub fn measurement_thresholds(&self) -> TriggerThresholds {
+        match self.accessory_gain {
+            Some(gain) => gain.scale(self.trigger_thresholds),
+            None => self.trigger_thresholds,
+        }
+    }
+
+    /// Learned, or the stock module's
+    pub fn response_time_nanos(&self) -> u32 {
+        self.accessory_gain
+            .map_or(hw::SENSOR_RESPONSE_TIME_NANOS, |gain| {
+                gain.response_time_nanos
+            })
+    }
+
+    pub fn adc_bits(&self) -> u32 {
+        hw::adc_bits(self.adc_resolution)
+    }
+
+    pub fn adc_range(&self) -> u16 {
+        hw::adc_range(self.adc_resolution)
+    }
+
+    pub fn sample_time(&self) -> SampleTime {
+        if self.high_gain {
+            hw::HIGH_GAIN_SAMPLE_TIME
+        } else {
+            hw::SAMPLE_TIME
+        }
+    }
+
+    /// Full scale, a saturated sensor reads this
+    pub fn adc_max_value(&self) -> u16 {
+        self.adc_range() - 1
+    }
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            trigger_thresholds: hw::TRIGGER_THRESHOLDS,
+            emitter_intensity: 0,
+            sensitivity: 0,
+            sound_profile: SoundProfile::Full,
+            transition: if cfg!(feature = "effects") {
+                Transition::Slide
+            } else {
+                Transition::Off
+            },
+            fx_intensity: 100,
+            auto_trigger_low: false,
+            long_press_ms: 800,
+            double_press_ms: 250,
+            speed_table: SpeedTable::Standard,
+            duration_method: DurationMethod::Integral,
+            fixture_settle_ms: 0,
+            tolerances: Tolerances::default(),
+            flipped: false,
+            refire_watch_secs: 0,
+            quick_recal: false,
+            outlier_rejection: OutlierRejection::Off,
+            idle_signal: IdleSignal::ActiveHigh,
+            instant: false,
+            adc_resolution: hw::ADC_RESOLUTION,
+            high_gain: false,
+            burst_capture: false,
+            beeper_offset: 0,
+            accessory_gain: None,
+            user_profile: None,
+        }
+    }
+}
+
+fn set<T>(setting: &mut T, value: Option<T>) -> bool {
+    match value {
+        Some(value) => {
+            *setting = value;
+            true
+        }
+        None => false,
+    }
+}
+
+fn parse<T: FromStr>(value: &str) -> Option<T> {
+    value.parse().ok()
+}
+
+fn labeled<T: Copy>(options: &[T], label: fn(&T) -> &'static str, value: &str) -> Option<T> {
+    options
+        .iter()
+        .copied()
+        .find(|o| label(o).eq_ignore_ascii_case(value))
+}
+
+fn on_off(value: bool) -> &'static str {
+    if value {
+        "on"
+    } else {
+        "off"
+    }
+}
+
+fn parse_on_off(value: &str) -> Option<bool> {
+    match value {
+        "on" | "1" => Some(true),
+        "off" | "0" => Some(false),
+        _ => None,
+    }
+}
+
+fn next_option<T: Copy + PartialEq>(options: &[T], current: T) -> T {
+    let index = options
+        .iter()
+        .position(|&o| o == current)
+        .map_or(0, |i| i + 1);
+    options[index % options.len()]
+}
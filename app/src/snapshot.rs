@@ -0,0 +1,123 @@
+use core::cell::UnsafeCell;
+use core::sync::atomic::{self, AtomicBool, AtomicU32, Ordering};
+
+use app_measurements::{AdcFaults, CalibrationStage, MeasurementPhase, PeakHold, Profile};
+use config as hw;
+
+/// What the screens show of the sampling side, kept current by the DMA handler
+#[derive(Clone, Copy, Debug)]
+pub struct SamplingView {
+    pub adc_value: u16,
+    pub phase: MeasurementPhase,
+    pub waiting_for_sync: bool,
+    pub waiting_for_release: bool,
+    pub calibration_stage: Option<CalibrationStage>,
+    pub calibration_progress: Option<u8>,
+    pub calibration_remaining_samples: Option<u32>,
+}
+
+impl SamplingView {
+    const fn new() -> Self {
+        Self {
+            adc_value: 0,
+            phase: MeasurementPhase::Armed,
+            waiting_for_sync: false,
+            waiting_for_release: false,
+            calibration_stage: None,
+            calibration_progress: None,
+            calibration_remaining_samples: None,
+        }
+    }
+}
+
+pub static SAMPLING_VIEW: Snapshot<SamplingView> = Snapshot::new(SamplingView::new());
+
+/// Samples between [`StatusView`] publishes, a few per frame is plenty
+pub const STATUS_INTERVAL_SAMPLES: u32 = 256;
+
+/// Counters the screens show, too big to copy out on every sample like [`SamplingView`]
+#[derive(Clone, Copy, Debug)]
+pub struct StatusView {
+    pub peak_hold: PeakHold,
+    pub adc_faults: AdcFaults,
+    pub profile: Profile,
+    /// `None` unless events are being counted
+    pub event_count: Option<u32>,
+    pub events_per_minute: Option<u32>,
+}
+
+impl StatusView {
+    const fn new() -> Self {
+        Self {
+            peak_hold: PeakHold {
+                min: u16::MAX,
+                max: 0,
+            },
+            adc_faults: AdcFaults {
+                transfer_errors: 0,
+                overruns: 0,
+                invalidated: 0,
+                stuck: 0,
+            },
+            profile: Profile::new(hw::SYSCLK, hw::SAMPLE_RATE_HZ),
+            event_count: None,
+            events_per_minute: None,
+        }
+    }
+}
+
+pub static STATUS_VIEW: Snapshot<StatusView> = Snapshot::new(StatusView::new());
+
+/// Copy of a value published from a hot interrupt handler, read without ever
+/// blocking it.
+///
+/// The single [`SnapshotPublisher`] writes into whichever buffer readers aren't
+/// pointed at and then flips the sequence over to it. A reader copies the current
+/// buffer and checks the sequence afterwards: one publish in between only touched
+/// the other buffer, two or more may have torn the copy, so it goes again.
+pub struct Snapshot<T> {
+    /// Bumped by every publish, its low bit picks the buffer to read
+    sequence: AtomicU32,
+    buffers: [UnsafeCell<T>; 2],
+    publisher_taken: AtomicBool,
+}
+
+// Only the one publisher writes, and only to the buffer the sequence doesn't point at
+unsafe impl<T: Copy + Send> Sync for Snapshot<T> {}
+
+impl<T: Copy> Snapshot<T> {
+    pub const fn new(initial: T) -> Self {
+        Self {
+            sequence: AtomicU32::new(0),
+            buffers: [UnsafeCell::new(initial), UnsafeCell::new(initial)],
+            publisher_taken: AtomicBool::new(false),
+        }
+    }
+
+    /// The write side, `None` once it has been handed out
+    pub fn publisher(&'static self) -> Option<SnapshotPublisher<T>> {
+        (!self.publisher_taken.swap(true, Ordering::AcqRel)).then_some(SnapshotPublisher(self))
+    }
+
+    pub fn read(&self) -> T {
+        loop {
+            let sequence = self.sequence.load(Ordering::Acquire);
+            let value =
+                unsafe { core::ptr::read_volatile(self.buffers[(sequence & 1) as usize].get()) };
+            atomic::fence(Ordering::Acquire);
+            if self.sequence.load(Ordering::Relaxed).wrapping_sub(sequence) < 2 {
+                return value;
+            }
+        }
+    }
+}
+
+pub struct SnapshotPublisher<T: 'static>(&'static Snapshot<T>);
+
+impl<T: Copy> SnapshotPublisher<T> {
+    pub fn publish(&mut self, value: T) {
+        let next = self.0.sequence.load(Ordering::Relaxed).wrapping_add(1);
+        unsafe { core::ptr::write_volatile(self.0.buffers[(next & 1) as usize].get(), value) };
+        self.0.sequence.store(next, Ordering::Release);
+    }
+}
@@ -2,15 +2,125 @@ use fugit::ExtU32;
 use note_frequencies::note_frequencies_32;
 use rtic_monotonics::systick::Systick;
 
+use self::TuneStep::{Note, Rest, Tone};
+
 note_frequencies_32!(440.0);
 
 pub const NOTE_A0: usize = 69;
 
+/// A5, what the beeper tuning offsets are played from
+pub const TUNING_NOTE: isize = 12;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Chirp {
     Startup,
     Button,
     Measuring,
+    /// The light crossed the trigger level
+    Exposing,
+    /// The light went away, the tail is still being sampled
+    Finishing,
     Done,
+    Cancel,
+    Error,
+    /// The beeper tuning tone at this offset, whatever the profile
+    Tuning(i8),
+}
+
+impl Chirp {
+    pub fn is_error(&self) -> bool {
+        matches!(self, Chirp::Cancel | Chirp::Error)
+    }
+}
+
+/// Notes are in semitones relative to A4
+#[derive(Clone, Copy, Debug)]
+pub enum TuneStep {
+    /// Plays with a short attack an octave lower
+    Note(isize, u32),
+    /// Plain tone, for clicks
+    Tone(isize, u32),
+    Rest(u32),
+}
+
+pub type Tune = &'static [TuneStep];
+
+/// Of the tuning tone at `offset` semitones
+pub fn tuning_frequency(offset: i8) -> u32 {
+    NOTE_FREQUENCIES[(NOTE_A0 as isize + TUNING_NOTE + offset as isize) as usize] as u32
+}
+
+// Remember
+pub const TUNE_STARTUP: Tune = &[
+    Note(12 - 2, 250),
+    Note(12 + 5, 250),
+    Note(12 + 9, 250),
+    Rest(2000),
+];
+pub const TUNE_BUTTON: Tune = &[Tone(9, 50)];
+pub const TUNE_MEASURING: Tune = &[Note(24 - 2, 100), Note(20, 100)];
+pub const TUNE_EXPOSING: Tune = &[Tone(24 + 2, 40)];
+pub const TUNE_FINISHING: Tune = &[Tone(24 - 3, 40)];
+pub const TUNE_DONE: Tune = &[Note(12 - 2, 100), Note(24 - 2, 100)];
+pub const TUNE_CANCEL: Tune = &[Note(12 - 2, 100), Note(-2, 200)];
+pub const TUNE_ERROR: Tune = &[
+    Note(-2, 150),
+    Rest(75),
+    Note(-2, 150),
+    Rest(75),
+    Note(-2, 150),
+];
+pub const TUNE_CLICK: Tune = &[Tone(9, 20)];
+pub const TUNE_SHORT_DONE: Tune = &[Tone(21, 60)];
+pub const TUNE_SILENT: Tune = &[];
+pub const TUNE_TUNING: Tune = &[Tone(TUNING_NOTE, 400)];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SoundProfile {
+    Full,
+    /// Clicks instead of melodies
+    Short,
+    /// Silent except errors
+    Quiet,
+}
+
+impl SoundProfile {
+    pub const ALL: [SoundProfile; 3] =
+        [SoundProfile::Full, SoundProfile::Short, SoundProfile::Quiet];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            SoundProfile::Full => "FULL",
+            SoundProfile::Short => "SHORT",
+            SoundProfile::Quiet => "QUIET",
+        }
+    }
+
+    pub fn next(&self) -> Self {
+        let index = Self::ALL.iter().position(|p| p == self).unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+
+    pub fn tune(&self, chirp: Chirp) -> Tune {
+        match (self, chirp) {
+            (SoundProfile::Full, Chirp::Startup) => TUNE_STARTUP,
+            (SoundProfile::Full, Chirp::Button) => TUNE_BUTTON,
+            (SoundProfile::Full, Chirp::Measuring) => TUNE_MEASURING,
+            (SoundProfile::Full, Chirp::Exposing) => TUNE_EXPOSING,
+            (SoundProfile::Full, Chirp::Finishing) => TUNE_FINISHING,
+            (SoundProfile::Full, Chirp::Done) => TUNE_DONE,
+            (SoundProfile::Short, Chirp::Startup) => TUNE_SILENT,
+            (SoundProfile::Short, Chirp::Done) => TUNE_SHORT_DONE,
+            (
+                SoundProfile::Short,
+                Chirp::Button | Chirp::Measuring | Chirp::Exposing | Chirp::Finishing,
+            ) => TUNE_CLICK,
+            (SoundProfile::Quiet, chirp) if !chirp.is_error() => TUNE_SILENT,
+            (_, Chirp::Cancel) => TUNE_CANCEL,
+            (_, Chirp::Error) => TUNE_ERROR,
+            _ => TUNE_SILENT,
+        }
+    }
 }
 
 pub trait BeeperExt {
@@ -33,4 +143,21 @@ pub trait BeeperExt {
         Systick::delay(duration_millis.millis()).await;
         self.disable();
     }
+
+    /// Moved by `transpose` semitones, see the beeper tuning
+    async fn play_tune(&mut self, tune: Tune, transpose: isize) {
+        for step in tune {
+            match *step {
+                TuneStep::Note(note, duration_millis) => {
+                    self.play(note + transpose, duration_millis).await
+                }
+                TuneStep::Tone(note, duration_millis) => {
+                    self.note(note + transpose);
+                    Systick::delay(duration_millis.millis()).await;
+                    self.disable();
+                }
+                TuneStep::Rest(duration_millis) => Systick::delay(duration_millis.millis()).await,
+            }
+        }
+    }
 }
@@ -0,0 +1,48 @@
+use app_measurements::{
+    CycleCounterClock, MeasurementObserver, MeasurementResult, OBSERVER_BLOCK_LEN,
+};
+use config as hw;
+use heapless::Vec;
+use rtic_sync::channel::{Receiver, Sender};
+
+/// Blocks in flight, a few milliseconds of samples
+pub const STREAM_QUEUE_LEN: usize = 8;
+
+pub type StreamReceiver = Receiver<'static, StreamEvent, STREAM_QUEUE_LEN>;
+
+/// What the measurement hands to `usb_task`
+#[derive(Clone, Debug)]
+pub enum StreamEvent {
+    /// Cycle counter ticks of the light crossing trigger high
+    Trigger(u64),
+    Samples(Vec<u16, OBSERVER_BLOCK_LEN>),
+    Done,
+}
+
+/// Forwards the measurement's events from the DMA interrupt, dropping them
+/// when the receiver falls behind
+pub struct StreamObserver {
+    sender: Sender<'static, StreamEvent, STREAM_QUEUE_LEN>,
+}
+
+impl StreamObserver {
+    pub fn new(sender: Sender<'static, StreamEvent, STREAM_QUEUE_LEN>) -> Self {
+        Self { sender }
+    }
+}
+
+impl MeasurementObserver<CycleCounterClock<{ hw::SYSCLK }>> for StreamObserver {
+    fn on_trigger(&mut self, at: fugit::TimerInstantU64<{ hw::SYSCLK }>) {
+        let _ = self.sender.try_send(StreamEvent::Trigger(at.ticks()));
+    }
+
+    fn on_sample_block(&mut self, samples: &[u16]) {
+        if let Ok(samples) = Vec::from_slice(samples) {
+            let _ = self.sender.try_send(StreamEvent::Samples(samples));
+        }
+    }
+
+    fn on_done(&mut self, _result: &MeasurementResult) {
+        let _ = self.sender.try_send(StreamEvent::Done);
+    }
+}
@@ -0,0 +1,87 @@
+#![cfg_attr(not(feature = "usb"), allow(dead_code))]
+
+pub use app_measurements::{CommandParser, DeviceEvent, LogQueue, UsbExport, UsbRequest};
+use heapless::Deque;
+use rtic_sync::channel::{Receiver, Sender};
+
+/// Pending wakeups collapse into one, `usb_task` handles everything there is once awake
+pub type UsbWakeSender = Sender<'static, (), 1>;
+pub type UsbWakeReceiver = Receiver<'static, (), 1>;
+
+/// Room for the header of a result dump and then some, `usb_task` drains it as the host reads
+pub const USB_LOG_LEN: usize = 1024;
+pub type UsbLog = LogQueue<USB_LOG_LEN>;
+
+pub const USB_EVENT_QUEUE_LEN: usize = 8;
+
+/// [`DeviceEvent`]s waiting for `usb_task`, only collected while the host is subscribed
+pub struct UsbEvents {
+    subscribed: bool,
+    pending: Deque<DeviceEvent, USB_EVENT_QUEUE_LEN>,
+}
+
+impl UsbEvents {
+    pub const fn new() -> Self {
+        Self {
+            subscribed: false,
+            pending: Deque::new(),
+        }
+    }
+
+    pub fn is_subscribed(&self) -> bool {
+        self.subscribed
+    }
+
+    pub fn set_subscribed(&mut self, subscribed: bool) {
+        self.subscribed = subscribed;
+        if !subscribed {
+            self.pending.clear();
+        }
+    }
+
+    /// The oldest gives way when the host falls behind
+    pub fn push(&mut self, event: DeviceEvent) {
+        if !self.subscribed {
+            return;
+        }
+        if self.pending.is_full() {
+            self.pending.pop_front();
+        }
+        let _ = self.pending.push_back(event);
+    }
+
+    pub fn pop(&mut self) -> Option<DeviceEvent> {
+        self.pending.pop_front()
+    }
+}
+
+// The host picks the console behaviour through the baud rate it opens the port with
+pub const BINARY_BAUD_RATE: u32 = 921_600;
+pub const BOOTLOADER_BAUD_RATE: u32 = 1200;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConsoleMode {
+    /// Readable logs and single key commands
+    Text,
+    /// Little endian u16 light level samples, no logs
+    Binary,
+}
+
+pub fn console_mode(data_rate: u32) -> ConsoleMode {
+    match data_rate {
+        BINARY_BAUD_RATE => ConsoleMode::Binary,
+        _ => ConsoleMode::Text,
+    }
+}
+
+/// Gets `usb_task` going right away instead of on its next tick
+pub fn wake_usb(usb_wake_sender: &mut impl rtic::Mutex<T = UsbWakeSender>) {
+    usb_wake_sender.lock(|sender| {
+        let _ = sender.try_send(());
+    });
+}
+
+/// Arduino style "1200 baud touch": the port gets opened at 1200 baud and closed again
+pub fn is_bootloader_touch(data_rate: u32, dtr: bool) -> bool {
+    data_rate == BOOTLOADER_BAUD_RATE && !dtr
+}
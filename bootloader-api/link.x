@@ -7,5 +7,6 @@ SECTIONS {
    .bootloader_flags ORIGIN(BOOTLOADER_FLAGS) :
    {
       BOOTLOADER_FLAGS = ORIGIN(BOOTLOADER_FLAGS);
+      BOOT_INFO = ORIGIN(BOOTLOADER_FLAGS) + 4;
    } > BOOTLOADER_FLAGS
 }
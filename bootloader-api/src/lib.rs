@@ -4,16 +4,136 @@ use core::ptr::{addr_of, addr_of_mut};
 
 extern "C" {
     static mut BOOTLOADER_FLAGS: u32;
+    static mut BOOT_INFO: [u32; BOOT_INFO_WORDS];
+    static APP_INFO: [u32; APP_INFO_WORDS];
     static mut APP_START: u32;
 }
 
 const FLAG_REBOOT_DFU: u32 = 0x5AA55AA5;
+/// RAM keeps its content through a reset but not through a power cycle
+const BOOT_INFO_MAGIC: u32 = 0xB0071AF0;
+const BOOT_INFO_WORDS: usize = 4;
+/// Written by flash.sh next to the bootloader
+const APP_INFO_MAGIC: u32 = 0xA991CC32;
+const APP_INFO_WORDS: usize = 3;
+
+// RCC_CSR reset flags
+const CSR_BORRSTF: u32 = 1 << 25;
+const CSR_PINRSTF: u32 = 1 << 26;
+const CSR_PORRSTF: u32 = 1 << 27;
+const CSR_SFTRSTF: u32 = 1 << 28;
+const CSR_IWDGRSTF: u32 = 1 << 29;
+const CSR_WWDGRSTF: u32 = 1 << 30;
+const CSR_LPWRRSTF: u32 = 1 << 31;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ResetCause {
+    PowerOn,
+    Brownout,
+    /// The reset button or a debug probe
+    Pin,
+    /// Includes rebooting into DFU
+    Software,
+    IndependentWatchdog,
+    WindowWatchdog,
+    LowPower,
+    Unknown,
+}
+
+impl ResetCause {
+    const ALL: [ResetCause; 8] = [
+        ResetCause::PowerOn,
+        ResetCause::Brownout,
+        ResetCause::Pin,
+        ResetCause::Software,
+        ResetCause::IndependentWatchdog,
+        ResetCause::WindowWatchdog,
+        ResetCause::LowPower,
+        ResetCause::Unknown,
+    ];
+
+    /// Power-on sets the pin and brownout flags too, so the order matters
+    pub fn from_csr(csr: u32) -> Self {
+        [
+            (CSR_LPWRRSTF, ResetCause::LowPower),
+            (CSR_WWDGRSTF, ResetCause::WindowWatchdog),
+            (CSR_IWDGRSTF, ResetCause::IndependentWatchdog),
+            (CSR_SFTRSTF, ResetCause::Software),
+            (CSR_PORRSTF, ResetCause::PowerOn),
+            (CSR_BORRSTF, ResetCause::Brownout),
+            (CSR_PINRSTF, ResetCause::Pin),
+        ]
+        .iter()
+        .find(|(flag, _)| csr & flag != 0)
+        .map_or(ResetCause::Unknown, |&(_, cause)| cause)
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ResetCause::PowerOn => "POWER ON",
+            ResetCause::Brownout => "BROWNOUT",
+            ResetCause::Pin => "PIN",
+            ResetCause::Software => "SOFTWARE",
+            ResetCause::IndependentWatchdog => "IWDG",
+            ResetCause::WindowWatchdog => "WWDG",
+            ResetCause::LowPower => "LOW POWER",
+            ResetCause::Unknown => "UNKNOWN",
+        }
+    }
+}
+
+/// Kept by the bootloader next to its flags for the app to show
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BootInfo {
+    /// Since the last power cycle
+    pub boot_count: u32,
+    pub reset_cause: ResetCause,
+    /// `None` when the image carries no checksum, e.g. flashed with a probe
+    pub app_crc_valid: Option<bool>,
+}
+
+impl BootInfo {
+    fn encode(&self) -> [u32; BOOT_INFO_WORDS] {
+        let crc = match self.app_crc_valid {
+            None => 0,
+            Some(true) => 1,
+            Some(false) => 2,
+        };
+        [
+            BOOT_INFO_MAGIC,
+            self.boot_count,
+            self.reset_cause as u32,
+            crc,
+        ]
+    }
+
+    fn decode(words: [u32; BOOT_INFO_WORDS]) -> Option<Self> {
+        if words[0] != BOOT_INFO_MAGIC {
+            return None;
+        }
+        Some(Self {
+            boot_count: words[1],
+            reset_cause: *ResetCause::ALL.get(words[2] as usize)?,
+            app_crc_valid: match words[3] {
+                1 => Some(true),
+                2 => Some(false),
+                _ => None,
+            },
+        })
+    }
+}
 
 #[allow(clippy::missing_safety_doc)]
 pub unsafe fn app_ptr() -> *const u32 {
     core::ptr::addr_of!(APP_START)
 }
 
+/// Length in bytes and CRC-32/MPEG-2 of the app image, as the CRC unit computes it
+pub fn app_info() -> Option<(u32, u32)> {
+    let words = unsafe { core::ptr::read_volatile(addr_of!(APP_INFO)) };
+    (words[0] == APP_INFO_MAGIC).then_some((words[1], words[2]))
+}
+
 fn read_flag() -> u32 {
     unsafe { core::ptr::read_volatile(addr_of!(BOOTLOADER_FLAGS)) }
 }
@@ -30,6 +150,24 @@ pub fn is_dfu_boot_flag_set() -> bool {
     read_flag() == FLAG_REBOOT_DFU
 }
 
+pub fn boot_info() -> Option<BootInfo> {
+    BootInfo::decode(unsafe { core::ptr::read_volatile(addr_of!(BOOT_INFO)) })
+}
+
+/// Called by the bootloader once per boot, counts up from whatever survived the reset
+pub fn record_boot(reset_cause: ResetCause, app_crc_valid: Option<bool>) {
+    let boot_count = match (reset_cause, boot_info()) {
+        (ResetCause::PowerOn | ResetCause::Brownout, _) | (_, None) => 1,
+        (_, Some(info)) => info.boot_count.wrapping_add(1),
+    };
+    let info = BootInfo {
+        boot_count,
+        reset_cause,
+        app_crc_valid,
+    };
+    unsafe { core::ptr::write_volatile(addr_of_mut!(BOOT_INFO), info.encode()) }
+}
+
 pub fn reboot_into_bootloader() -> ! {
     write_flag(FLAG_REBOOT_DFU);
     cortex_m::interrupt::disable();
@@ -1,11 +1,18 @@
 MEMORY
 {
-  FLASH : ORIGIN = 0x00000000, LENGTH = 16K
-  APP : ORIGIN = ORIGIN(FLASH) + LENGTH(FLASH), LENGTH = 1K
+  FLASH : ORIGIN = 0x00000000, LENGTH = 16K - 16
+  /* Length and CRC of the app, written by flash.sh */
+  APP_INFO : ORIGIN = ORIGIN(FLASH) + LENGTH(FLASH), LENGTH = 16
+  APP : ORIGIN = ORIGIN(APP_INFO) + LENGTH(APP_INFO), LENGTH = 1K
   RAM : ORIGIN = 0x20000000, LENGTH = 63K /* last KB left free for bootloader flags */
 }
 
 SECTIONS {
+   .app_info ORIGIN(APP_INFO) (NOLOAD) :
+   {
+      APP_INFO = ORIGIN(APP_INFO);
+   } > APP_INFO
+
    .app_start ORIGIN(APP) :
    {
       APP_START = ORIGIN(APP);
@@ -31,11 +31,27 @@ fn main() -> ! {
     let is_dfu_boot = bootloader_api::is_dfu_boot_flag_set();
     bootloader_api::reset_bootloader_flags();
 
+    // The flags stick until cleared, otherwise every reset looks like power-on
+    let csr = dp.RCC.csr.read().bits();
+    dp.RCC.csr.modify(|_, w| w.rmvf().set_bit());
+    let crc_unit = dp.CRC;
+    let app_crc_valid = bootloader_api::app_info().map(|(len, crc)| {
+        let app =
+            unsafe { core::slice::from_raw_parts(bootloader_api::app_ptr(), len as usize / 4) };
+        hal::crc32::Crc32::new(crc_unit).update(app) == crc
+    });
+    bootloader_api::record_boot(bootloader_api::ResetCause::from_csr(csr), app_crc_valid);
+
     if is_dfu_boot {
+        // Same panel window as the app, the flip setting doesn't survive a reset
+        let geometry = hw::BackupRegisters::new(dp.RTC, &dp.RCC, &dp.PWR)
+            .read_display_geometry()
+            .unwrap_or(hw::DISPLAY_GEOMETRY);
         let clocks = config::setup_clocks!(dp);
         let mut delay = config::delay_timer!(dp).delay_us(&clocks);
-        let mut display =
-            unsafe { hw::setup_display!(dp, gpio, &clocks, &mut delay).unwrap_unchecked() };
+        let mut display = unsafe {
+            hw::setup_display!(dp, gpio, &clocks, &mut delay, geometry, false).unwrap_unchecked()
+        };
         hw::display_backlight_pin!(gpio)
             .into_push_pull_output()
             .set_high();
@@ -0,0 +1,88 @@
+use app_measurements::CaptureEdge;
+
+use crate::hal::pac::{RCC, TIM1};
+use crate::hal::rcc::Clocks;
+
+// TIM1 CH2 captures rising edges on TI2 directly,
+// CH1 captures falling edges on TI2 through the indirect mapping
+const CCMR1_CAPTURE: u32 = 0b10 // CC1S: IC1 <- TI2
+    | 0b0011 << 4 // IC1F: 8 samples filter
+    | 0b01 << 8 // CC2S: IC2 <- TI2
+    | 0b0011 << 12; // IC2F: 8 samples filter
+const CCER_CAPTURE: u32 = 1 // CC1E
+    | 1 << 1 // CC1P: falling
+    | 1 << 4; // CC2E, rising
+const SR_UIF: u32 = 1;
+const SR_CC1IF: u32 = 1 << 1;
+const SR_CC2IF: u32 = 1 << 2;
+const DIER_CAPTURE: u32 = SR_UIF | SR_CC1IF | SR_CC2IF;
+
+/// Free-running 16 bit timer extended to 64 bits by counting overflows in software
+pub struct InputCapture {
+    tim: TIM1,
+    clock_hz: u32,
+    overflows: u32,
+}
+
+impl InputCapture {
+    pub fn new(tim: TIM1, clocks: &Clocks) -> Self {
+        unsafe {
+            (*RCC::ptr()).apb2enr.modify(|_, w| w.tim1en().set_bit());
+        }
+
+        tim.psc.write(|w| w.psc().bits(0));
+        tim.arr.write(|w| unsafe { w.bits(0xffff) });
+        tim.ccmr1_input()
+            .write(|w| unsafe { w.bits(CCMR1_CAPTURE) });
+        tim.ccer.write(|w| unsafe { w.bits(CCER_CAPTURE) });
+        tim.cr1.modify(|_, w| w.cen().set_bit());
+
+        Self {
+            tim,
+            clock_hz: clocks.timclk2().raw(),
+            overflows: 0,
+        }
+    }
+
+    pub fn clock_hz(&self) -> u32 {
+        self.clock_hz
+    }
+
+    pub fn listen(&mut self) {
+        self.overflows = 0;
+        self.tim.sr.write(|w| unsafe { w.bits(0) });
+        self.tim.dier.write(|w| unsafe { w.bits(DIER_CAPTURE) });
+    }
+
+    pub fn unlisten(&mut self) {
+        self.tim.dier.write(|w| unsafe { w.bits(0) });
+    }
+
+    /// Call from the update interrupt
+    pub fn on_update(&mut self) {
+        if self.tim.sr.read().bits() & SR_UIF != 0 {
+            self.tim.sr.write(|w| unsafe { w.bits(!SR_UIF) });
+            self.overflows += 1;
+        }
+    }
+
+    /// Call from the capture/compare interrupt
+    pub fn on_capture(&mut self) -> Option<(CaptureEdge, u64)> {
+        let sr = self.tim.sr.read().bits();
+        let (edge, captured) = if sr & SR_CC2IF != 0 {
+            (CaptureEdge::Rising, self.tim.ccr[1].read().bits())
+        } else if sr & SR_CC1IF != 0 {
+            (CaptureEdge::Falling, self.tim.ccr[0].read().bits())
+        } else {
+            return None;
+        };
+
+        // The counter has wrapped after the capture but before the update interrupt ran
+        let mut overflows = self.overflows as u64;
+        if sr & SR_UIF != 0 && captured < 0x8000 {
+            overflows += 1;
+        }
+
+        Some((edge, overflows << 16 | captured as u64))
+    }
+}
@@ -4,13 +4,105 @@ pub use {display_interface_spi, embedded_time, fugit, stm32f4xx_hal as hal};
 
 #[macro_use]
 mod macros;
+mod input_capture;
+
+pub use input_capture::InputCapture;
 
 // Timer allocation
 // TIM2 <-> ADC1
 // TIM3 -> display delay
 // TIM4 -> sound PWM
+// TIM9 -> emitter PWM
+// TIM1 -> digital trigger input capture
+// TIM5 -> linear sensor sweeps
+
+// Interrupt priorities, RTIC only takes literals in `priority = ..` so the tasks in
+// app/src/main.rs repeat these. `check_priorities` compares the bound ones in init,
+// the software tasks each call `check_task_priority` as they start
+/// ADC DMA transfers and overruns, a late one loses samples
+pub const PRIORITY_ADC_DMA: u8 = 5;
+/// Sync, rotary and input capture edges, timestamped as they come in
+pub const PRIORITY_EDGES: u8 = 5;
+/// Note lengths come from async delays, a busy UI would stretch them
+pub const PRIORITY_BEEPER: u8 = 5;
+pub const PRIORITY_BUTTON: u8 = 4;
+/// Starts each ADC scan and linear sensor sweep
+pub const PRIORITY_ADC_TIMER: u8 = 3;
+/// Measurement and mode tasks
+pub const PRIORITY_TASKS: u8 = 2;
+/// Display, USB, errors and banners
+pub const PRIORITY_UI: u8 = 1;
+
+// A scan's transfer is handled before the next one is started
+const _: () = assert!(PRIORITY_ADC_DMA > PRIORITY_ADC_TIMER);
+const _: () = assert!(PRIORITY_ADC_TIMER > PRIORITY_TASKS);
+const _: () = assert!(PRIORITY_TASKS > PRIORITY_UI);
+// Cancelling has to get through a busy mode task
+const _: () = assert!(PRIORITY_BUTTON > PRIORITY_TASKS);
+const _: () = assert!(PRIORITY_BEEPER > PRIORITY_UI);
+const _: () = assert!(PRIORITY_UI > 0);
+const _: () = assert!(PRIORITY_ADC_DMA as u32 <= 1 << hal::pac::NVIC_PRIO_BITS);
+const _: () = assert!(PRIORITY_EDGES as u32 <= 1 << hal::pac::NVIC_PRIO_BITS);
+const _: () = assert!(PRIORITY_BEEPER as u32 <= 1 << hal::pac::NVIC_PRIO_BITS);
+
+/// Panics on an interrupt bound in app/src/main.rs with another priority than
+/// the one above, RTIC has set them all before init runs
+pub fn check_priorities() {
+    use hal::pac::{Interrupt, NVIC};
+
+    for (interrupt, priority) in [
+        (Interrupt::DMA2_STREAM0, PRIORITY_ADC_DMA),
+        (Interrupt::ADC, PRIORITY_ADC_DMA),
+        (Interrupt::TIM2, PRIORITY_ADC_TIMER),
+        (Interrupt::TIM5, PRIORITY_ADC_TIMER),
+        (Interrupt::EXTI2, PRIORITY_BUTTON),
+        (Interrupt::EXTI15_10, PRIORITY_EDGES),
+        (Interrupt::TIM1_CC, PRIORITY_EDGES),
+        (Interrupt::TIM1_UP_TIM10, PRIORITY_EDGES),
+        (Interrupt::OTG_FS, PRIORITY_UI),
+    ] {
+        assert_eq!(
+            NVIC::get_priority(interrupt),
+            nvic_priority(priority),
+            "{:?}",
+            interrupt
+        );
+    }
+}
+
+/// Panics unless the calling task runs at `priority`. Software tasks get whichever
+/// dispatcher their `priority = ..` asks for, so [`check_priorities`] can't see them.
+pub fn check_task_priority(priority: u8) {
+    use hal::pac::{NVIC, SCB};
+
+    // VECTACTIVE, the dispatcher's interrupt while a software task runs
+    let active = unsafe { (*SCB::PTR).icsr.read() } & 0x1ff;
+    assert!(active >= 16, "not in a task");
+    let actual = unsafe { (*NVIC::PTR).ipr[active as usize - 16].read() };
+    assert_eq!(
+        actual,
+        nvic_priority(priority),
+        "task priority {}",
+        priority
+    );
+}
+
+// Lower numbers preempt, only the top bits are implemented
+const fn nvic_priority(priority: u8) -> u8 {
+    ((1 << hal::pac::NVIC_PRIO_BITS) - priority) << (8 - hal::pac::NVIC_PRIO_BITS)
+}
 
 pub const CALIBRATION_TIME_MS: u32 = 1000;
+/// Time given to cover or uncover the sensor when asked to
+pub const CALIBRATION_PROMPT_MS: u32 = 2000;
+/// Anything above reading covered counts as a faulty sensor
+pub const fn calibration_max_covered(adc_range: u16) -> u16 {
+    adc_range / 8
+}
+// How far the dark level may move before a quick recalibration falls back to a full one
+pub const fn calibration_max_drift(adc_range: u16) -> u16 {
+    adc_range / 128
+}
 
 pub const TRIGGER_THRESHOLDS: TriggerThresholds = TriggerThresholds {
     low_ratio: 1.0,
@@ -19,6 +111,59 @@ pub const TRIGGER_THRESHOLDS: TriggerThresholds = TriggerThresholds {
     high_delta: ADC_RANGE / 16u16,
 };
 
+pub const fn trigger_adjust_step(adc_range: u16) -> u16 {
+    if adc_range >= 256 {
+        adc_range / 256
+    } else {
+        1
+    }
+}
+
+// Well above the ADC sample rate so that the ripple averages out
+pub const EMITTER_PWM_FREQ_HZ: u32 = 1_000_000;
+pub const EMITTER_SETTLE_MS: u32 = 250;
+pub const EMITTER_INTENSITY_STEP: u8 = 25;
+
+// Rotary contacts are read this long after their last edge, 0 reads them on every edge
+pub const ROTARY_DEBOUNCE_MS: u32 = 0;
+
+// Accessory sense line: polled, a level change has to hold for the debounce time, unplugging
+// for the grace period while a measurement is running
+pub const ACCESSORY_POLL_MS: u32 = 25;
+pub const ACCESSORY_DEBOUNCE_MS: u32 = 100;
+pub const ACCESSORY_GRACE_MS: u32 = 2_000;
+// Identical raw conversions in a row before the sensor is reported stuck, 200 ms worth
+pub const SENSOR_STUCK_SAMPLES: u32 = 20_000;
+
+// Stock ST7735S modules, off-spec ones are adjusted from the menu and keep that instead
+pub const DISPLAY_GEOMETRY: DisplayGeometry = DisplayGeometry {
+    offset_x: 0,
+    offset_y: 0,
+    width: 132,
+    height: 162,
+};
+
+// From arming to the release output firing, and how long it stays on
+pub const RELEASE_DELAY_MS: u32 = 500;
+pub const RELEASE_PULSE_MS: u32 = 150;
+// Board LED blink as the light crosses the trigger levels, long enough to catch the eye
+pub const LED_FLASH_MS: u32 = 60;
+// Emitter pulse the self check measures back, 1/100, and how close the measurement has to come
+pub const SELF_CHECK_PULSE_MICROS: u32 = 10_000;
+pub const SELF_CHECK_TOLERANCE_PERCENT: u8 = 2;
+// What the stock sensor module reads above dark for that pulse at full emitter intensity,
+// in 12 bit counts. Learned gains are relative to it.
+pub const LOOPBACK_REFERENCE_AMPLITUDE: u16 = 2000;
+
+// How long a test stand lid has to stay closed before measuring, 0 ignores the input
+pub const FIXTURE_SETTLE_OPTIONS_MS: [u16; 4] = [0, 100, 500, 1000];
+
+// ADC conversions averaged per sample, indexed by sensitivity level
+pub const OVERSAMPLING_FACTORS: [u32; 3] = [1, 4, 16];
+
+// Indices into KNOWN_SHUTTER_DURATIONS walked through by the test sequence, 1s to 1/1000
+pub const TEST_SEQUENCE_SPEEDS: [usize; 11] = [6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16];
+
 // pub const TRIGGER_THRESHOLDS: TriggerThresholds = TriggerThresholds {
 //     low_ratio: 1.8,
 //     high_ratio: 2.0,
@@ -26,23 +171,106 @@ pub const TRIGGER_THRESHOLDS: TriggerThresholds = TriggerThresholds {
 //     high_delta: 0,
 // };
 
-pub const ADC_RESOLUTION: Resolution = Resolution::Twelve;
-pub const ADC_RANGE: u16 = 2u16.pow(match ADC_RESOLUTION {
-    Resolution::Six => 6,
-    Resolution::Eight => 8,
-    Resolution::Ten => 10,
-    Resolution::Twelve => 12,
-});
+// Offered in the menu, the first one is the default. Fewer bits convert faster and
+// spread less, at the cost of coarser thresholds
+pub const ADC_RESOLUTIONS: [Resolution; 2] = [Resolution::Twelve, Resolution::Eight];
+pub const ADC_RESOLUTION: Resolution = ADC_RESOLUTIONS[0];
+pub const ADC_RANGE: u16 = adc_range(ADC_RESOLUTION);
+
+pub const fn adc_bits(resolution: Resolution) -> u32 {
+    match resolution {
+        Resolution::Six => 6,
+        Resolution::Eight => 8,
+        Resolution::Ten => 10,
+        Resolution::Twelve => 12,
+    }
+}
+
+pub const fn adc_range(resolution: Resolution) -> u16 {
+    2u16.pow(adc_bits(resolution))
+}
+
+/// One of [`ADC_RESOLUTIONS`]
+pub fn adc_resolution_from_bits(bits: u32) -> Option<Resolution> {
+    ADC_RESOLUTIONS
+        .into_iter()
+        .find(|&resolution| adc_bits(resolution) == bits)
+}
 
 pub const SAMPLE_TIME: SampleTime = SampleTime::Cycles_3;
+// For dim light behind ground glass: a sensor with a large load resistor has a high
+// output impedance and needs longer to charge the sampling capacitor
+pub const HIGH_GAIN_SAMPLE_TIME: SampleTime = SampleTime::Cycles_28;
+pub const fn sample_cycles(sample_time: SampleTime) -> u32 {
+    match sample_time {
+        SampleTime::Cycles_3 => 3,
+        SampleTime::Cycles_15 => 15,
+        SampleTime::Cycles_28 => 28,
+        SampleTime::Cycles_56 => 56,
+        SampleTime::Cycles_84 => 84,
+        SampleTime::Cycles_112 => 112,
+        SampleTime::Cycles_144 => 144,
+        SampleTime::Cycles_480 => 480,
+    }
+}
 pub const SAMPLE_RATE_HZ: u32 = 100_000_u32;
 pub const SYSCLK: u32 = 84_000_000;
+// At startup the cycle counter is timed against SysTick for this long, both have to
+// agree on the core clock within the tolerance
+pub const CLOCK_CHECK_INTERVAL_MS: u32 = 100;
+pub const CLOCK_TOLERANCE_PPM: u32 = 1000;
 pub const HCLK: u32 = 42_000_000;
+pub const PCLK2_HZ: u32 = 80_000_000;
+// ADC1 runs off PCLK2 / 6, see _setup_adc
+pub const ADC_CLOCK_HZ: u32 = PCLK2_HZ / 6;
+// Sampling, then a cycle per bit of resolution
+pub const fn adc_conversion_cycles(resolution: Resolution, sample_time: SampleTime) -> u32 {
+    sample_cycles(sample_time) + adc_bits(resolution)
+}
+// From the center photodiode's sample being held to the end of the scan, when the DMA
+// handler timestamps it: the rest of its own conversion and all of the right one's.
+// Taken off the light edges, which otherwise trail the sync input by this much.
+pub const fn adc_latency_nanos(resolution: Resolution, sample_time: SampleTime) -> u32 {
+    ((adc_bits(resolution) + adc_conversion_cycles(resolution, sample_time)) as u64 * 1_000_000_000
+        / ADC_CLOCK_HZ as u64) as u32
+}
+// A whole scan has to be converted before the next sample clock tick
+const _: () = assert!(
+    ADC_CHANNELS as u32 * adc_conversion_cycles(ADC_RESOLUTIONS[0], HIGH_GAIN_SAMPLE_TIME)
+        <= ADC_CLOCK_HZ / SAMPLE_RATE_HZ
+);
 pub const SPI_FREQ_HZ: u32 = 10_000_000;
+// First order time constant of the light sensor module, measured on a flash or an LED step.
+// Integrated times are corrected for it, 0 leaves them as measured.
+pub const SENSOR_RESPONSE_TIME_NANOS: u32 = 0;
+
+// Rolling shutter accessory: a row of SCAN_CHANNELS photodiodes on an MCP3208 SPI ADC
+pub const LINEAR_SENSOR_SPI_FREQ_HZ: u32 = 2_000_000;
+pub const LINEAR_SENSOR_SWEEP_RATE_HZ: u32 = 2_000;
+// Photodiode spacing along the scan direction, results are scaled to a 24x36 frame height
+pub const LINEAR_SENSOR_PITCH_UM: u32 = 3_000;
+pub const FRAME_HEIGHT_UM: u32 = 24_000;
+
+// Focal plane accessory: left and right photodiodes next to the center one, scanned by ADC1
+// along with it. Curtain travel is scaled to a 24x36 frame width.
+pub const ADC_CHANNELS: usize = 3;
+pub const FOCAL_PLANE_SENSOR_PITCH_UM: u32 = 15_000;
+pub const FRAME_WIDTH_UM: u32 = 36_000;
+
+// Expansion header: I2C2 for accessories that bring their own chips, like an ambient light
+// sensor, an OLED viewfinder or an EEPROM. Scanned at startup, those with a driver again
+// every EXPANSION_POLL_MS.
+pub const EXPANSION_I2C_FREQ_HZ: u32 = 100_000;
+pub const EXPANSION_POLL_MS: u32 = 1_000;
 
 pub type DisplaySpiType = ExclusiveDevice<Spi<SPI1>, ErasedPin<Output>, NoDelay>;
-pub type DmaTransfer = Transfer<Stream0<DMA2>, 0, Adc<ADC1>, PeripheralToMemory, &'static mut u16>;
-pub type AdcTimerType = CounterHz<TIM2>;
+pub type LinearSensorSpiType = ExclusiveDevice<Spi<SPI2>, ErasedPin<Output>, NoDelay>;
+pub type SamplerType = Stm32f4Sampler<ADC_CHANNELS>;
+pub type LinearSensorTimerType = CounterHz<TIM5>;
+pub type DisplayDelayType = DelayUs<TIM3>;
+pub type ExpansionI2cType = I2c<I2C2>;
+/// Same pin as `accessory_idle_signal`, switches modes for the open drain emulation
+pub type AccessoryIdlePin = hal::gpio::DynamicPin<'B', 8>;
 
 #[macro_export]
 macro_rules! setup_clocks {
@@ -55,20 +283,36 @@ macro_rules! setup_clocks {
             .hclk($crate::HCLK.MHz())
             .use_hse(25.MHz())
             .pclk1(80.MHz())
-            .pclk2(80.MHz())
+            .pclk2($crate::PCLK2_HZ.Hz())
             .freeze()
     }};
 }
 
+/// What `setup_clocks` asked for actually happened: the PLL runs off the crystal and
+/// drives the core at [`SYSCLK`]. The HAL falls back quietly where it can't comply.
+pub fn clock_fault(clocks: &Clocks) -> Option<ClockFault> {
+    let rcc = unsafe { &*RCC::ptr() };
+    let from_crystal = rcc.cr.read().hserdy().bit_is_set()
+        && rcc.pllcfgr.read().pllsrc().bit_is_set()
+        // 0b10: the PLL is the system clock
+        && rcc.cfgr.read().sws().bits() == 0b10;
+    if !from_crystal {
+        return Some(ClockFault::NoCrystal);
+    }
+    let measured_hz = clocks.sysclk().raw();
+    (measured_hz != SYSCLK).then_some(ClockFault::WrongRate { measured_hz })
+}
+
+/// Left stopped, the sampler starts it
 pub fn _setup_adc_timer(t: TIM2, clocks: &Clocks) -> CounterHz<TIM2> {
     use hal::timer::Event;
 
     let mut timer = t.counter_hz(clocks);
     timer.listen(Event::Update);
-    timer.start(SAMPLE_RATE_HZ.Hz()).unwrap();
 
     timer
 }
+
 #[macro_export]
 macro_rules! setup_adc_timer {
     ($dp:expr, $clocks:expr) => {{
@@ -76,25 +320,69 @@ macro_rules! setup_adc_timer {
     }};
 }
 
-pub fn _setup_adc(adc: ADC1, adc_pin: Pin<'A', 1, Analog>) -> Adc<ADC1> {
+pub fn _setup_linear_sensor_timer(t: TIM5, clocks: &Clocks) -> CounterHz<TIM5> {
+    use hal::timer::Event;
+
+    let mut timer = t.counter_hz(clocks);
+    timer.listen(Event::Update);
+    timer.start(LINEAR_SENSOR_SWEEP_RATE_HZ.Hz()).unwrap();
+
+    timer
+}
+
+#[macro_export]
+macro_rules! setup_linear_sensor_timer {
+    ($dp:expr, $clocks:expr) => {{
+        $crate::_setup_linear_sensor_timer($dp.TIM5, $clocks)
+    }};
+}
+
+/// Scanned left to right, the other accessories only connect the center pin
+pub fn _setup_adc(
+    adc: ADC1,
+    adc_pin: Pin<'A', 1, Analog>,
+    left_pin: Pin<'A', 4, Analog>,
+    right_pin: Pin<'B', 0, Analog>,
+) -> Adc<ADC1> {
     use hal::adc::config::{AdcConfig, Clock, Scan, Sequence};
 
     let adc_config = AdcConfig::default()
         .dma(Dma::Continuous)
-        .scan(Scan::Disabled)
+        .scan(Scan::Enabled)
         .clock(Clock::Pclk2_div_6)
         .resolution(ADC_RESOLUTION);
 
     let mut adc = Adc::adc1(adc, true, adc_config);
-    adc.configure_channel(&adc_pin, Sequence::One, SAMPLE_TIME);
+    adc.configure_channel(&left_pin, Sequence::One, SAMPLE_TIME);
+    adc.configure_channel(&adc_pin, Sequence::Two, SAMPLE_TIME);
+    adc.configure_channel(&right_pin, Sequence::Three, SAMPLE_TIME);
+    unsafe {
+        (*ADC1::ptr()).cr1.modify(|_, w| w.ovrie().set_bit());
+    }
     adc
 }
 
+#[macro_export]
+macro_rules! setup_input_capture {
+    ($dp:expr, $gpio:expr, $clocks:expr) => {{
+        // AF1: TIM1_CH2
+        let _pin = $crate::capture_pin!($gpio).into_alternate::<1>();
+        $crate::InputCapture::new($dp.TIM1, $clocks)
+    }};
+}
+
 #[macro_export]
 macro_rules! setup_adc {
     ($dp:expr, $gpio:expr) => {{
         let pin = $crate::adc_pin!($gpio);
-        $crate::_setup_adc($dp.ADC1, pin.into_analog())
+        let left_pin = $crate::focal_plane_left_pin!($gpio);
+        let right_pin = $crate::focal_plane_right_pin!($gpio);
+        $crate::_setup_adc(
+            $dp.ADC1,
+            pin.into_analog(),
+            left_pin.into_analog(),
+            right_pin.into_analog(),
+        )
     }};
 }
 
@@ -107,12 +395,34 @@ macro_rules! setup_adc_dma_transfer {
         let dma = StreamsTuple::new($dp.DMA2);
         let dma_config = DmaConfig::default()
             .transfer_complete_interrupt(true)
+            .transfer_error_interrupt(true)
+            .memory_increment(true)
             .double_buffer(false);
 
         Transfer::init_peripheral_to_memory(dma.0, $adc, $buffer, None, dma_config)
     }};
 }
 
+/// Stopped until a mode that measures starts it
+#[macro_export]
+macro_rules! setup_sampler {
+    ($core:expr, $dp:expr, $gpio:expr, $clocks:expr, $first_buffer:expr, $spare_buffer:expr, $burst_buffer:expr) => {{
+        let mut adc = $crate::setup_adc!($dp, $gpio);
+        // Powered up along with its timer once a mode samples
+        adc.disable();
+        let transfer = $crate::setup_adc_dma_transfer!($core, $dp, adc, $first_buffer);
+        let timer = $crate::setup_adc_timer!($dp, $clocks);
+        $crate::SamplerType::new(
+            transfer,
+            timer,
+            $spare_buffer,
+            $crate::SAMPLE_RATE_HZ,
+            $crate::ADC_RESOLUTION,
+        )
+        .with_burst($burst_buffer, $crate::ADC_CLOCK_HZ)
+    }};
+}
+
 #[macro_export]
 macro_rules! delay_timer {
     ($dp:expr) => {
@@ -151,9 +461,55 @@ macro_rules! setup_display_spi {
     }};
 }
 
+#[macro_export]
+macro_rules! setup_linear_sensor_spi {
+    ($dp:expr, $gpio:expr, $clocks:expr) => {{
+        use $crate::fugit::RateExtU32;
+        use $crate::hal::spi::Spi;
+
+        let sclk_pin = $crate::linear_sensor_sclk_pin!($gpio).into_alternate();
+        let miso_pin = $crate::linear_sensor_miso_pin!($gpio).into_alternate();
+        let mosi_pin = $crate::linear_sensor_mosi_pin!($gpio).into_alternate();
+        let mut cs_pin = $crate::linear_sensor_cs_pin!($gpio).into_push_pull_output();
+        cs_pin.set_high();
+
+        let bus = Spi::new(
+            $dp.SPI2,
+            (sclk_pin, miso_pin, mosi_pin),
+            embedded_hal::spi::MODE_0,
+            $crate::LINEAR_SENSOR_SPI_FREQ_HZ.Hz(),
+            &$clocks,
+        );
+        embedded_hal_bus::spi::ExclusiveDevice::new(
+            bus,
+            cs_pin.erase(),
+            embedded_hal_bus::spi::NoDelay,
+        )
+        .unwrap()
+    }};
+}
+
+#[macro_export]
+macro_rules! setup_expansion_i2c {
+    ($dp:expr, $gpio:expr, $clocks:expr) => {{
+        use $crate::fugit::RateExtU32;
+        use $crate::hal::i2c::I2c;
+
+        // The header has no pull-ups of its own, these keep an empty bus from floating
+        let scl_pin = $crate::expansion_scl_pin!($gpio).internal_pull_up(true);
+        let sda_pin = $crate::expansion_sda_pin!($gpio).internal_pull_up(true);
+        I2c::new(
+            $dp.I2C2,
+            (scl_pin, sda_pin),
+            $crate::EXPANSION_I2C_FREQ_HZ.Hz(),
+            &$clocks,
+        )
+    }};
+}
+
 #[macro_export]
 macro_rules! setup_display {
-    ($dp:expr, $gpio:expr, $clocks:expr, $delay:expr) => {{
+    ($dp:expr, $gpio:expr, $clocks:expr, $delay:expr, $geometry:expr, $flipped:expr) => {{
         use $crate::display_interface_spi::SPIInterface;
         use $crate::hal::gpio::{Edge, ErasedPin, Input, Output, Speed};
         let spi = $crate::setup_display_spi!($dp, $gpio, $clocks);
@@ -163,17 +519,28 @@ macro_rules! setup_display {
         rst_pin.set_speed(Speed::VeryHigh);
 
         let di = SPIInterface::new(spi, dc_pin.erase());
-        mipidsi::Builder::new(mipidsi::models::ST7735s, di)
-            .reset_pin(rst_pin.erase())
-            .orientation(
-                mipidsi::options::Orientation::new().rotate(mipidsi::options::Rotation::Deg180),
-            )
-            .display_offset(0, 0)
-            .display_size(132, 162)
-            .init($delay)
+        $crate::init_display!(di, rst_pin.erase(), $delay, $geometry, $flipped)
     }};
 }
 
+/// Cycles the reset pin and sends the init sequence, also used to recover from bus errors.
+/// The panel is mounted upside down, `$flipped` turns it the right way up again.
+#[macro_export]
+macro_rules! init_display {
+    ($di:expr, $rst_pin:expr, $delay:expr, $geometry:expr, $flipped:expr) => {
+        mipidsi::Builder::new(mipidsi::models::ST7735s, $di)
+            .reset_pin($rst_pin)
+            .orientation(mipidsi::options::Orientation::new().rotate(if $flipped {
+                mipidsi::options::Rotation::Deg0
+            } else {
+                mipidsi::options::Rotation::Deg180
+            }))
+            .display_offset($geometry.offset_x, $geometry.offset_y)
+            .display_size($geometry.width, $geometry.height)
+            .init($delay)
+    };
+}
+
 #[macro_export]
 macro_rules! beeper_type {
     () => {
@@ -225,6 +592,111 @@ macro_rules! setup_sound_pwm {
     }};
 }
 
+#[macro_export]
+macro_rules! emitter_type {
+    () => {
+        use $crate::hal::pac::TIM9;
+
+        pub struct Emitter {
+            pwm: $crate::hal::timer::PwmHz<TIM9, $crate::hal::timer::ChannelBuilder<TIM9, 1>>,
+        }
+
+        impl EmitterExt for Emitter {
+            fn set_intensity_percent(&mut self, intensity_percent: u8) {
+                use hal::timer::Channel;
+
+                if intensity_percent == 0 {
+                    self.pwm.disable(Channel::C2);
+                    return;
+                }
+                self.pwm.set_duty(
+                    Channel::C2,
+                    (self.pwm.get_max_duty() as u32 * intensity_percent.min(100) as u32 / 100)
+                        as u16,
+                );
+                self.pwm.enable(Channel::C2);
+            }
+        }
+    };
+}
+
+#[macro_export]
+macro_rules! setup_emitter_pwm {
+    ($dp:expr, $gpio:expr, $clocks:expr) => {{
+        use hal::timer::Channel;
+
+        let emitter_pin = $crate::emitter_pin!($gpio).into_alternate();
+        let ch = hal::timer::pwm::Channel2::new(emitter_pin);
+        let mut pwm = $dp
+            .TIM9
+            .pwm_hz(ch, $crate::EMITTER_PWM_FREQ_HZ.Hz(), $clocks);
+        pwm.disable(Channel::C2);
+
+        Emitter { pwm }
+    }};
+}
+
+/// MCU identification shown on the About screen
+#[derive(Clone, Copy, Debug)]
+pub struct HardwareRevision {
+    pub dev_id: u16,
+    pub rev_id: u16,
+    pub flash_kb: u16,
+}
+
+impl HardwareRevision {
+    pub fn read(dbgmcu: &DBGMCU) -> Self {
+        let idcode = dbgmcu.idcode.read();
+        Self {
+            dev_id: idcode.dev_id().bits(),
+            rev_id: idcode.rev_id().bits(),
+            flash_kb: FlashSize::get().kilo_bytes(),
+        }
+    }
+
+    /// Silicon revision letter as printed in the errata sheet
+    pub fn rev_name(&self) -> &'static str {
+        match self.rev_id {
+            0x1000 => "A",
+            0x1001 => "Z",
+            _ => "?",
+        }
+    }
+}
+
+/// RTC backup registers, kept through resets as long as the board stays powered
+pub struct BackupRegisters {
+    rtc: RTC,
+}
+
+impl BackupRegisters {
+    pub fn new(rtc: RTC, rcc: &RCC, pwr: &PWR) -> Self {
+        rcc.apb1enr.modify(|_, w| w.pwren().set_bit());
+        // Lift the backup domain write protection
+        pwr.cr.modify(|_, w| w.dbp().set_bit());
+        Self { rtc }
+    }
+
+    pub fn read(&self) -> [u32; SESSION_WORDS] {
+        core::array::from_fn(|index| self.rtc.bkpr[index].read().bits())
+    }
+
+    pub fn write(&mut self, words: &[u32; SESSION_WORDS]) {
+        for (register, &word) in self.rtc.bkpr.iter().zip(words) {
+            register.write(|w| unsafe { w.bits(word) });
+        }
+    }
+
+    /// The register right after the session
+    pub fn read_display_geometry(&self) -> Option<DisplayGeometry> {
+        DisplayGeometry::decode(self.rtc.bkpr[SESSION_WORDS].read().bits())
+    }
+
+    pub fn write_display_geometry(&mut self, geometry: &DisplayGeometry) {
+        self.rtc.bkpr[SESSION_WORDS].write(|w| unsafe { w.bits(geometry.encode()) });
+    }
+}
+
 pub struct AllGpio {
     pub a: hal::gpio::gpioa::Parts,
     pub b: hal::gpio::gpiob::Parts,
@@ -237,9 +709,11 @@ pin_macro!($ display_sclk_pin, a, pa5);
 pin_macro!($ display_miso_pin, a, pa6);
 pin_macro!($ display_mosi_pin, a, pa7);
 pin_macro!($ display_backlight_pin, b, pb9);
-pin_macro!($ display_dummy_cs_pin, b, pb10);
+pin_macro!($ display_dummy_cs_pin, a, pa15);
 
 pin_macro!($ adc_pin, a, pa1);
+pin_macro!($ focal_plane_left_pin, a, pa4);
+pin_macro!($ focal_plane_right_pin, b, pb0);
 
 pin_macro!($ led_pin, c, pc13);
 
@@ -254,16 +728,39 @@ pin_macro!($ rotary_clk_pin, c, pc14);
 pin_macro!($ accessory_sense_pin, a, pa0);
 pin_macro!($ accessory_idle_signal, b, pb8);
 
-use app_measurements::TriggerThresholds;
+pin_macro!($ emitter_pin, a, pa3);
+
+pin_macro!($ capture_pin, a, pa9);
+
+pin_macro!($ sync_pin, b, pb12);
+
+// Drives a relay or MOSFET across an electronic cable release, high fires it
+pin_macro!($ release_pin, b, pb7);
+
+// Test stand lid switch to ground, closed reads low
+pin_macro!($ fixture_pin, a, pa10);
+
+pin_macro!($ linear_sensor_sclk_pin, b, pb13);
+pin_macro!($ linear_sensor_miso_pin, b, pb14);
+pin_macro!($ linear_sensor_mosi_pin, b, pb15);
+pin_macro!($ linear_sensor_cs_pin, b, pb1);
+
+// AF4 and AF9: I2C2
+pin_macro!($ expansion_scl_pin, b, pb10);
+pin_macro!($ expansion_sda_pin, b, pb3);
+
+use app_measurements::{ClockFault, DisplayGeometry, TriggerThresholds, SESSION_WORDS};
 use embedded_hal_bus::spi::{ExclusiveDevice, NoDelay};
 use fugit::RateExtU32;
 use hal::adc::config::{Dma, Resolution, SampleTime};
 use hal::adc::Adc;
-use hal::dma::{PeripheralToMemory, Stream0, Transfer};
 use hal::gpio::{Analog, Pin};
-use hal::pac::{ADC1, DMA2, SPI1, TIM2};
+use hal::i2c::I2c;
+use hal::pac::{ADC1, DBGMCU, I2C2, PWR, RCC, RTC, SPI1, SPI2, TIM2, TIM3, TIM5};
 use hal::rcc::Clocks;
+use hal::signature::FlashSize;
 use hal::spi::Spi;
-use hal::timer::{CounterHz, TimerExt};
+use hal::timer::{CounterHz, DelayUs, TimerExt};
 use hal::Listen;
+use sampler::stm32f4::Stm32f4Sampler;
 use stm32f4xx_hal::gpio::{ErasedPin, Output};
@@ -11,7 +11,27 @@ rust-objcopy -O binary target/thumbv7m-none-eabi/release/app firmware.app.bin
 
 cp firmware.app.bin firmware.bin
 dd conv=notrunc if=firmware.bootloader.bin of=firmware.bin
+# Length and CRC of the app for the bootloader to check, see bootloader-api
+python3 - firmware.bin <<'EOF'
+import struct, sys
+
+APP_INFO, APP_START = 0x3FF0, 0x4000
+with open(sys.argv[1], 'r+b') as f:
+    image = f.read()
+    app = image[APP_START:] + b'\xff' * (-len(image) % 4)
+    # CRC-32/MPEG-2 over little endian words, like the CRC unit
+    crc = 0xFFFFFFFF
+    for (word,) in struct.iter_unpack('<I', app):
+        crc ^= word
+        for _ in range(32):
+            crc = (crc << 1 ^ 0x04C11DB7 if crc & 0x80000000 else crc << 1) & 0xFFFFFFFF
+    f.write(app[len(image) - APP_START:])
+    f.seek(APP_INFO)
+    f.write(struct.pack('<III', 0xA991CC32, len(app), crc))
+EOF
 dfu-suffix -v 0483 -d df11 -a firmware.bin
 dfu-prefix -a firmware.bin -L
 mv firmware.bin firmware.dfu
+# Detach a running firmware, fails harmlessly if it's already in DFU mode
+dfu-util -d 16c0:27dd -e || true
 dfu-util -w -R -a 0 --dfuse-address 0x08000000 -D firmware.dfu
@@ -0,0 +1,58 @@
+#![no_std]
+
+//! The light sensor pipeline as the app sees it: scans of a few ADC channels at a
+//! fixed rate. A port to another MCU implements [`SamplerBackend`] for its converter.
+
+#[cfg(feature = "stm32f4")]
+pub mod stm32f4;
+
+/// Conversions in a [`SamplerBackend::start_burst`]
+pub const BURST_LEN: usize = 256;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SamplerError {
+    /// No free buffer to continue into, the stream is left as it was
+    NoBuffer,
+    /// The transfer failed, the scan is dropped and the stream restarted
+    Transfer,
+    /// The converter overran, samples went missing from the scan
+    Overrun,
+}
+
+pub trait SamplerBackend<const CHANNELS: usize> {
+    /// Powers the converter up and starts the sample clock
+    fn start(&mut self);
+    /// Stops the sample clock and powers the converter down, a scan under way is dropped
+    fn stop(&mut self);
+    fn is_running(&self) -> bool;
+    /// Applied right away if running
+    fn set_rate(&mut self, rate_hz: u32);
+    /// Bits per sample, taken up on the next start. One the converter doesn't
+    /// have leaves it as it was.
+    fn set_resolution(&mut self, bits: u32);
+    /// Converter clock cycles each channel is sampled for, longer lets a high impedance
+    /// sensor settle. Taken up on the next start, one the converter doesn't have
+    /// leaves it as it was.
+    fn set_sample_cycles(&mut self, cycles: u32);
+    /// Starts a scan, from the sample clock interrupt
+    fn trigger(&mut self);
+    /// The scan that just completed, from the transfer complete interrupt.
+    /// `None` if it was cut short by [`SamplerBackend::stop`] or was a burst.
+    fn next_scan(&mut self) -> Result<Option<[u16; CHANNELS]>, SamplerError>;
+    /// From the converter's error interrupt, `true` if it had overrun. The next
+    /// scan reports it.
+    fn check_overrun(&mut self) -> bool;
+    /// Holds the scans up and converts channel `channel` of them back to back, as fast as
+    /// the converter goes at a reduced resolution, [`BURST_LEN`] times. The scans pick up
+    /// again after [`SamplerBackend::next_scan`] sees it through with `Ok(None)`. `false`
+    /// if there is no burst to be had, the scans go on then.
+    fn start_burst(&mut self, _channel: usize) -> bool {
+        false
+    }
+    /// Hands the burst that just completed to `f` scaled to the scans' resolution, along
+    /// with the nanoseconds between its conversions. Only once, `false` if there was none
+    /// or it lost a conversion.
+    fn take_burst(&mut self, _f: impl FnOnce(&[u16], u32)) -> bool {
+        false
+    }
+}
@@ -0,0 +1,373 @@
+//! ADC1 scanning into DMA2 stream 0, one scan per TIM2 update
+
+use hal::adc::config::Resolution;
+use hal::adc::Adc;
+use hal::dma::traits::StreamISR;
+use hal::dma::{DMAError, DmaFlag, PeripheralToMemory, Stream0, Transfer};
+use hal::pac::{Interrupt, ADC1, DMA2, NVIC, TIM2};
+use hal::prelude::*;
+use hal::timer::{CounterHz, Flag};
+use stm32f4xx_hal as hal;
+
+use crate::{SamplerBackend, SamplerError, BURST_LEN};
+
+/// Bursts convert at 6 bits with the shortest sampling time, the fastest the ADC goes
+const BURST_BITS: u32 = 6;
+const BURST_CYCLES: u32 = 3 + BURST_BITS;
+/// TCIF0, HTIF0, TEIF0, DMEIF0 and FEIF0 in LIFCR
+const STREAM0_FLAGS: u32 = 0b11_1101;
+const CR1_SCAN: u32 = 1 << 8;
+const CR1_RES: u32 = 0b11 << 24;
+const CR2_ADON: u32 = 1;
+const CR2_DMA: u32 = 1 << 8;
+const SQR1_L: u32 = 0b1111 << 20;
+
+pub type AdcTransfer<const CHANNELS: usize> =
+    Transfer<Stream0<DMA2>, 0, Adc<ADC1>, PeripheralToMemory, &'static mut [u16; CHANNELS]>;
+
+pub struct Stm32f4Sampler<const CHANNELS: usize> {
+    transfer: AdcTransfer<CHANNELS>,
+    timer: CounterHz<TIM2>,
+    /// Swapped in for the buffer the DMA just filled
+    spare_buffer: Option<&'static mut [u16; CHANNELS]>,
+    rate_hz: u32,
+    resolution: Resolution,
+    /// SMPx field value for every channel, `None` keeps what the setup configured
+    sample_time: Option<u32>,
+    running: bool,
+    /// Set on an overrun, the buffer in flight is out of step
+    discard: bool,
+    /// See [`Self::with_burst`]
+    burst_buffer: Option<&'static mut [u16; BURST_LEN]>,
+    adc_clock_hz: u32,
+    /// What a burst under way changed, put back once it's through
+    burst: Option<AdcRegisters>,
+    /// A conversion went missing, the burst's timing is off
+    burst_overrun: bool,
+    burst_ready: bool,
+}
+
+impl<const CHANNELS: usize> Stm32f4Sampler<CHANNELS> {
+    /// Takes the ADC disabled and the timer stopped, listening for updates
+    pub fn new(
+        transfer: AdcTransfer<CHANNELS>,
+        timer: CounterHz<TIM2>,
+        spare_buffer: &'static mut [u16; CHANNELS],
+        rate_hz: u32,
+        resolution: Resolution,
+    ) -> Self {
+        Self {
+            transfer,
+            timer,
+            spare_buffer: Some(spare_buffer),
+            rate_hz,
+            resolution,
+            sample_time: None,
+            running: false,
+            discard: false,
+            burst_buffer: None,
+            adc_clock_hz: 0,
+            burst: None,
+            burst_overrun: false,
+            burst_ready: false,
+        }
+    }
+
+    /// Lets [`SamplerBackend::start_burst`] fill `buffer`, the ADC running at `adc_clock_hz`
+    pub fn with_burst(mut self, buffer: &'static mut [u16; BURST_LEN], adc_clock_hz: u32) -> Self {
+        self.burst_buffer = Some(buffer);
+        self.adc_clock_hz = adc_clock_hz;
+        self
+    }
+}
+
+impl<const CHANNELS: usize> SamplerBackend<CHANNELS> for Stm32f4Sampler<CHANNELS> {
+    fn start(&mut self) {
+        if self.running {
+            return;
+        }
+        self.running = true;
+        let resolution = self.resolution;
+        let sample_time = self.sample_time;
+        self.transfer.start(|adc| {
+            adc.set_resolution(resolution);
+            if let Some(sample_time) = sample_time {
+                set_adc_sample_time(sample_time);
+            }
+            adc.enable();
+        });
+        self.timer.start(self.rate_hz.Hz()).unwrap();
+    }
+
+    fn stop(&mut self) {
+        if !self.running {
+            return;
+        }
+        self.running = false;
+        let _ = self.timer.cancel();
+        // A tick that is already pending would start one more conversion
+        self.timer.clear_flags(Flag::Update);
+        // Stopping the stream completes a scan under way early, `next_scan`
+        // drops the partial buffer and rearms the stream
+        self.transfer.pause(|adc| adc.disable());
+    }
+
+    fn is_running(&self) -> bool {
+        self.running
+    }
+
+    fn set_rate(&mut self, rate_hz: u32) {
+        self.rate_hz = rate_hz;
+        if self.running {
+            self.timer.start(rate_hz.Hz()).unwrap();
+        }
+    }
+
+    fn set_resolution(&mut self, bits: u32) {
+        self.resolution = match bits {
+            6 => Resolution::Six,
+            8 => Resolution::Eight,
+            10 => Resolution::Ten,
+            12 => Resolution::Twelve,
+            _ => return,
+        };
+    }
+
+    fn set_sample_cycles(&mut self, cycles: u32) {
+        self.sample_time = Some(match cycles {
+            3 => 0,
+            15 => 1,
+            28 => 2,
+            56 => 3,
+            84 => 4,
+            112 => 5,
+            144 => 6,
+            480 => 7,
+            _ => return,
+        });
+    }
+
+    fn trigger(&mut self) {
+        // Cleared by a stop that came in first
+        if !self.timer.flags().contains(Flag::Update) {
+            return;
+        }
+        // The burst has the converter, these scans are skipped
+        if self.burst.is_some() {
+            self.timer.clear_flags(Flag::Update);
+            return;
+        }
+        self.transfer.start(|adc| {
+            adc.start_conversion();
+        });
+        self.timer.clear_flags(Flag::Update);
+    }
+
+    fn next_scan(&mut self) -> Result<Option<[u16; CHANNELS]>, SamplerError> {
+        let mut transfer_error = false;
+        // The stream stops on an error, the next transfer starts it again
+        if self.transfer.flags().contains(DmaFlag::TransferError) {
+            self.transfer.clear_flags(DmaFlag::TransferError);
+            transfer_error = true;
+        }
+
+        // Quiet the converter before the stream goes back to the scans
+        let burst = self.burst.take();
+        if let Some(registers) = &burst {
+            let overrun = core::mem::take(&mut self.burst_overrun);
+            self.burst_ready = self.running && !overrun && stream_remaining() == 0;
+            registers.restore(self.running);
+        }
+
+        let buffer = self.spare_buffer.take().ok_or(SamplerError::NoBuffer)?;
+        let values = match self.transfer.next_transfer(buffer) {
+            Ok((last_buffer, _)) => {
+                let values = *last_buffer;
+                self.spare_buffer = Some(last_buffer);
+                values
+            }
+            Err(
+                DMAError::NotReady(buffer)
+                | DMAError::SmallBuffer(buffer)
+                | DMAError::Overrun(buffer),
+            ) => {
+                // Keep the buffer for the next transfer
+                self.spare_buffer = Some(buffer);
+                return Err(SamplerError::NoBuffer);
+            }
+        };
+
+        let overrun = core::mem::take(&mut self.discard);
+        // Cut short by `stop`, nothing went missing
+        if !self.running {
+            return Ok(None);
+        }
+        if transfer_error {
+            return Err(SamplerError::Transfer);
+        }
+        if overrun {
+            return Err(SamplerError::Overrun);
+        }
+        // What the stream was armed with before the burst, not a scan
+        if burst.is_some() {
+            return Ok(None);
+        }
+        Ok(Some(values))
+    }
+
+    fn check_overrun(&mut self) -> bool {
+        if self.burst.is_some() {
+            // The converter runs on once the burst has its last sample, only an
+            // overrun before that costs one
+            if clear_adc_overrun() && stream_remaining() > 0 {
+                self.burst_overrun = true;
+            }
+            return false;
+        }
+        let mut overrun = false;
+        // Stopping the stream completes the transfer early, `next_scan` then
+        // drops the buffer and restarts it
+        self.transfer.pause(|_| overrun = clear_adc_overrun());
+        self.discard |= overrun;
+        overrun
+    }
+
+    fn start_burst(&mut self, channel: usize) -> bool {
+        // The scan sequence register holds the first six ranks
+        if !self.running || self.burst.is_some() || channel >= CHANNELS.min(6) {
+            return false;
+        }
+        let Some(buffer) = self.burst_buffer.as_mut() else {
+            return false;
+        };
+        let adc = unsafe { &*ADC1::ptr() };
+        let dma = unsafe { &*DMA2::ptr() };
+        let stream = &dma.st[0];
+
+        // From the transfer complete interrupt, so the stream is waiting for the
+        // next scan and no conversion is under way
+        stream.cr.modify(|_, w| w.en().clear_bit());
+        while stream.cr.read().en().bit_is_set() {}
+        // Disabling it completed the transfer, there was no scan
+        dma.lifcr.write(|w| unsafe { w.bits(STREAM0_FLAGS) });
+        NVIC::unpend(Interrupt::DMA2_STREAM0);
+
+        let registers = AdcRegisters::save();
+        let sequence = (registers.sqr3 >> (5 * channel)) & 0b1_1111;
+        adc.cr1
+            .write(|w| unsafe { w.bits(registers.cr1 & !CR1_SCAN | CR1_RES) });
+        adc.sqr1
+            .write(|w| unsafe { w.bits(registers.sqr1 & !SQR1_L) });
+        adc.sqr3.write(|w| unsafe { w.bits(sequence) });
+        set_adc_sample_time(0);
+
+        stream
+            .m0ar
+            .write(|w| unsafe { w.bits(buffer.as_mut_ptr() as u32) });
+        stream.ndtr.write(|w| unsafe { w.bits(BURST_LEN as u32) });
+        stream.cr.modify(|_, w| w.en().set_bit());
+        adc.cr2
+            .modify(|_, w| w.cont().set_bit().swstart().set_bit());
+
+        self.burst = Some(registers);
+        self.burst_ready = false;
+        true
+    }
+
+    fn take_burst(&mut self, f: impl FnOnce(&[u16], u32)) -> bool {
+        if !core::mem::take(&mut self.burst_ready) {
+            return false;
+        }
+        let Some(buffer) = self.burst_buffer.as_mut() else {
+            return false;
+        };
+        let shift = resolution_bits(self.resolution).saturating_sub(BURST_BITS);
+        for value in buffer.iter_mut() {
+            *value <<= shift;
+        }
+        f(
+            &buffer[..],
+            (BURST_CYCLES as u64 * 1_000_000_000 / self.adc_clock_hz.max(1) as u64) as u32,
+        );
+        true
+    }
+}
+
+/// The scan setup a burst replaces
+struct AdcRegisters {
+    cr1: u32,
+    cr2: u32,
+    sqr1: u32,
+    sqr3: u32,
+    smpr1: u32,
+    smpr2: u32,
+}
+
+impl AdcRegisters {
+    fn save() -> Self {
+        let regs = unsafe { &*ADC1::ptr() };
+        Self {
+            cr1: regs.cr1.read().bits(),
+            cr2: regs.cr2.read().bits(),
+            sqr1: regs.sqr1.read().bits(),
+            sqr3: regs.sqr3.read().bits(),
+            smpr1: regs.smpr1.read().bits(),
+            smpr2: regs.smpr2.read().bits(),
+        }
+    }
+
+    /// Powers down to stop the conversion under way, and back up if `enable`
+    fn restore(&self, enable: bool) {
+        let regs = unsafe { &*ADC1::ptr() };
+        regs.cr2
+            .write(|w| unsafe { w.bits(self.cr2 & !(CR2_ADON | CR2_DMA)) });
+        let _ = regs.dr.read();
+        regs.sr.write(|w| unsafe { w.bits(0) });
+        regs.cr1.write(|w| unsafe { w.bits(self.cr1) });
+        regs.sqr1.write(|w| unsafe { w.bits(self.sqr1) });
+        regs.sqr3.write(|w| unsafe { w.bits(self.sqr3) });
+        regs.smpr1.write(|w| unsafe { w.bits(self.smpr1) });
+        regs.smpr2.write(|w| unsafe { w.bits(self.smpr2) });
+        // Toggling the DMA bit lets the requests through again after an overrun
+        let adon = if enable { CR2_ADON } else { 0 };
+        regs.cr2
+            .write(|w| unsafe { w.bits(self.cr2 & !CR2_ADON | adon) });
+    }
+}
+
+/// Transfers the stream has left to go
+fn stream_remaining() -> u32 {
+    let dma = unsafe { &*DMA2::ptr() };
+    dma.st[0].ndtr.read().bits()
+}
+
+fn resolution_bits(resolution: Resolution) -> u32 {
+    match resolution {
+        Resolution::Six => 6,
+        Resolution::Eight => 8,
+        Resolution::Ten => 10,
+        Resolution::Twelve => 12,
+    }
+}
+
+/// The same for every channel, whichever of them the scan uses
+fn set_adc_sample_time(sample_time: u32) {
+    let regs = unsafe { &*ADC1::ptr() };
+    // 3 bits per channel, channels 0 to 9 in SMPR2 and 10 to 18 in SMPR1
+    let fields = |count: u32| (0..count).fold(0, |bits, i| bits | sample_time << (3 * i));
+    regs.smpr2.write(|w| unsafe { w.bits(fields(10)) });
+    regs.smpr1.write(|w| unsafe { w.bits(fields(9)) });
+}
+
+/// `false` if there was no overrun
+fn clear_adc_overrun() -> bool {
+    let regs = unsafe { &*ADC1::ptr() };
+    if regs.sr.read().ovr().bit_is_clear() {
+        return false;
+    }
+    // DMA requests stay off after an overrun until the DMA bit is toggled
+    regs.cr2.modify(|_, w| w.dma().clear_bit());
+    regs.sr.modify(|_, w| w.ovr().clear_bit());
+    regs.cr2.modify(|_, w| w.dma().set_bit());
+    true
+}
@@ -0,0 +1,411 @@
+//! Converts what the firmware sends over USB serial into other formats.
+//!
+//! Reads the human-readable result dump, the `h`, `t` and `s` CSV exports and its own
+//! JSON. Several inputs are merged shot by shot, so a history export and a trace export
+//! of the same session end up as one capture.
+//!
+//! ```text
+//! capture convert <input>... <output.json|output.csv>
+//! capture plot <input>... <output.svg>
+//! capture deviations <input>...
+//! ```
+
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use app_measurements::ShutterSpeed;
+use plotters::prelude::*;
+use serde::{Deserialize, Serialize};
+
+const HISTORY_HEADER: &str = "index,camera,nominal_us,duration_us,integrated_us,uncertainty_us";
+const TRACES_HEADER: &str = "trace,sample,value";
+const SEQUENCE_HEADER: &str = "step,nominal_us,duration_us,deviation_pct";
+/// Same as the firmware's ADC
+const SAMPLE_MAX: u16 = 4095;
+
+/// A single measured exposure, fields are `None` when the input didn't carry them
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct Shot {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    camera: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    nominal_us: Option<u64>,
+    /// Between the trigger crossings
+    #[serde(skip_serializing_if = "Option::is_none")]
+    duration_us: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    integrated_us: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    half_peak_us: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    uncertainty_us: Option<u64>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    samples: Vec<u16>,
+}
+
+impl Shot {
+    /// Fills in what `self` is missing
+    fn merge(&mut self, other: Shot) {
+        self.camera = self.camera.take().or(other.camera);
+        self.nominal_us = self.nominal_us.or(other.nominal_us);
+        self.duration_us = self.duration_us.or(other.duration_us);
+        self.integrated_us = self.integrated_us.or(other.integrated_us);
+        self.half_peak_us = self.half_peak_us.or(other.half_peak_us);
+        self.uncertainty_us = self.uncertainty_us.or(other.uncertainty_us);
+        if self.samples.is_empty() {
+            self.samples = other.samples;
+        }
+    }
+
+    /// The integrated time is what the results screen shows by default
+    fn measured_us(&self) -> Option<u64> {
+        self.integrated_us.or(self.duration_us)
+    }
+
+    /// Positive if the shutter is slower than nominal, same as the firmware
+    fn deviation_percent(&self) -> Option<i64> {
+        let nominal = self.nominal_us? as i64;
+        let actual = self.measured_us()? as i64;
+        (nominal > 0).then(|| (actual - nominal) * 100 / nominal)
+    }
+
+    /// Exposure error in stops, what a photographer would dial in to correct it
+    fn deviation_stops(&self) -> Option<f64> {
+        let nominal = self.nominal_us? as f64;
+        let actual = self.measured_us()? as f64;
+        (nominal > 0.0 && actual > 0.0).then(|| (actual / nominal).log2())
+    }
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+struct Capture {
+    shots: Vec<Shot>,
+}
+
+impl Capture {
+    /// Shots are matched by position, the firmware exports everything oldest first
+    fn merge(&mut self, other: Capture) {
+        for (index, shot) in other.shots.into_iter().enumerate() {
+            match self.shots.get_mut(index) {
+                Some(existing) => existing.merge(shot),
+                None => self.shots.push(shot),
+            }
+        }
+    }
+}
+
+fn parse(text: &str) -> Result<Capture, String> {
+    let first_line = text.lines().map(str::trim).find(|l| !l.is_empty());
+    match first_line {
+        Some(line) if line.starts_with('{') => {
+            serde_json::from_str(text).map_err(|e| format!("bad JSON: {}", e))
+        }
+        Some(HISTORY_HEADER) => parse_history(text),
+        Some(TRACES_HEADER) => parse_traces(text),
+        Some(SEQUENCE_HEADER) => parse_sequence(text),
+        _ => Ok(parse_dump(text)),
+    }
+}
+
+/// Rows after the header, with their line number for errors
+fn csv_rows(text: &str) -> impl Iterator<Item = (usize, Vec<&str>)> {
+    text.lines()
+        .enumerate()
+        .skip_while(|(_, line)| line.trim().is_empty())
+        .skip(1)
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(index, line)| (index + 1, line.trim().split(',').collect()))
+}
+
+/// Empty cells are `None`
+fn cell<T: std::str::FromStr>(
+    row: &[&str],
+    index: usize,
+    line: usize,
+) -> Result<Option<T>, String> {
+    match row.get(index).copied().unwrap_or_default() {
+        "" => Ok(None),
+        value => value
+            .parse()
+            .map(Some)
+            .map_err(|_| format!("line {}: bad value {:?}", line, value)),
+    }
+}
+
+fn parse_history(text: &str) -> Result<Capture, String> {
+    let mut capture = Capture::default();
+    for (line, row) in csv_rows(text) {
+        capture.shots.push(Shot {
+            camera: row.get(1).filter(|c| !c.is_empty()).map(|c| c.to_string()),
+            nominal_us: cell(&row, 2, line)?,
+            duration_us: cell(&row, 3, line)?,
+            integrated_us: cell(&row, 4, line)?,
+            uncertainty_us: cell(&row, 5, line)?,
+            ..Default::default()
+        });
+    }
+    Ok(capture)
+}
+
+fn parse_traces(text: &str) -> Result<Capture, String> {
+    let mut capture = Capture::default();
+    for (line, row) in csv_rows(text) {
+        let (Some(trace), Some(value)) = (cell::<usize>(&row, 0, line)?, cell(&row, 2, line)?)
+        else {
+            return Err(format!("line {}: missing trace or value", line));
+        };
+        if capture.shots.len() <= trace {
+            capture.shots.resize_with(trace + 1, Shot::default);
+        }
+        capture.shots[trace].samples.push(value);
+    }
+    Ok(capture)
+}
+
+fn parse_sequence(text: &str) -> Result<Capture, String> {
+    let mut capture = Capture::default();
+    for (line, row) in csv_rows(text) {
+        capture.shots.push(Shot {
+            nominal_us: cell(&row, 1, line)?,
+            integrated_us: cell(&row, 2, line)?,
+            ..Default::default()
+        });
+    }
+    Ok(capture)
+}
+
+/// `Label: 123 us` lines of the serial log, anything it doesn't know is skipped
+fn parse_dump(text: &str) -> Capture {
+    let mut capture = Capture::default();
+    let mut shot: Option<Shot> = None;
+
+    for line in text.lines().map(str::trim) {
+        if line == "Result:" {
+            capture.shots.extend(shot.replace(Shot::default()));
+            continue;
+        }
+        let Some(current) = shot.as_mut() else {
+            continue;
+        };
+        if line.is_empty() || line.ends_with("result:") {
+            // Other measurement kinds end the block too
+            capture.shots.extend(shot.take());
+            continue;
+        }
+
+        if let Some(sample) = line.strip_prefix("- ").and_then(|s| s.parse().ok()) {
+            current.samples.push(sample);
+            continue;
+        }
+        let Some((label, value)) = line.split_once(": ") else {
+            continue;
+        };
+        let micros = || {
+            value
+                .trim_start_matches("+-")
+                .trim_end_matches(" us")
+                .parse()
+                .ok()
+        };
+        match label {
+            "Camera" => current.camera = Some(value.to_string()),
+            "Nominal time" => current.nominal_us = micros(),
+            "Raw start-end time" => current.duration_us = micros(),
+            "Integrated time" => current.integrated_us = micros(),
+            "Half peak time" => current.half_peak_us = micros(),
+            "Uncertainty" => current.uncertainty_us = micros(),
+            _ => (),
+        }
+    }
+    capture.shots.extend(shot);
+    capture
+}
+
+/// Same columns as the firmware's history export
+fn write_history(capture: &Capture) -> String {
+    let mut s = format!("{}\r\n", HISTORY_HEADER);
+    for (index, shot) in capture.shots.iter().enumerate() {
+        let cell = |value: Option<u64>| value.map(|v| v.to_string()).unwrap_or_default();
+        writeln!(
+            s,
+            "{},{},{},{},{},{}\r",
+            index,
+            shot.camera.as_deref().unwrap_or_default(),
+            cell(shot.nominal_us),
+            cell(shot.duration_us),
+            cell(shot.integrated_us),
+            cell(shot.uncertainty_us)
+        )
+        .unwrap();
+    }
+    s
+}
+
+/// Same columns as the firmware's trace export
+fn write_traces(capture: &Capture) -> String {
+    let mut s = format!("{}\r\n", TRACES_HEADER);
+    for (index, shot) in capture.shots.iter().enumerate() {
+        for (sample, value) in shot.samples.iter().enumerate() {
+            writeln!(s, "{},{},{}\r", index, sample, value).unwrap();
+        }
+    }
+    s
+}
+
+/// `out.csv` gets the history, the samples go next to it into `out-traces.csv`
+fn write_csv(capture: &Capture, path: &Path) -> Result<(), String> {
+    write_file(path, &write_history(capture))?;
+    if capture.shots.iter().any(|shot| !shot.samples.is_empty()) {
+        let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+        write_file(
+            &path.with_file_name(format!("{}-traces.csv", stem)),
+            &write_traces(capture),
+        )?;
+    }
+    Ok(())
+}
+
+fn write_file(path: &Path, contents: &str) -> Result<(), String> {
+    std::fs::write(path, contents).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+/// One line per shot with samples, overlaid from the first sample
+fn plot(capture: &Capture, path: &Path) -> Result<(), String> {
+    let traces: Vec<_> = capture
+        .shots
+        .iter()
+        .enumerate()
+        .filter(|(_, shot)| !shot.samples.is_empty())
+        .collect();
+    if traces.is_empty() {
+        return Err("nothing to plot, the input has no samples".into());
+    }
+    let len = traces
+        .iter()
+        .map(|(_, shot)| shot.samples.len())
+        .max()
+        .unwrap_or_default();
+
+    let root = SVGBackend::new(path, (1024, 480)).into_drawing_area();
+    let draw = |e: DrawingAreaErrorKind<_>| e.to_string();
+    root.fill(&WHITE).map_err(draw)?;
+    let mut chart = ChartBuilder::on(&root)
+        .margin(10)
+        .x_label_area_size(30)
+        .y_label_area_size(50)
+        .build_cartesian_2d(0..len, 0..SAMPLE_MAX as u32 + 1)
+        .map_err(draw)?;
+    chart
+        .configure_mesh()
+        .x_desc("sample")
+        .y_desc("level")
+        .draw()
+        .map_err(draw)?;
+
+    for (color_index, (index, shot)) in traces.iter().enumerate() {
+        let color = Palette99::pick(color_index).to_rgba();
+        let label = match shot.measured_us() {
+            Some(micros) => format!("#{} {} us", index, micros),
+            None => format!("#{}", index),
+        };
+        chart
+            .draw_series(LineSeries::new(
+                shot.samples
+                    .iter()
+                    .enumerate()
+                    .map(|(x, &value)| (x, value as u32)),
+                &color,
+            ))
+            .map_err(draw)?
+            .label(label)
+            .legend(move |(x, y)| PathElement::new([(x, y), (x + 20, y)], color));
+    }
+    chart
+        .configure_series_labels()
+        .background_style(WHITE.mix(0.8))
+        .border_style(BLACK)
+        .draw()
+        .map_err(draw)?;
+    root.present().map_err(draw)
+}
+
+fn print_deviations(capture: &Capture) {
+    println!(
+        "{:>5} {:>6} {:>8} {:>10} {:>10} {:>8} {:>7} {:>8}",
+        "shot", "camera", "speed", "nominal", "measured", "+-", "dev%", "stops"
+    );
+    for (index, shot) in capture.shots.iter().enumerate() {
+        let cell = |value: Option<u64>| value.map(|v| v.to_string()).unwrap_or("-".into());
+        println!(
+            "{:>5} {:>6} {:>8} {:>10} {:>10} {:>8} {:>7} {:>8}",
+            index,
+            shot.camera.as_deref().unwrap_or("-"),
+            shot.nominal_us
+                .map(|n| ShutterSpeed::from_micros(n).to_string())
+                .unwrap_or("-".into()),
+            cell(shot.nominal_us),
+            cell(shot.measured_us()),
+            cell(shot.uncertainty_us),
+            shot.deviation_percent()
+                .map(|d| format!("{:+}", d))
+                .unwrap_or("-".into()),
+            shot.deviation_stops()
+                .map(|s| format!("{:+.2}", s))
+                .unwrap_or("-".into()),
+        );
+    }
+}
+
+fn read_inputs(paths: &[String]) -> Result<Capture, String> {
+    if paths.is_empty() {
+        return Err("no inputs".into());
+    }
+    let mut capture = Capture::default();
+    for path in paths {
+        let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        capture.merge(parse(&text).map_err(|e| format!("{}: {}", path, e))?);
+    }
+    Ok(capture)
+}
+
+fn run(args: &[String]) -> Result<(), String> {
+    match args {
+        [command, inputs @ .., output] if command == "convert" => {
+            let capture = read_inputs(inputs)?;
+            let output = PathBuf::from(output);
+            match output.extension().and_then(|e| e.to_str()) {
+                Some("json") => write_file(
+                    &output,
+                    &serde_json::to_string_pretty(&capture).map_err(|e| e.to_string())?,
+                ),
+                Some("csv") => write_csv(&capture, &output),
+                _ => Err("the output has to end in .json or .csv".into()),
+            }
+        }
+        [command, inputs @ .., output] if command == "plot" => {
+            plot(&read_inputs(inputs)?, Path::new(output))
+        }
+        [command, inputs @ ..] if command == "deviations" => {
+            print_deviations(&read_inputs(inputs)?);
+            Ok(())
+        }
+        _ => Err(
+            "usage:\n  capture convert <input>... <output.json|output.csv>\n  \
+             capture plot <input>... <output.svg>\n  capture deviations <input>..."
+                .into(),
+        ),
+    }
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match run(&args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -3,12 +3,19 @@ use std::thread;
 use std::time::{Duration, Instant};
 
 use app_measurements::{
-    CalibrationResult, CalibrationState, MeasurementResult, SamplingRate, TriggerThresholds,
+    AccessoryGain, Annotation, CalibrationResult, CalibrationStage, CalibrationState, CameraSlot,
+    ChannelTiming, ClockFault, FocalPlaneResult, LoopbackCheck, MeasurementResult, Profile,
+    ProfiledSection, SamplingRate, ScanResult, SecondPulse, SensorFault, SoakEvent, SoakLog,
+    TestSequence, TriggerThresholds, UsbRequest,
 };
 use app_ui::panic::draw_panic_screen;
 use app_ui::{
-    BootScreen, CalibrationScreen, DebugScreen, DrawFrameContext, HintRefresh, MeasurementScreen,
-    MenuScreen, NoAccessoryScreen, ResultsScreen, Screen, Screens, StartScreen, UpdateScreen,
+    AboutScreen, AnnotationScreen, Banner, BeeperTuningScreen, BootScreen, BuildInfo,
+    CalibrationScreen, ClockFaultScreen, CounterScreen, DebugPage, DebugScreen, DrawBudget,
+    DrawFrameContext, FocalPlaneScreen, GainScreen, HintRefresh, MeasurementScreen, MemoryUsage,
+    MenuItem, MenuScreen, NoAccessoryScreen, ResultsScreen, ResumeScreen, ScanScreen, Screen,
+    Screens, SelfCheckScreen, SensorFaultScreen, SequenceScreen, Severity, SoakScreen, StartScreen,
+    Toast, UpdateScreen,
 };
 use embedded_graphics::draw_target::DrawTarget;
 use embedded_graphics::geometry::{OriginDimensions, Size};
@@ -19,9 +26,20 @@ use embedded_graphics_simulator::{
     OutputSettingsBuilder, SimulatorDisplay, SimulatorEvent, Window,
 };
 use heapless::HistoryBuffer;
+use serial::{SerialConsole, SERIAL_PORT};
+use synth::{synthesize, PulseParams, TRIGGER_THRESHOLDS};
+
+mod serial;
+mod synth;
+
+#[cfg(not(feature = "monochrome"))]
+type SimulatorColor = Rgb565;
+/// Stands in for an SSD1306
+#[cfg(feature = "monochrome")]
+type SimulatorColor = embedded_graphics::pixelcolor::BinaryColor;
 
 struct LiveDisplay<'a> {
-    display: &'a mut SimulatorDisplay<Rgb565>,
+    display: &'a mut SimulatorDisplay<SimulatorColor>,
     window: &'a mut Window,
 }
 
@@ -45,6 +63,14 @@ impl DrawTarget for LiveDisplay<'_> {
     where
         I: IntoIterator<Item = Pixel<Self::Color>>,
     {
+        #[cfg(feature = "monochrome")]
+        let pixels = {
+            use app_ui::{Monochrome, Palette};
+            let palette = Monochrome::default();
+            pixels
+                .into_iter()
+                .map(move |Pixel(point, color)| Pixel(point, palette.map(color)))
+        };
         self.display.draw_iter(pixels)?;
         Ok(())
     }
@@ -53,10 +79,27 @@ impl DrawTarget for LiveDisplay<'_> {
 #[tokio::main(flavor = "current_thread")]
 async fn main() {
     let mut panic_visible = false;
+    let mut toast_visible = false;
+    let mut banner: Option<Severity> = None;
+    let mut pulse = PulseParams::default();
+    let mut console = match SerialConsole::bind() {
+        Ok(console) => {
+            println!("serial console on localhost:{}", SERIAL_PORT);
+            Some(console)
+        }
+        Err(e) => {
+            println!("serial console unavailable: {}", e);
+            None
+        }
+    };
 
     let mut display = SimulatorDisplay::new(Size::new(128, 160));
 
-    let output_settings = OutputSettingsBuilder::new().scale(2).build();
+    let output_settings = OutputSettingsBuilder::new().scale(2);
+    #[cfg(feature = "monochrome")]
+    let output_settings =
+        output_settings.theme(embedded_graphics_simulator::BinaryColorTheme::OledBlue);
+    let output_settings = output_settings.build();
     let mut w = Window::new("UI", &output_settings);
 
     let mut live_display = LiveDisplay {
@@ -65,7 +108,7 @@ async fn main() {
     };
 
     let mut screen = Screens::Boot(BootScreen::default());
-    screen.draw_init(&mut live_display).await;
+    screen.draw_init(&mut live_display).await.unwrap();
     live_display.hint_refresh();
 
     let t_start = Instant::now();
@@ -76,11 +119,23 @@ async fn main() {
                 &mut live_display,
                 DrawFrameContext {
                     animation_time_ms: t_start.elapsed().as_millis() as u32,
+                    budget: DrawBudget::UNLIMITED,
                 },
             )
-            .await;
+            .await
+            .unwrap();
         live_display.hint_refresh();
 
+        if toast_visible {
+            Toast::new("SAMPLE LOST").draw(&mut live_display).unwrap();
+        }
+
+        if let Some(severity) = banner {
+            Banner::new("ACCESSORY GLITCH", severity)
+                .draw(&mut live_display)
+                .unwrap();
+        }
+
         if panic_visible {
             draw_panic_screen(
                 &mut live_display,
@@ -89,6 +144,36 @@ async fn main() {
         }
 
         let mut need_init = false;
+        let mut measure = false;
+        match console.as_mut().and_then(SerialConsole::poll) {
+            Some(UsbRequest::Arm) => measure = true,
+            Some(UsbRequest::Bootloader) => {
+                screen = UpdateScreen::rebooting().into();
+                need_init = true;
+            }
+            // No emitter to pulse, a plausible outcome instead
+            Some(UsbRequest::SelfCheck) => {
+                screen = SelfCheckScreen::new(
+                    LoopbackCheck {
+                        commanded_micros: 10_000,
+                        measured_micros: 10_040,
+                        uncertainty_micros: 10,
+                        latency_micros: Some(85),
+                    },
+                    2,
+                )
+                .into();
+                need_init = true;
+            }
+            // No settings to keep in the simulator, the console answers the rest
+            Some(UsbRequest::Export(_))
+            | Some(UsbRequest::Subscribe(_))
+            | Some(UsbRequest::SetTolerance { .. })
+            | Some(UsbRequest::LoadConfig(_))
+            | Some(UsbRequest::ConfigLoaded)
+            | None => (),
+        }
+
         for e in live_display.window.events() {
             match e {
                 SimulatorEvent::Quit => {
@@ -96,11 +181,70 @@ async fn main() {
                 }
                 SimulatorEvent::KeyUp { keycode, .. } => {
                     panic_visible = false;
+                    if toast_visible {
+                        toast_visible = false;
+                        need_init = true;
+                    }
+                    let shown_banner = banner.take();
+                    if shown_banner.is_some() {
+                        need_init = true;
+                    }
                     match keycode {
                         Keycode::Num1 => {
                             screen = BootScreen::default().into();
                             need_init = true;
                         }
+                        Keycode::Num2 => {
+                            screen = SensorFaultScreen::new(SensorFault::Stuck { value: 0 }).into();
+                            need_init = true;
+                        }
+                        Keycode::Num3 => {
+                            banner = Some(match shown_banner {
+                                None | Some(Severity::Error) => Severity::Info,
+                                Some(Severity::Info) => Severity::Warning,
+                                Some(Severity::Warning) => Severity::Error,
+                            });
+                        }
+                        Keycode::Num4 => {
+                            let mut log = SoakLog::default();
+                            // A 1/125 that sticks once in a while
+                            for (index, duration_micros) in [8000, 8100, 7900, 8000, 31000, 8050]
+                                .into_iter()
+                                .enumerate()
+                            {
+                                log.record(SoakEvent {
+                                    at_millis: index as u32 * 1_234_567,
+                                    duration_micros,
+                                });
+                            }
+                            screen = SoakScreen::new(log).into();
+                            need_init = true;
+                        }
+                        Keycode::Num5 => {
+                            screen = ClockFaultScreen::new(ClockFault::WrongRate {
+                                measured_hz: 16_000_000,
+                            })
+                            .into();
+                            need_init = true;
+                        }
+                        Keycode::Num6 => {
+                            let mut tuning = BeeperTuningScreen::new(-2);
+                            tuning.tuner.adjust(5);
+                            tuning.frequency_hz = 587;
+                            screen = tuning.into();
+                            need_init = true;
+                        }
+                        Keycode::Num7 => {
+                            screen = GainScreen::new(
+                                Ok(AccessoryGain {
+                                    gain_percent: 62,
+                                    response_time_nanos: 48_000,
+                                }),
+                                None,
+                            )
+                            .into();
+                            need_init = true;
+                        }
                         Keycode::Q => {
                             screen = StartScreen::default().into();
                             need_init = true;
@@ -135,14 +279,32 @@ async fn main() {
                                     average: 128,
                                     max: 160,
                                     min: 80,
+                                    covered: 12,
                                 }),
                                 MeasurementResult {
                                     duration_micros: 125,
                                     integrated_duration_micros: 1000000 / 120,
+                                    half_peak_duration_micros: 1000000 / 118,
                                     sample_buffer,
                                     samples_since_end: margin + 30,
                                     samples_since_start: size - margin - 30,
                                     sample_rate: SamplingRate::new(1),
+                                    oversampling: 1,
+                                    second_pulse: Some(SecondPulse {
+                                        gap_micros: 1500,
+                                        duration_micros: 2100,
+                                    }),
+                                    release_lag_micros: Some(48_300),
+                                    sync_offset_micros: Some(-48_300),
+                                    fired_lag_micros: None,
+                                    sample_interval_nanos: 10_000,
+                                    clipped: true,
+                                    burst: None,
+                                },
+                                Annotation {
+                                    nominal_speed: Some(8),
+                                    custom_speed: None,
+                                    camera: CameraSlot::A,
                                 },
                             )
                             .into();
@@ -165,6 +327,7 @@ async fn main() {
                                     average: 128,
                                     max: 160,
                                     min: 80,
+                                    covered: 12,
                                 },
                                 TriggerThresholds {
                                     high_ratio: 1.2,
@@ -182,29 +345,281 @@ async fn main() {
                             screen = NoAccessoryScreen::default().into();
                             need_init = true;
                         }
+                        Keycode::P => {
+                            screen = CounterScreen::default().into();
+                            need_init = true;
+                        }
+                        Keycode::A => {
+                            screen = AnnotationScreen::default().into();
+                            need_init = true;
+                        }
+                        Keycode::S => {
+                            let mut ss = SequenceScreen::default();
+                            ss.sequence =
+                                TestSequence::new(&[6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16]);
+                            screen = ss.into();
+                            need_init = true;
+                        }
+                        Keycode::D => {
+                            screen = AboutScreen::new(
+                                BuildInfo {
+                                    version: env!("CARGO_PKG_VERSION"),
+                                    git_hash: "264cd3b",
+                                    build_date: "2024-05-01",
+                                    hal_version: "0.20.0",
+                                    rtic_version: "2.1.1",
+                                },
+                                "433 REV Z 512K",
+                            )
+                            .into();
+                            need_init = true;
+                        }
+                        Keycode::X => {
+                            toast_visible = true;
+                        }
+                        Keycode::V => {
+                            if let Screens::Results(ref mut screen) = screen {
+                                screen.speed_table = screen.speed_table.next();
+                                need_init = true;
+                            }
+                        }
+                        Keycode::Semicolon => {
+                            if let Screens::Results(ref mut screen) = screen {
+                                screen.duration_method = screen.duration_method.next();
+                                need_init = true;
+                            }
+                        }
+                        Keycode::Z => {
+                            screen = ResumeScreen::new("SEQUENCE 4/11").into();
+                            need_init = true;
+                        }
+                        Keycode::F => {
+                            let mut ss = ScanScreen::new(4095);
+                            ss.calibrating = false;
+                            for (index, level) in ss.levels.iter_mut().enumerate() {
+                                *level = 300 + index as u16 * 450;
+                            }
+                            screen = ss.into();
+                            need_init = true;
+                        }
+                        Keycode::C => {
+                            let mut fs = FocalPlaneScreen::new(4095);
+                            fs.calibrating = false;
+                            fs.levels = [900, 1400, 1100];
+                            screen = fs.into();
+                            need_init = true;
+                        }
                         Keycode::Up => match screen {
                             Screens::Menu(ref mut screen) => {
                                 screen.position = screen.position.saturating_sub(1);
                             }
                             Screens::Debug(ref mut screen) => {
                                 screen.step(screen.last_adc_value() + 5);
+                                screen.peak_hold.step(screen.last_adc_value());
                             }
                             _ => (),
                         },
                         Keycode::Down => match screen {
                             Screens::Menu(ref mut screen) => {
-                                screen.position = (screen.position + 1) % MenuScreen::options_len();
+                                screen.selected = MenuItem::ALL
+                                    [(screen.selected as usize + 1) % MenuItem::ALL.len()];
                             }
                             Screens::Debug(ref mut screen) => {
                                 screen.step(screen.last_adc_value() - 5);
+                                screen.peak_hold.step(screen.last_adc_value());
                             }
                             _ => (),
                         },
-                        Keycode::Left | Keycode::Right => {
-                            if let Screens::Menu(ref mut screen) = screen {
+                        Keycode::Left | Keycode::Right => match screen {
+                            Screens::Results(ref mut screen)
+                                if screen.viewport.is_zoomed()
+                                    && ResultsScreen::is_zoomable_page(screen.page) =>
+                            {
+                                screen
+                                    .viewport
+                                    .pan(if keycode == Keycode::Left { -1 } else { 1 });
+                            }
+                            Screens::Results(ref mut screen)
+                                if screen.cursor.active
+                                    && ResultsScreen::is_cursor_page(screen.page) =>
+                            {
+                                screen
+                                    .cursor
+                                    .step(if keycode == Keycode::Left { -1 } else { 1 });
+                            }
+                            Screens::Results(ref mut screen) => {
+                                screen.page = (screen.page + 1) % ResultsScreen::pages_len();
+                            }
+                            Screens::Menu(ref mut screen) => {
                                 screen.sensitivity = (screen.sensitivity + 1) % 3;
                             }
+                            Screens::Debug(ref mut screen) => {
+                                let d = if keycode == Keycode::Left { -5 } else { 5 };
+                                screen.editor.adjust(d, 128);
+                            }
+                            Screens::Annotation(ref mut screen) => {
+                                screen
+                                    .editor
+                                    .adjust(if keycode == Keycode::Left { -1 } else { 1 });
+                            }
+                            Screens::Sequence(ref mut screen) => {
+                                screen.sequence.skip();
+                            }
+                            _ => (),
+                        },
+                        Keycode::G
+                        | Keycode::H
+                        | Keycode::J
+                        | Keycode::K
+                        | Keycode::L
+                        | Keycode::N
+                        | Keycode::M
+                        | Keycode::B
+                        | Keycode::Comma
+                        | Keycode::Period
+                        | Keycode::Slash => {
+                            match keycode {
+                                Keycode::H => {
+                                    pulse.duration_micros = (pulse.duration_micros / 2).max(50)
+                                }
+                                Keycode::J => pulse.duration_micros *= 2,
+                                Keycode::K => pulse.rise_micros = (pulse.rise_micros / 2).max(10),
+                                Keycode::L => pulse.rise_micros *= 2,
+                                Keycode::N => pulse.noise = pulse.noise.saturating_sub(10),
+                                Keycode::M => pulse.noise += 10,
+                                Keycode::B => pulse.bounces = (pulse.bounces + 1) % 4,
+                                // Sensor lag, from 50 us up or off
+                                Keycode::Comma if pulse.response_micros <= 50 => {
+                                    pulse.response_micros = 0
+                                }
+                                Keycode::Comma => pulse.response_micros /= 2,
+                                Keycode::Period => {
+                                    pulse.response_micros = (pulse.response_micros * 2).max(50)
+                                }
+                                Keycode::Slash => pulse.x_sync = !pulse.x_sync,
+                                _ => (),
+                            }
+                            println!("{:?}", pulse);
+                            measure = true;
+                        }
+                        Keycode::Backspace => {
+                            if let Screens::Debug(ref mut screen) = screen {
+                                screen.peak_hold.reset();
+                                screen.profile.reset();
+                            }
+                        }
+                        Keycode::Tab => {
+                            if let Screens::Debug(ref mut screen) = screen {
+                                if screen.page == DebugPage::Live {
+                                    // Roughly what the firmware reports
+                                    let mut profile = Profile::new(84_000_000, 100_000);
+                                    for cycles in [310, 420, 650] {
+                                        profile.record(ProfiledSection::Dma, cycles);
+                                    }
+                                    profile.record(ProfiledSection::DisplayFrame, 1_260_000);
+                                    profile.record(ProfiledSection::DisplayFrame, 4_900_000);
+                                    profile.record(ProfiledSection::MeasureLoop, 2_100);
+                                    screen.profile = profile;
+                                }
+                                if screen.page == DebugPage::Timing {
+                                    // Roughly the firmware's layout
+                                    screen.memory = MemoryUsage {
+                                        ram_bytes: 63 * 1024,
+                                        static_bytes: 41_250,
+                                        stack_bytes: 23_262,
+                                        stack_peak_bytes: 5_120,
+                                        buffers: [
+                                            ("MEASURE", 8_480),
+                                            ("CALIBRATE", 2_080),
+                                            ("HISTORY", 3_604),
+                                            ("TRACES", 6_152),
+                                            ("SCAN", 2_624),
+                                            ("USB EP", 4_096),
+                                            ("USB LOG", 1_032),
+                                        ],
+                                    };
+                                }
+                                screen.page = match screen.page {
+                                    DebugPage::Live => DebugPage::Timing,
+                                    DebugPage::Timing => DebugPage::Memory,
+                                    DebugPage::Memory => DebugPage::Live,
+                                };
+                            }
                         }
+                        Keycode::Return => match screen {
+                            Screens::Results(ref mut screen)
+                                if ResultsScreen::is_zoomable_page(screen.page) =>
+                            {
+                                screen.viewport.cycle_zoom();
+                            }
+                            Screens::Results(ref mut screen)
+                                if ResultsScreen::is_cursor_page(screen.page) =>
+                            {
+                                screen.cursor.toggle();
+                            }
+                            Screens::Debug(ref mut screen) => {
+                                screen.editor.select_next();
+                            }
+                            Screens::Annotation(ref mut screen) => {
+                                screen.editor.select_next();
+                            }
+                            Screens::Sequence(ref mut screen) => {
+                                // Pretend the shutter runs 10% slow
+                                if let Some(step) = screen.sequence.current() {
+                                    screen
+                                        .sequence
+                                        .record(step.nominal_duration_micros() * 11 / 10);
+                                }
+                            }
+                            Screens::Menu(ref mut screen) => {
+                                screen.emitter_intensity = (screen.emitter_intensity + 25) % 125;
+                            }
+                            Screens::Counter(ref mut screen) => {
+                                screen.count += 1;
+                                screen.events_per_minute = Some(screen.count * 7);
+                            }
+                            Screens::About(ref mut screen) => {
+                                screen.measurement_count += 1;
+                            }
+                            Screens::Scan(ref mut screen) => {
+                                // 1/500 exposure scanned top to bottom in 8 ms
+                                screen.result = match screen.result {
+                                    Some(_) => None,
+                                    None => Some(ScanResult {
+                                        channels: core::array::from_fn(|index| {
+                                            let opened_at = 1_000 + index as u64 * 1_000;
+                                            ChannelTiming {
+                                                opened_at_micros: Some(opened_at),
+                                                closed_at_micros: Some(opened_at + 2_000),
+                                            }
+                                        }),
+                                        pitch_um: 3_000,
+                                        frame_height_um: 24_000,
+                                        sweep_interval_micros: 500,
+                                    }),
+                                };
+                            }
+                            Screens::FocalPlane(ref mut screen) => {
+                                // Horizontal travel in 12 ms, the right side 10% short
+                                screen.result = match screen.result {
+                                    Some(_) => None,
+                                    None => Some(FocalPlaneResult {
+                                        sensors: core::array::from_fn(|index| {
+                                            let opened_at = 1_000 + index as u64 * 5_000;
+                                            let open = if index == 2 { 1_800 } else { 2_000 };
+                                            ChannelTiming {
+                                                opened_at_micros: Some(opened_at),
+                                                closed_at_micros: Some(opened_at + open),
+                                            }
+                                        }),
+                                        pitch_um: 15_000,
+                                        frame_width_um: 36_000,
+                                        sample_interval_micros: 10,
+                                    }),
+                                };
+                            }
+                            _ => (),
+                        },
                         _ => (),
                     }
                 }
@@ -212,8 +627,38 @@ async fn main() {
             }
         }
 
+        if measure {
+            if let Some(console) = console.as_mut() {
+                console.log_armed();
+            }
+            match synthesize(&pulse, &TRIGGER_THRESHOLDS) {
+                Some(synthesized) => {
+                    println!(
+                        "measured {} us, integrated {} us",
+                        synthesized.result.duration_micros,
+                        synthesized.result.integrated_duration_micros
+                    );
+                    if let Some(console) = console.as_mut() {
+                        console.record(&synthesized, Annotation::default());
+                    }
+                    screen = ResultsScreen::new(
+                        CalibrationState::Done(synthesized.calibration),
+                        synthesized.result,
+                        Annotation::default(),
+                    )
+                    .into();
+                    need_init = true;
+                }
+                None => println!("not triggered"),
+            }
+        }
+
         if need_init {
-            screen.draw_init(&mut live_display).await;
+            // A new result, or the table or method it's compared by changed
+            if let Screens::Results(ref mut screen) = screen {
+                screen.cursor = screen.initial_cursor();
+            }
+            screen.draw_init(&mut live_display).await.unwrap();
             live_display.hint_refresh();
         }
 
@@ -221,9 +666,30 @@ async fn main() {
         match screen {
             Screens::Debug(ref mut screen) => {
                 screen.step(screen.last_adc_value());
+                if let Some(console) = console.as_mut() {
+                    console.monitor(
+                        screen.last_adc_value(),
+                        screen.editor.low,
+                        screen.editor.high,
+                    );
+                }
             }
             Screens::Calibration(ref mut screen) => {
-                screen.step(Some((t_start.elapsed().as_millis() / 10 % 100) as u8));
+                let progress = (t_start.elapsed().as_millis() / 10 % 100) as u8;
+                let stage = [
+                    CalibrationStage::Cover,
+                    CalibrationStage::Covered,
+                    CalibrationStage::Uncover,
+                    CalibrationStage::Ambient,
+                ][t_start.elapsed().as_secs() as usize % 4];
+                screen.step(
+                    Some(stage),
+                    Some(progress),
+                    Some((100 - progress as u32) * 5),
+                );
+            }
+            Screens::About(ref mut screen) => {
+                screen.uptime_secs = t_start.elapsed().as_secs() as u32;
             }
             _ => (),
         }
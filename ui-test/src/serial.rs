@@ -0,0 +1,325 @@
+use std::io::{ErrorKind, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::time::{Duration, Instant};
+
+use app_measurements::{
+    compress_trace, AdcFaults, Annotation, Capabilities, CommandParser, DeviceEvent, History,
+    HistoryEntry, LevelMonitor, MeasurementResult, TraceDecoder, TraceHistory, UsbExport,
+    UsbRequest, CAPABILITY_KEYS, FOCAL_PLANE_SENSORS, HISTORY_LEN, MONITOR_INTERVAL_MS,
+    SAMPLING_BUFFER_LEN_WITH_MARGINS, SCAN_CHANNELS, TRACE_HISTORY_LEN,
+};
+
+use crate::synth::{Synthesized, SAMPLE_RATE_HZ};
+
+/// `nc localhost 7878` stands in for the USB serial console
+pub const SERIAL_PORT: u16 = 7878;
+
+/// The firmware's text console on a local TCP port, one client at a time.
+/// Exports are answered here, arming and the bootloader are up to the caller.
+pub struct SerialConsole {
+    listener: TcpListener,
+    client: Option<TcpStream>,
+    parser: CommandParser,
+    history: History,
+    trace_history: TraceHistory,
+    /// Toggled by `monitor`, with when the last frame went out
+    monitor: Option<(LevelMonitor, Instant)>,
+    /// Set by `SUBSCRIBE`
+    subscribed: bool,
+    /// Only the modes measuring goes through, the screen keys don't count
+    mode: &'static str,
+}
+
+impl SerialConsole {
+    pub fn bind() -> std::io::Result<Self> {
+        let listener = TcpListener::bind(("127.0.0.1", SERIAL_PORT))?;
+        listener.set_nonblocking(true)?;
+        Ok(Self {
+            listener,
+            client: None,
+            parser: CommandParser::default(),
+            history: History::new(),
+            trace_history: TraceHistory::new(),
+            monitor: None,
+            subscribed: false,
+            mode: "START",
+        })
+    }
+
+    /// Never blocks for long, call it from the frame loop
+    pub fn poll(&mut self) -> Option<UsbRequest> {
+        // Lines that came in together go out one per frame
+        if let Some(request) = self.parser.next_request() {
+            return self.answer(request);
+        }
+
+        if let Ok((stream, _)) = self.listener.accept() {
+            // A new connection replaces the old one, like reopening the port
+            stream.set_nonblocking(false).ok()?;
+            stream
+                .set_read_timeout(Some(Duration::from_millis(1)))
+                .ok()?;
+            self.client = Some(stream);
+        }
+
+        let mut buf = [0; 64];
+        let count = match self.client.as_mut()?.read(&mut buf) {
+            Ok(0) => {
+                self.client = None;
+                self.subscribed = false;
+                return None;
+            }
+            Ok(count) => count,
+            Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+                return None;
+            }
+            Err(_) => {
+                self.client = None;
+                return None;
+            }
+        };
+        self.write(b"\r\n");
+        self.write(&buf[..count]);
+
+        let request = self.parser.feed(&buf[..count])?;
+        self.answer(request)
+    }
+
+    /// Exports are handled here, the rest is up to the caller
+    fn answer(&mut self, request: UsbRequest) -> Option<UsbRequest> {
+        match request {
+            UsbRequest::Export(export) => {
+                self.export(export);
+                None
+            }
+            UsbRequest::Subscribe(subscribed) => {
+                self.subscribed = subscribed;
+                self.event(DeviceEvent::Mode(self.mode));
+                None
+            }
+            request => Some(request),
+        }
+    }
+
+    /// Dropped without a client, the firmware doesn't buffer either
+    pub fn write(&mut self, data: &[u8]) {
+        if let Some(client) = self.client.as_mut() {
+            if client.write_all(data).is_err() {
+                self.client = None;
+            }
+        }
+    }
+
+    /// Streams the live view while monitoring, call it every frame
+    pub fn monitor(&mut self, level: u16, trigger_low: u16, trigger_high: u16) {
+        let Some((monitor, sent_at)) = self.monitor.as_mut() else {
+            return;
+        };
+        monitor.step(level, trigger_low, trigger_high);
+        if sent_at.elapsed() < Duration::from_millis(MONITOR_INTERVAL_MS as u64) {
+            return;
+        }
+        *sent_at = Instant::now();
+        if let Some(frame) = monitor.take_frame() {
+            self.write(frame.to_string().as_bytes());
+        }
+    }
+
+    pub fn log_armed(&mut self) {
+        self.set_mode("MEASURE");
+        self.write(b"MEAS:ARMED 0\r\n");
+    }
+
+    fn set_mode(&mut self, mode: &'static str) {
+        self.mode = mode;
+        self.event(DeviceEvent::Mode(mode));
+    }
+
+    fn event(&mut self, event: DeviceEvent) {
+        if self.subscribed {
+            self.write(event.to_string().as_bytes());
+        }
+    }
+
+    /// Reports and records a result the way the measure task does
+    pub fn record(&mut self, synthesized: &Synthesized, annotation: Annotation) {
+        let result = &synthesized.result;
+        self.event(DeviceEvent::calibrated(&synthesized.calibration));
+        self.write(format!("MEAS:TRIG {}\r\n", synthesized.triggered_at_micros).as_bytes());
+        self.event(DeviceEvent::measured(result));
+
+        self.history.write(HistoryEntry::new(result, annotation));
+        if let Some(trace) = compress_trace(result.sample_buffer.oldest_ordered().copied()) {
+            self.trace_history.write(trace);
+        }
+        self.log_result(result, annotation);
+        self.set_mode("RESULTS");
+    }
+
+    fn log_result(&mut self, result: &MeasurementResult, annotation: Annotation) {
+        self.write(b"Result: \r\n");
+        self.write(format!("Camera: {}\r\n", annotation.camera.label()).as_bytes());
+        if let Some(nominal_micros) = annotation.nominal_duration_micros() {
+            self.write(format!("Nominal time: {} us\r\n", nominal_micros).as_bytes());
+        }
+        self.write(format!("Raw start-end time: {} us\r\n", result.duration_micros).as_bytes());
+        self.write(
+            format!(
+                "Integrated time: {} us\r\n",
+                result.integrated_duration_micros
+            )
+            .as_bytes(),
+        );
+        self.write(
+            format!(
+                "Half peak time: {} us\r\n",
+                result.half_peak_duration_micros
+            )
+            .as_bytes(),
+        );
+        self.write(format!("Uncertainty: +-{} us\r\n", result.uncertainty_micros()).as_bytes());
+        if result.clipped {
+            self.write(b"Clipped: ADC saturated, reduce light\r\n");
+        }
+        self.write(
+            format!(
+                "Sample rate at the end: 1/{}\r\n",
+                result.effective_divisor()
+            )
+            .as_bytes(),
+        );
+        if let Some(second_pulse) = result.second_pulse {
+            self.write(
+                format!(
+                    "Second pulse detected: {} us, {} us after the first\r\n",
+                    second_pulse.duration_micros, second_pulse.gap_micros
+                )
+                .as_bytes(),
+            );
+        }
+        if let Some(lag_micros) = result.release_lag_micros {
+            self.write(format!("Release lag: {} us\r\n", lag_micros).as_bytes());
+        }
+        if let Some(offset_micros) = result.sync_offset_micros {
+            self.write(format!("Sync offset: {} us\r\n", offset_micros).as_bytes());
+        }
+        if let Some(lag_micros) = result.fired_lag_micros {
+            self.write(format!("Fired lag: {} us\r\n", lag_micros).as_bytes());
+        }
+        self.write(format!("Samples since start: {}\r\n", result.samples_since_start).as_bytes());
+        self.write(format!("Samples since end: {}\r\n", result.samples_since_end).as_bytes());
+
+        let l = result.sample_buffer.len();
+        for (index, item) in result.sample_buffer.oldest_ordered().enumerate() {
+            if index == l - result.samples_since_end {
+                self.write(b"** end **\r\n");
+            }
+            self.write(format!("- {}\r\n", item).as_bytes());
+            if index == l - result.samples_since_start {
+                self.write(b"** start **\r\n");
+            }
+        }
+        self.write(b"\r\n");
+    }
+
+    /// Same CSV as the firmware, the modes the simulator has no core for
+    /// export just the header
+    fn export(&mut self, export: UsbExport) {
+        match export {
+            UsbExport::History => {
+                self.write(b"index,camera,nominal_us,duration_us,integrated_us,uncertainty_us\r\n");
+                let rows: Vec<_> = self.history.oldest_ordered().copied().collect();
+                for (index, entry) in rows.iter().enumerate() {
+                    let nominal = entry
+                        .annotation
+                        .nominal_duration_micros()
+                        .map(|micros| micros.to_string())
+                        .unwrap_or_default();
+                    self.write(
+                        format!(
+                            "{},{},{},{},{},{}\r\n",
+                            index,
+                            entry.annotation.camera.label(),
+                            nominal,
+                            entry.duration_micros,
+                            entry.integrated_duration_micros,
+                            entry.uncertainty_micros
+                        )
+                        .as_bytes(),
+                    );
+                }
+            }
+            UsbExport::Traces => {
+                self.write(b"trace,sample,value\r\n");
+                let traces: Vec<_> = self.trace_history.oldest_ordered().cloned().collect();
+                for (index, trace) in traces.iter().enumerate() {
+                    for (sample, value) in TraceDecoder::new(trace).enumerate() {
+                        self.write(format!("{},{},{}\r\n", index, sample, value).as_bytes());
+                    }
+                }
+            }
+            UsbExport::Sequence => {
+                self.write(b"step,nominal_us,duration_us,deviation_pct\r\n");
+            }
+            UsbExport::Profile => {
+                self.write(b"section,count,min_cycles,mean_cycles,max_cycles,max_ns\r\n");
+            }
+            UsbExport::FocalPlane => {
+                self.write(b"row,exposure_us,deviation_pct,travel_us,uncertainty_us\r\n");
+            }
+            UsbExport::Monitor => {
+                self.monitor = match self.monitor {
+                    Some(_) => None,
+                    None => Some((LevelMonitor::default(), Instant::now())),
+                };
+            }
+            UsbExport::Config => {
+                self.write(b"config load\r\nend\r\n");
+            }
+            UsbExport::Report => {
+                self.write(
+                    b"SHUTTER SPEED TEST REPORT\r\nCALIBRATION  NONE\r\n\r\nNO TEST SEQUENCE\r\n",
+                );
+            }
+            UsbExport::Capabilities => {
+                // Synthesized pulses at one rate, none of the other accessories
+                let capabilities = Capabilities {
+                    firmware: concat!("simulator ", env!("CARGO_PKG_VERSION")),
+                    hardware: "SIMULATOR",
+                    modes: &["START", "MEASURE", "RESULTS"],
+                    sample_rate_hz: SAMPLE_RATE_HZ,
+                    oversampling: &[1],
+                    adc_bits: &[12],
+                    buffer_samples: SAMPLING_BUFFER_LEN_WITH_MARGINS,
+                    burst_samples: 0,
+                    history_len: HISTORY_LEN,
+                    trace_history_len: TRACE_HISTORY_LEN,
+                    accessories: &["SENSOR"],
+                    focal_plane_sensors: FOCAL_PLANE_SENSORS,
+                    scan_channels: SCAN_CHANNELS,
+                    features: &[],
+                };
+                let mut block = String::from("capabilities\r\n");
+                for key in CAPABILITY_KEYS {
+                    block.push_str(key);
+                    block.push('=');
+                    capabilities.write_value(key, &mut block).unwrap();
+                    block.push_str("\r\n");
+                }
+                block.push_str("end\r\n");
+                self.write(block.as_bytes());
+            }
+            UsbExport::Status => {
+                // Synthesized samples never go missing
+                let faults = AdcFaults::default();
+                self.write(
+                    format!(
+                        "ADC:TRANSFER_ERRORS {}\r\nADC:OVERRUNS {}\r\nMEAS:INVALIDATED {}\r\n",
+                        faults.transfer_errors, faults.overruns, faults.invalidated
+                    )
+                    .as_bytes(),
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,158 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use app_measurements::util::LaxMonotonic;
+use app_measurements::{
+    CalibrationResult, CalibrationState, Measurement, MeasurementResult, TriggerThresholds,
+};
+
+// Matches the firmware ADC setup
+const SAMPLE_PERIOD_MICROS: u64 = 10;
+pub const SAMPLE_RATE_HZ: u32 = (1_000_000 / SAMPLE_PERIOD_MICROS) as u32;
+const ADC_RANGE: u16 = 4096;
+const BASELINE: f32 = 200.0;
+const OPEN_LEVEL: f32 = 3000.0;
+const LEAD_IN_MICROS: u64 = 5_000;
+const TIMEOUT_MICROS: u64 = 200_000;
+const BOUNCE_INTERVAL_MICROS: f32 = 400.0;
+const BOUNCE_WIDTH_MICROS: f32 = 120.0;
+
+// Same as the firmware defaults
+pub const TRIGGER_THRESHOLDS: TriggerThresholds = TriggerThresholds {
+    low_ratio: 1.0,
+    high_ratio: 1.0,
+    low_delta: ADC_RANGE / 32,
+    high_delta: ADC_RANGE / 16,
+};
+
+static NOW_MICROS: AtomicU64 = AtomicU64::new(0);
+
+/// Clock driven by the synthesizer instead of wall time
+pub struct SimClock;
+
+impl LaxMonotonic for SimClock {
+    type Instant = fugit::TimerInstantU64<1_000_000>;
+    type Duration = fugit::TimerDurationU64<1_000_000>;
+
+    fn now() -> Self::Instant {
+        Self::Instant::from_ticks(NOW_MICROS.load(Ordering::Relaxed))
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct PulseParams {
+    /// Between the half-open points of the edges
+    pub duration_micros: u64,
+    pub rise_micros: u64,
+    /// First order sensor lag on top of the edges, 0 for an ideal sensor
+    pub response_micros: u64,
+    /// Peak-to-peak, in ADC counts
+    pub noise: u16,
+    /// Number of curtain bounces after closing
+    pub bounces: u8,
+    /// Pulses the sync input once fully open, like a flash X contact
+    pub x_sync: bool,
+}
+
+impl Default for PulseParams {
+    fn default() -> Self {
+        Self {
+            duration_micros: 8_000,
+            rise_micros: 200,
+            response_micros: 0,
+            noise: 20,
+            bounces: 0,
+            x_sync: false,
+        }
+    }
+}
+
+impl PulseParams {
+    fn level_at(&self, t: f32) -> f32 {
+        let rise = self.rise_micros.max(1) as f32;
+        let duration = self.duration_micros as f32;
+
+        let opening = ((t + rise / 2.0) / rise).clamp(0.0, 1.0);
+        let closing = ((t - duration + rise / 2.0) / rise).clamp(0.0, 1.0);
+        let mut level = opening - closing;
+
+        for i in 0..self.bounces {
+            let start = duration + rise / 2.0 + BOUNCE_INTERVAL_MICROS * (i + 1) as f32;
+            if t >= start && t < start + BOUNCE_WIDTH_MICROS {
+                level = level.max(0.5 / (i + 1) as f32);
+            }
+        }
+
+        BASELINE + level * (OPEN_LEVEL - BASELINE)
+    }
+}
+
+struct Noise(u32);
+
+impl Noise {
+    fn next(&mut self, amplitude: u16) -> f32 {
+        // xorshift32
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 17;
+        self.0 ^= self.0 << 5;
+        if amplitude == 0 {
+            return 0.0;
+        }
+        (self.0 % amplitude as u32) as f32 - amplitude as f32 / 2.0
+    }
+}
+
+pub struct Synthesized {
+    pub calibration: CalibrationResult,
+    pub result: MeasurementResult,
+    /// Since arming, in place of the firmware's cycle counter timestamp
+    pub triggered_at_micros: u64,
+}
+
+/// Runs a synthetic pulse through calibration and measurement like the firmware would
+pub fn synthesize(params: &PulseParams, thresholds: &TriggerThresholds) -> Option<Synthesized> {
+    let mut noise = Noise(0x1234_5678);
+    let sample = |level: f32, noise: &mut Noise| {
+        (level + noise.next(params.noise)).clamp(0.0, ADC_RANGE as f32 - 1.0) as u16
+    };
+
+    let mut calibration = CalibrationState::default();
+    calibration.begin();
+    let calibration = loop {
+        match calibration {
+            CalibrationState::Done(ref result) => break result.clone(),
+            // Nobody to prompt, the sensor is covered and uncovered on the spot
+            _ if calibration.is_waiting() => calibration.resume(),
+            CalibrationState::Covered { .. } => calibration.step(sample(0.0, &mut noise)),
+            _ => calibration.step(sample(BASELINE, &mut noise)),
+        }
+    };
+
+    let mut measurement = Measurement::<SimClock>::new(calibration.clone(), *thresholds)
+        .with_sample_rate(SAMPLE_RATE_HZ)
+        .with_saturation_level(ADC_RANGE - 1)
+        .with_response_time(params.response_micros as u32 * 1000);
+    let end = LEAD_IN_MICROS + params.duration_micros + TIMEOUT_MICROS;
+    // RC low pass, one step per sample
+    let smoothing = match params.response_micros {
+        0 => 1.0,
+        tau => 1.0 - (-(SAMPLE_PERIOD_MICROS as f32) / tau as f32).exp(),
+    };
+    let mut level = BASELINE;
+    let mut t = 0;
+    while t < end && !measurement.is_done() {
+        NOW_MICROS.store(t, Ordering::Relaxed);
+        level += (params.level_at(t as f32 - LEAD_IN_MICROS as f32) - level) * smoothing;
+        if params.x_sync && t >= LEAD_IN_MICROS + params.rise_micros / 2 {
+            measurement.mark_sync();
+        }
+        measurement.step(sample(level, &mut noise));
+        t += SAMPLE_PERIOD_MICROS;
+    }
+
+    let triggered_at_micros = measurement.opened_at()?.ticks();
+    Some(Synthesized {
+        calibration,
+        result: measurement.take_result()?,
+        triggered_at_micros,
+    })
+}